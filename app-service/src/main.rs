@@ -1,27 +1,47 @@
 use std::env;
 
 use askama::Template;
+use auth_client::AuthClient;
+use auth_middleware::{AuthLayer, AuthenticatedUser, Authenticator};
 use axum::{
-    http::StatusCode,
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
 };
-use axum_extra::extract::CookieJar;
 use serde::Serialize;
-use tower_http::services::ServeDir;
+use tower_http::{services::ServeDir, trace::TraceLayer};
+
+mod telemetry;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<(), service_telemetry::TelemetryError> {
+    let _telemetry = telemetry::init_tracing()?;
+
+    let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
+    // Propagate the trace so token checks show up under the request that needed them
+    let auth_client = AuthClient::new(format!("http://{}:3000", auth_hostname))
+        .with_header_provider(telemetry::trace_headers);
+    let mut auth_layer = AuthLayer::new(Authenticator::remote(auth_client));
+    // Optionally restrict `/protected` to users holding a role, e.g. `admin`
+    if let Some(role) = env::var("PROTECTED_ROUTE_ROLE")
+        .ok()
+        .filter(|role| !role.is_empty())
+    {
+        auth_layer = auth_layer.with_required_role(role);
+    }
+
     let app = Router::new()
         .nest_service("/assets", ServeDir::new("assets"))
         .route("/", get(root))
-        .route("/protected", get(protected));
+        .route("/protected", get(protected).route_layer(auth_layer))
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::make_span));
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
 
-    println!("listening on {}", listener.local_addr().unwrap());
+    tracing::info!("listening on {}", listener.local_addr().unwrap());
     axum::serve(listener, app).await.unwrap();
+
+    Ok(())
 }
 
 #[derive(Template)]
@@ -46,40 +66,10 @@ async fn root() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
-    let jwt_cookie = match jar.get("jwt") {
-        Some(cookie) => cookie,
-        None => {
-            return StatusCode::UNAUTHORIZED.into_response();
-        }
-    };
-
-    let api_client = reqwest::Client::builder().build().unwrap();
-
-    let verify_token_body = serde_json::json!({
-        "token": &jwt_cookie.value(),
-    });
-
-    let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
-    let url = format!("http://{}:3000/verify-token", auth_hostname);
-
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
-        Ok(response) => response,
-        Err(_) => {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
-
-    match response.status() {
-        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
-            StatusCode::UNAUTHORIZED.into_response()
-        }
-        reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
-            img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
-        })
-        .into_response(),
-        _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+async fn protected(_user: AuthenticatedUser) -> impl IntoResponse {
+    Json(ProtectedRouteResponse {
+        img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
+    })
 }
 
 #[derive(Serialize)]
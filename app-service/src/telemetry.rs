@@ -0,0 +1,45 @@
+use std::env;
+
+use axum::{body::Body, extract::Request, http::HeaderMap};
+use service_telemetry::{
+    extract_trace_context, inject_trace_context, TelemetryError, TelemetryGuard,
+};
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+const SERVICE_NAME: &str = "app-service";
+
+// Logs to stdout and, when OTEL_EXPORTER_OTLP_ENDPOINT is set, exports spans over OTLP/HTTP
+pub fn init_tracing() -> Result<TelemetryGuard, TelemetryError> {
+    let service_name = env::var("OTEL_SERVICE_NAME").unwrap_or(SERVICE_NAME.to_owned());
+    let otlp_endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty());
+
+    service_telemetry::init_tracing(
+        tracing_subscriber::fmt::layer().compact(),
+        &service_name,
+        otlp_endpoint.as_deref(),
+    )
+}
+
+// Starts a span per request that joins the caller's trace when a `traceparent` header is sent
+pub fn make_span(request: &Request<Body>) -> Span {
+    let span = tracing::span!(
+        Level::INFO,
+        "[REQUEST]",
+        method = %request.method(),
+        uri = %request.uri(),
+    );
+    let _ = span.set_parent(extract_trace_context(request.headers()));
+
+    span
+}
+
+// W3C trace context headers for an outgoing request made within the current span
+pub fn trace_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    inject_trace_context(&mut headers);
+
+    headers
+}
@@ -0,0 +1,196 @@
+use std::{fmt, sync::Arc};
+
+use auth_types::{
+    LoginRequest, LoginResponse, LoginResponse2FA, SignupRequest, SignupResponse, TokenResponse,
+    Verify2FARequest, VerifyTokenRequest, VerifyTokenResponse, JWT_COOKIE_NAME,
+};
+use reqwest::{header::HeaderMap, Client, RequestBuilder, Response, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::AuthClientError;
+
+// Result of a login with correct credentials
+#[derive(Debug)]
+pub enum LoginOutcome {
+    // A code was emailed to the user; finish with `AuthClient::verify_2fa`
+    TwoFactorRequired(LoginResponse2FA),
+    // The JWT, from the auth cookie or, with `TokenDelivery::Body`, the response body
+    Authenticated(Secret<String>),
+}
+
+// Supplies extra headers for each request, e.g. the W3C trace context of the current span
+pub type HeaderProvider = Arc<dyn Fn() -> HeaderMap + Send + Sync>;
+
+// Typed client for the auth service API. It keeps no session: tokens are handed to the
+// caller, who passes them back to `verify_token` and `logout`.
+#[derive(Clone)]
+pub struct AuthClient {
+    base_url: String,
+    http_client: Client,
+    header_provider: Option<HeaderProvider>,
+}
+
+impl fmt::Debug for AuthClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthClient")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthClient {
+    // `base_url` is the service root, e.g. `http://auth-service:3000`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, Client::new())
+    }
+
+    // Reuses a configured client, e.g. one with timeouts set
+    pub fn with_http_client(base_url: impl Into<String>, http_client: Client) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            http_client,
+            header_provider: None,
+        }
+    }
+
+    pub fn with_header_provider(
+        mut self,
+        provider: impl Fn() -> HeaderMap + Send + Sync + 'static,
+    ) -> Self {
+        self.header_provider = Some(Arc::new(provider));
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    #[tracing::instrument(name = "Auth client signup", skip_all)]
+    pub async fn signup(&self, request: &SignupRequest) -> Result<SignupResponse, AuthClientError> {
+        let response = self.post("/signup").json(request).send().await?;
+
+        match response.status() {
+            StatusCode::CREATED => Ok(response.json().await?),
+            _ => Err(AuthClientError::from_response(response).await),
+        }
+    }
+
+    #[tracing::instrument(name = "Auth client login", skip_all)]
+    pub async fn login(&self, request: &LoginRequest) -> Result<LoginOutcome, AuthClientError> {
+        let response = self.post("/login").json(request).send().await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let cookie_token = cookie_token(&response);
+                match (response.json().await?, cookie_token) {
+                    (LoginResponse::Token(body), _) => {
+                        Ok(LoginOutcome::Authenticated(Secret::new(body.token)))
+                    }
+                    (_, Some(token)) => Ok(LoginOutcome::Authenticated(token)),
+                    (_, None) => Err(AuthClientError::MissingToken),
+                }
+            }
+            StatusCode::PARTIAL_CONTENT => match response.json().await? {
+                LoginResponse::TwoFactorAuth(body) => Ok(LoginOutcome::TwoFactorRequired(body)),
+                _ => Err(AuthClientError::UnexpectedResponse(
+                    StatusCode::PARTIAL_CONTENT,
+                )),
+            },
+            _ => Err(AuthClientError::from_response(response).await),
+        }
+    }
+
+    // Returns the JWT issued once the code is accepted
+    #[tracing::instrument(name = "Auth client verify 2FA", skip_all)]
+    pub async fn verify_2fa(
+        &self,
+        request: &Verify2FARequest,
+    ) -> Result<Secret<String>, AuthClientError> {
+        let response = self.post("/verify-2fa").json(request).send().await?;
+
+        match response.status() {
+            StatusCode::OK => match cookie_token(&response) {
+                Some(token) => Ok(token),
+                None => {
+                    let body: TokenResponse = response
+                        .json()
+                        .await
+                        .map_err(|_| AuthClientError::MissingToken)?;
+                    Ok(Secret::new(body.token))
+                }
+            },
+            _ => Err(AuthClientError::from_response(response).await),
+        }
+    }
+
+    // Checks a JWT or personal API key and returns who it identifies
+    #[tracing::instrument(name = "Auth client verify token", skip_all)]
+    pub async fn verify_token(
+        &self,
+        token: &Secret<String>,
+    ) -> Result<VerifyTokenResponse, AuthClientError> {
+        self.verify(VerifyTokenRequest {
+            token: token.clone(),
+            permission: None,
+        })
+        .await
+    }
+
+    // As `verify_token`, but also requires one of the user's roles to grant `permission`;
+    // fails with a 403 `forbidden` error otherwise
+    #[tracing::instrument(name = "Auth client verify permission", skip_all)]
+    pub async fn verify_permission(
+        &self,
+        token: &Secret<String>,
+        permission: &str,
+    ) -> Result<VerifyTokenResponse, AuthClientError> {
+        self.verify(VerifyTokenRequest {
+            token: token.clone(),
+            permission: Some(permission.to_owned()),
+        })
+        .await
+    }
+
+    async fn verify(
+        &self,
+        request: VerifyTokenRequest,
+    ) -> Result<VerifyTokenResponse, AuthClientError> {
+        let response = self.post("/verify-token").json(&request).send().await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(AuthClientError::from_response(response).await),
+        }
+    }
+
+    // Bans the token, so `verify_token` rejects it from then on
+    #[tracing::instrument(name = "Auth client logout", skip_all)]
+    pub async fn logout(&self, token: &Secret<String>) -> Result<(), AuthClientError> {
+        let response = self
+            .post("/logout")
+            .bearer_auth(token.expose_secret())
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(AuthClientError::from_response(response).await),
+        }
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        let request = self.http_client.post(format!("{}{}", self.base_url, path));
+
+        match &self.header_provider {
+            Some(provider) => request.headers(provider()),
+            None => request,
+        }
+    }
+}
+
+fn cookie_token(response: &Response) -> Option<Secret<String>> {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .map(|cookie| Secret::new(cookie.value().to_owned()))
+}
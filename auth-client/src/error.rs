@@ -0,0 +1,52 @@
+use auth_types::ErrorResponse;
+use reqwest::{Response, StatusCode};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AuthClientError {
+    // The service rejected the request with a documented error body
+    #[error("{} ({})", .body.error, .status)]
+    Api {
+        status: StatusCode,
+        body: ErrorResponse,
+    },
+    // A status, or an error body, the API does not document for the operation
+    #[error("Unexpected response status {0}")]
+    UnexpectedResponse(StatusCode),
+    #[error("Response did not include the auth token")]
+    MissingToken,
+    #[error("Request failed")]
+    Http(#[from] reqwest::Error),
+}
+
+impl AuthClientError {
+    // Stable error code, e.g. `incorrect_credentials`, for errors returned by the service
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            AuthClientError::Api { body, .. } => Some(&body.code),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            AuthClientError::Api { status, .. } | AuthClientError::UnexpectedResponse(status) => {
+                Some(*status)
+            }
+            AuthClientError::Http(e) => e.status(),
+            AuthClientError::MissingToken => None,
+        }
+    }
+
+    pub(crate) async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return AuthClientError::UnexpectedResponse(status);
+        }
+
+        match response.json::<ErrorResponse>().await {
+            Ok(body) => AuthClientError::Api { status, body },
+            Err(_) => AuthClientError::UnexpectedResponse(status),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+mod client;
+mod error;
+
+pub use client::*;
+pub use error::*;
+
+// Request and response bodies come from `auth-types`, as the auth service's own do, so the two
+// cannot drift apart
+pub use auth_types::{
+    ErrorResponse, LoginRequest, LoginResponse2FA, SignupRequest, SignupResponse, TokenDelivery,
+    TokenResponse, TokenUse, Verify2FARequest, VerifyTokenRequest, VerifyTokenResponse,
+};
@@ -0,0 +1,192 @@
+use auth_client::{
+    AuthClientError, LoginOutcome, LoginRequest, SignupRequest, TokenDelivery, TokenUse,
+    Verify2FARequest,
+};
+use reqwest::StatusCode;
+use secrecy::Secret;
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn signup_request(email: &str, requires_2fa: bool) -> SignupRequest {
+    SignupRequest {
+        email: Secret::new(email.to_owned()),
+        password: Secret::new("Asdf1234@".to_owned()),
+        requires_2fa,
+    }
+}
+
+fn login_request(email: &str, password: &str) -> LoginRequest {
+    LoginRequest {
+        email: Secret::new(email.to_owned()),
+        password: Secret::new(password.to_owned()),
+        token_delivery: TokenDelivery::Cookie,
+    }
+}
+
+#[tokio::test]
+async fn login_returns_a_token_that_is_valid_until_logout() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+
+    let response = app
+        .client
+        .signup(&signup_request(&email, false))
+        .await
+        .unwrap();
+    assert_eq!(response.message, "User created successfully");
+
+    let outcome = app
+        .client
+        .login(&login_request(&email, "Asdf1234@"))
+        .await
+        .unwrap();
+    let LoginOutcome::Authenticated(token) = outcome else {
+        panic!("Expected to be authenticated, got {:?}", outcome);
+    };
+
+    let verified = app.client.verify_token(&token).await.unwrap();
+    assert_eq!(verified.email.as_deref(), Some(email.as_str()));
+    assert_eq!(verified.token_use, TokenUse::User);
+    app.client.logout(&token).await.unwrap();
+
+    let error = app.client.verify_token(&token).await.unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+    assert_eq!(error.code(), Some("invalid_token"));
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn login_can_return_the_token_in_the_body() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.client
+        .signup(&signup_request(&email, false))
+        .await
+        .unwrap();
+
+    let outcome = app
+        .client
+        .login(&LoginRequest {
+            token_delivery: TokenDelivery::Body,
+            ..login_request(&email, "Asdf1234@")
+        })
+        .await
+        .unwrap();
+    let LoginOutcome::Authenticated(token) = outcome else {
+        panic!("Expected to be authenticated, got {:?}", outcome);
+    };
+
+    app.client.verify_token(&token).await.unwrap();
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn two_factor_login_is_completed_with_the_emailed_code() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.client
+        .signup(&signup_request(&email, true))
+        .await
+        .unwrap();
+
+    let outcome = app
+        .client
+        .login(&login_request(&email, "Asdf1234@"))
+        .await
+        .unwrap();
+    let LoginOutcome::TwoFactorRequired(response) = outcome else {
+        panic!("Expected 2FA to be required, got {:?}", outcome);
+    };
+    let (login_attempt_id, code) = app.two_fa_code(&email).await;
+    assert_eq!(response.login_attempt_id, login_attempt_id);
+
+    let token = app
+        .client
+        .verify_2fa(&Verify2FARequest {
+            email: Secret::new(email),
+            login_attempt_id: Secret::new(login_attempt_id),
+            two_fa_code: Secret::new(code),
+            token_delivery: TokenDelivery::Body,
+        })
+        .await
+        .unwrap();
+
+    app.client.verify_token(&token).await.unwrap();
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn permissions_are_checked_against_the_users_roles() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.client
+        .signup(&signup_request(&email, false))
+        .await
+        .unwrap();
+    let outcome = app
+        .client
+        .login(&login_request(&email, "Asdf1234@"))
+        .await
+        .unwrap();
+    let LoginOutcome::Authenticated(token) = outcome else {
+        panic!("Expected to be authenticated, got {:?}", outcome);
+    };
+
+    // New users have no roles, so hold no permissions
+    let verified = app.client.verify_token(&token).await.unwrap();
+    assert!(verified.roles.is_empty());
+    let error = app
+        .client
+        .verify_permission(&token, "reports:read")
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::FORBIDDEN));
+    assert_eq!(error.code(), Some("forbidden"));
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn errors_carry_the_status_and_code() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.client
+        .signup(&signup_request(&email, false))
+        .await
+        .unwrap();
+
+    let error = app
+        .client
+        .signup(&signup_request(&email, false))
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::CONFLICT));
+    assert_eq!(error.code(), Some("user_already_exists"));
+
+    let error = app
+        .client
+        .login(&login_request(&email, "wrong-password"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+    assert_eq!(error.code(), Some("incorrect_credentials"));
+
+    let error = app
+        .client
+        .login(&login_request("not-an-email", "Asdf1234@"))
+        .await
+        .unwrap_err();
+    let AuthClientError::Api { status, body } = error else {
+        panic!("Expected an API error, got {:?}", error);
+    };
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body.code, "invalid_credentials");
+    assert_eq!(body.errors[0].field, "email");
+
+    let error = app
+        .client
+        .logout(&Secret::new("invalid".to_owned()))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Some("invalid_token"));
+    app.shutdown().await;
+}
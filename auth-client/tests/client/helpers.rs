@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use auth_client::AuthClient;
+use auth_service::{
+    app_state::{AppState, TwoFACodeStoreType},
+    config::{ApplicationSettings, AuthSettings, Settings},
+    domain::Email,
+    services::{HashmapTwoFACodeStore, HashmapUserStore, HashsetBannedTokenStore, MockEmailClient},
+    utils::{test, ShutdownHandle},
+    Application,
+};
+use secrecy::{ExposeSecret, Secret};
+use tokio::{sync::RwLock, task::JoinHandle};
+use uuid::Uuid;
+
+// An auth service running in-process on in-memory stores, with a client pointed at it
+pub struct TestApp {
+    pub client: AuthClient,
+    pub two_fa_code_store: TwoFACodeStoreType,
+    shutdown_handle: ShutdownHandle,
+    server: JoinHandle<Result<(), std::io::Error>>,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        let settings = Settings {
+            application: ApplicationSettings {
+                address: test::APP_ADDRESS.to_owned(),
+                shutdown_timeout_milliseconds: test::SHUTDOWN_TIMEOUT.as_millis() as u64,
+                ..ApplicationSettings::default()
+            },
+            auth: AuthSettings {
+                jwt_secret: Secret::new(test::JWT_SECRET.to_owned()),
+            },
+            ..Settings::default()
+        };
+        let app_state = AppState::new(
+            Arc::new(RwLock::new(HashmapUserStore::default())),
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(RwLock::new(MockEmailClient::default())),
+            settings.auth.clone(),
+        );
+        let two_fa_code_store = app_state.two_fa_code_store.clone();
+
+        let app = Application::build(app_state, &settings)
+            .await
+            .expect("Failed to build app");
+        let client = AuthClient::new(format!("http://{}", app.address));
+        let shutdown_handle = app.shutdown_handle();
+        let server = tokio::spawn(app.run());
+
+        Self {
+            client,
+            two_fa_code_store,
+            shutdown_handle,
+            server,
+        }
+    }
+
+    // The login attempt id and code most recently issued to `email`
+    pub async fn two_fa_code(&self, email: &str) -> (String, String) {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        let (login_attempt_id, code) = self
+            .two_fa_code_store
+            .write()
+            .await
+            .get_code(&email)
+            .await
+            .expect("No 2FA code was issued");
+
+        (
+            login_attempt_id.as_ref().expose_secret().to_owned(),
+            code.as_ref().expose_secret().to_owned(),
+        )
+    }
+
+    pub async fn shutdown(self) {
+        self.shutdown_handle.shutdown();
+        self.server
+            .await
+            .expect("Server task panicked")
+            .expect("Server failed while shutting down");
+    }
+}
+
+pub fn get_random_email() -> String {
+    format!("{}@example.com", Uuid::new_v4())
+}
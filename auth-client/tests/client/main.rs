@@ -0,0 +1,2 @@
+mod auth_client;
+mod helpers;
@@ -0,0 +1,56 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use auth_types::has_scope;
+
+use crate::{AuthRejection, Authenticator};
+
+// The user a request was authenticated as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedUser {
+    // The `sub` of the user's tokens, which stays the same when their email changes
+    pub user_id: String,
+    // Only known when the auth service checked the token; local validation sees just the JWT
+    pub email: Option<String>,
+    // When the token or API key expires, as a Unix timestamp; API keys may never expire
+    pub expires_at: Option<usize>,
+    // Space-separated scopes of an API key or OAuth token, which may be empty; login sessions
+    // have none
+    pub scope: Option<String>,
+    // The user's roles, as of their login for session tokens
+    pub roles: Vec<String>,
+}
+
+impl AuthenticatedUser {
+    // Login sessions may do anything their user can; API keys and OAuth tokens only what
+    // they were granted
+    pub fn has_scope(&self, wanted: &str) -> bool {
+        self.scope.is_none() || has_scope(self.scope.as_deref(), wanted)
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+// Reuses the result of `AuthLayer` when it ran; otherwise authenticates with an
+// `Authenticator` added as an `Extension`
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthenticatedUser {
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(user.clone());
+        }
+
+        let authenticator = parts
+            .extensions
+            .get::<Authenticator>()
+            .cloned()
+            .ok_or(AuthRejection::NotConfigured)?;
+        let user = authenticator.authenticate(&parts.headers).await?;
+        parts.extensions.insert(user.clone());
+
+        Ok(user)
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use auth_client::AuthClient;
+use auth_types::{TokenUse, JWT_COOKIE_NAME};
+use axum::http::{header::AUTHORIZATION, HeaderMap, StatusCode};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+
+#[cfg(feature = "local-validation")]
+use crate::LocalValidation;
+use crate::{AuthRejection, AuthenticatedUser};
+
+// How long an accepted token is trusted without asking again
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+// Beyond this many entries, expired ones are dropped and, failing that, the whole cache
+const MAX_CACHED_TOKENS: usize = 10_000;
+
+// How tokens are checked
+#[derive(Debug, Clone)]
+pub enum TokenValidator {
+    // Asks the auth service's `/verify-token`, so banned (logged out) tokens are rejected and
+    // personal API keys accepted
+    Remote(AuthClient),
+    // Checks tokens without a network call, and so without the auth service's revocation
+    // checks; only built with the `local-validation` feature
+    #[cfg(feature = "local-validation")]
+    Local(LocalValidation),
+}
+
+// Authenticates requests by their `Authorization: Bearer` header or `jwt` cookie.
+// Accepted tokens are cached for `DEFAULT_CACHE_TTL`, so a logout can take that long to be
+// noticed; rejected ones are checked every time.
+#[derive(Clone)]
+pub struct Authenticator {
+    validator: Arc<TokenValidator>,
+    cache: Arc<Mutex<TokenCache>>,
+}
+
+impl Authenticator {
+    pub fn new(validator: TokenValidator) -> Self {
+        Self {
+            validator: Arc::new(validator),
+            cache: Arc::new(Mutex::new(TokenCache::new(DEFAULT_CACHE_TTL))),
+        }
+    }
+
+    pub fn remote(client: AuthClient) -> Self {
+        Self::new(TokenValidator::Remote(client))
+    }
+
+    #[cfg(feature = "local-validation")]
+    pub fn local(validation: LocalValidation) -> Self {
+        Self::new(TokenValidator::Local(validation))
+    }
+
+    // `Duration::ZERO` disables caching
+    pub fn with_cache_ttl(self, ttl: Duration) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(TokenCache::new(ttl))),
+            ..self
+        }
+    }
+
+    pub async fn authenticate(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<AuthenticatedUser, AuthRejection> {
+        let token = token_from_headers(headers).ok_or(AuthRejection::MissingToken)?;
+        self.validate(&token).await
+    }
+
+    #[tracing::instrument(name = "Authenticate token", skip_all)]
+    pub async fn validate(
+        &self,
+        token: &Secret<String>,
+    ) -> Result<AuthenticatedUser, AuthRejection> {
+        if let Some(user) = self
+            .cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(token.expose_secret()))
+        {
+            return Ok(user);
+        }
+
+        let user = match self.validator.as_ref() {
+            TokenValidator::Remote(client) => {
+                let verified = client
+                    .verify_token(token)
+                    .await
+                    .map_err(|e| match e.status() {
+                        Some(StatusCode::UNAUTHORIZED) => AuthRejection::InvalidToken,
+                        _ => AuthRejection::Unavailable(e),
+                    })?;
+                // A client's own token from the `client_credentials` grant names no user
+                if verified.token_use == TokenUse::Service {
+                    return Err(AuthRejection::InvalidToken);
+                }
+                AuthenticatedUser {
+                    user_id: verified.sub,
+                    email: verified.email,
+                    expires_at: verified.exp,
+                    scope: verified.scope,
+                    roles: verified.roles,
+                }
+            }
+            #[cfg(feature = "local-validation")]
+            TokenValidator::Local(validation) => validation.validate(token)?,
+        };
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(token.expose_secret(), user.clone());
+        }
+
+        Ok(user)
+    }
+}
+
+// `Authorization: Bearer` takes precedence over the `jwt` cookie when both are sent
+pub fn token_from_headers(headers: &HeaderMap) -> Option<Secret<String>> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_owned());
+
+    bearer
+        .or_else(|| {
+            CookieJar::from_headers(headers)
+                .get(JWT_COOKIE_NAME)
+                .map(|cookie| cookie.value().to_owned())
+        })
+        .filter(|token| !token.is_empty())
+        .map(Secret::new)
+}
+
+struct TokenCache {
+    ttl: Duration,
+    entries: HashMap<String, CachedUser>,
+}
+
+struct CachedUser {
+    user: AuthenticatedUser,
+    valid_until: Instant,
+}
+
+impl TokenCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&self, token: &str) -> Option<AuthenticatedUser> {
+        self.entries
+            .get(token)
+            .filter(|entry| entry.valid_until > Instant::now())
+            .map(|entry| entry.user.clone())
+    }
+
+    fn insert(&mut self, token: &str, user: AuthenticatedUser) {
+        let now = Instant::now();
+        let unix_now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        // Never trust a token past its own expiry
+        let ttl = match user.expires_at {
+            Some(expires_at) => self.ttl.min(Duration::from_secs(
+                (expires_at as u64).saturating_sub(unix_now),
+            )),
+            None => self.ttl,
+        };
+        if ttl.is_zero() {
+            return;
+        }
+
+        if self.entries.len() >= MAX_CACHED_TOKENS {
+            self.entries.retain(|_, entry| entry.valid_until > now);
+            if self.entries.len() >= MAX_CACHED_TOKENS {
+                self.entries.clear();
+            }
+        }
+        self.entries.insert(
+            token.to_owned(),
+            CachedUser {
+                user,
+                valid_until: now + ttl,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::COOKIE, HeaderValue};
+
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn user(expires_in: u64) -> AuthenticatedUser {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        AuthenticatedUser {
+            user_id: "4f0c1d5e-6a8b-4c2d-9e3f-1a2b3c4d5e6f".to_owned(),
+            email: None,
+            expires_at: Some((now.as_secs() + expires_in) as usize),
+            scope: None,
+            roles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_bearer_token_takes_precedence_over_cookie() {
+        let token = |headers: HeaderMap| {
+            token_from_headers(&headers).map(|token| token.expose_secret().to_owned())
+        };
+
+        assert_eq!(
+            token(headers(&[
+                (AUTHORIZATION.as_str(), "Bearer from-header"),
+                (COOKIE.as_str(), "theme=dark; jwt=from-cookie"),
+            ])),
+            Some("from-header".to_owned())
+        );
+        assert_eq!(
+            token(headers(&[(COOKIE.as_str(), "theme=dark; jwt=from-cookie")])),
+            Some("from-cookie".to_owned())
+        );
+        assert_eq!(
+            token(headers(&[(AUTHORIZATION.as_str(), "Basic abc")])),
+            None
+        );
+        assert_eq!(token(headers(&[(AUTHORIZATION.as_str(), "Bearer ")])), None);
+        assert_eq!(token(HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_cached_tokens_expire_with_the_cache_or_the_token() {
+        let mut cache = TokenCache::new(Duration::from_secs(60));
+        cache.insert("fresh", user(600));
+        cache.insert("expiring", user(0));
+        assert_eq!(cache.get("fresh"), Some(user(600)));
+        assert_eq!(cache.get("expiring"), None);
+
+        let never_expiring = AuthenticatedUser {
+            expires_at: None,
+            ..user(0)
+        };
+        cache.insert("api-key", never_expiring.clone());
+        assert_eq!(cache.get("api-key"), Some(never_expiring));
+
+        let mut cache = TokenCache::new(Duration::ZERO);
+        cache.insert("fresh", user(600));
+        assert_eq!(cache.get("fresh"), None);
+    }
+
+    #[test]
+    fn test_sessions_have_every_scope() {
+        let session = user(600);
+        let api_key = AuthenticatedUser {
+            scope: Some("reports:read deploy".to_owned()),
+            ..user(600)
+        };
+        let unscoped_oauth_token = AuthenticatedUser {
+            scope: Some(String::new()),
+            ..user(600)
+        };
+
+        assert!(session.has_scope("deploy"));
+        assert!(api_key.has_scope("deploy"));
+        assert!(!api_key.has_scope("reports:write"));
+        assert!(!unscoped_oauth_token.has_scope("deploy"));
+    }
+}
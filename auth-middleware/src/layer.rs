@@ -0,0 +1,98 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    response::{IntoResponse, Response},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{AuthRejection, Authenticator};
+
+// Rejects requests without a valid token before they reach the inner service, and makes the
+// `AuthenticatedUser` available to handlers
+#[derive(Clone)]
+pub struct AuthLayer {
+    authenticator: Authenticator,
+    required_role: Option<String>,
+}
+
+impl AuthLayer {
+    pub fn new(authenticator: Authenticator) -> Self {
+        Self {
+            authenticator,
+            required_role: None,
+        }
+    }
+
+    // Also rejects users without `role` with a 403. Session tokens carry the roles the user
+    // had at login, so a newly assigned role takes effect at their next login.
+    pub fn with_required_role(self, role: impl Into<String>) -> Self {
+        Self {
+            required_role: Some(role.into()),
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            authenticator: self.authenticator.clone(),
+            required_role: self.required_role.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    authenticator: Authenticator,
+    required_role: Option<String>,
+}
+
+impl<S> Service<Request> for AuthService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // The service that was polled ready handles the request; the clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authenticator = self.authenticator.clone();
+        let required_role = self.required_role.clone();
+
+        Box::pin(async move {
+            let result = authenticator
+                .authenticate(request.headers())
+                .await
+                .and_then(|user| match required_role {
+                    Some(role) if !user.has_role(&role) => Err(AuthRejection::Forbidden),
+                    _ => Ok(user),
+                });
+            match result {
+                Ok(user) => {
+                    request.extensions_mut().insert(user);
+                    inner.call(request).await
+                }
+                Err(rejection) => Ok(rejection.into_response()),
+            }
+        })
+    }
+}
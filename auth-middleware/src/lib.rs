@@ -0,0 +1,13 @@
+mod authenticated_user;
+mod authenticator;
+mod layer;
+#[cfg(feature = "local-validation")]
+mod local_validation;
+mod rejection;
+
+pub use authenticated_user::*;
+pub use authenticator::*;
+pub use layer::*;
+#[cfg(feature = "local-validation")]
+pub use local_validation::*;
+pub use rejection::*;
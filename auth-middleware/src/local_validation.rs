@@ -0,0 +1,47 @@
+use auth_types::Claims;
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{AuthRejection, AuthenticatedUser};
+
+// Checks a token's signature, expiry and type with the auth service's JWT secret, without a
+// network call. Only the token itself is seen, so until they expire these are still accepted:
+// - tokens banned by a logout
+// - sessions revoked by a password change, an admin, or the user being locked or deleted
+// - roles removed from the user since they logged in
+// API keys are not JWTs, so they are always rejected. Prefer `Authenticator::remote` unless
+// the network call cannot be afforded.
+#[derive(Debug, Clone)]
+pub struct LocalValidation {
+    pub jwt_secret: Secret<String>,
+}
+
+impl LocalValidation {
+    pub fn new(jwt_secret: Secret<String>) -> Self {
+        Self { jwt_secret }
+    }
+
+    pub(crate) fn validate(
+        &self,
+        token: &Secret<String>,
+    ) -> Result<AuthenticatedUser, AuthRejection> {
+        let claims = decode::<Claims>(
+            token.expose_secret(),
+            &DecodingKey::from_secret(self.jwt_secret.expose_secret().as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|_| AuthRejection::InvalidToken)?
+        .claims;
+        if claims.is_service() {
+            return Err(AuthRejection::InvalidToken);
+        }
+
+        Ok(AuthenticatedUser {
+            user_id: claims.sub,
+            email: None,
+            expires_at: Some(claims.exp),
+            scope: claims.scope,
+            roles: claims.roles,
+        })
+    }
+}
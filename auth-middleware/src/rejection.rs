@@ -0,0 +1,64 @@
+use auth_client::AuthClientError;
+use auth_types::ErrorResponse;
+use axum::{
+    http::{header::WWW_AUTHENTICATE, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AuthRejection {
+    #[error("Missing token")]
+    MissingToken,
+    #[error("Invalid token")]
+    InvalidToken,
+    // Authenticated, but without the role the route requires
+    #[error("Forbidden")]
+    Forbidden,
+    // The auth service could not be asked, so the token is neither accepted nor rejected
+    #[error("Auth service unavailable")]
+    Unavailable(#[source] AuthClientError),
+    // `AuthenticatedUser` was extracted without an `AuthLayer` or `Authenticator` extension
+    #[error("Authenticator not configured")]
+    NotConfigured,
+}
+
+impl AuthRejection {
+    // Same codes as the auth service's own errors
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthRejection::MissingToken => "missing_token",
+            AuthRejection::InvalidToken => "invalid_token",
+            AuthRejection::Forbidden => "forbidden",
+            AuthRejection::Unavailable(_) | AuthRejection::NotConfigured => "unexpected_error",
+        }
+    }
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            AuthRejection::MissingToken | AuthRejection::InvalidToken => StatusCode::UNAUTHORIZED,
+            AuthRejection::Forbidden => StatusCode::FORBIDDEN,
+            AuthRejection::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AuthRejection::NotConfigured => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        if status.is_server_error() {
+            tracing::error!(error = ?self, "failed to authenticate request");
+        }
+
+        let body = Json(ErrorResponse {
+            error: self.to_string(),
+            code: self.code().to_owned(),
+            errors: Vec::new(),
+        });
+
+        match status {
+            StatusCode::UNAUTHORIZED => {
+                (status, [(WWW_AUTHENTICATE, "Bearer")], body).into_response()
+            }
+            _ => (status, body).into_response(),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::time::Duration;
+
+use auth_client::{AuthClient, ErrorResponse};
+use auth_middleware::{AuthLayer, Authenticator, LocalValidation};
+use auth_service::{
+    config::AuthSettings,
+    utils::{generate_service_token, test, JWT_COOKIE_NAME},
+};
+use reqwest::{header::COOKIE, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::helpers::{spawn_protected_app, whoami_router, TestAuthService};
+
+#[tokio::test]
+async fn accepts_bearer_and_cookie_tokens() {
+    let auth = TestAuthService::new().await;
+    let (user_id, token) = auth.logged_in_user().await;
+    let url = spawn_protected_app(
+        whoami_router().route_layer(AuthLayer::new(Authenticator::remote(auth.client.clone()))),
+    )
+    .await;
+    let http_client = reqwest::Client::new();
+
+    let response = http_client
+        .get(&url)
+        .bearer_auth(token.expose_secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), user_id);
+
+    let response = http_client
+        .get(&url)
+        .header(
+            COOKIE,
+            format!("{}={}", JWT_COOKIE_NAME, token.expose_secret()),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), user_id);
+}
+
+#[tokio::test]
+async fn rejects_missing_and_invalid_tokens_with_401() {
+    let auth = TestAuthService::new().await;
+    let url = spawn_protected_app(
+        whoami_router().route_layer(AuthLayer::new(Authenticator::remote(auth.client.clone()))),
+    )
+    .await;
+    let http_client = reqwest::Client::new();
+
+    let response = http_client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+    let body: ErrorResponse = response.json().await.unwrap();
+    assert_eq!(body.code, "missing_token");
+
+    let response = http_client
+        .get(&url)
+        .bearer_auth("invalid")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: ErrorResponse = response.json().await.unwrap();
+    assert_eq!(body.code, "invalid_token");
+}
+
+#[tokio::test]
+async fn logged_out_tokens_are_accepted_only_while_cached() {
+    let auth = TestAuthService::new().await;
+    let (_, token) = auth.logged_in_user().await;
+    let cached = spawn_protected_app(
+        whoami_router().route_layer(AuthLayer::new(Authenticator::remote(auth.client.clone()))),
+    )
+    .await;
+    let uncached = spawn_protected_app(whoami_router().route_layer(AuthLayer::new(
+        Authenticator::remote(auth.client.clone()).with_cache_ttl(Duration::ZERO),
+    )))
+    .await;
+    let http_client = reqwest::Client::new();
+    for url in [&cached, &uncached] {
+        let response = http_client
+            .get(url)
+            .bearer_auth(token.expose_secret())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    auth.client.logout(&token).await.unwrap();
+
+    let status = |url: String| {
+        let request = http_client.get(url).bearer_auth(token.expose_secret());
+        async move { request.send().await.unwrap().status() }
+    };
+    assert_eq!(status(cached).await, StatusCode::OK);
+    assert_eq!(status(uncached).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn local_validation_needs_the_shared_secret() {
+    let auth = TestAuthService::new().await;
+    let (user_id, token) = auth.logged_in_user().await;
+    let shared = spawn_protected_app(whoami_router().route_layer(AuthLayer::new(
+        Authenticator::local(LocalValidation::new(Secret::new(
+            test::JWT_SECRET.to_owned(),
+        ))),
+    )))
+    .await;
+    let other = spawn_protected_app(whoami_router().route_layer(AuthLayer::new(
+        Authenticator::local(LocalValidation::new(Secret::new(
+            "another-secret".to_owned(),
+        ))),
+    )))
+    .await;
+    let http_client = reqwest::Client::new();
+
+    let response = http_client
+        .get(&shared)
+        .bearer_auth(token.expose_secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), user_id);
+
+    let response = http_client
+        .get(&other)
+        .bearer_auth(token.expose_secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn local_validation_accepts_logged_out_tokens() {
+    let auth = TestAuthService::new().await;
+    let (user_id, token) = auth.logged_in_user().await;
+    let local = spawn_protected_app(
+        whoami_router().route_layer(AuthLayer::new(
+            Authenticator::local(LocalValidation::new(Secret::new(
+                test::JWT_SECRET.to_owned(),
+            )))
+            .with_cache_ttl(Duration::ZERO),
+        )),
+    )
+    .await;
+
+    auth.client.logout(&token).await.unwrap();
+
+    // The ban is only known to the auth service
+    let response = reqwest::Client::new()
+        .get(&local)
+        .bearer_auth(token.expose_secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), user_id);
+}
+
+#[tokio::test]
+async fn service_tokens_are_not_users() {
+    let auth = TestAuthService::new().await;
+    let settings = AuthSettings {
+        jwt_secret: Secret::new(test::JWT_SECRET.to_owned()),
+    };
+    let token = generate_service_token("nightly-reports".to_owned(), None, &settings).unwrap();
+    let remote = spawn_protected_app(
+        whoami_router().route_layer(AuthLayer::new(Authenticator::remote(auth.client.clone()))),
+    )
+    .await;
+    let local = spawn_protected_app(whoami_router().route_layer(AuthLayer::new(
+        Authenticator::local(LocalValidation::new(Secret::new(
+            test::JWT_SECRET.to_owned(),
+        ))),
+    )))
+    .await;
+
+    for url in [remote, local] {
+        let response = reqwest::Client::new()
+            .get(&url)
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn api_keys_are_accepted_by_remote_validation_only() {
+    let auth = TestAuthService::new().await;
+    let (user_id, token) = auth.logged_in_user().await;
+    let key = auth.api_key(&token, &["deploy"]).await;
+    let remote = spawn_protected_app(
+        whoami_router().route_layer(AuthLayer::new(Authenticator::remote(auth.client.clone()))),
+    )
+    .await;
+    let local = spawn_protected_app(whoami_router().route_layer(AuthLayer::new(
+        Authenticator::local(LocalValidation::new(Secret::new(
+            test::JWT_SECRET.to_owned(),
+        ))),
+    )))
+    .await;
+    let http_client = reqwest::Client::new();
+
+    let response = http_client
+        .get(&remote)
+        .bearer_auth(key.expose_secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), user_id);
+
+    let user = Authenticator::remote(auth.client.clone())
+        .validate(&key)
+        .await
+        .unwrap();
+    assert_eq!(user.user_id, user_id);
+    assert!(user.email.is_some());
+    assert_eq!(user.expires_at, None);
+    assert!(user.has_scope("deploy"));
+    assert!(!user.has_scope("admin"));
+
+    let response = http_client
+        .get(&local)
+        .bearer_auth(key.expose_secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn required_roles_are_checked_after_authentication() {
+    let auth = TestAuthService::new().await;
+    let (admin_id, admin_token) = auth.logged_in_user_with_roles(&["admin"]).await;
+    let (_, user_token) = auth.logged_in_user().await;
+    let remote = spawn_protected_app(whoami_router().route_layer(
+        AuthLayer::new(Authenticator::remote(auth.client.clone())).with_required_role("admin"),
+    ))
+    .await;
+    let local = spawn_protected_app(
+        whoami_router().route_layer(
+            AuthLayer::new(Authenticator::local(LocalValidation::new(Secret::new(
+                test::JWT_SECRET.to_owned(),
+            ))))
+            .with_required_role("admin"),
+        ),
+    )
+    .await;
+    let http_client = reqwest::Client::new();
+
+    for url in [remote, local] {
+        let response = http_client
+            .get(&url)
+            .bearer_auth(admin_token.expose_secret())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), admin_id);
+
+        let response = http_client
+            .get(&url)
+            .bearer_auth(user_token.expose_secret())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "forbidden");
+
+        let response = http_client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn unreachable_auth_service_is_not_a_401() {
+    let auth = TestAuthService::new().await;
+    let (_, token) = auth.logged_in_user().await;
+    // Nothing listens on the discard port
+    let authenticator = Authenticator::remote(AuthClient::new("http://127.0.0.1:9"));
+    let url = spawn_protected_app(whoami_router().route_layer(AuthLayer::new(authenticator))).await;
+
+    let response = reqwest::Client::new()
+        .get(&url)
+        .bearer_auth(token.expose_secret())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -0,0 +1,43 @@
+use auth_middleware::Authenticator;
+use axum::Extension;
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+
+use crate::helpers::{spawn_protected_app, whoami_router, TestAuthService};
+
+#[tokio::test]
+async fn extractor_authenticates_without_the_layer() {
+    let auth = TestAuthService::new().await;
+    let (user_id, token) = auth.logged_in_user().await;
+    let url = spawn_protected_app(
+        whoami_router().layer(Extension(Authenticator::remote(auth.client.clone()))),
+    )
+    .await;
+    let http_client = reqwest::Client::new();
+
+    let response = http_client
+        .get(&url)
+        .bearer_auth(token.expose_secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), user_id);
+
+    let response = http_client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn extractor_without_an_authenticator_is_a_server_error() {
+    let url = spawn_protected_app(whoami_router()).await;
+
+    let response = reqwest::Client::new()
+        .get(&url)
+        .bearer_auth("token")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use auth_client::{AuthClient, LoginOutcome, LoginRequest, SignupRequest, TokenDelivery};
+use auth_middleware::AuthenticatedUser;
+use auth_service::{
+    app_state::{AppState, RoleStoreType, UserStoreType},
+    config::{ApplicationSettings, AuthSettings, Settings},
+    domain::{Email, Role},
+    routes::CreateApiKeyResponse,
+    services::{HashmapTwoFACodeStore, HashmapUserStore, HashsetBannedTokenStore, MockEmailClient},
+    utils::{test, ShutdownHandle},
+    Application,
+};
+use axum::{routing::get, Router};
+use secrecy::{ExposeSecret, Secret};
+use tokio::{net::TcpListener, sync::RwLock};
+use uuid::Uuid;
+
+// An auth service running in-process on in-memory stores
+pub struct TestAuthService {
+    pub client: AuthClient,
+    user_store: UserStoreType,
+    role_store: RoleStoreType,
+    shutdown_handle: ShutdownHandle,
+}
+
+impl TestAuthService {
+    pub async fn new() -> Self {
+        let settings = Settings {
+            application: ApplicationSettings {
+                address: test::APP_ADDRESS.to_owned(),
+                shutdown_timeout_milliseconds: test::SHUTDOWN_TIMEOUT.as_millis() as u64,
+                ..ApplicationSettings::default()
+            },
+            auth: AuthSettings {
+                jwt_secret: Secret::new(test::JWT_SECRET.to_owned()),
+            },
+            ..Settings::default()
+        };
+        let app_state = AppState::new(
+            Arc::new(RwLock::new(HashmapUserStore::default())),
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(RwLock::new(MockEmailClient::default())),
+            settings.auth.clone(),
+        );
+        let user_store = app_state.user_store.clone();
+        let role_store = app_state.role_store.clone();
+
+        let app = Application::build(app_state, &settings)
+            .await
+            .expect("Failed to build app");
+        let client = AuthClient::new(format!("http://{}", app.address));
+        let shutdown_handle = app.shutdown_handle();
+        tokio::spawn(app.run());
+
+        Self {
+            client,
+            user_store,
+            role_store,
+            shutdown_handle,
+        }
+    }
+
+    // Signs up a user without 2FA and returns their id and a fresh token
+    pub async fn logged_in_user(&self) -> (String, Secret<String>) {
+        self.logged_in_user_with_roles(&[]).await
+    }
+
+    // As `logged_in_user`, assigning `roles` before the login so the token carries them
+    pub async fn logged_in_user_with_roles(&self, roles: &[&str]) -> (String, Secret<String>) {
+        let email = format!("{}@example.com", Uuid::new_v4());
+        let password = "Asdf1234@";
+        self.client
+            .signup(&SignupRequest {
+                email: Secret::new(email.clone()),
+                password: Secret::new(password.to_owned()),
+                requires_2fa: false,
+            })
+            .await
+            .unwrap();
+
+        let parsed = Email::parse(Secret::new(email.clone())).unwrap();
+        let user_id = self.user_store.read().await.get_user(&parsed).await.unwrap().id;
+        let mut role_store = self.role_store.write().await;
+        for role in roles {
+            // Shared by every test's users, so it may exist already
+            let _ = role_store
+                .add_role(Role {
+                    name: role.to_string(),
+                    permissions: Vec::new(),
+                })
+                .await;
+            role_store.assign_role(user_id, role).await.unwrap();
+        }
+        drop(role_store);
+
+        let outcome = self
+            .client
+            .login(&LoginRequest {
+                email: Secret::new(email.clone()),
+                password: Secret::new(password.to_owned()),
+                token_delivery: TokenDelivery::Body,
+            })
+            .await
+            .unwrap();
+        match outcome {
+            LoginOutcome::Authenticated(token) => {
+                let user_id = self.client.verify_token(&token).await.unwrap().sub;
+                (user_id, token)
+            }
+            LoginOutcome::TwoFactorRequired(_) => panic!("Expected to be authenticated"),
+        }
+    }
+
+    // Creates a personal API key with `scopes` from a login session
+    pub async fn api_key(&self, token: &Secret<String>, scopes: &[&str]) -> Secret<String> {
+        let response = reqwest::Client::new()
+            .post(format!("{}/api-keys", self.client.base_url()))
+            .bearer_auth(token.expose_secret())
+            .json(&serde_json::json!({ "name": "CI", "scopes": scopes }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let body: CreateApiKeyResponse = response.json().await.unwrap();
+
+        Secret::new(body.key)
+    }
+}
+
+impl Drop for TestAuthService {
+    fn drop(&mut self) {
+        self.shutdown_handle.shutdown();
+    }
+}
+
+// Echoes the authenticated user's id
+pub async fn whoami(user: AuthenticatedUser) -> String {
+    user.user_id
+}
+
+pub fn whoami_router() -> Router {
+    Router::new().route("/whoami", get(whoami))
+}
+
+// Serves `router` on a random port and returns its `/whoami` URL
+pub async fn spawn_protected_app(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    format!("http://{}/whoami", address)
+}
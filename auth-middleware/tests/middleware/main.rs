@@ -0,0 +1,3 @@
+mod auth_layer;
+mod extractor;
+mod helpers;
@@ -1,6 +1,7 @@
 const loginSection = document.getElementById("login-section");
 const twoFASection = document.getElementById("2fa-section");
 const signupSection = document.getElementById("signup-section");
+const consentSection = document.getElementById("consent-section");
 
 const signupLink = document.getElementById("signup-link");
 const twoFALoginLink = document.getElementById("2fa-login-link");
@@ -66,7 +67,7 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            loggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -145,10 +146,10 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
+            loggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -161,4 +162,40 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+// -----------------------------------------------------
+// Served at /oauth/authorize, the page asks a signed-in user to approve the client
+
+const oauthClient = document.body.dataset.oauthClient;
+const consentForm = document.getElementById("consent-form");
+
+function loggedIn() {
+    if (oauthClient === undefined) {
+        alert("You have successfully logged in.");
+        return;
+    }
+
+    loginSection.style.display = "none";
+    twoFASection.style.display = "none";
+    signupSection.style.display = "none";
+    consentSection.style.display = "block";
+}
+
+if (oauthClient !== undefined) {
+    document.getElementById("consent-client").textContent = oauthClient;
+    // The authorization request is sent back with the decision
+    new URLSearchParams(window.location.search).forEach((value, name) => {
+        const input = document.createElement("input");
+        input.type = "hidden";
+        input.name = name;
+        input.value = value;
+        consentForm.appendChild(input);
+    });
+
+    // Skip the login form when the session cookie is still valid
+    fetch('/verify-token', { method: 'POST' }).then(response => {
+        if (response.ok) {
+            loggedIn();
+        }
+    });
+}
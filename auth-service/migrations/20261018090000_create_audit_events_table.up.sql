@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS audit_events(
+   id UUID NOT NULL PRIMARY KEY,
+   occurred_at TIMESTAMPTZ NOT NULL,
+   event_type TEXT NOT NULL,
+   email TEXT,
+   event JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events (occurred_at DESC);
+CREATE INDEX IF NOT EXISTS audit_events_email_idx ON audit_events (email, occurred_at DESC);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS oauth_clients(
+   client_id TEXT NOT NULL PRIMARY KEY,
+   client_name TEXT NOT NULL,
+   -- SHA-256 of the secret; public clients have none
+   client_secret_hash TEXT,
+   redirect_uris TEXT[] NOT NULL DEFAULT '{}',
+   scopes TEXT[] NOT NULL DEFAULT '{}',
+   created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
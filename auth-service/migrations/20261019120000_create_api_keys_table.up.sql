@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS api_keys(
+   id UUID NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   name TEXT NOT NULL,
+   prefix TEXT NOT NULL UNIQUE,
+   -- SHA-256 of the whole key, which is never stored
+   key_hash TEXT NOT NULL,
+   scopes TEXT[] NOT NULL DEFAULT '{}',
+   created_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ,
+   last_used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_email_idx ON api_keys (email, created_at);
@@ -0,0 +1,6 @@
+ALTER TABLE user_roles DROP CONSTRAINT user_roles_email_fkey;
+ALTER TABLE users ADD CONSTRAINT users_pkey PRIMARY KEY (email);
+ALTER TABLE users DROP CONSTRAINT users_email_key;
+ALTER TABLE user_roles
+   ADD CONSTRAINT user_roles_email_fkey FOREIGN KEY (email)
+   REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
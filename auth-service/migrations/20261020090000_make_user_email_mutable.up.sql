@@ -0,0 +1,9 @@
+-- Email is a unique attribute users can change rather than the key of their account;
+-- role assignments still follow it through the cascading foreign key
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+
+ALTER TABLE user_roles DROP CONSTRAINT user_roles_email_fkey;
+ALTER TABLE users DROP CONSTRAINT users_pkey;
+ALTER TABLE user_roles
+   ADD CONSTRAINT user_roles_email_fkey FOREIGN KEY (email)
+   REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
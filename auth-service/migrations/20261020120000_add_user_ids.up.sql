@@ -0,0 +1,4 @@
+-- A stable key for each account, used as the `sub` of its tokens so they neither expose
+-- nor depend on its email
+ALTER TABLE users ADD COLUMN IF NOT EXISTS id UUID NOT NULL DEFAULT gen_random_uuid();
+ALTER TABLE users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
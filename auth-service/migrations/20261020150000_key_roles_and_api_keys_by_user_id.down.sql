@@ -0,0 +1,16 @@
+ALTER TABLE api_keys ADD COLUMN email TEXT;
+UPDATE api_keys SET email = users.email FROM users WHERE users.id = api_keys.user_id;
+DROP INDEX IF EXISTS api_keys_user_id_idx;
+ALTER TABLE api_keys DROP COLUMN user_id;
+ALTER TABLE api_keys ALTER COLUMN email SET NOT NULL;
+CREATE INDEX IF NOT EXISTS api_keys_email_idx ON api_keys (email, created_at);
+
+ALTER TABLE user_roles ADD COLUMN email TEXT;
+UPDATE user_roles SET email = users.email FROM users WHERE users.id = user_roles.user_id;
+ALTER TABLE user_roles DROP CONSTRAINT user_roles_pkey;
+ALTER TABLE user_roles DROP COLUMN user_id;
+ALTER TABLE user_roles ALTER COLUMN email SET NOT NULL;
+ALTER TABLE user_roles
+   ADD CONSTRAINT user_roles_email_fkey FOREIGN KEY (email)
+   REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE user_roles ADD CONSTRAINT user_roles_pkey PRIMARY KEY (email, role);
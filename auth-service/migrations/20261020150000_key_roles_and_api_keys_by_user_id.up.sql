@@ -0,0 +1,23 @@
+-- Role assignments and API keys belong to an account rather than to its current email,
+-- so they go with it when it is deleted and need no update when the email changes
+ALTER TABLE user_roles ADD COLUMN user_id UUID;
+UPDATE user_roles SET user_id = users.id FROM users WHERE users.email = user_roles.email;
+ALTER TABLE user_roles DROP CONSTRAINT user_roles_pkey;
+ALTER TABLE user_roles DROP COLUMN email;
+ALTER TABLE user_roles ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE user_roles
+   ADD CONSTRAINT user_roles_user_id_fkey FOREIGN KEY (user_id)
+   REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE user_roles ADD CONSTRAINT user_roles_pkey PRIMARY KEY (user_id, role);
+
+-- Keys whose owner no longer exists could not be used anyway
+ALTER TABLE api_keys ADD COLUMN user_id UUID;
+UPDATE api_keys SET user_id = users.id FROM users WHERE users.email = api_keys.email;
+DELETE FROM api_keys WHERE user_id IS NULL;
+DROP INDEX IF EXISTS api_keys_email_idx;
+ALTER TABLE api_keys DROP COLUMN email;
+ALTER TABLE api_keys ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE api_keys
+   ADD CONSTRAINT api_keys_user_id_fkey FOREIGN KEY (user_id)
+   REFERENCES users(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS api_keys_user_id_idx ON api_keys (user_id, created_at);
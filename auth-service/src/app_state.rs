@@ -1,12 +1,29 @@
+use sqlx::PgPool;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::{
+    config::{AuthSettings, HealthSettings},
+    domain::{
+        ApiKeyStore, AuditEvent, AuditRecord, AuditSink, BannedTokenStore, EmailClient,
+        OAuthClientStore, OAuthGrantStore, RoleStore, TwoFACodeStore, UserStore,
+    },
+    services::{
+        HashmapApiKeyStore, HashmapOAuthClientStore, HashmapOAuthGrantStore, HashmapRoleStore,
+        MemoryAuditSink,
+    },
+    utils::OidcProvider,
+};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<RwLock<dyn EmailClient + Send + Sync>>;
+pub type AuditSinkType = Arc<RwLock<dyn AuditSink + Send + Sync>>;
+pub type OAuthClientStoreType = Arc<RwLock<dyn OAuthClientStore + Send + Sync>>;
+pub type OAuthGrantStoreType = Arc<RwLock<dyn OAuthGrantStore + Send + Sync>>;
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore + Send + Sync>>;
+pub type RoleStoreType = Arc<RwLock<dyn RoleStore + Send + Sync>>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -14,6 +31,17 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client_type: EmailClientType,
+    pub audit_sink: AuditSinkType,
+    pub oauth_client_store: OAuthClientStoreType,
+    pub oauth_grant_store: OAuthGrantStoreType,
+    pub api_key_store: ApiKeyStoreType,
+    pub role_store: RoleStoreType,
+    // Issues ID tokens and serves discovery and /userinfo; OpenID Connect is off without it
+    pub oidc: Option<Arc<OidcProvider>>,
+    pub auth_settings: AuthSettings,
+    pub health_settings: HealthSettings,
+    // Shared by the Postgres-backed stores; kept here so it can be closed on shutdown
+    pub pg_pool: Option<PgPool>,
 }
 
 impl AppState {
@@ -22,12 +50,86 @@ impl AppState {
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client_type: EmailClientType,
+        auth_settings: AuthSettings,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client_type,
+            audit_sink: Arc::new(RwLock::new(MemoryAuditSink::default())),
+            // No clients, so the /oauth endpoints reject every caller
+            oauth_client_store: Arc::new(RwLock::new(HashmapOAuthClientStore::default())),
+            oauth_grant_store: Arc::new(RwLock::new(HashmapOAuthGrantStore::default())),
+            api_key_store: Arc::new(RwLock::new(HashmapApiKeyStore::default())),
+            role_store: Arc::new(RwLock::new(HashmapRoleStore::default())),
+            oidc: None,
+            auth_settings,
+            health_settings: HealthSettings::default(),
+            pg_pool: None,
+        }
+    }
+
+    pub fn with_health_settings(mut self, health_settings: HealthSettings) -> Self {
+        self.health_settings = health_settings;
+        self
+    }
+
+    pub fn with_audit_sink(mut self, audit_sink: AuditSinkType) -> Self {
+        self.audit_sink = audit_sink;
+        self
+    }
+
+    pub fn with_oauth_client_store(mut self, oauth_client_store: OAuthClientStoreType) -> Self {
+        self.oauth_client_store = oauth_client_store;
+        self
+    }
+
+    pub fn with_oauth_grant_store(mut self, oauth_grant_store: OAuthGrantStoreType) -> Self {
+        self.oauth_grant_store = oauth_grant_store;
+        self
+    }
+
+    pub fn with_api_key_store(mut self, api_key_store: ApiKeyStoreType) -> Self {
+        self.api_key_store = api_key_store;
+        self
+    }
+
+    pub fn with_role_store(mut self, role_store: RoleStoreType) -> Self {
+        self.role_store = role_store;
+        self
+    }
+
+    pub fn with_oidc(mut self, oidc: OidcProvider) -> Self {
+        self.oidc = Some(Arc::new(oidc));
+        self
+    }
+
+    pub fn with_pg_pool(mut self, pg_pool: PgPool) -> Self {
+        self.pg_pool = Some(pg_pool);
+        self
+    }
+
+    // Records a security event. A failing sink is logged but never fails the request.
+    pub async fn audit(&self, event: AuditEvent) {
+        let event_type = event.event_type();
+        if let Err(e) = self
+            .audit_sink
+            .write()
+            .await
+            .record(AuditRecord::new(event))
+            .await
+        {
+            tracing::error!(error = ?e, event_type, "failed to record audit event");
+        }
+    }
+
+    // Releases the backing connections once the server has stopped handling requests.
+    // Redis connections are owned by their stores and close as the last reference is dropped.
+    pub async fn close(self) {
+        if let Some(pg_pool) = &self.pg_pool {
+            pg_pool.close().await;
+            tracing::info!("closed Postgres connection pool");
         }
     }
 }
@@ -0,0 +1,35 @@
+use std::process::ExitCode;
+
+use color_eyre::eyre::Result;
+
+use auth_service::{
+    cli::{run, Command, USAGE},
+    config::Settings,
+};
+
+// Operator tasks that should not need the HTTP API, run against the service's own stores
+#[tokio::main]
+async fn main() -> Result<ExitCode> {
+    color_eyre::install()?;
+
+    let command = match Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Ok(ExitCode::from(2));
+        }
+    };
+
+    // Validated by the commands that need more than the database or OIDC settings
+    let settings = Settings::load_unvalidated()?;
+
+    run(
+        command,
+        &settings,
+        &mut std::io::stdin().lock(),
+        &mut std::io::stdout(),
+    )
+    .await?;
+
+    Ok(ExitCode::SUCCESS)
+}
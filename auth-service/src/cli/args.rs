@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+pub const USAGE: &str = "\
+Usage: auth-admin <command>
+
+Commands:
+  user create <email> [--role <role>]... [--requires-2fa]
+                        Create a user; the password is read from stdin
+  user disable <email>  Lock a user out and end their sessions
+  user enable <email>   Unlock a user
+  role create <name> [--permission <permission>]...
+                        Create a role
+  token ban [<token>]   Ban a session token; read from stdin when omitted
+  migrate               Apply pending database migrations
+  keys rotate [--out <path>]
+                        Generate a new OIDC signing key
+  purge                 Delete expired API keys
+  help                  Show this message
+
+Settings are loaded like the service's: config/settings.toml (or AUTH_SERVICE_CONFIG),
+environment variables and *_FILE secrets.";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum UsageError {
+    #[error("unknown command '{0}'")]
+    UnknownCommand(String),
+    #[error("missing {0}")]
+    MissingArgument(&'static str),
+    #[error("unexpected argument '{0}'")]
+    UnexpectedArgument(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    CreateUser {
+        email: String,
+        roles: Vec<String>,
+        requires_2fa: bool,
+    },
+    DisableUser {
+        email: String,
+    },
+    EnableUser {
+        email: String,
+    },
+    CreateRole {
+        name: String,
+        permissions: Vec<String>,
+    },
+    // Without a token on the command line, it is read from stdin so it stays out of the
+    // shell history
+    BanToken {
+        token: Option<String>,
+    },
+    Migrate,
+    RotateKeys {
+        out: Option<PathBuf>,
+    },
+    Purge,
+    Help,
+}
+
+impl Command {
+    // `args` excludes the program name
+    pub fn parse<I>(args: I) -> Result<Self, UsageError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = Args(args.into_iter().collect::<Vec<_>>().into_iter());
+
+        let command = match args.required("command")?.as_str() {
+            "help" | "--help" | "-h" => Command::Help,
+            "user" => match args.required("user command")?.as_str() {
+                "create" => {
+                    let email = args.required("email")?;
+                    let mut roles = Vec::new();
+                    let mut requires_2fa = false;
+                    while let Some(arg) = args.0.next() {
+                        match arg.as_str() {
+                            "--role" => roles.push(args.required("role")?),
+                            "--requires-2fa" => requires_2fa = true,
+                            _ => return Err(UsageError::UnexpectedArgument(arg)),
+                        }
+                    }
+                    Command::CreateUser {
+                        email,
+                        roles,
+                        requires_2fa,
+                    }
+                }
+                "disable" => Command::DisableUser {
+                    email: args.required("email")?,
+                },
+                "enable" => Command::EnableUser {
+                    email: args.required("email")?,
+                },
+                other => return Err(UsageError::UnknownCommand(format!("user {}", other))),
+            },
+            "role" => match args.required("role command")?.as_str() {
+                "create" => {
+                    let name = args.required("role name")?;
+                    let mut permissions = Vec::new();
+                    while let Some(arg) = args.0.next() {
+                        match arg.as_str() {
+                            "--permission" => permissions.push(args.required("permission")?),
+                            _ => return Err(UsageError::UnexpectedArgument(arg)),
+                        }
+                    }
+                    Command::CreateRole { name, permissions }
+                }
+                other => return Err(UsageError::UnknownCommand(format!("role {}", other))),
+            },
+            "token" => match args.required("token command")?.as_str() {
+                "ban" => Command::BanToken {
+                    token: args.0.next(),
+                },
+                other => return Err(UsageError::UnknownCommand(format!("token {}", other))),
+            },
+            "migrate" => Command::Migrate,
+            "keys" => match args.required("keys command")?.as_str() {
+                "rotate" => {
+                    let mut out = None;
+                    while let Some(arg) = args.0.next() {
+                        match arg.as_str() {
+                            "--out" => out = Some(PathBuf::from(args.required("path")?)),
+                            _ => return Err(UsageError::UnexpectedArgument(arg)),
+                        }
+                    }
+                    Command::RotateKeys { out }
+                }
+                other => return Err(UsageError::UnknownCommand(format!("keys {}", other))),
+            },
+            "purge" => Command::Purge,
+            other => return Err(UsageError::UnknownCommand(other.to_owned())),
+        };
+
+        match args.0.next() {
+            Some(arg) => Err(UsageError::UnexpectedArgument(arg)),
+            None => Ok(command),
+        }
+    }
+}
+
+struct Args(std::vec::IntoIter<String>);
+
+impl Args {
+    fn required(&mut self, name: &'static str) -> Result<String, UsageError> {
+        self.0.next().ok_or(UsageError::MissingArgument(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, UsageError> {
+        Command::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            parse("user create admin@example.com --role admin --requires-2fa --role support"),
+            Ok(Command::CreateUser {
+                email: "admin@example.com".to_owned(),
+                roles: vec!["admin".to_owned(), "support".to_owned()],
+                requires_2fa: true,
+            })
+        );
+        assert_eq!(
+            parse("user disable a@example.com"),
+            Ok(Command::DisableUser {
+                email: "a@example.com".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("role create admin --permission users:manage"),
+            Ok(Command::CreateRole {
+                name: "admin".to_owned(),
+                permissions: vec!["users:manage".to_owned()],
+            })
+        );
+        assert_eq!(parse("token ban"), Ok(Command::BanToken { token: None }));
+        assert_eq!(
+            parse("keys rotate --out key.pem"),
+            Ok(Command::RotateKeys {
+                out: Some(PathBuf::from("key.pem"))
+            })
+        );
+        assert_eq!(parse("migrate"), Ok(Command::Migrate));
+        assert_eq!(parse("--help"), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_usage() {
+        assert_eq!(parse(""), Err(UsageError::MissingArgument("command")));
+        assert_eq!(
+            parse("user create"),
+            Err(UsageError::MissingArgument("email"))
+        );
+        assert_eq!(
+            parse("user create a@example.com --role"),
+            Err(UsageError::MissingArgument("role"))
+        );
+        assert_eq!(
+            parse("user rename a@example.com"),
+            Err(UsageError::UnknownCommand("user rename".to_owned()))
+        );
+        assert_eq!(
+            parse("migrate now"),
+            Err(UsageError::UnexpectedArgument("now".to_owned()))
+        );
+        assert_eq!(
+            parse("user create a@example.com --admin"),
+            Err(UsageError::UnexpectedArgument("--admin".to_owned()))
+        );
+    }
+}
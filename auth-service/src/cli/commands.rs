@@ -0,0 +1,530 @@
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    app_state::AppState,
+    cli::{Command, USAGE},
+    config::{build_app_state, migrate, Settings},
+    domain::{AuditEvent, Email, Password, Role, User, UserSettings, UserStoreError},
+    routes::invalid_name,
+    utils::{audit_email, end_user_sessions, remaining_lifetime, validate_token, OidcProvider},
+};
+
+// Runs `command` with the service's settings. Commands that change accounts or tokens build
+// the same stores as the service.
+pub async fn run(
+    command: Command,
+    settings: &Settings,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<()> {
+    match command {
+        Command::Help => writeln!(output, "{}", USAGE)?,
+        Command::Migrate => {
+            migrate(settings).await?;
+            writeln!(output, "Database migrations are up to date")?;
+        }
+        Command::RotateKeys { out } => rotate_keys(settings, out.as_deref(), output)?,
+        command => {
+            let state = build_app_state(settings).await?;
+            let result = execute(command, &state, input, output).await;
+            state.close().await;
+            result?;
+        }
+    }
+
+    Ok(())
+}
+
+// Runs a command that works on the stores. Secrets are read from `input`, one per line.
+pub async fn execute(
+    command: Command,
+    state: &AppState,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<()> {
+    match command {
+        Command::CreateUser {
+            email,
+            roles,
+            requires_2fa,
+        } => {
+            let email = Email::parse(Secret::new(email))?;
+            let password = Password::parse(read_secret(input, "password")?)?;
+            create_user(
+                state,
+                User::new(email.clone(), password, requires_2fa),
+                &roles,
+            )
+            .await?;
+            writeln!(output, "Created user {}", email.value())?;
+        }
+        Command::DisableUser { email } => {
+            let email = Email::parse(Secret::new(email))?;
+            let user = set_locked(state, &email, true).await?;
+            end_user_sessions(state, user.id).await?;
+            writeln!(
+                output,
+                "Disabled user {} and ended their sessions",
+                email.value()
+            )?;
+        }
+        Command::EnableUser { email } => {
+            let email = Email::parse(Secret::new(email))?;
+            set_locked(state, &email, false).await?;
+            writeln!(output, "Enabled user {}", email.value())?;
+        }
+        Command::CreateRole { name, permissions } => {
+            create_role(state, name.clone(), permissions).await?;
+            writeln!(output, "Created role {}", name)?;
+        }
+        Command::BanToken { token } => {
+            let token = match token {
+                Some(token) => Secret::new(token),
+                None => read_secret(input, "token")?,
+            };
+            let subject = ban_token(state, token).await?;
+            writeln!(output, "Banned a token of {}", subject)?;
+        }
+        Command::Purge => {
+            let purged = state
+                .api_key_store
+                .write()
+                .await
+                .purge_expired(Utc::now())
+                .await?;
+            writeln!(output, "Deleted {} expired API keys", purged)?;
+        }
+        Command::Help | Command::Migrate | Command::RotateKeys { .. } => {
+            return Err(eyre!("{:?} does not use the stores", command));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_secret(input: &mut impl BufRead, name: &str) -> Result<Secret<String>> {
+    let mut line = String::new();
+    input
+        .read_line(&mut line)
+        .wrap_err_with(|| format!("failed to read the {}", name))?;
+    let secret = line.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        return Err(eyre!("expected the {} on stdin", name));
+    }
+
+    Ok(Secret::new(secret.to_owned()))
+}
+
+async fn create_user(state: &AppState, user: User, roles: &[String]) -> Result<()> {
+    // Checked first, so a typo does not leave a user without their roles
+    let known = state.role_store.read().await.list_roles().await?;
+    if let Some(role) = roles
+        .iter()
+        .find(|role| !known.iter().any(|known| &known.name == *role))
+    {
+        return Err(eyre!("role '{}' does not exist", role));
+    }
+
+    let id = user.id;
+    let email = user.email.clone();
+    let requires_2fa = user.requires_2fa;
+    {
+        let mut user_store = state.user_store.write().await;
+        if user_store.get_user(&email).await.is_ok() {
+            return Err(eyre!("user {} already exists", email.value()));
+        }
+        user_store.add_user(user).await?;
+    }
+    state
+        .audit(AuditEvent::UserSignedUp {
+            email: email.value().to_owned(),
+            requires_2fa,
+        })
+        .await;
+
+    for role in roles {
+        state.role_store.write().await.assign_role(id, role).await?;
+        state
+            .audit(AuditEvent::RoleAssigned {
+                email: email.value().to_owned(),
+                role: role.clone(),
+            })
+            .await;
+    }
+
+    Ok(())
+}
+
+async fn set_locked(state: &AppState, email: &Email, locked: bool) -> Result<User> {
+    let settings = UserSettings {
+        locked: Some(locked),
+        ..Default::default()
+    };
+    let lookup = state.user_store.read().await.get_user(email).await;
+    let result = match lookup {
+        Ok(user) => state
+            .user_store
+            .write()
+            .await
+            .update_settings(user.id, &settings)
+            .await
+            .map(|()| user),
+        Err(e) => Err(e),
+    };
+    let user = match result {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => {
+            return Err(eyre!("user {} does not exist", email.value()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    state
+        .audit(AuditEvent::UserUpdated {
+            email: email.value().to_owned(),
+            requires_2fa: None,
+            locked: Some(locked),
+            password_reset_required: None,
+        })
+        .await;
+
+    Ok(user)
+}
+
+async fn create_role(state: &AppState, name: String, mut permissions: Vec<String>) -> Result<()> {
+    if invalid_name(&name) {
+        return Err(eyre!("role names must be a single word"));
+    }
+    if let Some(permission) = permissions.iter().find(|p| invalid_name(p)) {
+        return Err(eyre!("'{}' is not a single permission", permission));
+    }
+    permissions.sort();
+    permissions.dedup();
+
+    state
+        .role_store
+        .write()
+        .await
+        .add_role(Role {
+            name: name.clone(),
+            permissions,
+        })
+        .await?;
+    state.audit(AuditEvent::RoleCreated { role: name }).await;
+
+    Ok(())
+}
+
+// Returns the email of the token's user, or its subject. Only tokens this service would accept
+// can be banned; expired ones are rejected anyway.
+async fn ban_token(state: &AppState, token: Secret<String>) -> Result<String> {
+    let claims = validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        token.clone(),
+    )
+    .await
+    .wrap_err("the token is not one this service accepts")?;
+
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(token, remaining_lifetime(claims.exp))
+        .await?;
+    let email = audit_email(&state.user_store, &claims).await;
+    state
+        .audit(AuditEvent::TokenBanned {
+            email: email.clone(),
+        })
+        .await;
+
+    Ok(email)
+}
+
+// Without `out`, the key is written to `output`. ID tokens signed with the old key fail
+// verification once the service restarts with the new one, as only one key is published.
+fn rotate_keys(settings: &Settings, out: Option<&Path>, output: &mut impl Write) -> Result<()> {
+    let signing_key = OidcProvider::generate_signing_key()?;
+    let key_id = OidcProvider::from_pem(&settings.oidc.issuer, &signing_key)?
+        .jwk()
+        .kid;
+
+    match out {
+        Some(path) => {
+            // Readable by the owner only, like the other *_FILE secrets should be
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(path)
+                .and_then(|mut file| file.write_all(signing_key.expose_secret().as_bytes()))
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+            writeln!(
+                output,
+                "Wrote OIDC signing key {} to {}; point OIDC_SIGNING_KEY_FILE at it and restart the service",
+                key_id,
+                path.display()
+            )?;
+        }
+        None => write!(output, "{}", signing_key.expose_secret())?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::{
+        config::{AuthSettings, BackendConfig, OidcSettings},
+        domain::AuditQuery,
+        utils::generate_auth_token,
+    };
+
+    const SIGNING_KEY: &str = include_str!("../../tests/fixtures/oidc_signing_key.pem");
+
+    async fn in_memory_state() -> AppState {
+        let settings = Settings {
+            auth: AuthSettings {
+                jwt_secret: Secret::new("secret".to_owned()),
+            },
+            backends: BackendConfig::in_memory(),
+            // Saves generating a key for every test
+            oidc: OidcSettings {
+                signing_key: Some(Secret::new(SIGNING_KEY.to_owned())),
+                ..OidcSettings::default()
+            },
+            ..Settings::default()
+        };
+
+        build_app_state(&settings).await.unwrap()
+    }
+
+    async fn execute_line(state: &AppState, args: &str, input: &str) -> Result<String> {
+        let command = Command::parse(args.split_whitespace().map(str::to_owned))?;
+        let mut output = Vec::new();
+        execute(command, state, &mut input.as_bytes(), &mut output).await?;
+
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_the_first_admin() {
+        let state = in_memory_state().await;
+        execute_line(&state, "role create admin --permission users:manage", "")
+            .await
+            .unwrap();
+
+        let output = execute_line(
+            &state,
+            "user create admin@example.com --role admin",
+            "Asdf1234!\n",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(output, "Created user admin@example.com\n");
+        let admin = email("admin@example.com");
+        let user = state
+            .user_store
+            .read()
+            .await
+            .get_user(&admin)
+            .await
+            .unwrap();
+        assert!(state
+            .user_store
+            .read()
+            .await
+            .validate_user(
+                &admin,
+                &Password::parse(Secret::new("Asdf1234!".to_owned())).unwrap()
+            )
+            .await
+            .is_ok());
+        assert!(!user.requires_2fa);
+        assert_eq!(
+            state
+                .role_store
+                .read()
+                .await
+                .get_user_roles(user.id)
+                .await
+                .unwrap(),
+            vec!["admin"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_user_checks_its_input_first() {
+        let state = in_memory_state().await;
+
+        let missing_role = execute_line(
+            &state,
+            "user create a@example.com --role admin",
+            "Asdf1234!\n",
+        )
+        .await;
+        assert!(missing_role.is_err());
+        let missing_password = execute_line(&state, "user create a@example.com", "").await;
+        assert!(missing_password.is_err());
+        assert!(state
+            .user_store
+            .read()
+            .await
+            .get_user(&email("a@example.com"))
+            .await
+            .is_err());
+
+        execute_line(&state, "user create a@example.com", "Asdf1234!\n")
+            .await
+            .unwrap();
+        let duplicate = execute_line(&state, "user create a@example.com", "Asdf1234!\n").await;
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disable_and_enable_user() {
+        let state = in_memory_state().await;
+        execute_line(&state, "user create a@example.com", "Asdf1234!\n")
+            .await
+            .unwrap();
+        let user = email("a@example.com");
+        let id = state
+            .user_store
+            .read()
+            .await
+            .get_user(&user)
+            .await
+            .unwrap()
+            .id;
+        let token = generate_auth_token(id, &[], &[], &state.auth_settings).unwrap();
+
+        execute_line(&state, "user disable a@example.com", "")
+            .await
+            .unwrap();
+        assert!(
+            state
+                .user_store
+                .read()
+                .await
+                .get_user(&user)
+                .await
+                .unwrap()
+                .locked
+        );
+        let validated = validate_token(
+            &state.auth_settings,
+            state.banned_token_store.clone(),
+            Secret::new(token),
+        )
+        .await;
+        assert!(validated.is_err());
+
+        execute_line(&state, "user enable a@example.com", "")
+            .await
+            .unwrap();
+        assert!(
+            !state
+                .user_store
+                .read()
+                .await
+                .get_user(&user)
+                .await
+                .unwrap()
+                .locked
+        );
+        assert!(execute_line(&state, "user disable b@example.com", "")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ban_token_from_stdin() {
+        let state = in_memory_state().await;
+        execute_line(&state, "user create a@example.com", "Asdf1234!\n")
+            .await
+            .unwrap();
+        let user = state
+            .user_store
+            .read()
+            .await
+            .get_user(&email("a@example.com"))
+            .await
+            .unwrap();
+        let token = generate_auth_token(user.id, &[], &[], &state.auth_settings).unwrap();
+
+        let output = execute_line(&state, "token ban", &format!("{}\n", token))
+            .await
+            .unwrap();
+
+        assert_eq!(output, "Banned a token of a@example.com\n");
+        assert!(state
+            .banned_token_store
+            .write()
+            .await
+            .contains_token(Secret::new(token.clone()))
+            .await
+            .unwrap());
+        // Banned tokens are no longer accepted, so there is nothing left to ban
+        assert!(execute_line(&state, &format!("token ban {}", token), "")
+            .await
+            .is_err());
+        assert!(execute_line(&state, "token ban not-a-token", "")
+            .await
+            .is_err());
+
+        let events = state
+            .audit_sink
+            .read()
+            .await
+            .query(&AuditQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(events[0].event.event_type(), "token_banned");
+    }
+
+    #[tokio::test]
+    async fn test_rotate_keys_writes_a_loadable_key() {
+        let settings = Settings::default();
+        let path = std::env::temp_dir().join(format!("oidc-{}.pem", uuid::Uuid::new_v4()));
+        let mut output = Vec::new();
+
+        run(
+            Command::RotateKeys {
+                out: Some(path.clone()),
+            },
+            &settings,
+            &mut "".as_bytes(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+
+        let pem = std::fs::read_to_string(&path).unwrap();
+        let provider = OidcProvider::from_pem(&settings.oidc.issuer, &Secret::new(pem)).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(&provider.jwk().kid));
+        let mode = std::os::unix::fs::PermissionsExt::mode(
+            &std::fs::metadata(&path).unwrap().permissions(),
+        );
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_file(path).unwrap();
+    }
+}
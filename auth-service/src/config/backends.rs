@@ -0,0 +1,204 @@
+use serde::{
+    de::{
+        value::{self, StrDeserializer},
+        DeserializeOwned, IntoDeserializer,
+    },
+    Deserialize,
+};
+
+use crate::config::ConfigError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStoreBackend {
+    #[default]
+    Postgres,
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OAuthClientStoreBackend {
+    #[default]
+    Postgres,
+    // Only the clients in the settings; registrations are lost on restart
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyStoreBackend {
+    #[default]
+    Postgres,
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoleStoreBackend {
+    #[default]
+    Postgres,
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenStoreBackend {
+    #[default]
+    Redis,
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSinkBackend {
+    #[default]
+    Postgres,
+    // JSON lines appended to `audit.file_path`
+    File,
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailClientBackend {
+    #[default]
+    Postmark,
+    Mock,
+}
+
+// A backend setting, named as in the configuration file
+pub trait Backend: DeserializeOwned {
+    // The accepted names, listed when another is given
+    const NAMES: &'static str;
+}
+
+impl Backend for UserStoreBackend {
+    const NAMES: &'static str = "postgres, memory";
+}
+
+impl Backend for OAuthClientStoreBackend {
+    const NAMES: &'static str = "postgres, memory";
+}
+
+impl Backend for ApiKeyStoreBackend {
+    const NAMES: &'static str = "postgres, memory";
+}
+
+impl Backend for RoleStoreBackend {
+    const NAMES: &'static str = "postgres, memory";
+}
+
+impl Backend for TokenStoreBackend {
+    const NAMES: &'static str = "redis, memory";
+}
+
+impl Backend for AuditSinkBackend {
+    const NAMES: &'static str = "postgres, file, memory";
+}
+
+impl Backend for EmailClientBackend {
+    const NAMES: &'static str = "postmark, mock";
+}
+
+// Parses the value of the `key` environment variable, ignoring case
+pub fn parse_backend<T: Backend>(key: &'static str, value: &str) -> Result<T, ConfigError> {
+    let name = value.trim().to_lowercase();
+    let deserializer: StrDeserializer<'_, value::Error> = name.as_str().into_deserializer();
+
+    T::deserialize(deserializer).map_err(|_| ConfigError::InvalidValue {
+        key,
+        value: value.to_owned(),
+        expected: T::NAMES,
+    })
+}
+
+// Names the implementation used for each store, the audit sink and the email client.
+// Defaults to the production setup: Postgres, Redis and Postmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct BackendConfig {
+    pub user_store: UserStoreBackend,
+    pub oauth_client_store: OAuthClientStoreBackend,
+    pub api_key_store: ApiKeyStoreBackend,
+    pub role_store: RoleStoreBackend,
+    pub banned_token_store: TokenStoreBackend,
+    pub two_fa_code_store: TokenStoreBackend,
+    // Authorization codes and refresh tokens
+    pub oauth_grant_store: TokenStoreBackend,
+    pub audit_sink: AuditSinkBackend,
+    pub email_client: EmailClientBackend,
+}
+
+impl BackendConfig {
+    pub fn in_memory() -> Self {
+        Self {
+            user_store: UserStoreBackend::Memory,
+            oauth_client_store: OAuthClientStoreBackend::Memory,
+            api_key_store: ApiKeyStoreBackend::Memory,
+            role_store: RoleStoreBackend::Memory,
+            banned_token_store: TokenStoreBackend::Memory,
+            two_fa_code_store: TokenStoreBackend::Memory,
+            oauth_grant_store: TokenStoreBackend::Memory,
+            audit_sink: AuditSinkBackend::Memory,
+            email_client: EmailClientBackend::Mock,
+        }
+    }
+
+    pub fn uses_postgres(&self) -> bool {
+        self.user_store == UserStoreBackend::Postgres
+            || self.oauth_client_store == OAuthClientStoreBackend::Postgres
+            || self.api_key_store == ApiKeyStoreBackend::Postgres
+            || self.role_store == RoleStoreBackend::Postgres
+            || self.audit_sink == AuditSinkBackend::Postgres
+    }
+
+    pub fn uses_redis(&self) -> bool {
+        self.banned_token_store == TokenStoreBackend::Redis
+            || self.two_fa_code_store == TokenStoreBackend::Redis
+            || self.oauth_grant_store == TokenStoreBackend::Redis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::constants::env;
+
+    #[test]
+    fn test_backend_values_are_parsed_case_insensitively() {
+        assert_eq!(
+            parse_backend::<UserStoreBackend>("KEY", "Memory").unwrap(),
+            UserStoreBackend::Memory
+        );
+        assert_eq!(
+            parse_backend::<OAuthClientStoreBackend>("KEY", "MEMORY").unwrap(),
+            OAuthClientStoreBackend::Memory
+        );
+        assert_eq!(
+            parse_backend::<EmailClientBackend>("KEY", "POSTMARK").unwrap(),
+            EmailClientBackend::Postmark
+        );
+        assert_eq!(
+            parse_backend::<TokenStoreBackend>("KEY", " redis ").unwrap(),
+            TokenStoreBackend::Redis
+        );
+        assert_eq!(
+            parse_backend::<AuditSinkBackend>("KEY", "File").unwrap(),
+            AuditSinkBackend::File
+        );
+    }
+
+    #[test]
+    fn test_unknown_backend_is_rejected() {
+        let result = parse_backend::<UserStoreBackend>(env::USER_STORE_BACKEND_ENV_VAR, "mysql");
+        assert_eq!(
+            result.unwrap_err(),
+            ConfigError::InvalidValue {
+                key: env::USER_STORE_BACKEND_ENV_VAR,
+                value: "mysql".to_owned(),
+                expected: "postgres, memory",
+            }
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::str::FromStr;
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::Deserialize;
+
+use crate::{config::ConfigError, utils::constants::env};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CorsSettings {
+    // Exact origins such as `http://localhost:8000`, or wildcard subdomains
+    // such as `https://*.example.com`
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["http://localhost:8000".to_owned()],
+            allowed_methods: vec!["GET".to_owned(), "POST".to_owned(), "DELETE".to_owned()],
+            allowed_headers: vec!["content-type".to_owned()],
+        }
+    }
+}
+
+impl CorsSettings {
+    pub fn origin_patterns(&self) -> Result<Vec<OriginPattern>, ConfigError> {
+        self.allowed_origins
+            .iter()
+            .map(|origin| origin.parse())
+            .collect()
+    }
+
+    pub fn methods(&self) -> Result<Vec<Method>, ConfigError> {
+        self.allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.trim().to_uppercase().as_bytes()).map_err(|_| {
+                    ConfigError::InvalidValue {
+                        key: env::CORS_ALLOWED_METHODS_ENV_VAR,
+                        value: method.to_owned(),
+                        expected: "HTTP method names",
+                    }
+                })
+            })
+            .collect()
+    }
+
+    pub fn headers(&self) -> Result<Vec<HeaderName>, ConfigError> {
+        self.allowed_headers
+            .iter()
+            .map(|header| {
+                HeaderName::from_str(header.trim()).map_err(|_| ConfigError::InvalidValue {
+                    key: env::CORS_ALLOWED_HEADERS_ENV_VAR,
+                    value: header.to_owned(),
+                    expected: "HTTP header names",
+                })
+            })
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.origin_patterns()?;
+        self.methods()?;
+        self.headers()?;
+
+        Ok(())
+    }
+
+    pub fn is_allowed(patterns: &[OriginPattern], origin: &HeaderValue) -> bool {
+        origin
+            .to_str()
+            .map(|origin| patterns.iter().any(|pattern| pattern.matches(origin)))
+            .unwrap_or(false)
+    }
+}
+
+// An allowed origin; a leading `*.` in the host matches any subdomain, but not the bare domain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginPattern {
+    scheme: String,
+    host: HostPattern,
+    port: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostPattern {
+    Exact(String),
+    Subdomain(String),
+}
+
+impl OriginPattern {
+    pub fn matches(&self, origin: &str) -> bool {
+        let Ok(origin) = parse_origin(origin) else {
+            return false;
+        };
+
+        if origin.scheme != self.scheme || origin.port != self.port {
+            return false;
+        }
+
+        match (&self.host, &origin.host) {
+            (HostPattern::Exact(expected), HostPattern::Exact(host)) => expected == host,
+            (HostPattern::Subdomain(domain), HostPattern::Exact(host)) => host
+                .strip_suffix(domain.as_str())
+                .is_some_and(|prefix| !prefix.is_empty()),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for OriginPattern {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_origin(value.trim()).map_err(|_| ConfigError::InvalidValue {
+            key: env::CORS_ALLOWED_ORIGINS_ENV_VAR,
+            value: value.to_owned(),
+            expected: "origins like http://localhost:8000 or https://*.example.com",
+        })
+    }
+}
+
+fn parse_origin(value: &str) -> Result<OriginPattern, ()> {
+    let (scheme, rest) = value.split_once("://").ok_or(())?;
+    if scheme != "http" && scheme != "https" {
+        return Err(());
+    }
+
+    let rest = rest.strip_suffix('/').unwrap_or(rest);
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port.parse::<u16>().map_err(|_| ())?)),
+        None => (rest, None),
+    };
+
+    let host = host.to_lowercase();
+    let is_valid_host = |host: &str| {
+        !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    };
+
+    let host = match host.strip_prefix("*.") {
+        Some(domain) if is_valid_host(domain) => HostPattern::Subdomain(format!(".{}", domain)),
+        None if is_valid_host(&host) => HostPattern::Exact(host),
+        _ => return Err(()),
+    };
+
+    Ok(OriginPattern {
+        scheme: scheme.to_owned(),
+        host,
+        port,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(value: &str) -> OriginPattern {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_exact_origin_matches_only_itself() {
+        let pattern = pattern("http://localhost:8000");
+
+        assert!(pattern.matches("http://localhost:8000"));
+        assert!(pattern.matches("http://LOCALHOST:8000"));
+        assert!(!pattern.matches("http://localhost:8001"));
+        assert!(!pattern.matches("https://localhost:8000"));
+        assert!(!pattern.matches("http://localhost"));
+    }
+
+    #[test]
+    fn test_wildcard_matches_subdomains_only() {
+        let pattern = pattern("https://*.example.com");
+
+        assert!(pattern.matches("https://app.example.com"));
+        assert!(pattern.matches("https://a.b.example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(!pattern.matches("https://evilexample.com"));
+        assert!(!pattern.matches("https://app.example.com.evil.io"));
+        assert!(!pattern.matches("http://app.example.com"));
+    }
+
+    #[test]
+    fn test_invalid_origins_are_rejected() {
+        for origin in [
+            "*",
+            "localhost:8000",
+            "ftp://example.com",
+            "http://*",
+            "http://a:b",
+        ] {
+            assert!(
+                origin.parse::<OriginPattern>().is_err(),
+                "accepted {}",
+                origin
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_methods_and_headers_fail_validation() {
+        let settings = CorsSettings {
+            allowed_methods: vec!["GET POST".to_owned()],
+            ..CorsSettings::default()
+        };
+        assert!(settings.validate().is_err());
+
+        let settings = CorsSettings {
+            allowed_headers: vec!["bad header".to_owned()],
+            ..CorsSettings::default()
+        };
+        assert!(settings.validate().is_err());
+
+        assert!(CorsSettings::default().validate().is_ok());
+    }
+}
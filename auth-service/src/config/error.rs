@@ -0,0 +1,65 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Invalid value '{value}' for {key}, expected one of: {expected}")]
+    InvalidValue {
+        key: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    // `origin` is where the client was listed: `OAUTH_CLIENTS` or the configuration file
+    #[error("Invalid OAuth client '{client_id}' in {origin}: {reason}")]
+    InvalidOAuthClient {
+        origin: &'static str,
+        client_id: String,
+        reason: String,
+    },
+    #[error("Missing required settings: {}", .0.join(", "))]
+    MissingSettings(Vec<String>),
+    #[error("Failed to read configuration file {path}")]
+    UnreadableFile {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid configuration file {path}")]
+    InvalidFile {
+        path: String,
+        #[source]
+        source: toml::de::Error,
+    },
+}
+
+impl PartialEq for ConfigError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::InvalidValue { key, value, .. },
+                Self::InvalidValue {
+                    key: other_key,
+                    value: other_value,
+                    ..
+                },
+            ) => key == other_key && value == other_value,
+            (
+                Self::InvalidOAuthClient {
+                    origin, client_id, ..
+                },
+                Self::InvalidOAuthClient {
+                    origin: other_origin,
+                    client_id: other_client_id,
+                    ..
+                },
+            ) => origin == other_origin && client_id == other_client_id,
+            (Self::MissingSettings(missing), Self::MissingSettings(other_missing)) => {
+                missing == other_missing
+            }
+            (Self::UnreadableFile { path, .. }, Self::UnreadableFile { path: other, .. })
+            | (Self::InvalidFile { path, .. }, Self::InvalidFile { path: other, .. }) => {
+                path == other
+            }
+            _ => false,
+        }
+    }
+}
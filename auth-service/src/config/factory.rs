@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Context, Result};
+use reqwest::Client;
+use secrecy::Secret;
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::{
+        ApiKeyStoreType, AppState, AuditSinkType, BannedTokenStoreType, EmailClientType,
+        OAuthClientStoreType, OAuthGrantStoreType, RoleStoreType, TwoFACodeStoreType,
+        UserStoreType,
+    },
+    config::{
+        ApiKeyStoreBackend, AuditSinkBackend, EmailClientBackend, OAuthClientStoreBackend,
+        RoleStoreBackend, Settings, TokenStoreBackend, UserStoreBackend,
+    },
+    domain::Email,
+    get_postgres_pool, get_redis_client,
+    services::{
+        HashmapApiKeyStore, HashmapOAuthClientStore, HashmapOAuthGrantStore, HashmapRoleStore,
+        HashmapTwoFACodeStore, HashmapUserStore, HashsetBannedTokenStore, JsonLinesAuditSink,
+        MemoryAuditSink, MockEmailClient, PostgresApiKeyStore, PostgresAuditSink,
+        PostgresOAuthClientStore, PostgresRoleStore, PostgresUserStore, PostmarkEmailClient,
+        RedisBannedTokenStore, RedisOAuthGrantStore, RedisTwoFACodeStore,
+    },
+    utils::OidcProvider,
+};
+
+// Builds the `AppState` with the store and email client implementations named in `settings`.
+#[tracing::instrument(name = "Build app state", skip_all)]
+pub async fn build_app_state(settings: &Settings) -> Result<AppState> {
+    settings.validate()?;
+
+    // One pool, shared by every Postgres-backed store
+    let pg_pool = if settings.backends.uses_postgres() {
+        let url = required(&settings.database.url, "database url")?;
+        Some(configure_postgresql(url).await?)
+    } else {
+        None
+    };
+    let postgres = || {
+        pg_pool
+            .clone()
+            .ok_or_else(|| eyre!("Postgres is not configured"))
+    };
+
+    let user_store: UserStoreType = match settings.backends.user_store {
+        UserStoreBackend::Postgres => Arc::new(RwLock::new(PostgresUserStore::new(postgres()?))),
+        UserStoreBackend::Memory => Arc::new(RwLock::new(HashmapUserStore::default())),
+    };
+
+    // Clients from the settings are kept alongside those registered through the admin API
+    let oauth_client_store: OAuthClientStoreType = match settings.backends.oauth_client_store {
+        OAuthClientStoreBackend::Postgres => {
+            let store = PostgresOAuthClientStore::new(postgres()?);
+            store.save_clients(settings.oauth.clients()).await?;
+            Arc::new(RwLock::new(store))
+        }
+        OAuthClientStoreBackend::Memory => Arc::new(RwLock::new(
+            HashmapOAuthClientStore::with_clients(settings.oauth.clients()),
+        )),
+    };
+
+    let api_key_store: ApiKeyStoreType = match settings.backends.api_key_store {
+        ApiKeyStoreBackend::Postgres => {
+            Arc::new(RwLock::new(PostgresApiKeyStore::new(postgres()?)))
+        }
+        ApiKeyStoreBackend::Memory => Arc::new(RwLock::new(HashmapApiKeyStore::default())),
+    };
+
+    let role_store: RoleStoreType = match settings.backends.role_store {
+        RoleStoreBackend::Postgres => Arc::new(RwLock::new(PostgresRoleStore::new(postgres()?))),
+        RoleStoreBackend::Memory => Arc::new(RwLock::new(HashmapRoleStore::default())),
+    };
+
+    let banned_token_store: BannedTokenStoreType = match settings.backends.banned_token_store {
+        TokenStoreBackend::Redis => Arc::new(RwLock::new(
+            RedisBannedTokenStore::new(configure_redis(settings)?)
+                .wrap_err("failed to get Redis connection")?,
+        )),
+        TokenStoreBackend::Memory => Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+    };
+
+    let two_fa_code_store: TwoFACodeStoreType = match settings.backends.two_fa_code_store {
+        TokenStoreBackend::Redis => Arc::new(RwLock::new(
+            RedisTwoFACodeStore::new(configure_redis(settings)?)
+                .wrap_err("failed to get Redis connection")?,
+        )),
+        TokenStoreBackend::Memory => Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+    };
+
+    let oauth_grant_store: OAuthGrantStoreType = match settings.backends.oauth_grant_store {
+        TokenStoreBackend::Redis => Arc::new(RwLock::new(
+            RedisOAuthGrantStore::new(configure_redis(settings)?)
+                .wrap_err("failed to get Redis connection")?,
+        )),
+        TokenStoreBackend::Memory => Arc::new(RwLock::new(HashmapOAuthGrantStore::default())),
+    };
+
+    let audit_sink: AuditSinkType = match settings.backends.audit_sink {
+        AuditSinkBackend::Postgres => Arc::new(RwLock::new(PostgresAuditSink::new(postgres()?))),
+        AuditSinkBackend::File => Arc::new(RwLock::new(
+            JsonLinesAuditSink::open(&settings.audit.file_path).await?,
+        )),
+        AuditSinkBackend::Memory => Arc::new(RwLock::new(MemoryAuditSink::default())),
+    };
+
+    let email_client: EmailClientType = match settings.backends.email_client {
+        EmailClientBackend::Postmark => {
+            Arc::new(RwLock::new(configure_postmark_email_client(settings)?))
+        }
+        EmailClientBackend::Mock => Arc::new(RwLock::new(MockEmailClient::default())),
+    };
+
+    let oidc = match &settings.oidc.signing_key {
+        Some(signing_key) => OidcProvider::from_pem(&settings.oidc.issuer, signing_key)?,
+        None => {
+            tracing::warn!(
+                "no OIDC signing key is configured; ID tokens issued now cannot be verified after a restart"
+            );
+            OidcProvider::generate(&settings.oidc.issuer)?
+        }
+    };
+
+    let app_state = AppState::new(
+        user_store,
+        banned_token_store,
+        two_fa_code_store,
+        email_client,
+        settings.auth.clone(),
+    )
+    .with_health_settings(settings.health.clone())
+    .with_audit_sink(audit_sink)
+    .with_oauth_client_store(oauth_client_store)
+    .with_oauth_grant_store(oauth_grant_store)
+    .with_api_key_store(api_key_store)
+    .with_role_store(role_store)
+    .with_oidc(oidc);
+
+    Ok(match pg_pool {
+        Some(pg_pool) => app_state.with_pg_pool(pg_pool),
+        None => app_state,
+    })
+}
+
+// Applies pending migrations to the configured database, without building the stores
+#[tracing::instrument(name = "Migrate database", skip_all)]
+pub async fn migrate(settings: &Settings) -> Result<()> {
+    let url = required(&settings.database.url, "database url")?;
+    configure_postgresql(url).await?.close().await;
+
+    Ok(())
+}
+
+async fn configure_postgresql(url: Secret<String>) -> Result<PgPool> {
+    // Create a new database connection pool
+    let pg_pool = get_postgres_pool(url)
+        .await
+        .wrap_err("failed to create Postgres connection pool")?;
+
+    // Run database migrations against our database
+    sqlx::migrate!()
+        .run(&pg_pool)
+        .await
+        .wrap_err("failed to run migrations")?;
+
+    Ok(pg_pool)
+}
+
+fn configure_redis(settings: &Settings) -> Result<redis::Client> {
+    get_redis_client(settings.redis.host_name.clone()).wrap_err("failed to get Redis client")
+}
+
+fn configure_postmark_email_client(settings: &Settings) -> Result<PostmarkEmailClient> {
+    let settings = &settings.email_client;
+
+    let http_client = Client::builder()
+        .timeout(settings.timeout())
+        .build()
+        .wrap_err("failed to build HTTP client")?;
+
+    let sender = Email::parse(required(&settings.sender, "email sender")?)?;
+
+    Ok(PostmarkEmailClient::new(
+        settings.base_url.clone(),
+        sender,
+        required(&settings.auth_token, "email auth token")?,
+        http_client,
+    ))
+}
+
+fn required(value: &Option<Secret<String>>, name: &str) -> Result<Secret<String>> {
+    value
+        .clone()
+        .ok_or_else(|| eyre!("{} is not configured", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::{
+        config::{AuthSettings, BackendConfig},
+        domain::{Password, User},
+    };
+
+    #[tokio::test]
+    async fn test_build_in_memory_app_state() {
+        let settings = Settings {
+            auth: AuthSettings {
+                jwt_secret: Secret::new("secret".to_owned()),
+            },
+            backends: BackendConfig::in_memory(),
+            ..Settings::default()
+        };
+
+        let app_state = build_app_state(&settings).await.unwrap();
+
+        let user = User::new(
+            Email::parse(Secret::new("test@example.com".to_owned())).unwrap(),
+            Password::parse(Secret::new("Asdf1234".to_owned())).unwrap(),
+            false,
+        );
+        app_state
+            .user_store
+            .write()
+            .await
+            .add_user(user.clone())
+            .await
+            .unwrap();
+        assert!(app_state
+            .user_store
+            .read()
+            .await
+            .get_user(&user.email)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_build_app_state_fails_when_settings_are_missing() {
+        let result = build_app_state(&Settings::default()).await;
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,11 @@
+mod backends;
+mod cors;
+mod error;
+mod factory;
+mod settings;
+
+pub use backends::*;
+pub use cors::*;
+pub use error::*;
+pub use factory::*;
+pub use settings::*;
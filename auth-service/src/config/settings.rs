@@ -0,0 +1,1019 @@
+use std::{env as std_env, fs, io::ErrorKind, str::FromStr, time::Duration};
+
+use dotenvy::dotenv;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    config::{
+        parse_backend, ApiKeyStoreBackend, AuditSinkBackend, BackendConfig, ConfigError,
+        CorsSettings, EmailClientBackend, OAuthClientStoreBackend, RoleStoreBackend,
+        UserStoreBackend,
+    },
+    domain::{Email, OAuthClient},
+    utils::{
+        constants::{env, prod, DEFAULT_REDIS_HOSTNAME},
+        hash_opaque_token, validate_redirect_uri, MIN_CLIENT_SECRET_LENGTH,
+    },
+};
+
+// All runtime settings of the auth service, loaded once at startup.
+// Layers, from lowest to highest precedence: built-in defaults, the TOML file,
+// environment variables and `<VAR>_FILE` secret files.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub application: ApplicationSettings,
+    pub auth: AuthSettings,
+    pub oauth: OAuthSettings,
+    pub oidc: OidcSettings,
+    pub health: HealthSettings,
+    pub audit: AuditSettings,
+    pub backends: BackendConfig,
+    pub database: DatabaseSettings,
+    pub redis: RedisSettings,
+    pub email_client: EmailClientSettings,
+    pub logging: LoggingSettings,
+    pub telemetry: TelemetrySettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ApplicationSettings {
+    pub address: String,
+    pub shutdown_timeout_milliseconds: u64,
+    pub cors: CorsSettings,
+}
+
+impl ApplicationSettings {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_millis(self.shutdown_timeout_milliseconds)
+    }
+}
+
+impl Default for ApplicationSettings {
+    fn default() -> Self {
+        Self {
+            address: prod::APP_ADDRESS.to_owned(),
+            shutdown_timeout_milliseconds: prod::SHUTDOWN_TIMEOUT.as_millis() as u64,
+            cors: CorsSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthSettings {
+    pub jwt_secret: Secret<String>,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self {
+            jwt_secret: Secret::new(String::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OAuthSettings {
+    // Clients known at startup, in addition to those registered through the admin API
+    pub clients: Vec<OAuthClientSettings>,
+    // Whether `OAUTH_CLIENTS` replaced the clients of the configuration file
+    #[serde(skip)]
+    pub clients_from_env: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthClientSettings {
+    pub client_id: String,
+    // Defaults to the client id
+    #[serde(default)]
+    pub client_name: Option<String>,
+    // Public clients, which must use PKCE, have none
+    #[serde(default)]
+    pub client_secret: Option<Secret<String>>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    // Scopes the client may request from users and, with a secret, for itself
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl OAuthSettings {
+    pub fn clients(&self) -> Vec<OAuthClient> {
+        self.clients
+            .iter()
+            .map(|client| OAuthClient {
+                client_id: client.client_id.clone(),
+                client_name: client
+                    .client_name
+                    .clone()
+                    .unwrap_or_else(|| client.client_id.clone()),
+                client_secret_hash: client.client_secret.as_ref().map(hash_opaque_token),
+                redirect_uris: client.redirect_uris.clone(),
+                scopes: client.scopes.clone(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OidcSettings {
+    // The public base URL of the service: the `iss` of ID tokens and the base of the
+    // endpoints advertised by discovery
+    pub issuer: String,
+    // PEM RSA private key for ID tokens. A key generated at startup is used when unset.
+    pub signing_key: Option<Secret<String>>,
+}
+
+impl Default for OidcSettings {
+    fn default() -> Self {
+        Self {
+            issuer: prod::OIDC_ISSUER.to_owned(),
+            signing_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    // Whether readiness also depends on the email provider being reachable
+    pub check_email_client: bool,
+    pub timeout_milliseconds: u64,
+}
+
+impl HealthSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            check_email_client: false,
+            timeout_milliseconds: prod::HEALTH_CHECK_TIMEOUT.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuditSettings {
+    // Used by the `file` audit sink
+    pub file_path: String,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        Self {
+            file_path: prod::AUDIT_LOG_FILE.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DatabaseSettings {
+    pub url: Option<Secret<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RedisSettings {
+    pub host_name: Secret<String>,
+}
+
+impl Default for RedisSettings {
+    fn default() -> Self {
+        Self {
+            host_name: Secret::new(DEFAULT_REDIS_HOSTNAME.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EmailClientSettings {
+    pub base_url: String,
+    pub sender: Option<Secret<String>>,
+    pub auth_token: Option<Secret<String>>,
+    pub timeout_milliseconds: u64,
+}
+
+impl EmailClientSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+impl Default for EmailClientSettings {
+    fn default() -> Self {
+        Self {
+            base_url: prod::email_client::BASE_URL.to_owned(),
+            sender: None,
+            auth_token: None,
+            timeout_milliseconds: prod::email_client::TIMEOUT.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Compact,
+    Pretty,
+    // One JSON object per line, for log pipelines
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "compact" => Ok(Self::Compact),
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(ConfigError::InvalidValue {
+                key: env::LOG_FORMAT_ENV_VAR,
+                value: value.to_owned(),
+                expected: "compact, pretty, json",
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LoggingSettings {
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    // Base URL of an OTLP/HTTP collector, e.g. `http://localhost:4318`; spans are only
+    // exported when set, but trace context is propagated either way
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: prod::SERVICE_NAME.to_owned(),
+        }
+    }
+}
+
+impl Settings {
+    // Loads every layer and validates the result, so a missing setting fails at boot
+    pub fn load() -> Result<Self, ConfigError> {
+        let settings = Self::load_unvalidated()?;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    // Loads every layer without checking that the selected backends are fully configured
+    pub fn load_unvalidated() -> Result<Self, ConfigError> {
+        dotenv().ok();
+
+        let env = |key: &str| std_env::var(key).ok();
+
+        // An explicitly configured file must exist, the default one is optional
+        let (path, required) = match non_empty(env(env::CONFIG_FILE_ENV_VAR)) {
+            Some(path) => (path, true),
+            None => (prod::CONFIG_FILE.to_owned(), false),
+        };
+
+        let mut settings = match fs::read_to_string(&path) {
+            Ok(contents) => Self::from_toml(&contents, &path)?,
+            Err(e) if !required && e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(source) => return Err(ConfigError::UnreadableFile { path, source }),
+        };
+
+        settings.apply_env_overrides(env)?;
+
+        Ok(settings)
+    }
+
+    pub fn from_toml(contents: &str, path: &str) -> Result<Self, ConfigError> {
+        toml::from_str(contents).map_err(|source| ConfigError::InvalidFile {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    pub fn apply_env_overrides<F>(&mut self, env: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(value) = read_override(&env, env::JWT_SECRET_ENV_VAR)? {
+            self.auth.jwt_secret = Secret::new(value);
+        }
+        if let Some(value) = read_override(&env, env::OAUTH_CLIENTS_ENV_VAR)? {
+            self.oauth.clients = split_list(&value)
+                .into_iter()
+                .map(|pair| match pair.split_once(':') {
+                    Some((client_id, client_secret)) => Ok(OAuthClientSettings {
+                        client_id: client_id.trim().to_owned(),
+                        client_name: None,
+                        client_secret: Some(Secret::new(client_secret.trim().to_owned())),
+                        redirect_uris: Vec::new(),
+                        scopes: Vec::new(),
+                    }),
+                    None => Err(ConfigError::InvalidValue {
+                        key: env::OAUTH_CLIENTS_ENV_VAR,
+                        value: pair,
+                        expected: "comma-separated client_id:client_secret pairs",
+                    }),
+                })
+                .collect::<Result<_, _>>()?;
+            self.oauth.clients_from_env = true;
+        }
+        if let Some(value) = read_override(&env, env::OIDC_ISSUER_ENV_VAR)? {
+            self.oidc.issuer = value;
+        }
+        if let Some(value) = read_override(&env, env::OIDC_SIGNING_KEY_ENV_VAR)? {
+            self.oidc.signing_key = Some(Secret::new(value));
+        }
+        if let Some(value) = read_override(&env, env::APP_ADDRESS_ENV_VAR)? {
+            self.application.address = value;
+        }
+        if let Some(value) = read_override(&env, env::SHUTDOWN_TIMEOUT_ENV_VAR)? {
+            self.application.shutdown_timeout_milliseconds =
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| ConfigError::InvalidValue {
+                        key: env::SHUTDOWN_TIMEOUT_ENV_VAR,
+                        value: value.clone(),
+                        expected: "a number of milliseconds",
+                    })?;
+        }
+        if let Some(value) = read_override(&env, env::CORS_ALLOWED_ORIGINS_ENV_VAR)? {
+            self.application.cors.allowed_origins = split_list(&value);
+        }
+        if let Some(value) = read_override(&env, env::CORS_ALLOWED_METHODS_ENV_VAR)? {
+            self.application.cors.allowed_methods = split_list(&value);
+        }
+        if let Some(value) = read_override(&env, env::CORS_ALLOWED_HEADERS_ENV_VAR)? {
+            self.application.cors.allowed_headers = split_list(&value);
+        }
+        if let Some(value) = read_override(&env, env::HEALTH_CHECK_EMAIL_CLIENT_ENV_VAR)? {
+            self.health.check_email_client =
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| ConfigError::InvalidValue {
+                        key: env::HEALTH_CHECK_EMAIL_CLIENT_ENV_VAR,
+                        value: value.clone(),
+                        expected: "true, false",
+                    })?;
+        }
+        if let Some(value) = read_override(&env, env::HEALTH_CHECK_TIMEOUT_ENV_VAR)? {
+            self.health.timeout_milliseconds =
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| ConfigError::InvalidValue {
+                        key: env::HEALTH_CHECK_TIMEOUT_ENV_VAR,
+                        value: value.clone(),
+                        expected: "a number of milliseconds",
+                    })?;
+        }
+        if let Some(value) = read_override(&env, env::AUDIT_LOG_FILE_ENV_VAR)? {
+            self.audit.file_path = value;
+        }
+        if let Some(value) = read_override(&env, env::DATABASE_URL_ENV_VAR)? {
+            self.database.url = Some(Secret::new(value));
+        }
+        if let Some(value) = read_override(&env, env::REDIS_HOST_NAME_ENV_VAR)? {
+            self.redis.host_name = Secret::new(value);
+        }
+        if let Some(value) = read_override(&env, env::POSTMARK_AUTH_TOKEN_ENV_VAR)? {
+            self.email_client.auth_token = Some(Secret::new(value));
+        }
+        if let Some(value) = read_override(&env, env::POSTMARK_EMAIL_ENV_VAR)? {
+            self.email_client.sender = Some(Secret::new(value));
+        }
+        if let Some(value) = read_override(&env, env::LOG_FORMAT_ENV_VAR)? {
+            self.logging.format = value.parse()?;
+        }
+        if let Some(value) = read_override(&env, env::OTLP_ENDPOINT_ENV_VAR)? {
+            self.telemetry.otlp_endpoint = Some(value);
+        }
+        if let Some(value) = read_override(&env, env::OTEL_SERVICE_NAME_ENV_VAR)? {
+            self.telemetry.service_name = value;
+        }
+        if let Some(value) = read_override(&env, env::USER_STORE_BACKEND_ENV_VAR)? {
+            self.backends.user_store = parse_backend(env::USER_STORE_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::OAUTH_CLIENT_STORE_BACKEND_ENV_VAR)? {
+            self.backends.oauth_client_store =
+                parse_backend(env::OAUTH_CLIENT_STORE_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::API_KEY_STORE_BACKEND_ENV_VAR)? {
+            self.backends.api_key_store =
+                parse_backend(env::API_KEY_STORE_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::ROLE_STORE_BACKEND_ENV_VAR)? {
+            self.backends.role_store = parse_backend(env::ROLE_STORE_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::BANNED_TOKEN_STORE_BACKEND_ENV_VAR)? {
+            self.backends.banned_token_store =
+                parse_backend(env::BANNED_TOKEN_STORE_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::TWO_FA_CODE_STORE_BACKEND_ENV_VAR)? {
+            self.backends.two_fa_code_store =
+                parse_backend(env::TWO_FA_CODE_STORE_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::OAUTH_GRANT_STORE_BACKEND_ENV_VAR)? {
+            self.backends.oauth_grant_store =
+                parse_backend(env::OAUTH_GRANT_STORE_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::AUDIT_SINK_BACKEND_ENV_VAR)? {
+            self.backends.audit_sink = parse_backend(env::AUDIT_SINK_BACKEND_ENV_VAR, &value)?;
+        }
+        if let Some(value) = read_override(&env, env::EMAIL_CLIENT_BACKEND_ENV_VAR)? {
+            self.backends.email_client = parse_backend(env::EMAIL_CLIENT_BACKEND_ENV_VAR, &value)?;
+        }
+
+        Ok(())
+    }
+
+    // Reports every missing setting at once, naming both the file key and the variable
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.application.cors.validate()?;
+
+        let origin = if self.oauth.clients_from_env {
+            env::OAUTH_CLIENTS_ENV_VAR
+        } else {
+            "[[oauth.clients]]"
+        };
+        for client in &self.oauth.clients {
+            let invalid = |reason: String| ConfigError::InvalidOAuthClient {
+                origin,
+                client_id: client.client_id.clone(),
+                reason,
+            };
+            let has_secret = client
+                .client_secret
+                .as_ref()
+                .is_some_and(|secret| !secret.expose_secret().trim().is_empty());
+            // An empty secret is a mistake, not a public client
+            let empty_secret = client.client_secret.is_some() && !has_secret;
+            if client.client_id.trim().is_empty()
+                || empty_secret
+                || (!has_secret && client.redirect_uris.is_empty())
+            {
+                return Err(invalid(
+                    "it needs a client id and a non-empty secret, redirect URIs or both".to_owned(),
+                ));
+            }
+            if client.client_secret.as_ref().is_some_and(|secret| {
+                secret.expose_secret().trim().chars().count() < MIN_CLIENT_SECRET_LENGTH
+            }) {
+                return Err(invalid(format!(
+                    "its secret must have at least {} random characters, e.g. from `openssl rand -base64 32`",
+                    MIN_CLIENT_SECRET_LENGTH
+                )));
+            }
+            if let Some(uri) = client
+                .redirect_uris
+                .iter()
+                .find(|uri| validate_redirect_uri(uri).is_err())
+            {
+                return Err(invalid(format!(
+                    "redirect URI '{}' must use https, http on a loopback address or a private-use scheme",
+                    uri
+                )));
+            }
+            if !has_secret && !client.scopes.is_empty() {
+                return Err(invalid("it needs a secret to have scopes".to_owned()));
+            }
+        }
+
+        let issuer = Url::parse(&self.oidc.issuer).ok();
+        if !issuer.is_some_and(|issuer| {
+            matches!(issuer.scheme(), "https" | "http")
+                && issuer.query().is_none()
+                && issuer.fragment().is_none()
+        }) {
+            return Err(ConfigError::InvalidValue {
+                key: env::OIDC_ISSUER_ENV_VAR,
+                value: self.oidc.issuer.clone(),
+                expected: "an http(s) URL without a query or fragment",
+            });
+        }
+
+        let mut missing = Vec::new();
+
+        if self.auth.jwt_secret.expose_secret().trim().is_empty() {
+            missing.push(describe("auth.jwt_secret", env::JWT_SECRET_ENV_VAR, None));
+        }
+
+        if self.database.url.is_none() {
+            let backends = &self.backends;
+            let postgres_backed = [
+                (
+                    backends.user_store == UserStoreBackend::Postgres,
+                    "the postgres user store",
+                ),
+                (
+                    backends.oauth_client_store == OAuthClientStoreBackend::Postgres,
+                    "the postgres OAuth client store",
+                ),
+                (
+                    backends.api_key_store == ApiKeyStoreBackend::Postgres,
+                    "the postgres API key store",
+                ),
+                (
+                    backends.role_store == RoleStoreBackend::Postgres,
+                    "the postgres role store",
+                ),
+                (
+                    backends.audit_sink == AuditSinkBackend::Postgres,
+                    "the postgres audit sink",
+                ),
+            ];
+            let required_by: Vec<&str> = postgres_backed
+                .into_iter()
+                .filter_map(|(uses_postgres, backend)| uses_postgres.then_some(backend))
+                .collect();
+            if !required_by.is_empty() {
+                missing.push(describe(
+                    "database.url",
+                    env::DATABASE_URL_ENV_VAR,
+                    Some(&required_by.join(", ")),
+                ));
+            }
+        }
+
+        if self.backends.email_client == EmailClientBackend::Postmark {
+            if self.email_client.auth_token.is_none() {
+                missing.push(describe(
+                    "email_client.auth_token",
+                    env::POSTMARK_AUTH_TOKEN_ENV_VAR,
+                    Some("the postmark email client"),
+                ));
+            }
+            match &self.email_client.sender {
+                None => missing.push(describe(
+                    "email_client.sender",
+                    env::POSTMARK_EMAIL_ENV_VAR,
+                    Some("the postmark email client"),
+                )),
+                Some(sender) if Email::parse(sender.clone()).is_err() => {
+                    return Err(ConfigError::InvalidValue {
+                        key: env::POSTMARK_EMAIL_ENV_VAR,
+                        value: sender.expose_secret().to_owned(),
+                        expected: "a valid email address",
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::MissingSettings(missing))
+        }
+    }
+}
+
+// Reads `<key>_FILE` first (Docker secrets), then `<key>` itself
+fn read_override<F>(env: &F, key: &str) -> Result<Option<String>, ConfigError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(path) = non_empty(env(&format!("{}{}", key, env::FILE_SUFFIX))) {
+        let value = fs::read_to_string(&path)
+            .map_err(|source| ConfigError::UnreadableFile { path, source })?;
+
+        return Ok(non_empty(Some(value.trim_end().to_owned())));
+    }
+
+    Ok(non_empty(env(key)))
+}
+
+// Splits comma-separated list variables such as CORS_ALLOWED_ORIGINS
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+// Treats unset and blank values alike
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+fn describe(key: &str, env_var: &str, required_by: Option<&str>) -> String {
+    match required_by {
+        Some(required_by) => format!("{} / {} (required by {})", key, env_var, required_by),
+        None => format!("{} / {}", key, env_var),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::TokenStoreBackend;
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    fn in_memory_settings() -> Settings {
+        Settings {
+            auth: AuthSettings {
+                jwt_secret: Secret::new("secret".to_owned()),
+            },
+            backends: BackendConfig::in_memory(),
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn test_settings_are_read_from_toml() {
+        let contents = r#"
+            [application]
+            address = "127.0.0.1:4000"
+
+            [backends]
+            user_store = "memory"
+            email_client = "mock"
+
+            [email_client]
+            timeout_milliseconds = 500
+        "#;
+
+        let settings = Settings::from_toml(contents, "settings.toml").unwrap();
+
+        assert_eq!(settings.application.address, "127.0.0.1:4000");
+        assert_eq!(settings.backends.user_store, UserStoreBackend::Memory);
+        assert_eq!(settings.backends.email_client, EmailClientBackend::Mock);
+        assert_eq!(
+            settings.backends.banned_token_store,
+            TokenStoreBackend::Redis
+        );
+        assert_eq!(settings.email_client.timeout(), Duration::from_millis(500));
+        assert_eq!(settings.email_client.base_url, prod::email_client::BASE_URL);
+    }
+
+    #[test]
+    fn test_cors_and_address_are_read_from_toml_and_environment() {
+        let contents = r#"
+            [application.cors]
+            allowed_origins = ["https://*.example.com"]
+            allowed_methods = ["GET"]
+        "#;
+        let mut settings = Settings::from_toml(contents, "settings.toml").unwrap();
+        assert_eq!(settings.application.address, prod::APP_ADDRESS);
+        assert_eq!(settings.application.cors.allowed_methods, vec!["GET"]);
+
+        settings
+            .apply_env_overrides(env_from(&[
+                (env::APP_ADDRESS_ENV_VAR, "127.0.0.1:4000"),
+                (
+                    env::CORS_ALLOWED_ORIGINS_ENV_VAR,
+                    "http://localhost:8000, https://*.example.org",
+                ),
+            ]))
+            .unwrap();
+
+        assert_eq!(settings.application.address, "127.0.0.1:4000");
+        assert_eq!(
+            settings.application.cors.allowed_origins,
+            vec!["http://localhost:8000", "https://*.example.org"]
+        );
+        assert_eq!(
+            settings.application.cors.allowed_headers,
+            vec!["content-type"]
+        );
+    }
+
+    #[test]
+    fn test_shutdown_timeout_is_read_from_environment() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            settings.application.shutdown_timeout(),
+            prod::SHUTDOWN_TIMEOUT
+        );
+
+        settings
+            .apply_env_overrides(env_from(&[(env::SHUTDOWN_TIMEOUT_ENV_VAR, "1500")]))
+            .unwrap();
+        assert_eq!(
+            settings.application.shutdown_timeout(),
+            Duration::from_millis(1500)
+        );
+
+        let result =
+            settings.apply_env_overrides(env_from(&[(env::SHUTDOWN_TIMEOUT_ENV_VAR, "soon")]));
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidValue { key, .. }) if key == env::SHUTDOWN_TIMEOUT_ENV_VAR
+        ));
+    }
+
+    #[test]
+    fn test_log_format_is_read_from_toml_and_environment() {
+        let mut settings =
+            Settings::from_toml("[logging]\nformat = \"pretty\"", "settings.toml").unwrap();
+        assert_eq!(settings.logging.format, LogFormat::Pretty);
+
+        settings
+            .apply_env_overrides(env_from(&[(env::LOG_FORMAT_ENV_VAR, "JSON")]))
+            .unwrap();
+        assert_eq!(settings.logging.format, LogFormat::Json);
+
+        let result = settings.apply_env_overrides(env_from(&[(env::LOG_FORMAT_ENV_VAR, "xml")]));
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidValue { key, .. }) if key == env::LOG_FORMAT_ENV_VAR
+        ));
+    }
+
+    #[test]
+    fn test_audit_settings_are_read_from_environment() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.backends.audit_sink, AuditSinkBackend::Postgres);
+
+        settings
+            .apply_env_overrides(env_from(&[
+                (env::AUDIT_SINK_BACKEND_ENV_VAR, "file"),
+                (env::AUDIT_LOG_FILE_ENV_VAR, "/var/log/auth/audit.jsonl"),
+            ]))
+            .unwrap();
+
+        assert_eq!(settings.backends.audit_sink, AuditSinkBackend::File);
+        assert_eq!(settings.audit.file_path, "/var/log/auth/audit.jsonl");
+    }
+
+    #[test]
+    fn test_oauth_clients_are_read_from_toml_and_environment() {
+        let contents = r#"
+            [[oauth.clients]]
+            client_id = "gateway"
+            client_secret = "from-file"
+        "#;
+        let mut settings = Settings::from_toml(contents, "settings.toml").unwrap();
+        assert_eq!(settings.oauth.clients.len(), 1);
+        assert_eq!(
+            settings.oauth.clients[0]
+                .client_secret
+                .as_ref()
+                .map(|secret| secret.expose_secret().as_str()),
+            Some("from-file")
+        );
+
+        settings
+            .apply_env_overrides(env_from(&[(
+                env::OAUTH_CLIENTS_ENV_VAR,
+                "gateway:from-env, proxy:other",
+            )]))
+            .unwrap();
+        let clients = settings.oauth.clients();
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients[0].client_id, "gateway");
+        assert!(clients[0].is_confidential());
+        assert_eq!(clients[0].client_name, "gateway");
+        assert_eq!(clients[1].client_id, "proxy");
+
+        let result =
+            settings.apply_env_overrides(env_from(&[(env::OAUTH_CLIENTS_ENV_VAR, "gateway")]));
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidValue { key, .. }) if key == env::OAUTH_CLIENTS_ENV_VAR
+        ));
+    }
+
+    const LONG_SECRET: &str = "pZ3m1xkX0b4Lq8QyVt7NcRw2HsJd5EaF";
+
+    fn invalid_client_origin(result: Result<(), ConfigError>) -> Option<&'static str> {
+        match result {
+            Err(ConfigError::InvalidOAuthClient { origin, .. }) => Some(origin),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_validate_checks_oauth_clients() {
+        let client =
+            |client_secret: Option<&str>, redirect_uri: Option<&str>| OAuthClientSettings {
+                client_id: "app".to_owned(),
+                client_name: None,
+                client_secret: client_secret.map(|secret| Secret::new(secret.to_owned())),
+                redirect_uris: redirect_uri.into_iter().map(str::to_owned).collect(),
+                scopes: Vec::new(),
+            };
+        let validate = |client| {
+            let mut settings = in_memory_settings();
+            settings.oauth.clients = vec![client];
+            settings.validate()
+        };
+
+        assert!(validate(client(Some(LONG_SECRET), None)).is_ok());
+        assert!(validate(client(None, Some("com.example.app:/callback"))).is_ok());
+        for invalid in [
+            client(Some(" "), None),
+            // Stored as an unsalted hash, so it must not be guessable
+            client(Some("secret"), None),
+            client(None, None),
+            client(None, Some("http://app.example.com/callback")),
+            // Only confidential clients can use the `client_credentials` grant
+            OAuthClientSettings {
+                scopes: vec!["reports:read".to_owned()],
+                ..client(None, Some("com.example.app:/callback"))
+            },
+        ] {
+            assert_eq!(
+                invalid_client_origin(validate(invalid)),
+                Some("[[oauth.clients]]")
+            );
+        }
+
+        // Clients from the environment are reported as such
+        let mut settings = in_memory_settings();
+        settings
+            .apply_env_overrides(env_from(&[(env::OAUTH_CLIENTS_ENV_VAR, "gateway:short")]))
+            .unwrap();
+        assert_eq!(
+            invalid_client_origin(settings.validate()),
+            Some(env::OAUTH_CLIENTS_ENV_VAR)
+        );
+    }
+
+    #[test]
+    fn test_oidc_settings_are_read_from_environment() {
+        let mut settings = in_memory_settings();
+        settings
+            .apply_env_overrides(env_from(&[
+                (env::OIDC_ISSUER_ENV_VAR, "https://auth.example.com"),
+                (env::OIDC_SIGNING_KEY_ENV_VAR, "pem"),
+            ]))
+            .unwrap();
+
+        assert_eq!(settings.oidc.issuer, "https://auth.example.com");
+        assert_eq!(
+            settings.oidc.signing_key.as_ref().unwrap().expose_secret(),
+            "pem"
+        );
+        assert!(settings.validate().is_ok());
+        for invalid in [
+            "auth.example.com",
+            "ftp://auth.example.com",
+            "https://a.com/?x=1",
+        ] {
+            settings.oidc.issuer = invalid.to_owned();
+            assert!(matches!(
+                settings.validate(),
+                Err(ConfigError::InvalidValue { key, .. }) if key == env::OIDC_ISSUER_ENV_VAR
+            ));
+        }
+    }
+
+    #[test]
+    fn test_postgres_backends_require_a_database_url() {
+        let mut settings = in_memory_settings();
+        settings.backends.audit_sink = AuditSinkBackend::Postgres;
+
+        let message = settings.validate().unwrap_err().to_string();
+        assert!(message.contains("the postgres audit sink"));
+
+        // Every backend that needs it is named, not just the first
+        settings.backends.role_store = RoleStoreBackend::Postgres;
+        let message = settings.validate().unwrap_err().to_string();
+        assert!(message.contains("the postgres role store, the postgres audit sink"));
+    }
+
+    #[test]
+    fn test_validate_rejects_malformed_origins() {
+        let mut settings = in_memory_settings();
+        settings.application.cors.allowed_origins = vec!["http:localhost//:8000".to_owned()];
+
+        assert!(matches!(
+            settings.validate(),
+            Err(ConfigError::InvalidValue { key, .. }) if key == env::CORS_ALLOWED_ORIGINS_ENV_VAR
+        ));
+    }
+
+    #[test]
+    fn test_invalid_toml_is_reported_with_its_path() {
+        let result = Settings::from_toml("[backends]\nuser_store = \"mysql\"", "bad.toml");
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidFile { path, .. }) if path == "bad.toml"
+        ));
+    }
+
+    #[test]
+    fn test_environment_overrides_the_file() {
+        let mut settings =
+            Settings::from_toml("[redis]\nhost_name = \"from-file\"", "settings.toml").unwrap();
+
+        settings
+            .apply_env_overrides(env_from(&[
+                (env::REDIS_HOST_NAME_ENV_VAR, "from-env"),
+                (env::USER_STORE_BACKEND_ENV_VAR, "memory"),
+                (env::JWT_SECRET_ENV_VAR, ""),
+            ]))
+            .unwrap();
+
+        assert_eq!(settings.redis.host_name.expose_secret(), "from-env");
+        assert_eq!(settings.backends.user_store, UserStoreBackend::Memory);
+        // Blank variables do not override anything
+        assert!(settings.auth.jwt_secret.expose_secret().is_empty());
+    }
+
+    #[test]
+    fn test_secret_files_take_precedence_over_variables() {
+        let path = std_env::temp_dir().join(format!("jwt-secret-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, "from-file\n").unwrap();
+
+        let mut settings = Settings::default();
+        settings
+            .apply_env_overrides(env_from(&[
+                (env::JWT_SECRET_ENV_VAR, "from-env"),
+                ("JWT_SECRET_FILE", path.to_str().unwrap()),
+            ]))
+            .unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(settings.auth.jwt_secret.expose_secret(), "from-file");
+    }
+
+    #[test]
+    fn test_missing_secret_file_is_an_error() {
+        let mut settings = Settings::default();
+        let result =
+            settings.apply_env_overrides(env_from(&[("DATABASE_URL_FILE", "/does/not/exist")]));
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::UnreadableFile { path, .. }) if path == "/does/not/exist"
+        ));
+    }
+
+    #[test]
+    fn test_in_memory_settings_only_require_a_jwt_secret() {
+        assert!(in_memory_settings().validate().is_ok());
+
+        let settings = Settings {
+            auth: AuthSettings::default(),
+            ..in_memory_settings()
+        };
+        let message = settings.validate().unwrap_err().to_string();
+        assert!(message.contains(env::JWT_SECRET_ENV_VAR));
+    }
+
+    #[test]
+    fn test_validate_reports_every_missing_setting() {
+        let settings = Settings {
+            auth: in_memory_settings().auth,
+            ..Settings::default()
+        };
+
+        let message = settings.validate().unwrap_err().to_string();
+
+        assert!(message.contains(env::DATABASE_URL_ENV_VAR));
+        assert!(message.contains(env::POSTMARK_AUTH_TOKEN_ENV_VAR));
+        assert!(message.contains(env::POSTMARK_EMAIL_ENV_VAR));
+        assert!(!message.contains(env::JWT_SECRET_ENV_VAR));
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_sender() {
+        let mut settings = in_memory_settings();
+        settings.backends.email_client = EmailClientBackend::Postmark;
+        settings.email_client.sender = Some(Secret::new("not-an-email".to_owned()));
+        settings.email_client.auth_token = Some(Secret::new("token".to_owned()));
+
+        assert!(matches!(
+            settings.validate(),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+}
@@ -0,0 +1,386 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Why a security-relevant request was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditFailureReason {
+    InvalidInput,
+    UserAlreadyExists,
+    IncorrectCredentials,
+    // No 2FA code is stored any more for the email, usually because its TTL ran out
+    CodeExpired,
+    CodeMismatch,
+    MissingToken,
+    InvalidToken,
+    AccountLocked,
+    PasswordResetRequired,
+    UnexpectedError,
+}
+
+// A security-relevant outcome of a request. `email` is the account the request was about,
+// which is unknown when the request did not carry a valid address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    UserSignedUp {
+        email: String,
+        requires_2fa: bool,
+    },
+    SignupFailed {
+        email: Option<String>,
+        reason: AuditFailureReason,
+    },
+    LoginSucceeded {
+        email: String,
+    },
+    LoginFailed {
+        email: Option<String>,
+        reason: AuditFailureReason,
+    },
+    TwoFactorCodeIssued {
+        email: String,
+    },
+    TwoFactorVerified {
+        email: String,
+    },
+    TwoFactorFailed {
+        email: Option<String>,
+        reason: AuditFailureReason,
+    },
+    LoggedOut {
+        email: String,
+    },
+    LogoutFailed {
+        reason: AuditFailureReason,
+    },
+    TokenBanned {
+        email: String,
+    },
+    TokenVerified {
+        email: String,
+    },
+    // A machine client's own token, from the `client_credentials` grant
+    ServiceTokenVerified {
+        client_id: String,
+    },
+    TokenRejected {
+        reason: AuditFailureReason,
+    },
+    // `client_id` is the one presented, if any
+    #[serde(rename = "oauth_client_rejected")]
+    OAuthClientRejected {
+        client_id: Option<String>,
+    },
+    // `email` is only known for active tokens
+    TokenIntrospected {
+        client_id: String,
+        email: Option<String>,
+    },
+    TokenRevoked {
+        client_id: String,
+        email: String,
+    },
+    ClientRegistered {
+        client_id: String,
+    },
+    ConsentGranted {
+        client_id: String,
+        email: String,
+    },
+    ConsentDenied {
+        client_id: String,
+        email: String,
+    },
+    // `grant_type` is the one exchanged, `authorization_code` or `refresh_token`
+    TokensIssued {
+        client_id: String,
+        email: String,
+        grant_type: String,
+    },
+    GrantRejected {
+        client_id: String,
+    },
+    ServiceTokenIssued {
+        client_id: String,
+        scope: String,
+    },
+    // `prefix` identifies the key without revealing it
+    ApiKeyCreated {
+        email: String,
+        prefix: String,
+    },
+    ApiKeyRevoked {
+        email: String,
+        prefix: String,
+    },
+    RoleCreated {
+        role: String,
+    },
+    RoleAssigned {
+        email: String,
+        role: String,
+    },
+    RoleUnassigned {
+        email: String,
+        role: String,
+    },
+    // A valid token lacked the permission `/verify-token` was asked to check. `email` is
+    // unknown for service tokens.
+    PermissionDenied {
+        email: Option<String>,
+        permission: String,
+    },
+    // An administrator changed the account; unchanged settings are `None`
+    UserUpdated {
+        email: String,
+        requires_2fa: Option<bool>,
+        locked: Option<bool>,
+        password_reset_required: Option<bool>,
+    },
+    SessionsRevoked {
+        email: String,
+    },
+    UserDeleted {
+        email: String,
+    },
+    // The user changed their own password, ending their other sessions
+    PasswordChanged {
+        email: String,
+    },
+    // A confirmation was sent to `new_email`; nothing changes until it is used
+    EmailChangeRequested {
+        email: String,
+        new_email: String,
+    },
+    // `email` is the old address
+    EmailChanged {
+        email: String,
+        new_email: String,
+    },
+}
+
+impl AuditEvent {
+    // Same as the serialized `type` tag
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            AuditEvent::UserSignedUp { .. } => "user_signed_up",
+            AuditEvent::SignupFailed { .. } => "signup_failed",
+            AuditEvent::LoginSucceeded { .. } => "login_succeeded",
+            AuditEvent::LoginFailed { .. } => "login_failed",
+            AuditEvent::TwoFactorCodeIssued { .. } => "two_factor_code_issued",
+            AuditEvent::TwoFactorVerified { .. } => "two_factor_verified",
+            AuditEvent::TwoFactorFailed { .. } => "two_factor_failed",
+            AuditEvent::LoggedOut { .. } => "logged_out",
+            AuditEvent::LogoutFailed { .. } => "logout_failed",
+            AuditEvent::TokenBanned { .. } => "token_banned",
+            AuditEvent::TokenVerified { .. } => "token_verified",
+            AuditEvent::ServiceTokenVerified { .. } => "service_token_verified",
+            AuditEvent::TokenRejected { .. } => "token_rejected",
+            AuditEvent::OAuthClientRejected { .. } => "oauth_client_rejected",
+            AuditEvent::TokenIntrospected { .. } => "token_introspected",
+            AuditEvent::TokenRevoked { .. } => "token_revoked",
+            AuditEvent::ClientRegistered { .. } => "client_registered",
+            AuditEvent::ConsentGranted { .. } => "consent_granted",
+            AuditEvent::ConsentDenied { .. } => "consent_denied",
+            AuditEvent::TokensIssued { .. } => "tokens_issued",
+            AuditEvent::GrantRejected { .. } => "grant_rejected",
+            AuditEvent::ServiceTokenIssued { .. } => "service_token_issued",
+            AuditEvent::ApiKeyCreated { .. } => "api_key_created",
+            AuditEvent::ApiKeyRevoked { .. } => "api_key_revoked",
+            AuditEvent::RoleCreated { .. } => "role_created",
+            AuditEvent::RoleAssigned { .. } => "role_assigned",
+            AuditEvent::RoleUnassigned { .. } => "role_unassigned",
+            AuditEvent::PermissionDenied { .. } => "permission_denied",
+            AuditEvent::UserUpdated { .. } => "user_updated",
+            AuditEvent::SessionsRevoked { .. } => "sessions_revoked",
+            AuditEvent::UserDeleted { .. } => "user_deleted",
+            AuditEvent::PasswordChanged { .. } => "password_changed",
+            AuditEvent::EmailChangeRequested { .. } => "email_change_requested",
+            AuditEvent::EmailChanged { .. } => "email_changed",
+        }
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        match self {
+            AuditEvent::UserSignedUp { email, .. }
+            | AuditEvent::LoginSucceeded { email }
+            | AuditEvent::TwoFactorCodeIssued { email }
+            | AuditEvent::TwoFactorVerified { email }
+            | AuditEvent::LoggedOut { email }
+            | AuditEvent::TokenBanned { email }
+            | AuditEvent::TokenVerified { email }
+            | AuditEvent::TokenRevoked { email, .. }
+            | AuditEvent::ConsentGranted { email, .. }
+            | AuditEvent::ConsentDenied { email, .. }
+            | AuditEvent::TokensIssued { email, .. }
+            | AuditEvent::ApiKeyCreated { email, .. }
+            | AuditEvent::ApiKeyRevoked { email, .. }
+            | AuditEvent::RoleAssigned { email, .. }
+            | AuditEvent::RoleUnassigned { email, .. }
+            | AuditEvent::UserUpdated { email, .. }
+            | AuditEvent::SessionsRevoked { email }
+            | AuditEvent::UserDeleted { email }
+            | AuditEvent::PasswordChanged { email }
+            | AuditEvent::EmailChangeRequested { email, .. }
+            | AuditEvent::EmailChanged { email, .. } => Some(email),
+            AuditEvent::SignupFailed { email, .. }
+            | AuditEvent::LoginFailed { email, .. }
+            | AuditEvent::TwoFactorFailed { email, .. }
+            | AuditEvent::TokenIntrospected { email, .. }
+            | AuditEvent::PermissionDenied { email, .. } => email.as_deref(),
+            AuditEvent::LogoutFailed { .. }
+            | AuditEvent::TokenRejected { .. }
+            | AuditEvent::OAuthClientRejected { .. }
+            | AuditEvent::ClientRegistered { .. }
+            | AuditEvent::GrantRejected { .. }
+            | AuditEvent::ServiceTokenVerified { .. }
+            | AuditEvent::ServiceTokenIssued { .. }
+            | AuditEvent::RoleCreated { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AuditRecord {
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+impl AuditRecord {
+    pub fn new(event: AuditEvent) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            occurred_at: Utc::now(),
+            event,
+        }
+    }
+}
+
+// Filters for `AuditSink::query`; `from` is inclusive and `to` exclusive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditQuery {
+    pub email: Option<String>,
+    pub event_type: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl Default for AuditQuery {
+    fn default() -> Self {
+        Self {
+            email: None,
+            event_type: None,
+            from: None,
+            to: None,
+            limit: 100,
+        }
+    }
+}
+
+impl AuditQuery {
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        self.email
+            .as_deref()
+            .is_none_or(|email| record.event.email() == Some(email))
+            && self
+                .event_type
+                .as_deref()
+                .is_none_or(|event_type| record.event.event_type() == event_type)
+            && self.from.is_none_or(|from| record.occurred_at >= from)
+            && self.to.is_none_or(|to| record.occurred_at < to)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuditSink {
+    async fn record(&mut self, record: AuditRecord) -> Result<()>;
+    // Matching records, newest first and at most `query.limit` of them
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_event_type_matches_the_serialized_tag() {
+        let events = [
+            AuditEvent::UserSignedUp {
+                email: "a@example.com".to_owned(),
+                requires_2fa: true,
+            },
+            AuditEvent::LoginFailed {
+                email: None,
+                reason: AuditFailureReason::InvalidInput,
+            },
+            AuditEvent::TokenRejected {
+                reason: AuditFailureReason::InvalidToken,
+            },
+            AuditEvent::OAuthClientRejected { client_id: None },
+        ];
+
+        for event in events {
+            let json = serde_json::to_value(&event).unwrap();
+            assert_eq!(json["type"], event.event_type());
+        }
+    }
+
+    #[test]
+    fn test_record_round_trips_as_flat_json() {
+        let record = AuditRecord::new(AuditEvent::TwoFactorFailed {
+            email: Some("a@example.com".to_owned()),
+            reason: AuditFailureReason::CodeMismatch,
+        });
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["type"], "two_factor_failed");
+        assert_eq!(json["email"], "a@example.com");
+        assert_eq!(json["reason"], "code_mismatch");
+
+        assert_eq!(serde_json::from_value::<AuditRecord>(json).unwrap(), record);
+    }
+
+    #[test]
+    fn test_query_filters_by_email_type_and_time_range() {
+        let record = AuditRecord::new(AuditEvent::LoginSucceeded {
+            email: "a@example.com".to_owned(),
+        });
+        let at = record.occurred_at;
+
+        assert!(AuditQuery::default().matches(&record));
+        assert!(AuditQuery {
+            email: Some("a@example.com".to_owned()),
+            event_type: Some("login_succeeded".to_owned()),
+            from: Some(at),
+            to: Some(at + Duration::seconds(1)),
+            ..AuditQuery::default()
+        }
+        .matches(&record));
+
+        for query in [
+            AuditQuery {
+                email: Some("b@example.com".to_owned()),
+                ..AuditQuery::default()
+            },
+            AuditQuery {
+                event_type: Some("login_failed".to_owned()),
+                ..AuditQuery::default()
+            },
+            AuditQuery {
+                to: Some(at),
+                ..AuditQuery::default()
+            },
+        ] {
+            assert!(!query.matches(&record), "{:?} matched", query);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::utils::{constant_time_eq, hash_opaque_token};
+
+// A long-lived credential a user creates for scripts. The key itself is shown once; only
+// its `prefix`, which identifies it, and a hash are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKey {
+    pub id: Uuid,
+    // The owning user
+    pub user_id: Uuid,
+    // Chosen by the user to tell their keys apart
+    pub name: String,
+    // The start of the key, e.g. `lbk_1a2b3c4d`; unique, and safe to display
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    // Keys without an expiry work until they are revoked
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn verify(&self, key: &Secret<String>) -> bool {
+        constant_time_eq(&self.key_hash, &hash_opaque_token(key))
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::data_stores::{ApiKey, ApiKeyStoreError};
+
+#[async_trait::async_trait]
+pub trait ApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError>;
+    async fn get_key(&self, prefix: &str) -> Result<ApiKey, ApiKeyStoreError>;
+    // Oldest first
+    async fn list_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    // Keys of other users are `KeyNotFound`. Returns the revoked key.
+    async fn revoke_key(&mut self, user_id: Uuid, id: Uuid) -> Result<ApiKey, ApiKeyStoreError>;
+    async fn record_use(
+        &mut self,
+        id: Uuid,
+        used_at: DateTime<Utc>,
+    ) -> Result<(), ApiKeyStoreError>;
+    // Deletes keys that expired at or before `now`. Returns how many were deleted.
+    async fn purge_expired(&mut self, now: DateTime<Utc>) -> Result<u64, ApiKeyStoreError>;
+}
@@ -0,0 +1,23 @@
+use color_eyre::eyre::Report;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    #[error("API key already exists")]
+    KeyAlreadyExists,
+    #[error("API key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ApiKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyAlreadyExists, Self::KeyAlreadyExists)
+                | (Self::KeyNotFound, Self::KeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
@@ -3,6 +3,15 @@ use secrecy::Secret;
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<()>;
+    // Bans `token` for `ttl_seconds`, which must cover the rest of its lifetime
+    async fn add_token(&mut self, token: Secret<String>, ttl_seconds: u64) -> Result<()>;
     async fn contains_token(&mut self, token: Secret<String>) -> Result<bool>;
+    // Bans every token of `subject` issued at or before `revoked_at`, in microseconds since
+    // the epoch
+    async fn revoke_sessions(&mut self, subject: &str, revoked_at: u64) -> Result<()>;
+    async fn sessions_revoked_at(&mut self, subject: &str) -> Result<Option<u64>>;
+    // Checks the connection to the backing service; in-memory stores are always healthy
+    async fn health_check(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
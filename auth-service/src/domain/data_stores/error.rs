@@ -1,18 +1,107 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+pub use auth_types::{field_error, FieldError};
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
     UserAlreadyExists,
+    // Names the request fields that failed validation, if known
     #[error("Invalid credentials")]
-    InvalidCredentials,
+    InvalidCredentials(Vec<FieldError>),
+    // Input other than credentials failed validation; names the failing fields
+    #[error("Invalid request")]
+    InvalidRequest(Vec<FieldError>),
     #[error("Incorrect credentials")]
     IncorrectCredentials,
     #[error("Missing token")]
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("API key not found")]
+    ApiKeyNotFound,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Role already exists")]
+    RoleAlreadyExists,
+    #[error("Role not found")]
+    RoleNotFound,
+    // The token is valid but its user lacks the required permission
+    #[error("Forbidden")]
+    Forbidden,
+    // The credentials are correct but an administrator locked the account
+    #[error("Account locked")]
+    AccountLocked,
+    #[error("Password reset required")]
+    PasswordResetRequired,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
+
+impl AuthAPIError {
+    // Stable, machine-readable identifier; clients should match on this, not the message
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthAPIError::UserAlreadyExists => "user_already_exists",
+            AuthAPIError::InvalidCredentials(_) => "invalid_credentials",
+            AuthAPIError::InvalidRequest(_) => "invalid_request",
+            AuthAPIError::IncorrectCredentials => "incorrect_credentials",
+            AuthAPIError::MissingToken => "missing_token",
+            AuthAPIError::InvalidToken => "invalid_token",
+            AuthAPIError::ApiKeyNotFound => "api_key_not_found",
+            AuthAPIError::UserNotFound => "user_not_found",
+            AuthAPIError::RoleAlreadyExists => "role_already_exists",
+            AuthAPIError::RoleNotFound => "role_not_found",
+            AuthAPIError::Forbidden => "forbidden",
+            AuthAPIError::AccountLocked => "account_locked",
+            AuthAPIError::PasswordResetRequired => "password_reset_required",
+            AuthAPIError::UnexpectedError(_) => "unexpected_error",
+        }
+    }
+
+    pub fn field_errors(&self) -> &[FieldError] {
+        match self {
+            AuthAPIError::InvalidCredentials(errors) | AuthAPIError::InvalidRequest(errors) => {
+                errors
+            }
+            _ => &[],
+        }
+    }
+}
+
+// Errors of the /oauth endpoints, answered in the RFC 6749 format instead of `ErrorResponse`
+#[derive(Debug, Error)]
+pub enum OAuthError {
+    #[error("Client authentication failed")]
+    InvalidClient,
+    // Each carries the description returned to the client
+    #[error("{0}")]
+    InvalidRequest(&'static str),
+    #[error("{0}")]
+    InvalidGrant(&'static str),
+    #[error("Unsupported grant type")]
+    UnsupportedGrantType,
+    // The client is known but may not use the grant type
+    #[error("{0}")]
+    UnauthorizedClient(&'static str),
+    #[error("The requested scope exceeds the scopes of the client")]
+    InvalidScope,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl OAuthError {
+    // The RFC 6749 `error` value
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidClient => "invalid_client",
+            OAuthError::InvalidRequest(_) => "invalid_request",
+            OAuthError::InvalidGrant(_) => "invalid_grant",
+            OAuthError::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthError::UnauthorizedClient(_) => "unauthorized_client",
+            OAuthError::InvalidScope => "invalid_scope",
+            OAuthError::UnexpectedError(_) => "server_error",
+        }
+    }
+}
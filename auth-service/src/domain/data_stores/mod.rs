@@ -1,9 +1,19 @@
+mod api_key;
+mod api_key_store;
+mod api_key_store_error;
 mod banned_token_store;
 mod banned_token_store_error;
 mod email;
 mod error;
 mod login_attempt_id;
+mod oauth_client;
+mod oauth_client_store;
+mod oauth_client_store_error;
+mod oauth_grant_store;
 mod password;
+mod role;
+mod role_store;
+mod role_store_error;
 mod two_fa_code;
 mod two_fa_code_store;
 mod two_fa_code_store_error;
@@ -11,12 +21,22 @@ mod user;
 mod user_store;
 mod user_store_error;
 
+pub use api_key::*;
+pub use api_key_store::*;
+pub use api_key_store_error::*;
 pub use banned_token_store::*;
 pub use banned_token_store_error::*;
 pub use email::*;
 pub use error::*;
 pub use login_attempt_id::*;
+pub use oauth_client::*;
+pub use oauth_client_store::*;
+pub use oauth_client_store_error::*;
+pub use oauth_grant_store::*;
 pub use password::*;
+pub use role::*;
+pub use role_store::*;
+pub use role_store_error::*;
 pub use two_fa_code::*;
 pub use two_fa_code_store::*;
 pub use two_fa_code_store_error::*;
@@ -0,0 +1,162 @@
+use secrecy::Secret;
+
+use crate::utils::{constant_time_eq, hash_opaque_token, EMAIL_SCOPE, OPENID_SCOPE};
+
+// An application that may use the /oauth endpoints. Confidential clients (servers, API
+// gateways, backend jobs) authenticate with their secret; public clients (mobile and
+// single-page apps) have none and rely on PKCE instead.
+#[derive(Debug, Clone)]
+pub struct OAuthClient {
+    pub client_id: String,
+    // Shown on the consent page
+    pub client_name: String,
+    // Only the hash is kept, so a leaked store does not leak the secrets
+    pub client_secret_hash: Option<String>,
+    // Exact URIs the authorization code may be sent to
+    pub redirect_uris: Vec<String>,
+    // What the client may request, from users besides the OpenID Connect scopes and for
+    // itself with the `client_credentials` grant; clients without any cannot use that grant
+    pub scopes: Vec<String>,
+}
+
+impl OAuthClient {
+    pub fn new(client_id: String, client_secret: &Secret<String>) -> Self {
+        Self {
+            client_name: client_id.clone(),
+            client_id,
+            client_secret_hash: Some(hash_opaque_token(client_secret)),
+            redirect_uris: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn is_confidential(&self) -> bool {
+        self.client_secret_hash.is_some()
+    }
+
+    pub fn verify_secret(&self, client_secret: &Secret<String>) -> bool {
+        self.client_secret_hash
+            .as_ref()
+            .is_some_and(|expected| constant_time_eq(expected, &hash_opaque_token(client_secret)))
+    }
+
+    // The scope of a `client_credentials` token: all the client's scopes when none are
+    // requested, otherwise the requested ones if the client has each of them
+    pub fn grant_scope(&self, requested: Option<&str>) -> Option<String> {
+        let Some(requested) = requested.filter(|scope| !scope.trim().is_empty()) else {
+            return Some(self.scopes.join(" "));
+        };
+
+        self.allowed_scope(requested, &[])
+    }
+
+    // The scope a user may grant the client: the requested scopes if the client has each of
+    // them or they are the OpenID Connect ones, which every client may ask for
+    pub fn authorization_scope(&self, requested: &str) -> Option<String> {
+        self.allowed_scope(requested, &[OPENID_SCOPE, EMAIL_SCOPE])
+    }
+
+    fn allowed_scope(&self, requested: &str, also_allowed: &[&str]) -> Option<String> {
+        let requested: Vec<&str> = requested.split_whitespace().collect();
+
+        requested
+            .iter()
+            .all(|scope| {
+                also_allowed.contains(scope) || self.scopes.iter().any(|allowed| allowed == scope)
+            })
+            .then(|| requested.join(" "))
+    }
+
+    // Resolves the redirect URI of an authorization request. Registered URIs are compared
+    // as strings, and may only be omitted when the client has exactly one.
+    pub fn redirect_uri<'a>(&'a self, requested: Option<&'a str>) -> Option<&'a str> {
+        match requested {
+            Some(uri) => self
+                .redirect_uris
+                .iter()
+                .any(|registered| registered == uri)
+                .then_some(uri),
+            None => match self.redirect_uris.as_slice() {
+                [uri] => Some(uri),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_uri_must_be_registered() {
+        let mut client = OAuthClient::new("app".to_owned(), &Secret::new("secret".to_owned()));
+        client.redirect_uris = vec!["https://app.example.com/callback".to_owned()];
+
+        assert_eq!(
+            client.redirect_uri(Some("https://app.example.com/callback")),
+            Some("https://app.example.com/callback")
+        );
+        assert_eq!(
+            client.redirect_uri(None),
+            Some("https://app.example.com/callback")
+        );
+        assert_eq!(
+            client.redirect_uri(Some("https://app.example.com/callback/../evil")),
+            None
+        );
+        assert_eq!(
+            client.redirect_uri(Some("https://app.example.com/callback?next=/")),
+            None
+        );
+
+        client
+            .redirect_uris
+            .push("com.example.app:/callback".to_owned());
+        assert_eq!(client.redirect_uri(None), None);
+    }
+
+    #[test]
+    fn test_only_the_hash_of_the_secret_is_kept() {
+        let client = OAuthClient::new("app".to_owned(), &Secret::new("s3cret".to_owned()));
+
+        assert_ne!(client.client_secret_hash.as_deref(), Some("s3cret"));
+        assert!(client.verify_secret(&Secret::new("s3cret".to_owned())));
+        assert!(!client.verify_secret(&Secret::new("s3cre".to_owned())));
+    }
+
+    #[test]
+    fn test_grant_scope_is_limited_to_the_client_scopes() {
+        let client = OAuthClient {
+            scopes: vec!["reports:read".to_owned(), "reports:write".to_owned()],
+            ..OAuthClient::new("job".to_owned(), &Secret::new("s3cret".to_owned()))
+        };
+
+        assert_eq!(
+            client.grant_scope(None).as_deref(),
+            Some("reports:read reports:write")
+        );
+        assert_eq!(
+            client.grant_scope(Some(" reports:read ")).as_deref(),
+            Some("reports:read")
+        );
+        assert_eq!(client.grant_scope(Some("reports:read admin")), None);
+        assert_eq!(client.grant_scope(Some("openid")), None);
+    }
+
+    #[test]
+    fn test_authorization_scope_adds_the_openid_scopes() {
+        let client = OAuthClient {
+            scopes: vec!["reports:read".to_owned()],
+            ..OAuthClient::new("app".to_owned(), &Secret::new("s3cret".to_owned()))
+        };
+
+        assert_eq!(
+            client
+                .authorization_scope("openid  email reports:read")
+                .as_deref(),
+            Some("openid email reports:read")
+        );
+        assert_eq!(client.authorization_scope("openid reports:write"), None);
+    }
+}
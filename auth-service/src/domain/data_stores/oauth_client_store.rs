@@ -0,0 +1,15 @@
+use secrecy::Secret;
+
+use crate::domain::data_stores::{OAuthClient, OAuthClientStoreError};
+
+#[async_trait::async_trait]
+pub trait OAuthClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError>;
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, OAuthClientStoreError>;
+    // Unknown clients, public clients and wrong secrets are all `InvalidClientCredentials`
+    async fn validate_client(
+        &self,
+        client_id: &str,
+        client_secret: &Secret<String>,
+    ) -> Result<(), OAuthClientStoreError>;
+}
@@ -0,0 +1,29 @@
+use color_eyre::eyre::Report;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OAuthClientStoreError {
+    #[error("Client already exists")]
+    ClientAlreadyExists,
+    #[error("Client not found")]
+    ClientNotFound,
+    #[error("Invalid client credentials")]
+    InvalidClientCredentials,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OAuthClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientAlreadyExists, Self::ClientAlreadyExists)
+                | (Self::ClientNotFound, Self::ClientNotFound)
+                | (
+                    Self::InvalidClientCredentials,
+                    Self::InvalidClientCredentials
+                )
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
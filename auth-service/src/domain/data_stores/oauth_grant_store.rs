@@ -0,0 +1,74 @@
+use color_eyre::eyre::Result;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// What an authorization code stands for, checked again when it is exchanged for tokens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorizationCodeGrant {
+    pub client_id: String,
+    pub user_id: Uuid,
+    pub scope: Option<String>,
+    pub redirect_uri: String,
+    // Whether the authorization request named `redirect_uri`, in which case the exchange must
+    // name it again
+    #[serde(default)]
+    pub redirect_uri_sent: bool,
+    // The S256 PKCE challenge
+    pub code_challenge: String,
+    // OpenID Connect: echoed in the ID token
+    #[serde(default)]
+    pub nonce: Option<String>,
+    // When and how the user logged in, carried into ID tokens
+    #[serde(default)]
+    pub auth_time: usize,
+    #[serde(default)]
+    pub amr: Vec<String>,
+}
+
+// What a refresh token stands for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshTokenGrant {
+    pub client_id: String,
+    pub user_id: Uuid,
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub auth_time: usize,
+    #[serde(default)]
+    pub amr: Vec<String>,
+}
+
+// Authorization codes and refresh tokens issued by the /oauth endpoints. Both are single
+// use: taking one removes it, so a replayed code or rotated refresh token is unknown.
+#[async_trait::async_trait]
+pub trait OAuthGrantStore {
+    async fn add_authorization_code(
+        &mut self,
+        code: &Secret<String>,
+        grant: AuthorizationCodeGrant,
+    ) -> Result<()>;
+    async fn take_authorization_code(
+        &mut self,
+        code: &Secret<String>,
+    ) -> Result<Option<AuthorizationCodeGrant>>;
+    async fn add_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+        grant: RefreshTokenGrant,
+    ) -> Result<()>;
+    async fn take_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+    ) -> Result<Option<RefreshTokenGrant>>;
+    // Looks a refresh token up without using it, e.g. to check whose it is before revoking it
+    async fn get_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+    ) -> Result<Option<RefreshTokenGrant>>;
+    // Deletes every refresh token of the user, e.g. when their sessions are ended
+    async fn revoke_refresh_tokens(&mut self, user_id: Uuid) -> Result<()>;
+    // Checks the connection to the backing service; in-memory stores are always healthy
+    async fn health_check(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
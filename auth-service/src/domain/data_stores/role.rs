@@ -0,0 +1,13 @@
+// A named set of permissions, e.g. `admin` with `users:manage`. Users are assigned roles;
+// services check the permissions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Role {
+    pub name: String,
+    pub permissions: Vec<String>,
+}
+
+impl Role {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| granted == permission)
+    }
+}
@@ -0,0 +1,22 @@
+use uuid::Uuid;
+
+use crate::domain::data_stores::{Role, RoleStoreError};
+
+#[async_trait::async_trait]
+pub trait RoleStore {
+    async fn add_role(&mut self, role: Role) -> Result<(), RoleStoreError>;
+    // By name
+    async fn list_roles(&self) -> Result<Vec<Role>, RoleStoreError>;
+    // Unknown roles are `RoleNotFound`; assigning a role twice is not an error
+    async fn assign_role(&mut self, user_id: Uuid, role: &str) -> Result<(), RoleStoreError>;
+    // `RoleNotFound` unless the user has the role
+    async fn unassign_role(&mut self, user_id: Uuid, role: &str) -> Result<(), RoleStoreError>;
+    // The names of the user's roles, sorted
+    async fn get_user_roles(&self, user_id: Uuid) -> Result<Vec<String>, RoleStoreError>;
+    // Whether any of `roles` grants `permission`
+    async fn has_permission(
+        &self,
+        roles: &[String],
+        permission: &str,
+    ) -> Result<bool, RoleStoreError>;
+}
@@ -0,0 +1,23 @@
+use color_eyre::eyre::Report;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RoleStoreError {
+    #[error("Role already exists")]
+    RoleAlreadyExists,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RoleStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RoleAlreadyExists, Self::RoleAlreadyExists)
+                | (Self::RoleNotFound, Self::RoleNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
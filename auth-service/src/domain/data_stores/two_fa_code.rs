@@ -76,6 +76,6 @@ mod tests {
     async fn test_default_2fa_code() {
         let test_code = crate::domain::TwoFACode::default();
         assert_eq!(6, test_code.0.expose_secret().len());
-        assert!(test_code.0.expose_secret().chars().all(|c| c.is_digit(10)));
+        assert!(test_code.0.expose_secret().chars().all(|c| c.is_ascii_digit()));
     }
 }
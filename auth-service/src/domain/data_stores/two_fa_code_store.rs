@@ -1,3 +1,5 @@
+use color_eyre::eyre::Result;
+
 use crate::domain::data_stores::{Email, LoginAttemptId, TwoFACode, TwoFACodeStoreError};
 
 #[async_trait::async_trait]
@@ -13,4 +15,8 @@ pub trait TwoFACodeStore {
         &mut self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    // Checks the connection to the backing service; in-memory stores are always healthy
+    async fn health_check(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
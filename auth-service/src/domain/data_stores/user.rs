@@ -1,18 +1,29 @@
+use uuid::Uuid;
+
 use crate::domain::{Email, Password};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
+    // Stable across email changes; the `sub` of the user's tokens
+    pub id: Uuid,
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    // Locked accounts cannot log in; set by administrators
+    pub locked: bool,
+    // The user must choose a new password before logging in again
+    pub password_reset_required: bool,
 }
 
 impl User {
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
         Self {
+            id: Uuid::new_v4(),
             email,
             password,
             requires_2fa,
+            locked: false,
+            password_reset_required: false,
         }
     }
 }
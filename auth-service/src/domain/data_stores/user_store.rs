@@ -1,9 +1,55 @@
+use uuid::Uuid;
+
 use crate::domain::data_stores::{Email, Password, User, UserStoreError};
 
+// A page of users for administrators, ordered by email
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserQuery {
+    // Case-insensitive part of the email address
+    pub search: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    // Users matching the search, on every page
+    pub total: usize,
+}
+
+// Account settings to change together; `None` leaves a setting as it is
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserSettings {
+    pub requires_2fa: Option<bool>,
+    pub locked: Option<bool>,
+    pub password_reset_required: Option<bool>,
+}
+
 #[async_trait::async_trait]
 pub trait UserStore {
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(&self, id: Uuid) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+    async fn update_settings(
+        &mut self,
+        id: Uuid,
+        settings: &UserSettings,
+    ) -> Result<(), UserStoreError>;
+    // Also clears `password_reset_required`
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    // `UserAlreadyExists` when another user has `new_email`
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), UserStoreError>;
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
 }
@@ -5,4 +5,8 @@ use crate::domain::data_stores::Email;
 #[async_trait::async_trait]
 pub trait EmailClient {
     async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()>;
+    // Checks that the email provider is reachable; clients without a remote provider are always healthy
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
 }
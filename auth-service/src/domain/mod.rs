@@ -1,5 +1,7 @@
+mod audit;
 mod data_stores;
 mod email_client;
 
+pub use audit::*;
 pub use data_stores::*;
 pub use email_client::*;
@@ -1,59 +1,128 @@
-use std::error::Error;
+use std::{error::Error, future::IntoFuture, time::Duration};
 
 use axum::{
-    http::{HeaderValue, StatusCode},
+    http::{header, HeaderName, StatusCode},
+    middleware,
     response::{IntoResponse, Response},
-    routing::post,
-    serve::Serve,
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use redis::{Client, RedisResult};
-use reqwest::Method;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use tokio::{net::TcpListener, time::Instant};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
+use utoipa::ToSchema;
 
 use crate::{
-    domain::AuthAPIError,
-    routes::{login, logout, signup, verify_2fa, verify_token},
-    utils::{make_span_with_request_id, on_request, on_response, DROPLET_IP},
+    config::{CorsSettings, Settings},
+    domain::{AuthAPIError, OAuthError},
+    routes::{
+        api_docs, assign_role, audit_events, change_email, change_password, confirm_email_change,
+        create_api_key, create_role, delete_user, get_user_details, health_live, health_ready,
+        jwks, list_api_keys, list_roles, list_users, login, logout, metrics, oauth_authorize,
+        oauth_consent, oauth_introspect, oauth_revoke, oauth_token, openapi_json,
+        openid_configuration, register_oauth_client, revoke_api_key, revoke_user_sessions, signup,
+        unassign_role, update_user, userinfo, verify_2fa, verify_token,
+    },
+    utils::{
+        make_span_with_request_id, negotiate_error_format, on_request, on_response, track_metrics,
+        ProblemDetails, ShutdownHandle, REQUEST_ID_HEADER,
+    },
 };
 use app_state::AppState;
 
+pub use auth_types::ErrorResponse;
+
 pub mod app_state;
+pub mod cli;
+pub mod config;
 pub mod domain;
 pub mod routes;
 pub mod services;
 pub mod utils;
 
-#[derive(Serialize, Deserialize)]
-pub struct ErrorResponse {
+// The RFC 6749 error body of the /oauth endpoints
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OAuthErrorResponse {
     pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        log_error_chain(&self);
+
+        let status = match self {
+            OAuthError::InvalidClient => StatusCode::UNAUTHORIZED,
+            OAuthError::InvalidRequest(_)
+            | OAuthError::InvalidGrant(_)
+            | OAuthError::UnsupportedGrantType
+            | OAuthError::UnauthorizedClient(_)
+            | OAuthError::InvalidScope => StatusCode::BAD_REQUEST,
+            OAuthError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = Json(OAuthErrorResponse {
+            error: self.code().to_owned(),
+            error_description: Some(self.to_string()),
+        });
+
+        match self {
+            // RFC 6749 section 5.2: tell the client which scheme to authenticate with
+            OAuthError::InvalidClient => (
+                status,
+                [(header::WWW_AUTHENTICATE, r#"Basic realm="oauth""#)],
+                body,
+            )
+                .into_response(),
+            _ => (status, body).into_response(),
+        }
+    }
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
 
-        let (status, error_message) = match self {
-            AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
-            AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
-            AuthAPIError::IncorrectCredentials => {
-                (StatusCode::UNAUTHORIZED, "Incorrect credentials")
-            }
-            AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token"),
-            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
-            AuthAPIError::UnexpectedError(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
+        let status = match self {
+            AuthAPIError::UserAlreadyExists => StatusCode::CONFLICT,
+            AuthAPIError::InvalidCredentials(_) | AuthAPIError::InvalidRequest(_) => {
+                StatusCode::BAD_REQUEST
             }
+            AuthAPIError::IncorrectCredentials => StatusCode::UNAUTHORIZED,
+            AuthAPIError::MissingToken => StatusCode::BAD_REQUEST,
+            AuthAPIError::InvalidToken => StatusCode::UNAUTHORIZED,
+            AuthAPIError::ApiKeyNotFound => StatusCode::NOT_FOUND,
+            AuthAPIError::UserNotFound => StatusCode::NOT_FOUND,
+            AuthAPIError::RoleAlreadyExists => StatusCode::CONFLICT,
+            AuthAPIError::RoleNotFound => StatusCode::NOT_FOUND,
+            AuthAPIError::Forbidden
+            | AuthAPIError::AccountLocked
+            | AuthAPIError::PasswordResetRequired => StatusCode::FORBIDDEN,
+            AuthAPIError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
+        // Display is the message; for unexpected errors it never includes the cause
+        let message = self.to_string();
+        let errors = self.field_errors().to_vec();
+        let problem = ProblemDetails::new(self.code(), &message, status.as_u16(), errors.clone());
+
         let body = Json(ErrorResponse {
-            error: error_message.to_string(),
+            error: message,
+            code: self.code().to_owned(),
+            errors,
         });
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        response.extensions_mut().insert(problem);
+        response
     }
 }
 
@@ -72,23 +141,30 @@ fn log_error_chain(e: &(dyn Error + 'static)) {
 }
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    listener: TcpListener,
+    router: Router,
+    app_state: AppState,
+    shutdown: ShutdownHandle,
+    shutdown_timeout: Duration,
     // Address is pub so tests know it
     pub address: String,
 }
 
 impl Application {
-    pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = vec![
-            "http://localhost:8000".parse::<HeaderValue>()?,
-            format!("http:{}//:8000", *DROPLET_IP).parse::<HeaderValue>()?,
-        ];
+    pub async fn build(app_state: AppState, settings: &Settings) -> Result<Self, Box<dyn Error>> {
+        let cors_settings = &settings.application.cors;
+        let allowed_origins = cors_settings.origin_patterns()?;
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods(cors_settings.methods()?)
+            .allow_headers(cors_settings.headers()?)
             // Allow cookies to be included in requests
             .allow_credentials(true)
-            .allow_origin(allowed_origins);
+            // Let browser clients read the request id for support requests
+            .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)])
+            .allow_origin(AllowOrigin::predicate(move |origin, _| {
+                CorsSettings::is_allowed(&allowed_origins, origin)
+            }));
 
         let router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
@@ -97,27 +173,110 @@ impl Application {
             .route("/logout", post(logout))
             .route("/verify-2fa", post(verify_2fa))
             .route("/verify-token", post(verify_token))
-            .with_state(app_state)
+            .route("/account/password", post(change_password))
+            .route("/account/email", post(change_email))
+            .route("/account/email/confirm", post(confirm_email_change))
+            .route("/api-keys", get(list_api_keys).post(create_api_key))
+            .route("/api-keys/:id", delete(revoke_api_key))
+            .route("/oauth/authorize", get(oauth_authorize).post(oauth_consent))
+            .route("/oauth/token", post(oauth_token))
+            .route("/oauth/introspect", post(oauth_introspect))
+            .route("/oauth/revoke", post(oauth_revoke))
+            .route(
+                "/.well-known/openid-configuration",
+                get(openid_configuration),
+            )
+            .route("/.well-known/jwks.json", get(jwks))
+            .route("/userinfo", get(userinfo).post(userinfo))
+            .route("/health/live", get(health_live))
+            .route("/health/ready", get(health_ready))
+            .route("/metrics", get(metrics))
+            .route("/admin/audit-events", get(audit_events))
+            .route("/admin/oauth/clients", post(register_oauth_client))
+            .route("/admin/roles", get(list_roles).post(create_role))
+            .route("/admin/users", get(list_users))
+            .route(
+                "/admin/users/:user",
+                get(get_user_details).patch(update_user).delete(delete_user),
+            )
+            .route("/admin/users/:user/sessions", delete(revoke_user_sessions))
+            .route(
+                "/admin/users/:user/roles/:role",
+                put(assign_role).delete(unassign_role),
+            )
+            .route("/openapi.json", get(openapi_json))
+            .route("/docs", get(api_docs))
+            // A route layer, so the matched route template is known when recording
+            .route_layer(middleware::from_fn(track_metrics))
+            .layer(middleware::from_fn(negotiate_error_format))
+            .with_state(app_state.clone())
             .layer(cors)
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
                     .on_response(on_response),
-            );
+            )
+            // Echo the request id on every response, errors included
+            .layer(PropagateRequestIdLayer::x_request_id())
+            // Keep the caller's X-Request-Id, or generate one, before anything is logged
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
-        let listener = tokio::net::TcpListener::bind(address).await?;
+        let listener = TcpListener::bind(&settings.application.address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
 
-        let app = Application { server, address };
+        let app = Application {
+            listener,
+            router,
+            app_state,
+            shutdown: ShutdownHandle::default(),
+            shutdown_timeout: settings.application.shutdown_timeout(),
+            address,
+        };
 
         Ok(app)
     }
 
+    // Triggering the handle makes `run` stop accepting connections, drain in-flight
+    // requests for up to the configured shutdown timeout and close the stores
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
     pub async fn run(self) -> Result<(), std::io::Error> {
         tracing::info!("listening on {}", &self.address);
-        self.server.await
+
+        let shutdown = self.shutdown.clone();
+        let server = axum::serve(self.listener, self.router)
+            .with_graceful_shutdown(async move { shutdown.wait().await })
+            .into_future();
+        tokio::pin!(server);
+
+        tokio::select! {
+            result = &mut server => return result,
+            _ = self.shutdown.wait() => {}
+        }
+
+        tracing::info!("shutting down, draining in-flight requests");
+        let deadline = Instant::now() + self.shutdown_timeout;
+
+        match tokio::time::timeout_at(deadline, server).await {
+            Ok(result) => result?,
+            Err(_) => tracing::warn!(
+                timeout = ?self.shutdown_timeout,
+                "shutdown timeout elapsed, dropping in-flight requests"
+            ),
+        }
+
+        if tokio::time::timeout_at(deadline, self.app_state.close())
+            .await
+            .is_err()
+        {
+            tracing::warn!("shutdown timeout elapsed before connections were closed");
+        }
+
+        tracing::info!("shutdown complete");
+        Ok(())
     }
 }
 
@@ -132,3 +291,12 @@ pub fn get_redis_client(redis_hostname: Secret<String>) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname.expose_secret());
     redis::Client::open(redis_url)
 }
+
+// Pings over a new async connection, so unlike a blocking one it stops when its caller times
+// out
+pub async fn ping_redis(client: &Client) -> RedisResult<()> {
+    let mut conn = client.get_multiplexed_tokio_connection().await?;
+    redis::cmd("PING").query_async::<_, String>(&mut conn).await?;
+
+    Ok(())
+}
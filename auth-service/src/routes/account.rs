@@ -0,0 +1,314 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    domain::{field_error, AuditEvent, AuthAPIError, Email, FieldError, Password, UserStoreError},
+    routes::{session, TokenDelivery, TokenResponse},
+    utils::{
+        create_auth_cookie, generate_email_change_token, remaining_lifetime, renew_auth_token,
+        revoke_other_sessions, serialize_secret, validate_email_change_token, AuthToken, METRICS,
+    },
+    AppState, ErrorResponse,
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChangePasswordRequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = Password)]
+    pub current_password: Secret<String>,
+    // At least 8 characters
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = Password, min_length = 8)]
+    pub new_password: Secret<String>,
+    // Where to return the session that replaces the caller's
+    #[serde(default, rename = "tokenDelivery")]
+    pub token_delivery: TokenDelivery,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChangeEmailRequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = "email")]
+    pub new_email: Secret<String>,
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = Password)]
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConfirmEmailChangeRequest {
+    // From the confirmation sent to the new address
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String)]
+    pub token: Secret<String>,
+}
+
+fn invalid_field(field: &'static str, code: &'static str, e: impl ToString) -> AuthAPIError {
+    AuthAPIError::InvalidRequest(vec![FieldError::new(field, code, e)])
+}
+
+// Sensitive changes need the password again, not just a session that may have been left open
+async fn reauthenticate(
+    state: &AppState,
+    email: &Email,
+    password: Secret<String>,
+) -> Result<(), AuthAPIError> {
+    let password = Password::parse(password).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    match state
+        .user_store
+        .read()
+        .await
+        .validate_user(email, &password)
+        .await
+    {
+        Ok(()) => Ok(()),
+        Err(UserStoreError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+        Err(_) => Err(AuthAPIError::IncorrectCredentials),
+    }
+}
+
+async fn send_email(
+    state: &AppState,
+    recipient: &Email,
+    subject: &str,
+    content: &str,
+) -> Result<(), AuthAPIError> {
+    let result = state
+        .email_client_type
+        .write()
+        .await
+        .send_email(recipient, subject, content)
+        .await;
+
+    result.map_err(|e| {
+        METRICS.email_send_failures_total.inc();
+        AuthAPIError::UnexpectedError(e)
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/password",
+    tag = "account",
+    request_body = ChangePasswordRequest,
+    security(("jwt_bearer" = []), ("jwt_cookie" = [])),
+    responses(
+        (status = 200, description = "Password changed, and every other session and OAuth refresh token ended. With `tokenDelivery: cookie` (default) the `jwt` cookie is replaced; otherwise the new token is in the body.", body = TokenResponse),
+        (status = 400, description = "The new password is too weak (`invalid_request`), or no bearer token or `jwt` cookie (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Wrong current password (`incorrect_credentials`), or an invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    AuthToken(token): AuthToken,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let (user, claims) = session(&state, token).await?;
+    let email = user.email;
+    let new_password = Password::parse(request.new_password)
+        .map_err(|e| invalid_field("new_password", field_error::TOO_WEAK, e))?;
+    reauthenticate(&state, &email, request.current_password).await?;
+
+    match state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, new_password)
+        .await
+    {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    revoke_other_sessions(state.banned_token_store.clone(), &user.id.to_string())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    // OAuth clients would otherwise keep getting access tokens for the account
+    state
+        .oauth_grant_store
+        .write()
+        .await
+        .revoke_refresh_tokens(user.id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    state
+        .audit(AuditEvent::PasswordChanged {
+            email: email.value().to_owned(),
+        })
+        .await;
+
+    // The caller's own session was revoked with the others, so it is replaced
+    let roles = state
+        .role_store
+        .read()
+        .await
+        .get_user_roles(user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let token = renew_auth_token(user.id, &roles, &claims, &state.auth_settings)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(match request.token_delivery {
+        TokenDelivery::Cookie => (
+            jar.add(create_auth_cookie(token)),
+            StatusCode::OK.into_response(),
+        ),
+        TokenDelivery::Body => (jar, Json(TokenResponse { token }).into_response()),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/email",
+    tag = "account",
+    request_body = ChangeEmailRequest,
+    security(("jwt_bearer" = []), ("jwt_cookie" = [])),
+    responses(
+        (status = 202, description = "A confirmation was sent to the new address; the email is unchanged until it is used at `/account/email/confirm`, within an hour"),
+        (status = 400, description = "Invalid new email (`invalid_request`), or no bearer token or `jwt` cookie (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Wrong password (`incorrect_credentials`), or an invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 409, description = "The new address belongs to another account (`user_already_exists`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Request email change", skip_all)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let (user, _) = session(&state, token).await?;
+    let email = user.email;
+    let new_email = Email::parse(request.new_email)
+        .map_err(|e| invalid_field("new_email", field_error::INVALID_FORMAT, e))?;
+    if new_email == email {
+        return Err(invalid_field(
+            "new_email",
+            field_error::INVALID_FORMAT,
+            "must differ from the current email",
+        ));
+    }
+    reauthenticate(&state, &email, request.password).await?;
+
+    // Checked again on confirmation, as the address may be taken in the meantime
+    match state.user_store.read().await.get_user(&new_email).await {
+        Ok(_) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let token = generate_email_change_token(user.id, &new_email, &state.auth_settings)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    send_email(
+        &state,
+        &new_email,
+        "Confirm your new email address",
+        &format!(
+            "To use this address for your account, submit this token to \
+            /account/email/confirm within an hour:\n\n{}",
+            token
+        ),
+    )
+    .await?;
+    state
+        .audit(AuditEvent::EmailChangeRequested {
+            email: email.value().to_owned(),
+            new_email: new_email.value().to_owned(),
+        })
+        .await;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/email/confirm",
+    tag = "account",
+    request_body = ConfirmEmailChangeRequest,
+    responses(
+        (status = 204, description = "Email changed and, unless sending fails, the old address notified. Roles, API keys and sessions are unaffected."),
+        (status = 401, description = "Invalid, expired or already used token (`invalid_token`)", body = ErrorResponse),
+        (status = 409, description = "The new address was taken since the change was requested (`user_already_exists`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Confirm email change", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    Json(request): Json<ConfirmEmailChangeRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let claims = validate_email_change_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        &request.token,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let ttl_seconds = remaining_lifetime(claims.exp);
+    let new_email =
+        Email::parse(Secret::new(claims.new_email)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // The account was deleted since the change was requested
+    let email = match state.user_store.read().await.get_user_by_id(user_id).await {
+        Ok(user) => user.email,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // The only change to the account, as roles, API keys and sessions name the user by id
+    match state
+        .user_store
+        .write()
+        .await
+        .update_email(&email, &new_email)
+        .await
+    {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(request.token, ttl_seconds)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    state
+        .audit(AuditEvent::EmailChanged {
+            email: email.value().to_owned(),
+            new_email: new_email.value().to_owned(),
+        })
+        .await;
+
+    // So the owner of the old address learns of a change they did not make. The change has
+    // been made by now, so a failure to send is only logged.
+    if let Err(e) = send_email(
+        &state,
+        &email,
+        "Your email address was changed",
+        &format!(
+            "Your account now uses {}. If you did not make this change, contact support.",
+            new_email.value()
+        ),
+    )
+    .await
+    {
+        tracing::error!(error = ?e, "failed to notify the old address of an email change");
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
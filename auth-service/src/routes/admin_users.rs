@@ -0,0 +1,336 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        field_error, AuditEvent, AuthAPIError, Email, FieldError, User, UserQuery, UserSettings,
+        UserStoreError,
+    },
+    routes::ApiKeyInfo,
+    utils::{end_user_sessions, AdminAccess},
+    AppState, ErrorResponse,
+};
+
+pub const DEFAULT_USERS_LIMIT: usize = 50;
+pub const MAX_USERS_LIMIT: usize = 500;
+
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListUsersParams {
+    // Case-insensitive part of the email address
+    pub search: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserSummary {
+    pub id: Uuid,
+    pub email: String,
+    pub requires_2fa: bool,
+    pub locked: bool,
+    pub password_reset_required: bool,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            email: user.email.value().to_owned(),
+            requires_2fa: user.requires_2fa,
+            locked: user.locked,
+            password_reset_required: user.password_reset_required,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ListUsersResponse {
+    pub users: Vec<UserSummary>,
+    // Users matching the search, for paging through them
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserDetails {
+    #[serde(flatten)]
+    pub user: UserSummary,
+    pub roles: Vec<String>,
+    pub api_keys: Vec<ApiKeyInfo>,
+}
+
+// Omitted settings are left as they are
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct UpdateUserRequest {
+    pub requires_2fa: Option<bool>,
+    // Locking also ends the user's sessions
+    pub locked: Option<bool>,
+    // Forcing a reset also ends the user's sessions
+    pub password_reset_required: Option<bool>,
+}
+
+// The user named in an admin path by id or email, who must have an account
+pub(crate) async fn existing_user(state: &AppState, user: String) -> Result<User, AuthAPIError> {
+    let result = match Uuid::parse_str(&user) {
+        Ok(id) => state.user_store.read().await.get_user_by_id(id).await,
+        Err(_) => {
+            let email = Email::parse(Secret::new(user)).map_err(|e| {
+                AuthAPIError::InvalidRequest(vec![FieldError::new(
+                    "user",
+                    field_error::INVALID_FORMAT,
+                    e,
+                )])
+            })?;
+            state.user_store.read().await.get_user(&email).await
+        }
+    };
+
+    match result {
+        Ok(user) => Ok(user),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::UserNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+async fn user_details(state: &AppState, user: User) -> Result<UserDetails, AuthAPIError> {
+    let roles = state
+        .role_store
+        .read()
+        .await
+        .get_user_roles(user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let api_keys = state
+        .api_key_store
+        .read()
+        .await
+        .list_keys(user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(UserDetails {
+        user: user.into(),
+        roles,
+        api_keys: api_keys.into_iter().map(ApiKeyInfo::from).collect(),
+    })
+}
+
+async fn end_sessions(state: &AppState, user_id: Uuid) -> Result<(), AuthAPIError> {
+    end_user_sessions(state, user_id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    tag = "admin",
+    params(ListUsersParams),
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 200, description = "A page of matching users, by email", body = ListUsersResponse),
+        (status = 400, description = "No bearer token (`missing_token`), or a malformed query"),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "List users", skip_all)]
+pub async fn list_users(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Query(params): Query<ListUsersParams>,
+) -> Result<Json<ListUsersResponse>, AuthAPIError> {
+    let query = UserQuery {
+        search: params.search.filter(|search| !search.is_empty()),
+        offset: params.offset.unwrap_or(0),
+        limit: params
+            .limit
+            .unwrap_or(DEFAULT_USERS_LIMIT)
+            .min(MAX_USERS_LIMIT),
+    };
+
+    let page = state
+        .user_store
+        .read()
+        .await
+        .list_users(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ListUsersResponse {
+        users: page.users.into_iter().map(UserSummary::from).collect(),
+        total: page.total,
+        offset: query.offset,
+        limit: query.limit,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{user}",
+    tag = "admin",
+    params(("user" = String, Path, description = "The user's id or email address")),
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 200, description = "The user's settings, roles and API keys", body = UserDetails),
+        (status = 400, description = "Invalid user id or email (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 404, description = "No such user (`user_not_found`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Get user details", skip_all)]
+pub async fn get_user_details(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Path(user): Path<String>,
+) -> Result<Json<UserDetails>, AuthAPIError> {
+    let user = existing_user(&state, user).await?;
+
+    Ok(Json(user_details(&state, user).await?))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/admin/users/{user}",
+    tag = "admin",
+    params(("user" = String, Path, description = "The user's id or email address")),
+    request_body = UpdateUserRequest,
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 200, description = "The updated user", body = UserDetails),
+        (status = 400, description = "Invalid user id or email (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 404, description = "No such user (`user_not_found`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Update user", skip_all)]
+pub async fn update_user(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Path(user): Path<String>,
+    Json(request): Json<UpdateUserRequest>,
+) -> Result<Json<UserDetails>, AuthAPIError> {
+    let User { id, email, .. } = existing_user(&state, user).await?;
+
+    let settings = UserSettings {
+        requires_2fa: request.requires_2fa,
+        locked: request.locked,
+        password_reset_required: request.password_reset_required,
+    };
+    let result = state
+        .user_store
+        .write()
+        .await
+        .update_settings(id, &settings)
+        .await;
+    match result {
+        Ok(()) => {}
+        // Deleted since it was looked up
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::UserNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    if request.locked == Some(true) || request.password_reset_required == Some(true) {
+        end_sessions(&state, id).await?;
+    }
+    if settings != UserSettings::default() {
+        state
+            .audit(AuditEvent::UserUpdated {
+                email: email.value().to_owned(),
+                requires_2fa: request.requires_2fa,
+                locked: request.locked,
+                password_reset_required: request.password_reset_required,
+            })
+            .await;
+    }
+
+    let user = existing_user(&state, id.to_string()).await?;
+    Ok(Json(user_details(&state, user).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{user}",
+    tag = "admin",
+    params(("user" = String, Path, description = "The user's id or email address")),
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 204, description = "The account, its roles and API keys are deleted, and its sessions ended"),
+        (status = 400, description = "Invalid user id or email (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 404, description = "No such user (`user_not_found`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Delete user", skip_all)]
+pub async fn delete_user(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Path(user): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let User { id, email, .. } = existing_user(&state, user).await?;
+
+    // PostgreSQL cascades role assignments and API keys. The in-memory stores keep
+    // them, but under an id no other account will have
+    match state.user_store.write().await.delete_user(&email).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::UserNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    end_sessions(&state, id).await?;
+    state
+        .audit(AuditEvent::UserDeleted {
+            email: email.value().to_owned(),
+        })
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{user}/sessions",
+    tag = "admin",
+    params(("user" = String, Path, description = "The user's id or email address")),
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 204, description = "Every token issued to the user so far is rejected; API keys keep working"),
+        (status = 400, description = "Invalid user id or email (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 404, description = "No such user (`user_not_found`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Revoke user sessions", skip_all)]
+pub async fn revoke_user_sessions(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Path(user): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let User { id, email, .. } = existing_user(&state, user).await?;
+
+    end_sessions(&state, id).await?;
+    state
+        .audit(AuditEvent::SessionsRevoked {
+            email: email.value().to_owned(),
+        })
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
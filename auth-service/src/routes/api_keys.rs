@@ -0,0 +1,262 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    domain::{field_error, ApiKey, ApiKeyStoreError, AuditEvent, AuthAPIError, FieldError, User},
+    utils::{generate_api_key, hash_opaque_token, token_user, validate_token, AuthToken, Claims},
+    AppState, ErrorResponse,
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    // What the key may be used for; callers check these the way they check OAuth scopes
+    pub scopes: Vec<String>,
+    // Without one, the key works until it is revoked
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<ApiKey> for ApiKeyInfo {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            name: key.name,
+            prefix: key.prefix,
+            scopes: key.scopes,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiKeyResponse {
+    // Only returned here; it cannot be retrieved again
+    pub key: String,
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ListApiKeysResponse {
+    pub keys: Vec<ApiKeyInfo>,
+}
+
+// Keys and accounts are managed from a login session only: neither a key nor a token issued
+// to an OAuth client can mint or revoke keys
+pub(crate) async fn session(
+    state: &AppState,
+    token: Secret<String>,
+) -> Result<(User, Claims), AuthAPIError> {
+    let claims = validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        token,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+    if claims.client_id.is_some() || claims.is_service() {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    // The account may have been deleted since the token was issued
+    let user = token_user(&state.user_store, &claims)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?
+        .ok_or(AuthAPIError::InvalidToken)?;
+    Ok((user, claims))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api-keys",
+    tag = "api_keys",
+    request_body = CreateApiKeyRequest,
+    security(("jwt_bearer" = []), ("jwt_cookie" = [])),
+    responses(
+        (status = 201, description = "Key created; the key itself is only returned here", body = CreateApiKeyResponse),
+        (status = 400, description = "Invalid name, scopes or expiry (`invalid_request`), or no bearer token or `jwt` cookie (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Create API key", skip_all)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>), AuthAPIError> {
+    let (user, _) = session(&state, token).await?;
+
+    let now = Utc::now();
+    let mut errors = Vec::new();
+    if request.name.trim().is_empty() {
+        errors.push(FieldError::new(
+            "name",
+            field_error::INVALID_FORMAT,
+            "must not be empty",
+        ));
+    }
+    // A key without scopes would look like a session, which may do anything
+    if request.scopes.is_empty() {
+        errors.push(FieldError::new(
+            "scopes",
+            field_error::INVALID_FORMAT,
+            "at least one is required",
+        ));
+    }
+    if let Some(scope) = request
+        .scopes
+        .iter()
+        .find(|scope| scope.is_empty() || scope.contains(char::is_whitespace))
+    {
+        errors.push(FieldError::new(
+            "scopes",
+            field_error::INVALID_FORMAT,
+            format!("'{}' is not a single scope", scope),
+        ));
+    }
+    if request
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        errors.push(FieldError::new(
+            "expires_at",
+            field_error::INVALID_FORMAT,
+            "must be in the future",
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(AuthAPIError::InvalidRequest(errors));
+    }
+
+    let (prefix, key) = generate_api_key();
+    let api_key = ApiKey {
+        id: Uuid::new_v4(),
+        user_id: user.id,
+        name: request.name.trim().to_owned(),
+        prefix,
+        key_hash: hash_opaque_token(&key),
+        scopes: request.scopes,
+        created_at: now,
+        expires_at: request.expires_at,
+        last_used_at: None,
+    };
+    state
+        .api_key_store
+        .write()
+        .await
+        .add_key(api_key.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .audit(AuditEvent::ApiKeyCreated {
+            email: user.email.value().to_owned(),
+            prefix: api_key.prefix.clone(),
+        })
+        .await;
+
+    let response = CreateApiKeyResponse {
+        key: key.expose_secret().to_owned(),
+        info: api_key.into(),
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api-keys",
+    tag = "api_keys",
+    security(("jwt_bearer" = []), ("jwt_cookie" = [])),
+    responses(
+        (status = 200, description = "The caller's keys, oldest first", body = ListApiKeysResponse),
+        (status = 400, description = "No bearer token or `jwt` cookie (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "List API keys", skip_all)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+) -> Result<Json<ListApiKeysResponse>, AuthAPIError> {
+    let (user, _) = session(&state, token).await?;
+
+    let keys = state
+        .api_key_store
+        .read()
+        .await
+        .list_keys(user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ListApiKeysResponse {
+        keys: keys.into_iter().map(ApiKeyInfo::from).collect(),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api-keys/{id}",
+    tag = "api_keys",
+    params(("id" = Uuid, Path, description = "The key's id")),
+    security(("jwt_bearer" = []), ("jwt_cookie" = [])),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 400, description = "No bearer token or `jwt` cookie (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 404, description = "The caller has no key with this id (`api_key_not_found`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Revoke API key", skip_all)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AuthAPIError> {
+    let (user, _) = session(&state, token).await?;
+
+    let result = state
+        .api_key_store
+        .write()
+        .await
+        .revoke_key(user.id, id)
+        .await;
+    let api_key = match result {
+        Ok(api_key) => api_key,
+        Err(ApiKeyStoreError::KeyNotFound) => return Err(AuthAPIError::ApiKeyNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    state
+        .audit(AuditEvent::ApiKeyRevoked {
+            email: user.email.value().to_owned(),
+            prefix: api_key.prefix,
+        })
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
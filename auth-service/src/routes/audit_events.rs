@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    domain::{AuditQuery, AuditRecord, AuthAPIError},
+    utils::AdminAccess,
+    AppState, ErrorResponse,
+};
+
+pub const DEFAULT_AUDIT_EVENTS_LIMIT: usize = 100;
+pub const MAX_AUDIT_EVENTS_LIMIT: usize = 1000;
+
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditEventsParams {
+    // Email address of the account
+    pub user: Option<String>,
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    // RFC 3339 timestamps; `from` is inclusive, `to` exclusive
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditEventsResponse {
+    pub events: Vec<AuditRecord>,
+}
+
+// Lists security audit events, newest first, for administrators
+#[utoipa::path(
+    get,
+    path = "/admin/audit-events",
+    tag = "admin",
+    params(AuditEventsParams),
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 200, description = "Matching events, newest first", body = AuditEventsResponse),
+        (status = 400, description = "No bearer token (`missing_token`), or a malformed query"),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 500, description = "The audit sink failed (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "List audit events", skip_all)]
+pub async fn audit_events(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Query(params): Query<AuditEventsParams>,
+) -> Result<Json<AuditEventsResponse>, AuthAPIError> {
+    let query = AuditQuery {
+        email: params.user,
+        event_type: params.event_type,
+        from: params.from,
+        to: params.to,
+        limit: params
+            .limit
+            .unwrap_or(DEFAULT_AUDIT_EVENTS_LIMIT)
+            .min(MAX_AUDIT_EVENTS_LIMIT),
+    };
+
+    let events = state
+        .audit_sink
+        .read()
+        .await
+        .query(&query)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(Json(AuditEventsResponse { events }))
+}
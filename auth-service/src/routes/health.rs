@@ -0,0 +1,226 @@
+use std::{collections::BTreeMap, future::Future, time::Duration};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, PgPool};
+use tokio::time::Instant;
+use utoipa::ToSchema;
+
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DependencyHealth {
+    pub status: HealthStatus,
+    pub latency_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+    // Keyed by dependency name, e.g. `postgres` or `banned_token_store`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, DependencyHealth>,
+}
+
+impl HealthResponse {
+    // The service is only up when every dependency is
+    pub fn from_checks(checks: BTreeMap<String, DependencyHealth>) -> Self {
+        let status = if checks
+            .values()
+            .all(|check| check.status == HealthStatus::Up)
+        {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        };
+
+        Self { status, checks }
+    }
+}
+
+// The process is running and able to answer requests
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "operations",
+    responses((status = 200, description = "The process is running", body = HealthResponse))
+)]
+#[tracing::instrument(name = "Liveness check", skip_all)]
+pub async fn health_live() -> impl IntoResponse {
+    Json(HealthResponse::from_checks(BTreeMap::new()))
+}
+
+// Every configured dependency answers; returns 503 with the failing checks otherwise
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Every dependency is up", body = HealthResponse),
+        (status = 503, description = "At least one dependency is down", body = HealthResponse),
+    )
+)]
+#[tracing::instrument(name = "Readiness check", skip_all)]
+pub async fn health_ready(State(state): State<AppState>) -> impl IntoResponse {
+    let settings = &state.health_settings;
+    let timeout = settings.timeout();
+
+    let postgres = async {
+        match &state.pg_pool {
+            Some(pg_pool) => Some(check(timeout, ping_postgres(pg_pool)).await),
+            None => None,
+        }
+    };
+    let banned_token_store = check(timeout, async {
+        state.banned_token_store.write().await.health_check().await
+    });
+    let two_fa_code_store = check(timeout, async {
+        state.two_fa_code_store.write().await.health_check().await
+    });
+    let oauth_grant_store = check(timeout, async {
+        state.oauth_grant_store.write().await.health_check().await
+    });
+    let email_client = async {
+        if settings.check_email_client {
+            Some(
+                check(timeout, async {
+                    state.email_client_type.read().await.health_check().await
+                })
+                .await,
+            )
+        } else {
+            None
+        }
+    };
+
+    let (postgres, banned_token_store, two_fa_code_store, oauth_grant_store, email_client) = tokio::join!(
+        postgres,
+        banned_token_store,
+        two_fa_code_store,
+        oauth_grant_store,
+        email_client
+    );
+
+    let mut checks = BTreeMap::new();
+    if let Some(postgres) = postgres {
+        checks.insert("postgres".to_owned(), postgres);
+    }
+    checks.insert("banned_token_store".to_owned(), banned_token_store);
+    checks.insert("two_fa_code_store".to_owned(), two_fa_code_store);
+    checks.insert("oauth_grant_store".to_owned(), oauth_grant_store);
+    if let Some(email_client) = email_client {
+        checks.insert("email_client".to_owned(), email_client);
+    }
+
+    let response = HealthResponse::from_checks(checks);
+    let status = match response.status {
+        HealthStatus::Up => StatusCode::OK,
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, Json(response))
+}
+
+async fn ping_postgres(pg_pool: &PgPool) -> Result<()> {
+    let mut connection = pg_pool
+        .acquire()
+        .await
+        .wrap_err("failed to acquire Postgres connection")?;
+
+    connection.ping().await.wrap_err("failed to ping Postgres")
+}
+
+// Runs a single dependency check, timing it and bounding it by `timeout`
+async fn check<F>(timeout: Duration, check: F) -> DependencyHealth
+where
+    F: Future<Output = Result<()>>,
+{
+    let start = Instant::now();
+    let result = tokio::time::timeout(timeout, check).await;
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => {
+            tracing::warn!(error = ?e, "health check failed");
+            Some(e.to_string())
+        }
+        Err(_) => {
+            tracing::warn!(?timeout, "health check timed out");
+            Some(format!("timed out after {}ms", timeout.as_millis()))
+        }
+    };
+
+    DependencyHealth {
+        status: if error.is_none() {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        },
+        latency_ms,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn test_check_reports_success_and_failure() {
+        let up = check(TIMEOUT, async { Ok(()) }).await;
+        assert_eq!(up.status, HealthStatus::Up);
+        assert_eq!(up.error, None);
+
+        let down = check(TIMEOUT, async { Err(eyre!("connection refused")) }).await;
+        assert_eq!(down.status, HealthStatus::Down);
+        assert_eq!(down.error.as_deref(), Some("connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_check_times_out() {
+        let result = check(TIMEOUT, async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+
+        assert_eq!(result.status, HealthStatus::Down);
+        assert!(result.latency_ms < 5000.0);
+    }
+
+    #[test]
+    fn test_one_failing_dependency_marks_the_service_down() {
+        let dependency = |status| DependencyHealth {
+            status,
+            latency_ms: 1.0,
+            error: None,
+        };
+
+        let response = HealthResponse::from_checks(BTreeMap::from([
+            ("postgres".to_owned(), dependency(HealthStatus::Up)),
+            ("email_client".to_owned(), dependency(HealthStatus::Down)),
+        ]));
+        assert_eq!(response.status, HealthStatus::Down);
+
+        let response = HealthResponse::from_checks(BTreeMap::from([(
+            "postgres".to_owned(),
+            dependency(HealthStatus::Up),
+        )]));
+        assert_eq!(response.status, HealthStatus::Up);
+    }
+}
@@ -1,49 +1,75 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
 use color_eyre::eyre::eyre;
-use secrecy::{ExposeSecret, Secret};
-use serde::{Deserialize, Serialize};
+use secrecy::ExposeSecret;
 
 use crate::{
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
-    AppState,
+    domain::{
+        field_error, AuditEvent, AuditFailureReason, AuthAPIError, Email, FieldError,
+        LoginAttemptId, Password, TwoFACode, User,
+    },
+    utils::{
+        create_auth_cookie, generate_auth_token, login_outcome, two_fa_event, AMR_PASSWORD, METRICS,
+    },
+    AppState, ErrorResponse,
 };
 
-#[derive(Debug, Deserialize)]
-pub struct LoginRequest {
-    pub email: Secret<String>,
-    pub password: Secret<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct LoginResponse2FA {
-    pub message: String,
-    #[serde(rename = "loginAttemptId")]
-    pub login_attempt_id: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-#[serde(untagged)]
-pub enum LoginResponse {
-    RegularAuth,
-    TwoFactorAuth(LoginResponse2FA),
-}
-
+pub use auth_types::{LoginRequest, LoginResponse, LoginResponse2FA, TokenDelivery, TokenResponse};
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Logged in; the JWT is set in the `jwt` cookie, or returned in the body with `tokenDelivery: body`", body = LoginResponse,
+            headers(("set-cookie" = String, description = "`jwt` cookie holding the token, unless it is returned in the body"))),
+        (status = 206, description = "2FA required; a code was emailed to the user", body = LoginResponse),
+        (status = 400, description = "Invalid email or password (`invalid_credentials`), with the failing fields", body = ErrorResponse),
+        (status = 401, description = "Incorrect credentials (`incorrect_credentials`)", body = ErrorResponse),
+        (status = 403, description = "Correct credentials, but the account is locked (`account_locked`) or needs a new password (`password_reset_required`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let token_delivery = request.token_delivery;
     let email = Email::parse(request.email);
     let password = Password::parse(request.password);
 
     if email.is_err() || password.is_err() {
-        return (jar, Err(AuthAPIError::InvalidCredentials));
+        let errors = [
+            email
+                .as_ref()
+                .err()
+                .map(|e| FieldError::new("email", field_error::INVALID_FORMAT, e)),
+            password
+                .as_ref()
+                .err()
+                .map(|e| FieldError::new("password", field_error::INVALID_FORMAT, e)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        METRICS.record_login(login_outcome::INVALID_INPUT);
+        state
+            .audit(AuditEvent::LoginFailed {
+                email: email
+                    .ok()
+                    .map(|email| email.as_ref().expose_secret().to_owned()),
+                reason: AuditFailureReason::InvalidInput,
+            })
+            .await;
+        return (jar, Err(AuthAPIError::InvalidCredentials(errors)));
     }
+    let audit_email = email.as_ref().unwrap().as_ref().expose_secret().to_owned();
 
-    let (user_requires_2fa, validation_result) = {
+    let (user, validation_result) = {
         let user_store = state.user_store.write().await;
 
         let validation = user_store
@@ -51,20 +77,79 @@ pub async fn login(
             .await;
 
         if validation.is_err() {
+            METRICS.record_login(login_outcome::INCORRECT_CREDENTIALS);
+            state
+                .audit(AuditEvent::LoginFailed {
+                    email: Some(audit_email),
+                    reason: AuditFailureReason::IncorrectCredentials,
+                })
+                .await;
             return (jar, Err(AuthAPIError::IncorrectCredentials));
         }
 
         let user = user_store.get_user(email.as_ref().unwrap()).await.unwrap();
-        (user.requires_2fa, validation)
+        if let Some((error, reason)) = refusal(&user) {
+            METRICS.record_login(login_outcome::REFUSED);
+            state
+                .audit(AuditEvent::LoginFailed {
+                    email: Some(audit_email),
+                    reason,
+                })
+                .await;
+            return (jar, Err(error));
+        }
+        (user, validation)
     };
 
     if validation_result.is_err() {
+        METRICS.record_login(login_outcome::INCORRECT_CREDENTIALS);
+        state
+            .audit(AuditEvent::LoginFailed {
+                email: Some(audit_email),
+                reason: AuditFailureReason::IncorrectCredentials,
+            })
+            .await;
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
-    match user_requires_2fa {
+    let user_requires_2fa = user.requires_2fa;
+    let (jar, result) = match user_requires_2fa {
         true => handle_2fa(jar, &state, email.as_ref().unwrap()).await,
-        false => handle_no_2fa(jar, email.as_ref().unwrap()).await,
+        false => handle_no_2fa(jar, &state, &user, token_delivery).await,
+    };
+
+    METRICS.record_login(match (&result, user_requires_2fa) {
+        (Err(_), _) => login_outcome::ERROR,
+        (Ok(_), true) => login_outcome::TWO_FA_REQUIRED,
+        (Ok(_), false) => login_outcome::SUCCESS,
+    });
+    state
+        .audit(match (&result, user_requires_2fa) {
+            (Err(_), _) => AuditEvent::LoginFailed {
+                email: Some(audit_email),
+                reason: AuditFailureReason::UnexpectedError,
+            },
+            (Ok(_), true) => AuditEvent::TwoFactorCodeIssued { email: audit_email },
+            (Ok(_), false) => AuditEvent::LoginSucceeded { email: audit_email },
+        })
+        .await;
+
+    (jar, result)
+}
+
+// Correct credentials are refused while an administrator has locked the account or asked for
+// a new password
+pub(crate) fn refusal(user: &User) -> Option<(AuthAPIError, AuditFailureReason)> {
+    match (user.locked, user.password_reset_required) {
+        (true, _) => Some((
+            AuthAPIError::AccountLocked,
+            AuditFailureReason::AccountLocked,
+        )),
+        (false, true) => Some((
+            AuthAPIError::PasswordResetRequired,
+            AuditFailureReason::PasswordResetRequired,
+        )),
+        (false, false) => None,
     }
 }
 
@@ -87,12 +172,14 @@ async fn handle_2fa(
     {
         return (jar, Err(AuthAPIError::UnexpectedError(eyre!(e))));
     }
+    METRICS.record_two_fa(two_fa_event::ISSUED);
 
     let email_client = state.email_client_type.write().await;
     if let Err(e) = email_client
         .send_email(email, "2FA Code", two_fa_code.as_ref().expose_secret())
         .await
     {
+        METRICS.email_send_failures_total.inc();
         return (jar, Err(AuthAPIError::UnexpectedError(eyre!(e))));
     }
 
@@ -113,21 +200,33 @@ async fn handle_2fa(
 #[tracing::instrument(name = "Handle No 2FA", skip_all)]
 async fn handle_no_2fa(
     jar: CookieJar,
-    email: &Email,
+    state: &AppState,
+    user: &User,
+    token_delivery: TokenDelivery,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    match generate_auth_cookie(email) {
-        Ok(auth_cookie) => (
-            jar.add(auth_cookie),
+    let roles = match state.role_store.read().await.get_user_roles(user.id).await {
+        Ok(roles) => roles,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    let token = match generate_auth_token(user.id, &roles, &[AMR_PASSWORD], &state.auth_settings) {
+        Ok(token) => token,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    match token_delivery {
+        TokenDelivery::Cookie => (
+            jar.add(create_auth_cookie(token)),
             Ok((StatusCode::OK, Json(LoginResponse::RegularAuth))),
         ),
-        Err(_) => (
+        TokenDelivery::Body => (
             jar,
-            Err(AuthAPIError::UnexpectedError(eyre!(
-                "Failed to generate auth cookie"
-            ))),
+            Ok((
+                StatusCode::OK,
+                Json(LoginResponse::Token(TokenResponse { token })),
+            )),
         ),
     }
 }
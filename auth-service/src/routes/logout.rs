@@ -1,43 +1,84 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::{cookie::Cookie, CookieJar};
 use color_eyre::eyre::Result;
-use secrecy::Secret;
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{AuditEvent, AuditFailureReason, AuthAPIError},
+    utils::{audit_email, remaining_lifetime, validate_token, AuthToken, JWT_COOKIE_NAME, METRICS},
+    ErrorResponse,
 };
 
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "auth",
+    security(("jwt_bearer" = []), ("jwt_cookie" = [])),
+    responses(
+        (status = 200, description = "Logged out; the token is banned and the `jwt` cookie removed"),
+        (status = 400, description = "No bearer token or `jwt` cookie (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token (`invalid_token`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
     jar: CookieJar,
+    token: Option<AuthToken>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let cookie = jar.get(JWT_COOKIE_NAME);
-    if cookie.is_none() {
+    let Some(AuthToken(token)) = token else {
+        state
+            .audit(AuditEvent::LogoutFailed {
+                reason: AuditFailureReason::MissingToken,
+            })
+            .await;
         return (jar, Err(AuthAPIError::MissingToken));
-    }
-
-    let token = Secret::new(cookie.unwrap().value().to_string());
+    };
 
     // Validate the token (checks if it's banned and if it's properly formatted/valid)
-    if validate_token(state.banned_token_store.clone(), token.clone())
-        .await
-        .is_err()
+    let claims = match validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        token.clone(),
+    )
+    .await
     {
-        return (jar, Err(AuthAPIError::InvalidToken));
-    }
+        Ok(claims) => claims,
+        Err(_) => {
+            state
+                .audit(AuditEvent::LogoutFailed {
+                    reason: AuditFailureReason::InvalidToken,
+                })
+                .await;
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+    };
 
     // Add the token to banned list
     let result = {
         let mut banned_token_store = state.banned_token_store.write().await;
-        banned_token_store.add_token(token).await
+        banned_token_store
+            .add_token(token, remaining_lifetime(claims.exp))
+            .await
     };
 
     if let Err(e) = result {
+        state
+            .audit(AuditEvent::LogoutFailed {
+                reason: AuditFailureReason::UnexpectedError,
+            })
+            .await;
         return (jar, Err(AuthAPIError::UnexpectedError(e)));
     }
+    METRICS.tokens_banned_total.inc();
+    let email = audit_email(&state.user_store, &claims).await;
+    state
+        .audit(AuditEvent::TokenBanned {
+            email: email.clone(),
+        })
+        .await;
+    state.audit(AuditEvent::LoggedOut { email }).await;
 
     let jar = jar.remove(Cookie::from(JWT_COOKIE_NAME));
 
@@ -1,11 +1,39 @@
+mod account;
+mod admin_users;
+mod api_keys;
+mod audit_events;
+mod health;
 mod login;
 mod logout;
+mod metrics;
+mod oauth_authorize;
+mod oauth_clients;
+mod oauth_introspect;
+mod oauth_revoke;
+mod oauth_token;
+mod oidc;
+mod openapi;
+mod roles;
 mod signup;
 mod verify_2fa;
 mod verify_token;
 
+pub use account::*;
+pub use admin_users::*;
+pub use api_keys::*;
+pub use audit_events::*;
+pub use health::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use oauth_authorize::*;
+pub use oauth_clients::*;
+pub use oauth_introspect::*;
+pub use oauth_revoke::*;
+pub use oauth_token::*;
+pub use oidc::*;
+pub use openapi::*;
+pub use roles::*;
 pub use signup::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
@@ -0,0 +1,289 @@
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use color_eyre::eyre::Context;
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditEvent, AuthAPIError, AuthorizationCodeGrant, OAuthClient, OAuthClientStoreError,
+        OAuthError,
+    },
+    utils::{generate_opaque_token, token_user, validate_token, AuthToken},
+    ErrorResponse, OAuthErrorResponse,
+};
+
+// The login UI, served at /oauth/authorize to sign the user in and ask for consent
+const LOGIN_PAGE: &str = "assets/index.html";
+
+// RFC 6749 section 4.1.1 with the RFC 7636 PKCE parameters, which are required.
+// Every field is optional so that missing ones are reported as OAuth errors.
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct AuthorizeParams {
+    // Must be `code`
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    // May be omitted when the client registered exactly one
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    // Returned unchanged to the client
+    pub state: Option<String>,
+    // OpenID Connect: returned in the ID token, to tie it to the client's session
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    // Must be `S256`
+    pub code_challenge_method: Option<String>,
+}
+
+// The consent form posted by the login UI: the authorization request and the user's answer
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConsentForm {
+    #[serde(flatten)]
+    pub params: AuthorizeParams,
+    // `allow`, or anything else to deny
+    pub decision: String,
+}
+
+// An authorization request that matches the client's registration
+struct AuthorizationRequest {
+    client: OAuthClient,
+    redirect_uri: String,
+    redirect_uri_sent: bool,
+    scope: Option<String>,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/oauth/authorize",
+    tag = "oauth",
+    params(AuthorizeParams),
+    responses(
+        (status = 200, description = "The login page, which asks for consent once the user is signed in", body = String, content_type = "text/html"),
+        (status = 303, description = "Invalid request, reported to the client's redirect URI with `error` and `state`"),
+        (status = 400, description = "Unknown client or unregistered redirect URI (`invalid_request`); the user is not redirected", body = OAuthErrorResponse),
+        (status = 500, description = "Unexpected error (`server_error`)", body = OAuthErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Authorize", skip_all)]
+pub async fn oauth_authorize(
+    State(state): State<AppState>,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
+    let request = match authorization_request(&state, &params).await {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    let page = match tokio::fs::read_to_string(LOGIN_PAGE)
+        .await
+        .wrap_err("failed to read the login page")
+    {
+        Ok(page) => page,
+        Err(e) => return OAuthError::UnexpectedError(e).into_response(),
+    };
+    let page = page.replacen(
+        "<body>",
+        &format!(
+            r#"<body data-oauth-client="{}">"#,
+            escape_html(&request.client.client_name)
+        ),
+        1,
+    );
+
+    (
+        // The consent buttons must not be clickable from another site's frame
+        [
+            (header::X_FRAME_OPTIONS, "DENY"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        Html(page),
+    )
+        .into_response()
+}
+
+// Answers the consent form: redirects back to the client with an authorization code, or
+// with `access_denied`
+#[utoipa::path(
+    post,
+    path = "/oauth/authorize",
+    tag = "oauth",
+    request_body(content = ConsentForm, content_type = "application/x-www-form-urlencoded"),
+    security(("jwt_cookie" = []), ("jwt_bearer" = [])),
+    responses(
+        (status = 303, description = "Redirect to the client with `code` and `state`, or with `error`"),
+        (status = 400, description = "Unknown client or unregistered redirect URI (`invalid_request`), or the user is not signed in (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned session (`invalid_token`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`server_error`)", body = OAuthErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Consent", skip_all)]
+pub async fn oauth_consent(
+    State(state): State<AppState>,
+    token: Option<AuthToken>,
+    Form(form): Form<ConsentForm>,
+) -> Response {
+    let request = match authorization_request(&state, &form.params).await {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    let Some(AuthToken(token)) = token else {
+        return AuthAPIError::MissingToken.into_response();
+    };
+    // Only the user's own session may consent, not a token already issued to a client
+    let session = match validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        token,
+    )
+    .await
+    {
+        Ok(claims) if claims.client_id.is_none() => claims,
+        _ => return AuthAPIError::InvalidToken.into_response(),
+    };
+    let user = match token_user(&state.user_store, &session).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return AuthAPIError::InvalidToken.into_response(),
+        Err(e) => return OAuthError::UnexpectedError(e).into_response(),
+    };
+    let email = user.email.value().to_owned();
+    let client_id = request.client.client_id.clone();
+
+    if form.decision != "allow" {
+        state
+            .audit(AuditEvent::ConsentDenied { client_id, email })
+            .await;
+        return redirect_error(&request.redirect_uri, "access_denied", request.state);
+    }
+
+    let code = generate_opaque_token();
+    let grant = AuthorizationCodeGrant {
+        client_id: client_id.clone(),
+        user_id: user.id,
+        scope: request.scope,
+        redirect_uri: request.redirect_uri.clone(),
+        redirect_uri_sent: request.redirect_uri_sent,
+        code_challenge: request.code_challenge,
+        nonce: request.nonce,
+        auth_time: session.authenticated_at(),
+        amr: session.amr,
+    };
+    if let Err(e) = state
+        .oauth_grant_store
+        .write()
+        .await
+        .add_authorization_code(&code, grant)
+        .await
+    {
+        return OAuthError::UnexpectedError(e).into_response();
+    }
+    state
+        .audit(AuditEvent::ConsentGranted { client_id, email })
+        .await;
+
+    let mut params = vec![("code", code.expose_secret().to_owned())];
+    params.extend(request.state.map(|state| ("state", state)));
+    redirect(&request.redirect_uri, &params)
+}
+
+// Checks the request against the client's registration. Until the redirect URI is known to
+// be registered, errors are shown to the user; after that they are sent to the client.
+async fn authorization_request(
+    state: &AppState,
+    params: &AuthorizeParams,
+) -> Result<AuthorizationRequest, Response> {
+    let client_id = params
+        .client_id
+        .as_deref()
+        .ok_or_else(|| OAuthError::InvalidRequest("Missing `client_id`").into_response())?;
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(client_id)
+        .await
+    {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::UnexpectedError(e)) => {
+            return Err(OAuthError::UnexpectedError(e).into_response())
+        }
+        Err(_) => return Err(OAuthError::InvalidRequest("Unknown client").into_response()),
+    };
+    let redirect_uri = client
+        .redirect_uri(params.redirect_uri.as_deref())
+        .ok_or_else(|| {
+            OAuthError::InvalidRequest("The redirect URI is not registered for this client")
+                .into_response()
+        })?
+        .to_owned();
+
+    let error = |error: &str| redirect_error(&redirect_uri, error, params.state.clone());
+    if params.response_type.as_deref() != Some("code") {
+        return Err(error("unsupported_response_type"));
+    }
+    let Some(code_challenge) = params.code_challenge.clone() else {
+        return Err(error("invalid_request"));
+    };
+    if params.code_challenge_method.as_deref() != Some("S256") {
+        return Err(error("invalid_request"));
+    }
+    let scope = match params
+        .scope
+        .as_deref()
+        .filter(|scope| !scope.trim().is_empty())
+    {
+        Some(requested) => Some(
+            client
+                .authorization_scope(requested)
+                .ok_or_else(|| error("invalid_scope"))?,
+        ),
+        None => None,
+    };
+
+    Ok(AuthorizationRequest {
+        client,
+        redirect_uri,
+        redirect_uri_sent: params.redirect_uri.is_some(),
+        scope,
+        state: params.state.clone(),
+        nonce: params.nonce.clone(),
+        code_challenge,
+    })
+}
+
+fn redirect_error(redirect_uri: &str, error: &str, state: Option<String>) -> Response {
+    let mut params = vec![("error", error.to_owned())];
+    params.extend(state.map(|state| ("state", state)));
+    redirect(redirect_uri, &params)
+}
+
+// Adds `params` to the registered redirect URI, keeping any query it already has
+fn redirect(redirect_uri: &str, params: &[(&str, String)]) -> Response {
+    match Url::parse(redirect_uri) {
+        Ok(mut url) => {
+            url.query_pairs_mut().extend_pairs(params);
+            Redirect::to(url.as_str()).into_response()
+        }
+        Err(e) => OAuthError::UnexpectedError(e.into()).into_response(),
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
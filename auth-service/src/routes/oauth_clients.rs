@@ -0,0 +1,141 @@
+use axum::{extract::State, http::StatusCode, Json};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    domain::{field_error, AuditEvent, AuthAPIError, FieldError, OAuthClient},
+    utils::{generate_opaque_token, hash_opaque_token, validate_redirect_uri, AdminAccess},
+    AppState, ErrorResponse,
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RegisterClientRequest {
+    // Shown to users on the consent page
+    pub client_name: String,
+    pub redirect_uris: Vec<String>,
+    // Public clients (mobile and single-page apps) get no secret and must use PKCE
+    #[serde(default)]
+    pub public: bool,
+    // Scopes the client may request for itself with the `client_credentials` grant, for
+    // backend jobs calling other services. Confidential clients only.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RegisterClientResponse {
+    pub client_id: String,
+    pub client_name: String,
+    // Only returned here; it cannot be retrieved again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub redirect_uris: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+}
+
+// Registers an application that signs users in through /oauth/authorize or, with scopes,
+// a machine client that gets tokens for itself
+#[utoipa::path(
+    post,
+    path = "/admin/oauth/clients",
+    tag = "admin",
+    request_body = RegisterClientRequest,
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 201, description = "Client registered", body = RegisterClientResponse),
+        (status = 400, description = "Invalid name, redirect URIs or scopes (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Register OAuth client", skip_all)]
+pub async fn register_oauth_client(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Json(request): Json<RegisterClientRequest>,
+) -> Result<(StatusCode, Json<RegisterClientResponse>), AuthAPIError> {
+    let mut errors: Vec<FieldError> = request
+        .redirect_uris
+        .iter()
+        .filter_map(|uri| {
+            validate_redirect_uri(uri).err().map(|reason| {
+                FieldError::new(
+                    "redirect_uris",
+                    field_error::INVALID_FORMAT,
+                    format!("{} {}", uri, reason),
+                )
+            })
+        })
+        .collect();
+    if request.client_name.trim().is_empty() {
+        errors.push(FieldError::new(
+            "client_name",
+            field_error::INVALID_FORMAT,
+            "must not be empty",
+        ));
+    }
+    if request.public && request.redirect_uris.is_empty() {
+        errors.push(FieldError::new(
+            "redirect_uris",
+            field_error::INVALID_FORMAT,
+            "public clients need at least one",
+        ));
+    }
+    if request.public && !request.scopes.is_empty() {
+        errors.push(FieldError::new(
+            "scopes",
+            field_error::INVALID_FORMAT,
+            "public clients cannot have any",
+        ));
+    }
+    if let Some(scope) = request
+        .scopes
+        .iter()
+        .find(|scope| scope.is_empty() || scope.contains(char::is_whitespace))
+    {
+        errors.push(FieldError::new(
+            "scopes",
+            field_error::INVALID_FORMAT,
+            format!("'{}' is not a single scope", scope),
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(AuthAPIError::InvalidRequest(errors));
+    }
+
+    let client_secret = (!request.public).then(generate_opaque_token);
+    let client = OAuthClient {
+        client_id: Uuid::new_v4().to_string(),
+        client_name: request.client_name.trim().to_owned(),
+        client_secret_hash: client_secret.as_ref().map(hash_opaque_token),
+        redirect_uris: request.redirect_uris,
+        scopes: request.scopes,
+    };
+    state
+        .oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .audit(AuditEvent::ClientRegistered {
+            client_id: client.client_id.clone(),
+        })
+        .await;
+
+    let response = RegisterClientResponse {
+        client_id: client.client_id,
+        client_name: client.client_name,
+        client_secret: client_secret.map(|secret| secret.expose_secret().to_owned()),
+        redirect_uris: client.redirect_uris,
+        scopes: client.scopes,
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
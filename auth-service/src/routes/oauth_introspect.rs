@@ -0,0 +1,117 @@
+use axum::{
+    extract::{rejection::FormRejection, State},
+    http::HeaderMap,
+    Form, Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, OAuthError},
+    utils::{audit_email, authenticate_client, serialize_secret, validate_token, TokenUse},
+    OAuthErrorResponse,
+};
+
+// RFC 7662 introspection request; the client credentials may be sent as form fields instead
+// of HTTP Basic
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IntrospectRequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String)]
+    pub token: Secret<String>,
+    // Accepted but ignored: the service only issues access tokens
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing)]
+    #[schema(value_type = Option<String>)]
+    pub client_secret: Option<Secret<String>>,
+}
+
+// Inactive tokens are only `{"active": false}`, whatever made them so
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // The OAuth client the token was issued to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    // `service` for a client's own token, whose `sub` is the client id rather than a user id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_use: Option<TokenUse>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/introspect",
+    tag = "oauth",
+    request_body(content = IntrospectRequest, content_type = "application/x-www-form-urlencoded"),
+    security(("oauth_client" = [])),
+    responses(
+        (status = 200, description = "Whether the token is active and, if so, its claims", body = IntrospectResponse),
+        (status = 400, description = "Malformed request (`invalid_request`)", body = OAuthErrorResponse),
+        (status = 401, description = "Unknown client or wrong secret (`invalid_client`)", body = OAuthErrorResponse),
+        (status = 500, description = "Unexpected error (`server_error`)", body = OAuthErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Introspect token", skip_all)]
+pub async fn oauth_introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Result<Form<IntrospectRequest>, FormRejection>,
+) -> Result<Json<IntrospectResponse>, OAuthError> {
+    // Unauthenticated callers learn nothing, not even that their request was malformed
+    let request = request.ok().map(|Form(request)| request);
+    let client_id = authenticate_client(
+        &state,
+        &headers,
+        request.as_ref().and_then(|r| r.client_id.as_deref()),
+        request.as_ref().and_then(|r| r.client_secret.as_ref()),
+    )
+    .await?;
+    let request = request.ok_or(OAuthError::InvalidRequest(
+        "Expected a form with a `token` field",
+    ))?;
+
+    let claims = validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        request.token,
+    )
+    .await;
+    let email = match &claims {
+        Ok(claims) if claims.token_use == TokenUse::User => {
+            Some(audit_email(&state.user_store, claims).await)
+        }
+        _ => None,
+    };
+    let response = match claims {
+        Ok(claims) => IntrospectResponse {
+            active: true,
+            token_use: Some(claims.token_use),
+            sub: Some(claims.sub),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat).filter(|iat| *iat > 0),
+            scope: claims.scope,
+            client_id: claims.client_id,
+            token_type: Some("Bearer".to_owned()),
+        },
+        Err(_) => IntrospectResponse::default(),
+    };
+
+    state
+        .audit(AuditEvent::TokenIntrospected { client_id, email })
+        .await;
+
+    Ok(Json(response))
+}
@@ -0,0 +1,172 @@
+use axum::{
+    extract::{rejection::FormRejection, State},
+    http::{HeaderMap, StatusCode},
+    Form,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, OAuthError},
+    utils::{
+        audit_email, authenticate_client, remaining_lifetime, serialize_secret, validate_token,
+        METRICS,
+    },
+    OAuthErrorResponse,
+};
+
+// RFC 7009 revocation request; the client credentials may be sent as form fields instead
+// of HTTP Basic
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RevokeRequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String)]
+    pub token: Secret<String>,
+    // `access_token` or `refresh_token`: which kind to look for first
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing)]
+    #[schema(value_type = Option<String>)]
+    pub client_secret: Option<Secret<String>>,
+}
+
+// Clients may only revoke the access and refresh tokens issued to them
+#[utoipa::path(
+    post,
+    path = "/oauth/revoke",
+    tag = "oauth",
+    request_body(content = RevokeRequest, content_type = "application/x-www-form-urlencoded"),
+    security(("oauth_client" = [])),
+    responses(
+        (status = 200, description = "The token is revoked, or was already invalid"),
+        (status = 400, description = "Malformed request (`invalid_request`), or a token issued to another client or a login session (`unauthorized_client`)", body = OAuthErrorResponse),
+        (status = 401, description = "Unknown client or wrong secret (`invalid_client`)", body = OAuthErrorResponse),
+        (status = 500, description = "Unexpected error (`server_error`)", body = OAuthErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Revoke token", skip_all)]
+pub async fn oauth_revoke(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Result<Form<RevokeRequest>, FormRejection>,
+) -> Result<StatusCode, OAuthError> {
+    let request = request.ok().map(|Form(request)| request);
+    let client_id = authenticate_client(
+        &state,
+        &headers,
+        request.as_ref().and_then(|r| r.client_id.as_deref()),
+        request.as_ref().and_then(|r| r.client_secret.as_ref()),
+    )
+    .await?;
+    let request = request.ok_or(OAuthError::InvalidRequest(
+        "Expected a form with a `token` field",
+    ))?;
+
+    // The hint only orders the lookups: a token of the other kind is still revoked
+    let token = &request.token;
+    if request.token_type_hint.as_deref() == Some("refresh_token") {
+        if !revoke_refresh_token(&state, &client_id, token).await? {
+            revoke_access_token(&state, &client_id, token).await?;
+        }
+    } else if !revoke_access_token(&state, &client_id, token).await? {
+        revoke_refresh_token(&state, &client_id, token).await?;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+// Bans an access token issued to the client. False when it is not a valid access token.
+async fn revoke_access_token(
+    state: &AppState,
+    client_id: &str,
+    token: &Secret<String>,
+) -> Result<bool, OAuthError> {
+    // RFC 7009 section 2.2: invalid, expired and already revoked tokens need no action
+    let Ok(claims) = validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        token.clone(),
+    )
+    .await
+    else {
+        return Ok(false);
+    };
+    if claims.client_id.as_deref() != Some(client_id) {
+        return Err(OAuthError::UnauthorizedClient(
+            "The token was not issued to this client",
+        ));
+    }
+
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(token.clone(), remaining_lifetime(claims.exp))
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+    METRICS.tokens_banned_total.inc();
+    let email = audit_email(&state.user_store, &claims).await;
+    state
+        .audit(AuditEvent::TokenBanned {
+            email: email.clone(),
+        })
+        .await;
+    state
+        .audit(AuditEvent::TokenRevoked {
+            client_id: client_id.to_owned(),
+            email,
+        })
+        .await;
+
+    Ok(true)
+}
+
+// Deletes a refresh token issued to the client. False when the token is unknown.
+async fn revoke_refresh_token(
+    state: &AppState,
+    client_id: &str,
+    token: &Secret<String>,
+) -> Result<bool, OAuthError> {
+    let grant = {
+        let mut oauth_grant_store = state.oauth_grant_store.write().await;
+        let Some(grant) = oauth_grant_store
+            .get_refresh_token(token)
+            .await
+            .map_err(OAuthError::UnexpectedError)?
+        else {
+            return Ok(false);
+        };
+        if grant.client_id != client_id {
+            return Err(OAuthError::UnauthorizedClient(
+                "The token was not issued to this client",
+            ));
+        }
+        oauth_grant_store
+            .take_refresh_token(token)
+            .await
+            .map_err(OAuthError::UnexpectedError)?;
+        grant
+    };
+
+    // Named by the raw user id once the account is gone, like `audit_email`
+    let email = match state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(grant.user_id)
+        .await
+    {
+        Ok(user) => user.email.value().to_owned(),
+        Err(_) => grant.user_id.to_string(),
+    };
+    state
+        .audit(AuditEvent::TokenRevoked {
+            client_id: client_id.to_owned(),
+            email,
+        })
+        .await;
+
+    Ok(true)
+}
@@ -0,0 +1,359 @@
+use axum::{
+    extract::{rejection::FormRejection, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use chrono::Utc;
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, Email, OAuthClient, OAuthError, RefreshTokenGrant, User, UserStoreError},
+    routes::login::refusal,
+    utils::{
+        generate_access_token, generate_opaque_token, generate_service_token, has_scope,
+        identify_client, serialize_optional_secret, serialize_secret, verify_pkce, IdTokenClaims,
+        OidcProvider, EMAIL_SCOPE, OPENID_SCOPE, TOKEN_TTL_SECONDS,
+    },
+    OAuthErrorResponse,
+};
+
+// RFC 6749 token request for the `authorization_code` (with a PKCE `code_verifier`),
+// `refresh_token` and `client_credentials` grants. Confidential clients authenticate as for
+// /oauth/introspect; public clients send only `client_id`.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct OAuthTokenRequest {
+    pub grant_type: Option<String>,
+    #[serde(default, skip_serializing)]
+    #[schema(value_type = Option<String>)]
+    pub code: Option<Secret<String>>,
+    pub redirect_uri: Option<String>,
+    #[serde(default, skip_serializing)]
+    #[schema(value_type = Option<String>)]
+    pub code_verifier: Option<Secret<String>>,
+    #[serde(default, skip_serializing)]
+    #[schema(value_type = Option<String>)]
+    pub refresh_token: Option<Secret<String>>,
+    // `client_credentials` only: a subset of the client's scopes, all of them if omitted
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing)]
+    #[schema(value_type = Option<String>)]
+    pub client_secret: Option<Secret<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OAuthTokenResponse {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String)]
+    pub access_token: Secret<String>,
+    // Always `Bearer`
+    pub token_type: String,
+    pub expires_in: i64,
+    // Single use: each refresh returns a new one. Service tokens have none; the client
+    // requests a new token with its credentials instead.
+    #[serde(
+        default,
+        serialize_with = "serialize_optional_secret",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<String>)]
+    pub refresh_token: Option<Secret<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // OpenID Connect: an RS256 JWT, when the `openid` scope was granted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/token",
+    tag = "oauth",
+    request_body(content = OAuthTokenRequest, content_type = "application/x-www-form-urlencoded"),
+    security((), ("oauth_client" = [])),
+    responses(
+        (status = 200, description = "An access token for the user, bound to the client, and a refresh token; for `client_credentials`, a service token for the client itself", body = OAuthTokenResponse),
+        (status = 400, description = "Malformed request (`invalid_request`), unknown grant type (`unsupported_grant_type`), an invalid, used or mismatched code or refresh token (`invalid_grant`), a client without scopes using `client_credentials` (`unauthorized_client`), or a scope the client does not have (`invalid_scope`)", body = OAuthErrorResponse),
+        (status = 401, description = "Unknown client, or a confidential client without its secret (`invalid_client`)", body = OAuthErrorResponse),
+        (status = 500, description = "Unexpected error (`server_error`)", body = OAuthErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Token", skip_all)]
+pub async fn oauth_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Result<Form<OAuthTokenRequest>, FormRejection>,
+) -> Result<Response, OAuthError> {
+    let request = request.ok().map(|Form(request)| request);
+    let client = identify_client(
+        &state,
+        &headers,
+        request.as_ref().and_then(|r| r.client_id.as_deref()),
+        request.as_ref().and_then(|r| r.client_secret.as_ref()),
+    )
+    .await?;
+    let request = request.ok_or(OAuthError::InvalidRequest(
+        "Expected a form with a `grant_type` field",
+    ))?;
+    let grant_type = request
+        .grant_type
+        .clone()
+        .ok_or(OAuthError::InvalidRequest("Missing `grant_type`"))?;
+
+    let grant = match grant_type.as_str() {
+        "authorization_code" => authorization_code_grant(&state, &client.client_id, request).await,
+        "refresh_token" => refresh_token_grant(&state, &client.client_id, request)
+            .await
+            .map(|grant| (grant, None)),
+        "client_credentials" => {
+            return client_credentials_grant(&state, &client, request.scope.as_deref()).await
+        }
+        _ => return Err(OAuthError::UnsupportedGrantType),
+    };
+    let result = match grant {
+        Ok((grant, nonce)) => grant_user(&state, grant.user_id)
+            .await
+            .map(|user| (grant, nonce, user)),
+        Err(e) => Err(e),
+    };
+    let (grant, nonce, user) = match result {
+        Ok(granted) => granted,
+        Err(e) => {
+            if let OAuthError::InvalidGrant(_) = e {
+                state
+                    .audit(AuditEvent::GrantRejected {
+                        client_id: client.client_id,
+                    })
+                    .await;
+            }
+            return Err(e);
+        }
+    };
+    let email = user.email;
+
+    let access_token = generate_access_token(
+        grant.user_id.to_string(),
+        grant.client_id.clone(),
+        grant.scope.clone(),
+        &state.auth_settings,
+    )
+    .map_err(OAuthError::UnexpectedError)?;
+    let id_token = match &state.oidc {
+        Some(oidc) if has_scope(grant.scope.as_deref(), OPENID_SCOPE) => Some(
+            id_token(oidc, &grant, &email, nonce)
+                .and_then(|claims| oidc.sign_id_token(&claims))
+                .map_err(OAuthError::UnexpectedError)?,
+        ),
+        _ => None,
+    };
+    let refresh_token = generate_opaque_token();
+    state
+        .oauth_grant_store
+        .write()
+        .await
+        .add_refresh_token(&refresh_token, grant.clone())
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+    state
+        .audit(AuditEvent::TokensIssued {
+            client_id: grant.client_id,
+            email: email.value().to_owned(),
+            grant_type,
+        })
+        .await;
+
+    Ok(token_response(OAuthTokenResponse {
+        access_token: Secret::new(access_token),
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        refresh_token: Some(refresh_token),
+        scope: grant.scope,
+        id_token,
+    }))
+}
+
+// RFC 6749 section 4.4: a token for the client itself, limited to its registered scopes.
+// Only confidential clients can use it, since public ones cannot keep a secret.
+async fn client_credentials_grant(
+    state: &AppState,
+    client: &OAuthClient,
+    requested_scope: Option<&str>,
+) -> Result<Response, OAuthError> {
+    if !client.is_confidential() || client.scopes.is_empty() {
+        return Err(OAuthError::UnauthorizedClient(
+            "The client is not allowed to use the client_credentials grant",
+        ));
+    }
+    let scope = client
+        .grant_scope(requested_scope)
+        .ok_or(OAuthError::InvalidScope)?;
+
+    let access_token = generate_service_token(
+        client.client_id.clone(),
+        Some(scope.clone()),
+        &state.auth_settings,
+    )
+    .map_err(OAuthError::UnexpectedError)?;
+    state
+        .audit(AuditEvent::ServiceTokenIssued {
+            client_id: client.client_id.clone(),
+            scope: scope.clone(),
+        })
+        .await;
+
+    Ok(token_response(OAuthTokenResponse {
+        access_token: Secret::new(access_token),
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        refresh_token: None,
+        scope: Some(scope),
+        id_token: None,
+    }))
+}
+
+// RFC 6749 section 5.1: token responses must not be cached
+fn token_response(response: OAuthTokenResponse) -> Response {
+    (
+        [
+            (header::CACHE_CONTROL, "no-store"),
+            (header::PRAGMA, "no-cache"),
+        ],
+        Json(response),
+    )
+        .into_response()
+}
+
+// Returns the grant and the OpenID Connect nonce of the authorization request
+async fn authorization_code_grant(
+    state: &AppState,
+    client_id: &str,
+    request: OAuthTokenRequest,
+) -> Result<(RefreshTokenGrant, Option<String>), OAuthError> {
+    let code = request
+        .code
+        .ok_or(OAuthError::InvalidRequest("Missing `code`"))?;
+    let code_verifier = request
+        .code_verifier
+        .ok_or(OAuthError::InvalidRequest("Missing `code_verifier`"))?;
+
+    // Taken before it is checked, so a stolen code cannot be retried with other parameters
+    let grant = state
+        .oauth_grant_store
+        .write()
+        .await
+        .take_authorization_code(&code)
+        .await
+        .map_err(OAuthError::UnexpectedError)?
+        .ok_or(OAuthError::InvalidGrant(
+            "The authorization code is invalid, expired or already used",
+        ))?;
+    if grant.client_id != client_id {
+        return Err(OAuthError::InvalidGrant(
+            "The authorization code was issued to another client",
+        ));
+    }
+    // RFC 6749 section 4.1.3: required when it was part of the authorization request, which
+    // is always the case when the client registered several
+    match request.redirect_uri {
+        Some(redirect_uri) if redirect_uri != grant.redirect_uri => {
+            return Err(OAuthError::InvalidGrant("The redirect URI does not match"));
+        }
+        None if grant.redirect_uri_sent => {
+            return Err(OAuthError::InvalidGrant(
+                "The redirect URI of the authorization request is missing",
+            ));
+        }
+        _ => {}
+    }
+    if !verify_pkce(code_verifier.expose_secret(), &grant.code_challenge) {
+        return Err(OAuthError::InvalidGrant(
+            "The code verifier does not match the code challenge",
+        ));
+    }
+
+    Ok((
+        RefreshTokenGrant {
+            client_id: grant.client_id,
+            user_id: grant.user_id,
+            scope: grant.scope,
+            auth_time: grant.auth_time,
+            amr: grant.amr,
+        },
+        grant.nonce,
+    ))
+}
+
+async fn refresh_token_grant(
+    state: &AppState,
+    client_id: &str,
+    request: OAuthTokenRequest,
+) -> Result<RefreshTokenGrant, OAuthError> {
+    let refresh_token = request
+        .refresh_token
+        .ok_or(OAuthError::InvalidRequest("Missing `refresh_token`"))?;
+
+    let grant = state
+        .oauth_grant_store
+        .write()
+        .await
+        .take_refresh_token(&refresh_token)
+        .await
+        .map_err(OAuthError::UnexpectedError)?
+        .ok_or(OAuthError::InvalidGrant(
+            "The refresh token is invalid, expired or already used",
+        ))?;
+    if grant.client_id != client_id {
+        return Err(OAuthError::InvalidGrant(
+            "The refresh token was issued to another client",
+        ));
+    }
+
+    Ok(grant)
+}
+
+// Looked up on every grant rather than kept in it, so ID tokens follow email changes and
+// accounts locked or awaiting a password reset get no more tokens
+async fn grant_user(state: &AppState, user_id: Uuid) -> Result<User, OAuthError> {
+    let user = match state.user_store.read().await.get_user_by_id(user_id).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => {
+            return Err(OAuthError::InvalidGrant("The user no longer exists"))
+        }
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    if refusal(&user).is_some() {
+        return Err(OAuthError::InvalidGrant(
+            "The account is locked or must reset its password",
+        ));
+    }
+
+    Ok(user)
+}
+
+// ID tokens from refreshes carry the original `auth_time` and `amr` but no nonce
+fn id_token(
+    oidc: &OidcProvider,
+    grant: &RefreshTokenGrant,
+    email: &Email,
+    nonce: Option<String>,
+) -> color_eyre::Result<IdTokenClaims> {
+    let iat = usize::try_from(Utc::now().timestamp()).wrap_err("failed to cast iat to usize")?;
+
+    Ok(IdTokenClaims {
+        iss: oidc.issuer().to_owned(),
+        sub: grant.user_id.to_string(),
+        aud: grant.client_id.clone(),
+        exp: iat + TOKEN_TTL_SECONDS as usize,
+        iat,
+        auth_time: grant.auth_time,
+        nonce,
+        amr: grant.amr.clone(),
+        email: has_scope(grant.scope.as_deref(), EMAIL_SCOPE).then(|| email.value().to_owned()),
+    })
+}
@@ -0,0 +1,169 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{
+        has_scope, token_user, validate_token, AuthToken, PublicJwk, EMAIL_SCOPE, OPENID_SCOPE,
+    },
+    ErrorResponse,
+};
+
+// OpenID Connect Discovery 1.0 provider metadata
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct JwksResponse {
+    pub keys: Vec<PublicJwk>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    // With the `email` scope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/openid-configuration",
+    tag = "oauth",
+    responses(
+        (status = 200, description = "Provider metadata", body = OpenIdConfiguration),
+        (status = 404, description = "OpenID Connect is not configured"),
+    )
+)]
+#[tracing::instrument(name = "OpenID configuration", skip_all)]
+pub async fn openid_configuration(State(state): State<AppState>) -> Response {
+    let Some(oidc) = &state.oidc else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+
+    Json(OpenIdConfiguration {
+        issuer: oidc.issuer().to_owned(),
+        authorization_endpoint: oidc.url("/oauth/authorize"),
+        token_endpoint: oidc.url("/oauth/token"),
+        userinfo_endpoint: oidc.url("/userinfo"),
+        jwks_uri: oidc.url("/.well-known/jwks.json"),
+        introspection_endpoint: oidc.url("/oauth/introspect"),
+        revocation_endpoint: oidc.url("/oauth/revoke"),
+        scopes_supported: strings(&[OPENID_SCOPE, EMAIL_SCOPE]),
+        response_types_supported: strings(&["code"]),
+        grant_types_supported: strings(&[
+            "authorization_code",
+            "refresh_token",
+            "client_credentials",
+        ]),
+        subject_types_supported: strings(&["public"]),
+        id_token_signing_alg_values_supported: strings(&["RS256"]),
+        token_endpoint_auth_methods_supported: strings(&[
+            "client_secret_basic",
+            "client_secret_post",
+            "none",
+        ]),
+        code_challenge_methods_supported: strings(&["S256"]),
+        claims_supported: strings(&[
+            "iss",
+            "sub",
+            "aud",
+            "exp",
+            "iat",
+            "auth_time",
+            "nonce",
+            "amr",
+            "email",
+        ]),
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    tag = "oauth",
+    responses(
+        (status = 200, description = "The public keys that verify ID tokens", body = JwksResponse),
+        (status = 404, description = "OpenID Connect is not configured"),
+    )
+)]
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks(State(state): State<AppState>) -> Response {
+    match &state.oidc {
+        Some(oidc) => Json(JwksResponse {
+            keys: vec![oidc.jwk()],
+        })
+        .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// OpenID Connect Core section 5.3. Takes an access token issued with the `openid` scope.
+#[utoipa::path(
+    get,
+    path = "/userinfo",
+    tag = "oauth",
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 200, description = "Claims about the user", body = UserInfoResponse),
+        (status = 400, description = "No bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, a token without the `openid` scope, or a deleted user (`invalid_token`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Userinfo", skip_all)]
+pub async fn userinfo(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+) -> Result<Json<UserInfoResponse>, AuthAPIError> {
+    let claims = validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        token,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+    // Service tokens belong to no user, whatever scopes their client was given
+    if claims.client_id.is_none()
+        || claims.is_service()
+        || !has_scope(claims.scope.as_deref(), OPENID_SCOPE)
+    {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let user = token_user(&state.user_store, &claims)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?
+        .ok_or(AuthAPIError::InvalidToken)?;
+
+    Ok(Json(UserInfoResponse {
+        email: has_scope(claims.scope.as_deref(), EMAIL_SCOPE)
+            .then(|| user.email.value().to_owned()),
+        sub: claims.sub,
+    }))
+}
@@ -0,0 +1,142 @@
+use std::sync::LazyLock;
+
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_scalar::Scalar;
+
+use crate::utils::JWT_COOKIE_NAME;
+
+// The API description, derived from the handlers' `#[utoipa::path]` attributes and the
+// request and response types, so it cannot drift from the code
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Authentication Service API",
+        description = "JWT authentication with optional email 2FA.\n\n\
+            Errors are `{\"error\", \"code\", \"errors\"}` objects by default. Send \
+            `Accept: application/problem+json` to get RFC 7807 problem documents instead."
+    ),
+    paths(
+        super::signup,
+        super::login,
+        super::verify_2fa,
+        super::logout,
+        super::verify_token,
+        super::change_password,
+        super::change_email,
+        super::confirm_email_change,
+        super::create_api_key,
+        super::list_api_keys,
+        super::revoke_api_key,
+        super::oauth_authorize,
+        super::oauth_consent,
+        super::oauth_token,
+        super::oauth_introspect,
+        super::oauth_revoke,
+        super::openid_configuration,
+        super::jwks,
+        super::userinfo,
+        super::health_live,
+        super::health_ready,
+        super::metrics,
+        super::audit_events,
+        super::register_oauth_client,
+        super::create_role,
+        super::list_roles,
+        super::assign_role,
+        super::unassign_role,
+        super::list_users,
+        super::get_user_details,
+        super::update_user,
+        super::delete_user,
+        super::revoke_user_sessions,
+    ),
+    components(schemas(crate::utils::ProblemDetails)),
+    modifiers(&ApiDocAddons),
+    tags(
+        (name = "auth", description = "Signup, login and token checks"),
+        (name = "account", description = "Password and email changes for the signed-in user"),
+        (name = "api_keys", description = "Personal API keys for scripts, managed from a login session"),
+        (name = "oauth", description = "Authorization code grant with PKCE (RFC 6749, RFC 7636), OpenID Connect, RFC 7662 token introspection and RFC 7009 revocation"),
+        (name = "operations", description = "Health checks and metrics"),
+        (name = "admin", description = "Administration, authenticated by the admin API token"),
+    )
+)]
+pub struct ApiDoc;
+
+struct ApiDocAddons;
+
+impl Modify for ApiDocAddons {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        // Cargo.toml declares no license, which would otherwise be rendered as an empty one
+        openapi.info.license = None;
+
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "jwt_cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(JWT_COOKIE_NAME))),
+        );
+        components.add_security_scheme(
+            "jwt_bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "oauth_client",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+        );
+    }
+}
+
+static OPENAPI_JSON: LazyLock<String> = LazyLock::new(|| {
+    ApiDoc::openapi()
+        .to_pretty_json()
+        .expect("failed to serialize the OpenAPI document")
+});
+
+// Scalar's page, loading the renderer from its CDN; `$spec` is replaced by the document
+const DOCS_TEMPLATE: &str = r#"<!doctype html>
+<html>
+<head>
+    <title>Authentication Service API</title>
+    <meta charset="utf-8"/>
+    <meta name="viewport" content="width=device-width, initial-scale=1"/>
+</head>
+<body>
+<script id="api-reference" type="application/json">
+    $spec
+</script>
+<script src="https://cdn.jsdelivr.net/npm/@scalar/api-reference"></script>
+</body>
+</html>
+"#;
+
+static DOCS_HTML: LazyLock<String> = LazyLock::new(|| {
+    Scalar::new(ApiDoc::openapi())
+        .custom_html(DOCS_TEMPLATE)
+        .to_html()
+});
+
+#[tracing::instrument(name = "OpenAPI document", skip_all)]
+pub async fn openapi_json() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        OPENAPI_JSON.as_str(),
+    )
+}
+
+// Interactive API reference rendering the same document
+#[tracing::instrument(name = "API docs", skip_all)]
+pub async fn api_docs() -> Html<&'static str> {
+    Html(DOCS_HTML.as_str())
+}
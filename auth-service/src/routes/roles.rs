@@ -0,0 +1,230 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    domain::{field_error, AuditEvent, AuthAPIError, FieldError, Role, RoleStoreError, User},
+    routes::admin_users::existing_user,
+    utils::AdminAccess,
+    AppState, ErrorResponse,
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    // Checked by services through `/verify-token`, e.g. `reports:read`
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RoleResponse {
+    pub name: String,
+    pub permissions: Vec<String>,
+}
+
+impl From<Role> for RoleResponse {
+    fn from(role: Role) -> Self {
+        Self {
+            name: role.name,
+            permissions: role.permissions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ListRolesResponse {
+    pub roles: Vec<RoleResponse>,
+}
+
+// Role names and permissions are single words, like OAuth scopes
+pub(crate) fn invalid_name(name: &str) -> bool {
+    name.is_empty() || name.contains(char::is_whitespace)
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/roles",
+    tag = "admin",
+    request_body = CreateRoleRequest,
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 201, description = "Role created", body = RoleResponse),
+        (status = 400, description = "Invalid name or permissions (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 409, description = "A role with this name exists (`role_already_exists`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Create role", skip_all)]
+pub async fn create_role(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Json(request): Json<CreateRoleRequest>,
+) -> Result<(StatusCode, Json<RoleResponse>), AuthAPIError> {
+    let mut errors = Vec::new();
+    if invalid_name(&request.name) {
+        errors.push(FieldError::new(
+            "name",
+            field_error::INVALID_FORMAT,
+            "must be a single word",
+        ));
+    }
+    if let Some(permission) = request.permissions.iter().find(|p| invalid_name(p)) {
+        errors.push(FieldError::new(
+            "permissions",
+            field_error::INVALID_FORMAT,
+            format!("'{}' is not a single permission", permission),
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(AuthAPIError::InvalidRequest(errors));
+    }
+
+    let mut permissions = request.permissions;
+    permissions.sort();
+    permissions.dedup();
+    let role = Role {
+        name: request.name,
+        permissions,
+    };
+    match state.role_store.write().await.add_role(role.clone()).await {
+        Ok(()) => {}
+        Err(RoleStoreError::RoleAlreadyExists) => return Err(AuthAPIError::RoleAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    state
+        .audit(AuditEvent::RoleCreated {
+            role: role.name.clone(),
+        })
+        .await;
+
+    Ok((StatusCode::CREATED, Json(role.into())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/roles",
+    tag = "admin",
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 200, description = "Every role, by name", body = ListRolesResponse),
+        (status = 400, description = "No bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "List roles", skip_all)]
+pub async fn list_roles(
+    _: AdminAccess,
+    State(state): State<AppState>,
+) -> Result<Json<ListRolesResponse>, AuthAPIError> {
+    let roles = state
+        .role_store
+        .read()
+        .await
+        .list_roles()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ListRolesResponse {
+        roles: roles.into_iter().map(RoleResponse::from).collect(),
+    }))
+}
+
+// Takes effect at the user's next login, since session tokens carry the roles they had then
+#[utoipa::path(
+    put,
+    path = "/admin/users/{user}/roles/{role}",
+    tag = "admin",
+    params(
+        ("user" = String, Path, description = "The user's id or email address"),
+        ("role" = String, Path, description = "The role's name"),
+    ),
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 204, description = "Role assigned, or the user already had it"),
+        (status = 400, description = "Invalid user id or email (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 404, description = "No such user (`user_not_found`) or role (`role_not_found`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Assign role", skip_all)]
+pub async fn assign_role(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Path((user, role)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let User { id, email, .. } = existing_user(&state, user).await?;
+
+    let result = state.role_store.write().await.assign_role(id, &role).await;
+    match result {
+        Ok(()) => {}
+        Err(RoleStoreError::RoleNotFound) => return Err(AuthAPIError::RoleNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    state
+        .audit(AuditEvent::RoleAssigned {
+            email: email.value().to_owned(),
+            role,
+        })
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{user}/roles/{role}",
+    tag = "admin",
+    params(
+        ("user" = String, Path, description = "The user's id or email address"),
+        ("role" = String, Path, description = "The role's name"),
+    ),
+    security(("jwt_bearer" = [])),
+    responses(
+        (status = 204, description = "Role unassigned"),
+        (status = 400, description = "Invalid user id or email (`invalid_request`), or no bearer token (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token, or not a login session (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "The caller's roles do not grant the `admin` permission (`forbidden`)", body = ErrorResponse),
+        (status = 404, description = "No such user (`user_not_found`), or the user does not have the role (`role_not_found`)", body = ErrorResponse),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Unassign role", skip_all)]
+pub async fn unassign_role(
+    _: AdminAccess,
+    State(state): State<AppState>,
+    Path((user, role)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let User { id, email, .. } = existing_user(&state, user).await?;
+
+    let result = state
+        .role_store
+        .write()
+        .await
+        .unassign_role(id, &role)
+        .await;
+    match result {
+        Ok(()) => {}
+        Err(RoleStoreError::RoleNotFound) => return Err(AuthAPIError::RoleNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    state
+        .audit(AuditEvent::RoleUnassigned {
+            email: email.value().to_owned(),
+            role,
+        })
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,25 +1,30 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use secrecy::Secret;
-use serde::{Deserialize, Serialize};
+use secrecy::ExposeSecret;
 
 use crate::{
-    domain::{AuthAPIError, Email, Password, User},
-    AppState,
+    domain::{
+        field_error, AuditEvent, AuditFailureReason, AuthAPIError, Email, FieldError, Password,
+        User,
+    },
+    utils::METRICS,
+    AppState, ErrorResponse,
 };
 
-#[derive(Debug, Deserialize)]
-pub struct SignupRequest {
-    pub email: Secret<String>,
-    pub password: Secret<String>,
-    #[serde(rename = "requires2FA")]
-    pub requires_2fa: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct SignupResponse {
-    pub message: String,
-}
+pub use auth_types::{SignupRequest, SignupResponse};
 
+#[utoipa::path(
+    post,
+    path = "/signup",
+    tag = "auth",
+    request_body = SignupRequest,
+    responses(
+        (status = 201, description = "User created", body = SignupResponse),
+        (status = 400, description = "Invalid email or password (`invalid_credentials`), with the failing fields", body = ErrorResponse),
+        (status = 409, description = "User already exists (`user_already_exists`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
@@ -27,20 +32,67 @@ pub async fn signup(
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let email = Email::parse(request.email);
     let password = Password::parse(request.password);
-    let mut user_store = state.user_store.write().await;
 
     if email.is_err() || password.is_err() {
-        return Err(AuthAPIError::InvalidCredentials);
+        let errors = [
+            email
+                .as_ref()
+                .err()
+                .map(|e| FieldError::new("email", field_error::INVALID_FORMAT, e)),
+            password
+                .as_ref()
+                .err()
+                .map(|e| FieldError::new("password", field_error::TOO_WEAK, e)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        state
+            .audit(AuditEvent::SignupFailed {
+                email: email
+                    .ok()
+                    .map(|email| email.as_ref().expose_secret().to_owned()),
+                reason: AuditFailureReason::InvalidInput,
+            })
+            .await;
+        return Err(AuthAPIError::InvalidCredentials(errors));
     }
 
     let user = User::new(email.unwrap(), password.unwrap(), request.requires_2fa);
-    if user_store.get_user(&user.email).await.is_ok() {
-        return Err(AuthAPIError::UserAlreadyExists);
-    }
+    let audit_email = user.email.as_ref().expose_secret().to_owned();
+
+    let result = {
+        let mut user_store = state.user_store.write().await;
+        if user_store.get_user(&user.email).await.is_ok() {
+            Err(AuthAPIError::UserAlreadyExists)
+        } else {
+            user_store
+                .add_user(user)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+        }
+    };
 
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+    if let Err(e) = result {
+        let reason = match e {
+            AuthAPIError::UserAlreadyExists => AuditFailureReason::UserAlreadyExists,
+            _ => AuditFailureReason::UnexpectedError,
+        };
+        state
+            .audit(AuditEvent::SignupFailed {
+                email: Some(audit_email),
+                reason,
+            })
+            .await;
+        return Err(e);
     }
+    METRICS.signups_total.inc();
+    state
+        .audit(AuditEvent::UserSignedUp {
+            email: audit_email,
+            requires_2fa: request.requires_2fa,
+        })
+        .await;
 
     let response = Json(SignupResponse {
         message: "User created successfully".to_string(),
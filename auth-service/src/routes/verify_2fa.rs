@@ -2,37 +2,79 @@ use axum::{extract::State, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
 use color_eyre::eyre::eyre;
 use reqwest::StatusCode;
-use secrecy::Secret;
-use serde::Deserialize;
+use secrecy::ExposeSecret;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::generate_auth_cookie,
+    domain::{
+        field_error, AuditEvent, AuditFailureReason, AuthAPIError, Email, FieldError,
+        LoginAttemptId, TwoFACode, UserStoreError,
+    },
+    routes::{login::refusal, TokenDelivery, TokenResponse},
+    utils::{
+        create_auth_cookie, generate_auth_token, two_fa_event, AMR_MULTI_FACTOR,
+        AMR_ONE_TIME_PASSWORD, AMR_PASSWORD, METRICS,
+    },
+    ErrorResponse,
 };
 
-#[derive(Debug, Deserialize)]
-pub struct Verify2FARequest {
-    pub email: Secret<String>,
-    #[serde(rename = "loginAttemptId")]
-    pub login_attempt_id: Secret<String>,
-    #[serde(rename = "2FACode")]
-    pub two_fa_code: Secret<String>,
-}
+pub use auth_types::Verify2FARequest;
 
+#[utoipa::path(
+    post,
+    path = "/verify-2fa",
+    tag = "auth",
+    request_body = Verify2FARequest,
+    responses(
+        (status = 200, description = "Code accepted; the JWT is set in the `jwt` cookie, or returned in the body with `tokenDelivery: body`", body = TokenResponse,
+            headers(("set-cookie" = String, description = "`jwt` cookie holding the token, unless it is returned in the body"))),
+        (status = 400, description = "Invalid email, login attempt id or code (`invalid_credentials`), with the failing fields", body = ErrorResponse),
+        (status = 401, description = "Wrong or expired code (`incorrect_credentials`)", body = ErrorResponse),
+        (status = 403, description = "The account was locked (`account_locked`) or flagged for a password reset (`password_reset_required`) since the login", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let token_delivery = request.token_delivery;
     let email = Email::parse(request.email);
     let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id);
     let two_fa_code = TwoFACode::parse(request.two_fa_code);
 
     if email.is_err() || login_attempt_id.is_err() || two_fa_code.is_err() {
-        return (jar, Err(AuthAPIError::InvalidCredentials));
+        let errors = [
+            email
+                .as_ref()
+                .err()
+                .map(|e| FieldError::new("email", field_error::INVALID_FORMAT, e)),
+            login_attempt_id
+                .as_ref()
+                .err()
+                .map(|e| FieldError::new("loginAttemptId", field_error::INVALID_FORMAT, e)),
+            two_fa_code
+                .as_ref()
+                .err()
+                .map(|e| FieldError::new("2FACode", field_error::INVALID_FORMAT, e)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        state
+            .audit(AuditEvent::TwoFactorFailed {
+                email: email
+                    .ok()
+                    .map(|email| email.as_ref().expose_secret().to_owned()),
+                reason: AuditFailureReason::InvalidInput,
+            })
+            .await;
+        return (jar, Err(AuthAPIError::InvalidCredentials(errors)));
     }
+    let audit_email = email.as_ref().unwrap().as_ref().expose_secret().to_owned();
 
     let validation_result = {
         let mut two_fa_code_store = state.two_fa_code_store.write().await;
@@ -40,6 +82,13 @@ pub async fn verify_2fa(
 
         // if no email found with some
         if two_fa_stored_code_result.is_err() {
+            METRICS.record_two_fa(two_fa_event::EXPIRED);
+            state
+                .audit(AuditEvent::TwoFactorFailed {
+                    email: Some(audit_email),
+                    reason: AuditFailureReason::CodeExpired,
+                })
+                .await;
             return (jar, Err(AuthAPIError::IncorrectCredentials));
         }
 
@@ -48,6 +97,13 @@ pub async fn verify_2fa(
         if &stored_login_attempt_id != login_attempt_id.as_ref().unwrap()
             || &stored_two_fa_code != two_fa_code.as_ref().unwrap()
         {
+            METRICS.record_two_fa(two_fa_event::REJECTED);
+            state
+                .audit(AuditEvent::TwoFactorFailed {
+                    email: Some(audit_email),
+                    reason: AuditFailureReason::CodeMismatch,
+                })
+                .await;
             return (jar, Err(AuthAPIError::IncorrectCredentials));
         }
 
@@ -65,15 +121,45 @@ pub async fn verify_2fa(
         );
     }
 
-    let auth_cookie = generate_auth_cookie(email.as_ref().unwrap()).unwrap();
-    if auth_cookie.value().is_empty() {
-        return (
-            jar,
-            Err(AuthAPIError::UnexpectedError(eyre!(
-                "Failed to generate auth cookie"
-            ))),
-        );
+    let amr = [AMR_PASSWORD, AMR_ONE_TIME_PASSWORD, AMR_MULTI_FACTOR];
+    let email = email.as_ref().unwrap();
+    // An administrator may have locked or deleted the account since the code was sent
+    let user = match state.user_store.read().await.get_user(email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    if let Some((error, reason)) = refusal(&user) {
+        METRICS.record_two_fa(two_fa_event::REJECTED);
+        state
+            .audit(AuditEvent::TwoFactorFailed {
+                email: Some(audit_email),
+                reason,
+            })
+            .await;
+        return (jar, Err(error));
     }
+    let roles = match state.role_store.read().await.get_user_roles(user.id).await {
+        Ok(roles) => roles,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    let token = match generate_auth_token(user.id, &roles, &amr, &state.auth_settings) {
+        Ok(token) => token,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
 
-    (jar.add(auth_cookie), Ok(StatusCode::OK.into_response()))
+    METRICS.record_two_fa(two_fa_event::VERIFIED);
+    state
+        .audit(AuditEvent::TwoFactorVerified { email: audit_email })
+        .await;
+    match token_delivery {
+        TokenDelivery::Cookie => (
+            jar.add(create_auth_cookie(token)),
+            Ok(StatusCode::OK.into_response()),
+        ),
+        TokenDelivery::Body => (
+            jar,
+            Ok((StatusCode::OK, Json(TokenResponse { token })).into_response()),
+        ),
+    }
 }
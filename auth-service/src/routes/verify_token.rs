@@ -1,26 +1,195 @@
-use axum::{extract::State, response::IntoResponse, Json};
-use reqwest::StatusCode;
+use axum::{
+    extract::{rejection::JsonRejection, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
 use secrecy::Secret;
 use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::validate_token};
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditFailureReason, AuthAPIError, UserStoreError},
+    utils::{is_api_key, token_user, validate_api_key, validate_token, AuthToken, TokenUse},
+    ErrorResponse,
+};
 
-#[derive(Debug, Deserialize)]
-pub struct VerifyTokenRequest {
-    pub token: Secret<String>,
+pub use auth_types::{VerifyTokenRequest, VerifyTokenResponse};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct VerifyTokenQuery {
+    // For callers sending the token as a bearer token or cookie; a JSON body's wins
+    pub permission: Option<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/verify-token",
+    tag = "auth",
+    params(VerifyTokenQuery),
+    request_body(content = VerifyTokenRequest, description = "The token or API key to check; without a JSON body, the bearer token or `jwt` cookie is checked"),
+    security((), ("jwt_bearer" = []), ("jwt_cookie" = [])),
+    responses(
+        (status = 200, description = "The token is valid and not banned, or the API key is valid and not expired or revoked", body = VerifyTokenResponse),
+        (status = 400, description = "No body, bearer token or `jwt` cookie (`missing_token`)", body = ErrorResponse),
+        (status = 401, description = "Invalid, expired or banned token or API key (`invalid_token`)", body = ErrorResponse),
+        (status = 403, description = "Valid, but none of the user's roles grants the required permission (`forbidden`)", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body"),
+        (status = 500, description = "Unexpected error (`unexpected_error`)", body = ErrorResponse),
+    )
+)]
 #[tracing::instrument(name = "Verify Token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
-    Json(request): Json<VerifyTokenRequest>,
-) -> Result<impl IntoResponse, AuthAPIError> {
-    if validate_token(state.banned_token_store, request.token)
-        .await
-        .is_err()
-    {
-        return Err(AuthAPIError::InvalidToken);
+    Query(query): Query<VerifyTokenQuery>,
+    header_token: Option<AuthToken>,
+    request: Result<Json<VerifyTokenRequest>, JsonRejection>,
+) -> Result<Response, AuthAPIError> {
+    // A JSON body takes precedence; without one, the caller's own token is checked
+    let (token, permission) = match (request, header_token) {
+        (Ok(Json(request)), _) => (request.token, request.permission.or(query.permission)),
+        (Err(JsonRejection::MissingJsonContentType(_)), Some(AuthToken(token))) => {
+            (token, query.permission)
+        }
+        (Err(JsonRejection::MissingJsonContentType(_)), None) => {
+            state
+                .audit(AuditEvent::TokenRejected {
+                    reason: AuditFailureReason::MissingToken,
+                })
+                .await;
+            return Err(AuthAPIError::MissingToken);
+        }
+        (Err(rejection), _) => return Ok(rejection.into_response()),
+    };
+
+    let verified = match is_api_key(&token) {
+        true => verify_api_key(&state, &token).await?,
+        false => verify_jwt(&state, token).await?,
+    };
+
+    if let Some(permission) = permission {
+        let granted = state
+            .role_store
+            .read()
+            .await
+            .has_permission(&verified.roles, &permission)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        if !granted {
+            state
+                .audit(AuditEvent::PermissionDenied {
+                    email: verified.email.clone(),
+                    permission,
+                })
+                .await;
+            return Err(AuthAPIError::Forbidden);
+        }
     }
 
-    Ok(StatusCode::OK.into_response())
+    Ok(Json(verified).into_response())
+}
+
+async fn verify_jwt(
+    state: &AppState,
+    token: Secret<String>,
+) -> Result<VerifyTokenResponse, AuthAPIError> {
+    let claims = match validate_token(
+        &state.auth_settings,
+        state.banned_token_store.clone(),
+        token,
+    )
+    .await
+    {
+        Ok(claims) => claims,
+        Err(_) => return Err(rejected(state).await),
+    };
+
+    let email = token_user(&state.user_store, &claims)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?
+        .map(|user| user.email.value().to_owned());
+
+    let event = match claims.is_service() {
+        true => AuditEvent::ServiceTokenVerified {
+            client_id: claims.sub.clone(),
+        },
+        false => AuditEvent::TokenVerified {
+            email: email.clone().unwrap_or_else(|| claims.sub.clone()),
+        },
+    };
+    state.audit(event).await;
+
+    Ok(VerifyTokenResponse {
+        sub: claims.sub,
+        email,
+        exp: Some(claims.exp),
+        client_id: claims.client_id,
+        scope: claims.scope,
+        token_use: claims.token_use,
+        roles: claims.roles,
+    })
+}
+
+async fn verify_api_key(
+    state: &AppState,
+    key: &Secret<String>,
+) -> Result<VerifyTokenResponse, AuthAPIError> {
+    let api_key = match validate_api_key(state.api_key_store.clone(), key).await {
+        Ok(api_key) => api_key,
+        Err(_) => return Err(rejected(state).await),
+    };
+    // Keys stop working while their owner's account is locked
+    let owner = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(api_key.user_id)
+        .await;
+    let owner = match owner {
+        Ok(user) if !user.locked => user,
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(rejected(state).await),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    let roles = user_roles(state, owner.id).await?;
+
+    let email = owner.email.value().to_owned();
+    state
+        .audit(AuditEvent::TokenVerified {
+            email: email.clone(),
+        })
+        .await;
+
+    Ok(VerifyTokenResponse {
+        sub: owner.id.to_string(),
+        email: Some(email),
+        exp: api_key
+            .expires_at
+            .and_then(|expires_at| expires_at.timestamp().try_into().ok()),
+        client_id: None,
+        scope: Some(api_key.scopes.join(" ")),
+        token_use: TokenUse::ApiKey,
+        roles,
+    })
+}
+
+async fn rejected(state: &AppState) -> AuthAPIError {
+    state
+        .audit(AuditEvent::TokenRejected {
+            reason: AuditFailureReason::InvalidToken,
+        })
+        .await;
+
+    AuthAPIError::InvalidToken
+}
+
+// Keys outlive any login, so their owner's roles are looked up on each use
+async fn user_roles(state: &AppState, user_id: Uuid) -> Result<Vec<String>, AuthAPIError> {
+    state
+        .role_store
+        .read()
+        .await
+        .get_user_roles(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
 }
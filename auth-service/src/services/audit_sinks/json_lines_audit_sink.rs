@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context, Result};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::domain::{AuditQuery, AuditRecord, AuditSink};
+
+// Appends one JSON object per line, e.g. for shipping with a log forwarder.
+// Queries scan the whole file, so it suits modest volumes or rotated files.
+#[derive(Debug)]
+pub struct JsonLinesAuditSink {
+    path: PathBuf,
+    file: File,
+}
+
+impl JsonLinesAuditSink {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .await
+                .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .wrap_err_with(|| format!("failed to open audit log {}", path.display()))?;
+
+        Ok(Self { path, file })
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditSink for JsonLinesAuditSink {
+    #[tracing::instrument(name = "Appending audit event to file", skip_all)]
+    async fn record(&mut self, record: AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(&record).wrap_err("failed to serialize audit event")?;
+        line.push(b'\n');
+
+        self.file
+            .write_all(&line)
+            .await
+            .wrap_err("failed to write audit event")?;
+        self.file
+            .flush()
+            .await
+            .wrap_err("failed to flush audit log")
+    }
+
+    #[tracing::instrument(name = "Querying audit events from file", skip_all)]
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>> {
+        let contents = fs::read_to_string(&self.path)
+            .await
+            .wrap_err_with(|| format!("failed to read audit log {}", self.path.display()))?;
+
+        let mut records = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<AuditRecord>(line) {
+                Ok(record) if query.matches(&record) => records.push(record),
+                Ok(_) => {}
+                // A line cut short by a crash should not hide every other event
+                Err(e) => tracing::warn!(error = ?e, "skipping malformed audit log line"),
+            }
+        }
+        records.sort_by_key(|record| std::cmp::Reverse(record.occurred_at));
+        records.truncate(query.limit);
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::{AuditEvent, AuditFailureReason};
+
+    #[tokio::test]
+    async fn test_records_survive_reopening_the_file() {
+        let path = env::temp_dir()
+            .join(format!("audit-{}", Uuid::new_v4()))
+            .join("audit.jsonl");
+
+        let mut sink = JsonLinesAuditSink::open(&path).await.unwrap();
+        sink.record(AuditRecord::new(AuditEvent::LoginFailed {
+            email: Some("a@example.com".to_owned()),
+            reason: AuditFailureReason::IncorrectCredentials,
+        }))
+        .await
+        .unwrap();
+        sink.record(AuditRecord::new(AuditEvent::TokenRejected {
+            reason: AuditFailureReason::InvalidToken,
+        }))
+        .await
+        .unwrap();
+        drop(sink);
+
+        let sink = JsonLinesAuditSink::open(&path).await.unwrap();
+        let all = sink.query(&AuditQuery::default()).await.unwrap();
+        let failures = sink
+            .query(&AuditQuery {
+                event_type: Some("login_failed".to_owned()),
+                ..AuditQuery::default()
+            })
+            .await
+            .unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).await.ok();
+
+        assert_eq!(all.len(), 2);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].event.email(), Some("a@example.com"));
+    }
+}
@@ -0,0 +1,62 @@
+use color_eyre::eyre::Result;
+
+use crate::domain::{AuditQuery, AuditRecord, AuditSink};
+
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink {
+    records: Vec<AuditRecord>,
+}
+
+#[async_trait::async_trait]
+impl AuditSink for MemoryAuditSink {
+    async fn record(&mut self, record: AuditRecord) -> Result<()> {
+        self.records.push(record);
+
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>> {
+        let mut records: Vec<AuditRecord> = self
+            .records
+            .iter()
+            .filter(|record| query.matches(record))
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.occurred_at));
+        records.truncate(query.limit);
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::AuditEvent;
+
+    #[tokio::test]
+    async fn test_query_returns_newest_matching_records_first() {
+        let mut sink = MemoryAuditSink::default();
+        for email in ["a@example.com", "b@example.com", "a@example.com"] {
+            sink.record(AuditRecord::new(AuditEvent::LoginSucceeded {
+                email: email.to_owned(),
+            }))
+            .await
+            .unwrap();
+        }
+
+        let query = AuditQuery {
+            email: Some("a@example.com".to_owned()),
+            ..AuditQuery::default()
+        };
+        let records = sink.query(&query).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records[0].occurred_at >= records[1].occurred_at);
+
+        let query = AuditQuery {
+            limit: 1,
+            ..AuditQuery::default()
+        };
+        assert_eq!(sink.query(&query).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod json_lines_audit_sink;
+pub mod memory_audit_sink;
+pub mod postgres_audit_sink;
+
+pub use json_lines_audit_sink::*;
+pub use memory_audit_sink::*;
+pub use postgres_audit_sink::*;
@@ -0,0 +1,76 @@
+use color_eyre::eyre::{Context, Result};
+use sqlx::PgPool;
+
+use crate::domain::{AuditEvent, AuditQuery, AuditRecord, AuditSink};
+
+pub struct PostgresAuditSink {
+    pool: PgPool,
+}
+
+impl PostgresAuditSink {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditSink for PostgresAuditSink {
+    #[tracing::instrument(name = "Adding audit event to PostgreSQL", skip_all)]
+    async fn record(&mut self, record: AuditRecord) -> Result<()> {
+        let event =
+            serde_json::to_value(&record.event).wrap_err("failed to serialize audit event")?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events(id, occurred_at, event_type, email, event)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            record.id,
+            record.occurred_at,
+            record.event.event_type(),
+            record.event.email(),
+            event
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to insert audit event")?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events from PostgreSQL", skip_all)]
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>> {
+        let limit = i64::try_from(query.limit).unwrap_or(i64::MAX);
+
+        sqlx::query!(
+            r#"
+            SELECT id, occurred_at, event
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR email = $1)
+              AND ($2::TEXT IS NULL OR event_type = $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR occurred_at >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR occurred_at < $4)
+            ORDER BY occurred_at DESC
+            LIMIT $5
+            "#,
+            query.email,
+            query.event_type,
+            query.from,
+            query.to,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("failed to query audit events")?
+        .into_iter()
+        .map(|row| {
+            Ok(AuditRecord {
+                id: row.id,
+                occurred_at: row.occurred_at,
+                event: serde_json::from_value::<AuditEvent>(row.event)
+                    .wrap_err("failed to deserialize audit event")?,
+            })
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{ApiKey, ApiKeyStore, ApiKeyStoreError};
+
+// Keys by prefix
+#[derive(Debug, Default)]
+pub struct HashmapApiKeyStore {
+    keys: HashMap<String, ApiKey>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for HashmapApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        if self.keys.contains_key(&key.prefix) {
+            return Err(ApiKeyStoreError::KeyAlreadyExists);
+        }
+        self.keys.insert(key.prefix.clone(), key);
+
+        Ok(())
+    }
+
+    async fn get_key(&self, prefix: &str) -> Result<ApiKey, ApiKeyStoreError> {
+        self.keys
+            .get(prefix)
+            .cloned()
+            .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+
+    async fn list_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut keys: Vec<ApiKey> = self
+            .keys
+            .values()
+            .filter(|key| key.user_id == user_id)
+            .cloned()
+            .collect();
+        keys.sort_by_key(|key| key.created_at);
+
+        Ok(keys)
+    }
+
+    async fn revoke_key(&mut self, user_id: Uuid, id: Uuid) -> Result<ApiKey, ApiKeyStoreError> {
+        let prefix = self
+            .keys
+            .values()
+            .find(|key| key.id == id && key.user_id == user_id)
+            .map(|key| key.prefix.clone())
+            .ok_or(ApiKeyStoreError::KeyNotFound)?;
+
+        self.keys
+            .remove(&prefix)
+            .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+
+    async fn record_use(
+        &mut self,
+        id: Uuid,
+        used_at: DateTime<Utc>,
+    ) -> Result<(), ApiKeyStoreError> {
+        let key = self
+            .keys
+            .values_mut()
+            .find(|key| key.id == id)
+            .ok_or(ApiKeyStoreError::KeyNotFound)?;
+        key.last_used_at = Some(used_at);
+
+        Ok(())
+    }
+
+    async fn purge_expired(&mut self, now: DateTime<Utc>) -> Result<u64, ApiKeyStoreError> {
+        let before = self.keys.len();
+        self.keys.retain(|_, key| !key.is_expired(now));
+
+        Ok((before - self.keys.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{generate_api_key, hash_opaque_token};
+
+    fn api_key(owner: Uuid) -> ApiKey {
+        let (prefix, key) = generate_api_key();
+        ApiKey {
+            id: Uuid::new_v4(),
+            user_id: owner,
+            name: "deploy script".to_owned(),
+            prefix,
+            key_hash: hash_opaque_token(&key),
+            scopes: vec!["deploy".to_owned()],
+            created_at: Utc::now(),
+            expires_at: None,
+            last_used_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_listed_and_revoked_by_their_owner_only() {
+        let mut store = HashmapApiKeyStore::default();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let key = api_key(alice);
+        store.add_key(key.clone()).await.unwrap();
+        store.add_key(api_key(bob)).await.unwrap();
+        assert_eq!(
+            store.add_key(key.clone()).await,
+            Err(ApiKeyStoreError::KeyAlreadyExists)
+        );
+
+        assert_eq!(store.list_keys(alice).await.unwrap(), vec![key.clone()]);
+        assert_eq!(
+            store.revoke_key(bob, key.id).await.unwrap_err(),
+            ApiKeyStoreError::KeyNotFound
+        );
+        assert_eq!(store.revoke_key(alice, key.id).await.unwrap(), key);
+        assert_eq!(
+            store.get_key(&key.prefix).await.unwrap_err(),
+            ApiKeyStoreError::KeyNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_use_sets_last_used_at() {
+        let mut store = HashmapApiKeyStore::default();
+        let key = api_key(Uuid::new_v4());
+        store.add_key(key.clone()).await.unwrap();
+
+        let now = Utc::now();
+        store.record_use(key.id, now).await.unwrap();
+
+        assert_eq!(
+            store.get_key(&key.prefix).await.unwrap().last_used_at,
+            Some(now)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired_deletes_expired_keys_only() {
+        let mut store = HashmapApiKeyStore::default();
+        let now = Utc::now();
+        let expired = ApiKey {
+            expires_at: Some(now),
+            ..api_key(Uuid::new_v4())
+        };
+        let current = ApiKey {
+            expires_at: Some(now + chrono::Duration::days(1)),
+            ..api_key(Uuid::new_v4())
+        };
+        let unlimited = api_key(Uuid::new_v4());
+        for key in [&expired, &current, &unlimited] {
+            store.add_key(key.clone()).await.unwrap();
+        }
+
+        assert_eq!(store.purge_expired(now).await.unwrap(), 1);
+        assert_eq!(store.purge_expired(now).await.unwrap(), 0);
+        assert!(store.get_key(&expired.prefix).await.is_err());
+        assert!(store.get_key(&current.prefix).await.is_ok());
+        assert!(store.get_key(&unlimited.prefix).await.is_ok());
+    }
+}
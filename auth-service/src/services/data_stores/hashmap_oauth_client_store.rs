@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use secrecy::Secret;
+
+use crate::domain::{OAuthClient, OAuthClientStore, OAuthClientStoreError};
+
+#[derive(Debug, Default)]
+pub struct HashmapOAuthClientStore {
+    pub clients: HashMap<String, OAuthClient>,
+}
+
+impl HashmapOAuthClientStore {
+    pub fn with_clients(clients: impl IntoIterator<Item = OAuthClient>) -> Self {
+        Self {
+            clients: clients
+                .into_iter()
+                .map(|client| (client.client_id.clone(), client))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for HashmapOAuthClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError> {
+        if self.clients.contains_key(&client.client_id) {
+            return Err(OAuthClientStoreError::ClientAlreadyExists);
+        }
+        self.clients.insert(client.client_id.clone(), client);
+
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, OAuthClientStoreError> {
+        self.clients
+            .get(client_id)
+            .cloned()
+            .ok_or(OAuthClientStoreError::ClientNotFound)
+    }
+
+    async fn validate_client(
+        &self,
+        client_id: &str,
+        client_secret: &Secret<String>,
+    ) -> Result<(), OAuthClientStoreError> {
+        match self.clients.get(client_id) {
+            Some(client) if client.verify_secret(client_secret) => Ok(()),
+            _ => Err(OAuthClientStoreError::InvalidClientCredentials),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(client_id: &str, client_secret: &str) -> OAuthClient {
+        OAuthClient::new(client_id.to_owned(), &Secret::new(client_secret.to_owned()))
+    }
+
+    #[tokio::test]
+    async fn test_validate_client() {
+        let mut store = HashmapOAuthClientStore::with_clients([client("gateway", "s3cret")]);
+        assert_eq!(
+            store.add_client(client("gateway", "other")).await,
+            Err(OAuthClientStoreError::ClientAlreadyExists)
+        );
+
+        let secret = |value: &str| Secret::new(value.to_owned());
+        assert!(store
+            .validate_client("gateway", &secret("s3cret"))
+            .await
+            .is_ok());
+        assert_eq!(
+            store.validate_client("gateway", &secret("s3cre")).await,
+            Err(OAuthClientStoreError::InvalidClientCredentials)
+        );
+        assert_eq!(
+            store.validate_client("unknown", &secret("s3cret")).await,
+            Err(OAuthClientStoreError::InvalidClientCredentials)
+        );
+
+        // Public clients have no secret to authenticate with
+        let public = OAuthClient {
+            client_secret_hash: None,
+            ..client("mobile", "")
+        };
+        store.add_client(public).await.unwrap();
+        assert_eq!(
+            store.validate_client("mobile", &secret("")).await,
+            Err(OAuthClientStoreError::InvalidClientCredentials)
+        );
+        assert!(!store.get_client("mobile").await.unwrap().is_confidential());
+        assert_eq!(
+            store.get_client("unknown").await.unwrap_err(),
+            OAuthClientStoreError::ClientNotFound
+        );
+    }
+}
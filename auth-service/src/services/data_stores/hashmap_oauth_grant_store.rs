@@ -0,0 +1,198 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+use crate::{
+    domain::{AuthorizationCodeGrant, OAuthGrantStore, RefreshTokenGrant},
+    utils::{AUTHORIZATION_CODE_TTL_SECONDS, REFRESH_TOKEN_TTL_SECONDS},
+};
+
+// Entries are kept with their expiry; expired ones are dropped as new ones are added
+#[derive(Debug, Default)]
+pub struct HashmapOAuthGrantStore {
+    pub authorization_codes: HashMap<String, (AuthorizationCodeGrant, Instant)>,
+    pub refresh_tokens: HashMap<String, (RefreshTokenGrant, Instant)>,
+}
+
+fn insert<T>(
+    entries: &mut HashMap<String, (T, Instant)>,
+    key: &Secret<String>,
+    value: T,
+    ttl: u64,
+) {
+    let now = Instant::now();
+    entries.retain(|_, (_, expires_at)| *expires_at > now);
+    entries.insert(
+        key.expose_secret().to_owned(),
+        (value, now + Duration::from_secs(ttl)),
+    );
+}
+
+fn take<T>(entries: &mut HashMap<String, (T, Instant)>, key: &Secret<String>) -> Option<T> {
+    entries
+        .remove(key.expose_secret())
+        .filter(|(_, expires_at)| *expires_at > Instant::now())
+        .map(|(value, _)| value)
+}
+
+#[async_trait::async_trait]
+impl OAuthGrantStore for HashmapOAuthGrantStore {
+    async fn add_authorization_code(
+        &mut self,
+        code: &Secret<String>,
+        grant: AuthorizationCodeGrant,
+    ) -> Result<()> {
+        insert(
+            &mut self.authorization_codes,
+            code,
+            grant,
+            AUTHORIZATION_CODE_TTL_SECONDS,
+        );
+        Ok(())
+    }
+
+    async fn take_authorization_code(
+        &mut self,
+        code: &Secret<String>,
+    ) -> Result<Option<AuthorizationCodeGrant>> {
+        Ok(take(&mut self.authorization_codes, code))
+    }
+
+    async fn add_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+        grant: RefreshTokenGrant,
+    ) -> Result<()> {
+        insert(
+            &mut self.refresh_tokens,
+            token,
+            grant,
+            REFRESH_TOKEN_TTL_SECONDS,
+        );
+        Ok(())
+    }
+
+    async fn take_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+    ) -> Result<Option<RefreshTokenGrant>> {
+        Ok(take(&mut self.refresh_tokens, token))
+    }
+
+    async fn get_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+    ) -> Result<Option<RefreshTokenGrant>> {
+        Ok(self
+            .refresh_tokens
+            .get(token.expose_secret())
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(grant, _)| grant.clone()))
+    }
+
+    async fn revoke_refresh_tokens(&mut self, user_id: Uuid) -> Result<()> {
+        self.refresh_tokens
+            .retain(|_, (grant, _)| grant.user_id != user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refresh_grant(user_id: Uuid) -> RefreshTokenGrant {
+        RefreshTokenGrant {
+            client_id: "app".to_owned(),
+            user_id,
+            scope: None,
+            auth_time: 1_700_000_000,
+            amr: vec!["pwd".to_owned()],
+        }
+    }
+
+    fn grant() -> AuthorizationCodeGrant {
+        AuthorizationCodeGrant {
+            client_id: "app".to_owned(),
+            user_id: Uuid::nil(),
+            scope: None,
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            redirect_uri_sent: true,
+            code_challenge: "challenge".to_owned(),
+            nonce: None,
+            auth_time: 1_700_000_000,
+            amr: vec!["pwd".to_owned()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorization_codes_are_single_use() {
+        let mut store = HashmapOAuthGrantStore::default();
+        let code = Secret::new("code".to_owned());
+
+        store.add_authorization_code(&code, grant()).await.unwrap();
+
+        assert_eq!(
+            store.take_authorization_code(&code).await.unwrap(),
+            Some(grant())
+        );
+        assert_eq!(store.take_authorization_code(&code).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_getting_a_refresh_token_keeps_it() {
+        let mut store = HashmapOAuthGrantStore::default();
+        let token = Secret::new("token".to_owned());
+        store
+            .add_refresh_token(&token, refresh_grant(Uuid::nil()))
+            .await
+            .unwrap();
+
+        let grant = Some(refresh_grant(Uuid::nil()));
+        assert_eq!(store.get_refresh_token(&token).await.unwrap(), grant);
+        assert_eq!(store.take_refresh_token(&token).await.unwrap(), grant);
+        assert_eq!(store.get_refresh_token(&token).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_expired_grants_are_unknown() {
+        let mut store = HashmapOAuthGrantStore::default();
+        let token = Secret::new("token".to_owned());
+        store.refresh_tokens.insert(
+            "token".to_owned(),
+            (refresh_grant(Uuid::nil()), Instant::now()),
+        );
+
+        assert_eq!(store.get_refresh_token(&token).await.unwrap(), None);
+        assert_eq!(store.take_refresh_token(&token).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_revoking_refresh_tokens_only_affects_their_user() {
+        let mut store = HashmapOAuthGrantStore::default();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let alice_token = Secret::new("alice".to_owned());
+        let bob_token = Secret::new("bob".to_owned());
+        store
+            .add_refresh_token(&alice_token, refresh_grant(alice))
+            .await
+            .unwrap();
+        store
+            .add_refresh_token(&bob_token, refresh_grant(bob))
+            .await
+            .unwrap();
+
+        store.revoke_refresh_tokens(alice).await.unwrap();
+
+        assert_eq!(store.take_refresh_token(&alice_token).await.unwrap(), None);
+        assert_eq!(
+            store.take_refresh_token(&bob_token).await.unwrap(),
+            Some(refresh_grant(bob))
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use uuid::Uuid;
+
+use crate::domain::{Role, RoleStore, RoleStoreError};
+
+#[derive(Debug, Default)]
+pub struct HashmapRoleStore {
+    roles: BTreeMap<String, Role>,
+    // Role names by user
+    assignments: HashMap<Uuid, BTreeSet<String>>,
+}
+
+#[async_trait::async_trait]
+impl RoleStore for HashmapRoleStore {
+    async fn add_role(&mut self, role: Role) -> Result<(), RoleStoreError> {
+        if self.roles.contains_key(&role.name) {
+            return Err(RoleStoreError::RoleAlreadyExists);
+        }
+        self.roles.insert(role.name.clone(), role);
+
+        Ok(())
+    }
+
+    async fn list_roles(&self) -> Result<Vec<Role>, RoleStoreError> {
+        Ok(self.roles.values().cloned().collect())
+    }
+
+    async fn assign_role(&mut self, user_id: Uuid, role: &str) -> Result<(), RoleStoreError> {
+        if !self.roles.contains_key(role) {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+        self.assignments
+            .entry(user_id)
+            .or_default()
+            .insert(role.to_owned());
+
+        Ok(())
+    }
+
+    async fn unassign_role(&mut self, user_id: Uuid, role: &str) -> Result<(), RoleStoreError> {
+        let removed = self
+            .assignments
+            .get_mut(&user_id)
+            .is_some_and(|roles| roles.remove(role));
+
+        match removed {
+            true => Ok(()),
+            false => Err(RoleStoreError::RoleNotFound),
+        }
+    }
+
+    async fn get_user_roles(&self, user_id: Uuid) -> Result<Vec<String>, RoleStoreError> {
+        Ok(self
+            .assignments
+            .get(&user_id)
+            .map(|roles| roles.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn has_permission(
+        &self,
+        roles: &[String],
+        permission: &str,
+    ) -> Result<bool, RoleStoreError> {
+        Ok(roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .any(|role| role.has_permission(permission)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(name: &str, permissions: &[&str]) -> Role {
+        Role {
+            name: name.to_owned(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assigned_roles_grant_their_permissions() {
+        let mut store = HashmapRoleStore::default();
+        store
+            .add_role(role("support", &["users:read"]))
+            .await
+            .unwrap();
+        store
+            .add_role(role("admin", &["users:read", "users:manage"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.add_role(role("admin", &[])).await,
+            Err(RoleStoreError::RoleAlreadyExists)
+        );
+
+        let alice = Uuid::new_v4();
+        store.assign_role(alice, "support").await.unwrap();
+        store.assign_role(alice, "support").await.unwrap();
+        assert_eq!(
+            store.assign_role(alice, "owner").await,
+            Err(RoleStoreError::RoleNotFound)
+        );
+        let roles = store.get_user_roles(alice).await.unwrap();
+        assert_eq!(roles, vec!["support".to_owned()]);
+        assert!(store.has_permission(&roles, "users:read").await.unwrap());
+        assert!(!store.has_permission(&roles, "users:manage").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unassigned_roles_are_removed() {
+        let mut store = HashmapRoleStore::default();
+        store
+            .add_role(role("admin", &["users:manage"]))
+            .await
+            .unwrap();
+        let alice = Uuid::new_v4();
+        store.assign_role(alice, "admin").await.unwrap();
+
+        store.unassign_role(alice, "admin").await.unwrap();
+
+        assert!(store.get_user_roles(alice).await.unwrap().is_empty());
+        assert_eq!(
+            store.unassign_role(alice, "admin").await,
+            Err(RoleStoreError::RoleNotFound)
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use uuid::Uuid;
+
+use crate::domain::{
+    Email, Password, User, UserPage, UserQuery, UserSettings, UserStore, UserStoreError,
+};
 
 #[derive(Debug, Default)]
 pub struct HashmapUserStore {
@@ -26,6 +30,14 @@ impl UserStore for HashmapUserStore {
         }
     }
 
+    async fn get_user_by_id(&self, id: Uuid) -> Result<User, UserStoreError> {
+        self.users
+            .values()
+            .find(|user| user.id == id)
+            .cloned()
+            .ok_or(UserStoreError::UserNotFound)
+    }
+
     async fn validate_user(
         &self,
         email: &Email,
@@ -41,6 +53,99 @@ impl UserStore for HashmapUserStore {
             Err(UserStoreError::UserNotFound)
         }
     }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let search = query.search.as_deref().map(str::to_lowercase);
+        let mut users: Vec<&User> = self
+            .users
+            .values()
+            .filter(|user| {
+                search
+                    .as_deref()
+                    .is_none_or(|search| user.email.value().to_lowercase().contains(search))
+            })
+            .collect();
+        users.sort_by(|a, b| a.email.value().cmp(b.email.value()));
+
+        Ok(UserPage {
+            total: users.len(),
+            users: users
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn update_settings(
+        &mut self,
+        id: Uuid,
+        settings: &UserSettings,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .values_mut()
+            .find(|user| user.id == id)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = settings.requires_2fa.unwrap_or(user.requires_2fa);
+        user.locked = settings.locked.unwrap_or(user.locked);
+        user.password_reset_required = settings
+            .password_reset_required
+            .unwrap_or(user.password_reset_required);
+        Ok(())
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        self.update(email, |user| {
+            user.password = password;
+            user.password_reset_required = false;
+        })
+    }
+
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), UserStoreError> {
+        if self.users.contains_key(new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self
+            .users
+            .remove(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.email = new_email.clone();
+        self.users.insert(new_email.clone(), user);
+
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        self.users
+            .remove(email)
+            .map(|_| ())
+            .ok_or(UserStoreError::UserNotFound)
+    }
+}
+
+impl HashmapUserStore {
+    fn update(
+        &mut self,
+        email: &Email,
+        change: impl FnOnce(&mut User),
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        change(user);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -48,7 +153,7 @@ mod tests {
     use secrecy::Secret;
 
     use crate::domain::UserStore;
-    use crate::domain::{Email, Password, User};
+    use crate::domain::{Email, Password, User, UserPage, UserQuery, UserSettings};
     use crate::services::hashmap_user_store::{HashmapUserStore, UserStoreError};
 
     const TEST_EMAIL: &str = "test@example.com";
@@ -89,6 +194,21 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_get_user_by_id() {
+        let mut test_subject = HashmapUserStore::default();
+        let user = setup_user();
+        test_subject.add_user(user.clone()).await.unwrap();
+
+        let found = test_subject.get_user_by_id(user.id).await.unwrap();
+
+        assert_eq!(found.email, user.email);
+        assert_eq!(
+            test_subject.get_user_by_id(uuid::Uuid::new_v4()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
     #[tokio::test]
     async fn test_get_user_that_does_not_exist() {
         let test_subject = HashmapUserStore::default();
@@ -151,6 +271,168 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_list_users_searches_and_pages_by_email() {
+        let mut test_subject = HashmapUserStore::default();
+        for email in ["carol@example.com", "alice@example.com", "bob@test.org"] {
+            let user = User::new(
+                Email::parse(Secret::new(email.to_string())).unwrap(),
+                Password::parse(Secret::new(TEST_PASSWORD.to_string())).unwrap(),
+                false,
+            );
+            test_subject.add_user(user).await.unwrap();
+        }
+        let emails = |page: UserPage| -> Vec<String> {
+            page.users
+                .iter()
+                .map(|user| user.email.value().to_owned())
+                .collect()
+        };
+
+        let page = test_subject
+            .list_users(&UserQuery {
+                search: None,
+                offset: 1,
+                limit: 1,
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(emails(page), vec!["bob@test.org"]);
+
+        let page = test_subject
+            .list_users(&UserQuery {
+                search: Some("EXAMPLE".to_owned()),
+                offset: 0,
+                limit: 10,
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(emails(page), vec!["alice@example.com", "carol@example.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_updating_and_deleting_users() {
+        let mut test_subject = HashmapUserStore::default();
+        let user = setup_user();
+        test_subject.add_user(user.clone()).await.unwrap();
+
+        test_subject
+            .update_settings(
+                user.id,
+                &UserSettings {
+                    requires_2fa: Some(false),
+                    locked: Some(true),
+                    password_reset_required: Some(true),
+                },
+            )
+            .await
+            .unwrap();
+        let updated = test_subject.get_user(&user.email).await.unwrap();
+        assert!(updated.locked);
+        assert!(updated.password_reset_required);
+        assert!(!updated.requires_2fa);
+
+        test_subject
+            .update_settings(
+                user.id,
+                &UserSettings {
+                    locked: Some(false),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let updated = test_subject.get_user(&user.email).await.unwrap();
+        assert!(!updated.locked);
+        assert!(updated.password_reset_required);
+
+        test_subject.delete_user(&user.email).await.unwrap();
+        assert_eq!(
+            test_subject.delete_user(&user.email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(
+            test_subject
+                .update_settings(user.id, &UserSettings::default())
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_password_clears_reset_requirement() {
+        let mut test_subject = HashmapUserStore::default();
+        let user = setup_user();
+        test_subject.add_user(user.clone()).await.unwrap();
+        test_subject
+            .update_settings(
+                user.id,
+                &UserSettings {
+                    password_reset_required: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let new_password = Password::parse(Secret::new("Qwer5678".to_string())).unwrap();
+        test_subject
+            .update_password(&user.email, new_password.clone())
+            .await
+            .unwrap();
+
+        assert!(test_subject
+            .validate_user(&user.email, &user.password)
+            .await
+            .is_err());
+        assert!(test_subject
+            .validate_user(&user.email, &new_password)
+            .await
+            .is_ok());
+        assert!(
+            !test_subject
+                .get_user(&user.email)
+                .await
+                .unwrap()
+                .password_reset_required
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_email() {
+        let mut test_subject = HashmapUserStore::default();
+        let user = setup_user();
+        test_subject.add_user(user.clone()).await.unwrap();
+        let other = Email::parse(Secret::new("other@example.com".to_string())).unwrap();
+        test_subject
+            .add_user(User::new(other.clone(), user.password.clone(), false))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            test_subject.update_email(&user.email, &other).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+
+        let new_email = Email::parse(Secret::new("new@example.com".to_string())).unwrap();
+        test_subject
+            .update_email(&user.email, &new_email)
+            .await
+            .unwrap();
+
+        assert!(test_subject.get_user(&user.email).await.is_err());
+        assert_eq!(
+            test_subject.get_user_by_id(user.id).await.unwrap().email,
+            new_email
+        );
+        assert_eq!(
+            test_subject.update_email(&user.email, &user.email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
     pub fn setup_user() -> User {
         User::new(
             Email::parse(Secret::new(TEST_EMAIL.to_string())).unwrap(),
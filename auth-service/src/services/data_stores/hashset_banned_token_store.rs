@@ -1,31 +1,52 @@
+use chrono::Utc;
 use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, Secret};
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use crate::domain::{BannedTokenStore, BannedTokenStoreError};
 
 #[derive(Debug, Default)]
 pub struct HashsetBannedTokenStore {
-    pub tokens: HashSet<String>,
+    // When each ban lapses, in seconds since the epoch
+    pub tokens: HashMap<String, i64>,
+    // When each subject's sessions were last revoked
+    pub revoked_sessions: HashMap<String, u64>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<()> {
-        if !self.tokens.insert(token.expose_secret().to_owned()) {
+    async fn add_token(&mut self, token: Secret<String>, ttl_seconds: u64) -> Result<()> {
+        if self.contains_token(token.clone()).await? {
             return Err(
                 BannedTokenStoreError::UnexpectedError(eyre!("Token already exists")).into(),
             );
         }
+        self.tokens.insert(
+            token.expose_secret().to_owned(),
+            Utc::now().timestamp() + ttl_seconds as i64,
+        );
 
         Ok(())
     }
 
     async fn contains_token(&mut self, token: Secret<String>) -> Result<bool> {
-        let flag = self.tokens.contains(token.expose_secret());
+        let flag = self
+            .tokens
+            .get(token.expose_secret())
+            .is_some_and(|banned_until| *banned_until > Utc::now().timestamp());
 
         Ok(flag)
     }
+
+    async fn revoke_sessions(&mut self, subject: &str, revoked_at: u64) -> Result<()> {
+        self.revoked_sessions.insert(subject.to_owned(), revoked_at);
+
+        Ok(())
+    }
+
+    async fn sessions_revoked_at(&mut self, subject: &str) -> Result<Option<u64>> {
+        Ok(self.revoked_sessions.get(subject).copied())
+    }
 }
 
 #[cfg(test)]
@@ -49,7 +70,7 @@ mod tests {
 
         // Add the token to the banned list
         store
-            .add_token(secrecy::Secret::new(token.to_string()))
+            .add_token(secrecy::Secret::new(token.to_string()), 600)
             .await
             .unwrap();
         // Now, the token should be banned
@@ -59,4 +80,52 @@ mod tests {
             .unwrap();
         assert!(is_banned, "Token should be banned after adding");
     }
+
+    #[tokio::test]
+    async fn test_revoke_sessions() {
+        use crate::domain::BannedTokenStore;
+        use crate::services::hashset_banned_token_store::HashsetBannedTokenStore;
+
+        let mut store = HashsetBannedTokenStore::default();
+        assert_eq!(
+            store.sessions_revoked_at("user@example.com").await.unwrap(),
+            None
+        );
+
+        store
+            .revoke_sessions("user@example.com", 100)
+            .await
+            .unwrap();
+        store
+            .revoke_sessions("user@example.com", 200)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.sessions_revoked_at("user@example.com").await.unwrap(),
+            Some(200)
+        );
+        assert_eq!(
+            store
+                .sessions_revoked_at("other@example.com")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bans_lapse_after_their_ttl() {
+        use crate::domain::BannedTokenStore;
+        use crate::services::hashset_banned_token_store::HashsetBannedTokenStore;
+
+        let mut store = HashsetBannedTokenStore::default();
+        let token = || secrecy::Secret::new("sample_token".to_string());
+
+        store.add_token(token(), 0).await.unwrap();
+        assert!(!store.contains_token(token()).await.unwrap());
+
+        // A lapsed ban may be renewed
+        store.add_token(token(), 600).await.unwrap();
+        assert!(store.contains_token(token()).await.unwrap());
+    }
 }
@@ -1,13 +1,29 @@
+pub mod hashmap_api_key_store;
+pub mod hashmap_oauth_client_store;
+pub mod hashmap_oauth_grant_store;
+pub mod hashmap_role_store;
 pub mod hashmap_two_fa_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
+pub mod postgres_api_key_store;
+pub mod postgres_oauth_client_store;
+pub mod postgres_role_store;
 pub mod postgres_user_store;
 pub mod redis_banned_tokens_store;
+pub mod redis_oauth_grant_store;
 pub mod redis_two_fa_code_store;
 
+pub use hashmap_api_key_store::*;
+pub use hashmap_oauth_client_store::*;
+pub use hashmap_oauth_grant_store::*;
+pub use hashmap_role_store::*;
 pub use hashmap_two_fa_store::*;
 pub use hashmap_user_store::*;
 pub use hashset_banned_token_store::*;
+pub use postgres_api_key_store::*;
+pub use postgres_oauth_client_store::*;
+pub use postgres_role_store::*;
 pub use postgres_user_store::*;
 pub use redis_banned_tokens_store::*;
+pub use redis_oauth_grant_store::*;
 pub use redis_two_fa_code_store::*;
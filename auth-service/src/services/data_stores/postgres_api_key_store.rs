@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{ApiKey, ApiKeyStore, ApiKeyStoreError};
+
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to PostgreSQL", skip_all)]
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO api_keys(id, user_id, name, prefix, key_hash, scopes, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT DO NOTHING
+            "#,
+            key.id,
+            key.user_id,
+            key.name,
+            key.prefix,
+            key.key_hash,
+            &key.scopes,
+            key.created_at,
+            key.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(ApiKeyStoreError::KeyAlreadyExists),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving API key from PostgreSQL", skip_all)]
+    async fn get_key(&self, prefix: &str) -> Result<ApiKey, ApiKeyStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, user_id, name, prefix, key_hash, scopes, created_at, expires_at, last_used_at
+            FROM api_keys
+            WHERE prefix = $1
+            "#,
+            prefix
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(ApiKeyStoreError::KeyNotFound)?;
+
+        Ok(ApiKey {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            prefix: row.prefix,
+            key_hash: row.key_hash,
+            scopes: row.scopes,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Listing API keys from PostgreSQL", skip_all)]
+    async fn list_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT id, user_id, name, prefix, key_hash, scopes, created_at, expires_at, last_used_at
+            FROM api_keys
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(ApiKey {
+                id: row.id,
+                user_id: row.user_id,
+                name: row.name,
+                prefix: row.prefix,
+                key_hash: row.key_hash,
+                scopes: row.scopes,
+                created_at: row.created_at,
+                expires_at: row.expires_at,
+                last_used_at: row.last_used_at,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking API key in PostgreSQL", skip_all)]
+    async fn revoke_key(&mut self, user_id: Uuid, id: Uuid) -> Result<ApiKey, ApiKeyStoreError> {
+        let row = sqlx::query!(
+            r#"
+            DELETE FROM api_keys
+            WHERE id = $1 AND user_id = $2
+            RETURNING id, user_id, name, prefix, key_hash, scopes, created_at, expires_at, last_used_at
+            "#,
+            id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(ApiKeyStoreError::KeyNotFound)?;
+
+        Ok(ApiKey {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            prefix: row.prefix,
+            key_hash: row.key_hash,
+            scopes: row.scopes,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Recording API key use in PostgreSQL", skip_all)]
+    async fn record_use(
+        &mut self,
+        id: Uuid,
+        used_at: DateTime<Utc>,
+    ) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            "UPDATE api_keys SET last_used_at = $2 WHERE id = $1",
+            id,
+            used_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(ApiKeyStoreError::KeyNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Purging expired API keys from PostgreSQL", skip_all)]
+    async fn purge_expired(&mut self, now: DateTime<Utc>) -> Result<u64, ApiKeyStoreError> {
+        let result = sqlx::query!("DELETE FROM api_keys WHERE expires_at <= $1", now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
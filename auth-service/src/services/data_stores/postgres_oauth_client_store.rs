@@ -0,0 +1,109 @@
+use color_eyre::eyre::{Context, Result};
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use crate::domain::{OAuthClient, OAuthClientStore, OAuthClientStoreError};
+
+pub struct PostgresOAuthClientStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Creates or replaces the clients configured in the settings, so that a secret or
+    // redirect URI changed there takes effect on restart
+    #[tracing::instrument(name = "Saving OAuth clients to PostgreSQL", skip_all)]
+    pub async fn save_clients(&self, clients: impl IntoIterator<Item = OAuthClient>) -> Result<()> {
+        for client in clients {
+            sqlx::query!(
+                r#"
+                INSERT INTO oauth_clients(client_id, client_name, client_secret_hash, redirect_uris, scopes)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (client_id) DO UPDATE
+                SET client_name = EXCLUDED.client_name,
+                    client_secret_hash = EXCLUDED.client_secret_hash,
+                    redirect_uris = EXCLUDED.redirect_uris,
+                    scopes = EXCLUDED.scopes
+                "#,
+                client.client_id,
+                client.client_name,
+                client.client_secret_hash,
+                &client.redirect_uris,
+                &client.scopes
+            )
+            .execute(&self.pool)
+            .await
+            .wrap_err_with(|| format!("failed to save OAuth client {}", client.client_id))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for PostgresOAuthClientStore {
+    #[tracing::instrument(name = "Adding OAuth client to PostgreSQL", skip_all)]
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO oauth_clients(client_id, client_name, client_secret_hash, redirect_uris, scopes)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (client_id) DO NOTHING
+            "#,
+            client.client_id,
+            client.client_name,
+            client.client_secret_hash,
+            &client.redirect_uris,
+            &client.scopes
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(OAuthClientStoreError::ClientAlreadyExists),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth client from PostgreSQL", skip_all)]
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, OAuthClientStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT client_id, client_name, client_secret_hash, redirect_uris, scopes
+            FROM oauth_clients
+            WHERE client_id = $1
+            "#,
+            client_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?
+        .map(|row| OAuthClient {
+            client_id: row.client_id,
+            client_name: row.client_name,
+            client_secret_hash: row.client_secret_hash,
+            redirect_uris: row.redirect_uris,
+            scopes: row.scopes,
+        })
+        .ok_or(OAuthClientStoreError::ClientNotFound)
+    }
+
+    #[tracing::instrument(name = "Validating OAuth client credentials in PostgreSQL", skip_all)]
+    async fn validate_client(
+        &self,
+        client_id: &str,
+        client_secret: &Secret<String>,
+    ) -> Result<(), OAuthClientStoreError> {
+        match self.get_client(client_id).await {
+            Ok(client) if client.verify_secret(client_secret) => Ok(()),
+            Err(OAuthClientStoreError::UnexpectedError(e)) => {
+                Err(OAuthClientStoreError::UnexpectedError(e))
+            }
+            _ => Err(OAuthClientStoreError::InvalidClientCredentials),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{Role, RoleStore, RoleStoreError};
+
+pub struct PostgresRoleStore {
+    pool: PgPool,
+}
+
+impl PostgresRoleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for PostgresRoleStore {
+    #[tracing::instrument(name = "Adding role to PostgreSQL", skip_all)]
+    async fn add_role(&mut self, role: Role) -> Result<(), RoleStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            "INSERT INTO roles(name) VALUES ($1) ON CONFLICT DO NOTHING",
+            role.name
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(RoleStoreError::RoleAlreadyExists);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO role_permissions(role, permission)
+            SELECT $1, permission FROM UNNEST($2::TEXT[]) AS permission
+            ON CONFLICT DO NOTHING
+            "#,
+            role.name,
+            &role.permissions
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Listing roles from PostgreSQL", skip_all)]
+    async fn list_roles(&self) -> Result<Vec<Role>, RoleStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT roles.name,
+                   COALESCE(
+                       ARRAY_AGG(role_permissions.permission ORDER BY role_permissions.permission)
+                           FILTER (WHERE role_permissions.permission IS NOT NULL),
+                       '{}'
+                   ) AS "permissions!"
+            FROM roles
+            LEFT JOIN role_permissions ON role_permissions.role = roles.name
+            GROUP BY roles.name
+            ORDER BY roles.name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Role {
+                name: row.name,
+                permissions: row.permissions,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Assigning role in PostgreSQL", skip_all)]
+    async fn assign_role(&mut self, user_id: Uuid, role: &str) -> Result<(), RoleStoreError> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1) AS "exists!""#,
+            role
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        if !exists {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        sqlx::query!(
+            "INSERT INTO user_roles(user_id, role) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            user_id,
+            role
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Unassigning role in PostgreSQL", skip_all)]
+    async fn unassign_role(&mut self, user_id: Uuid, role: &str) -> Result<(), RoleStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM user_roles WHERE user_id = $1 AND role = $2",
+            user_id,
+            role
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(RoleStoreError::RoleNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving user roles from PostgreSQL", skip_all)]
+    async fn get_user_roles(&self, user_id: Uuid) -> Result<Vec<String>, RoleStoreError> {
+        sqlx::query_scalar!(
+            "SELECT role FROM user_roles WHERE user_id = $1 ORDER BY role",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Checking permission in PostgreSQL", skip_all)]
+    async fn has_permission(
+        &self,
+        roles: &[String],
+        permission: &str,
+    ) -> Result<bool, RoleStoreError> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM role_permissions WHERE role = ANY($1) AND permission = $2
+            ) AS "exists!"
+            "#,
+            roles,
+            permission
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))
+    }
+}
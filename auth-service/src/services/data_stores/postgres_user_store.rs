@@ -6,8 +6,12 @@ use argon2::{
 use color_eyre::eyre::{eyre, Context, Result};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
+use uuid::Uuid;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::{
+    domain::{Email, Password, User, UserPage, UserQuery, UserSettings, UserStore, UserStoreError},
+    utils::{hash_operation, METRICS},
+};
 
 pub struct PostgresUserStore {
     pool: PgPool,
@@ -31,7 +35,8 @@ impl UserStore for PostgresUserStore {
                 .map_err(UserStoreError::UnexpectedError)?;
 
             sqlx::query!(
-                "INSERT INTO users(email, password_hash, requires_2fa) VALUES ($1, $2, $3)",
+                "INSERT INTO users(id, email, password_hash, requires_2fa) VALUES ($1, $2, $3, $4)",
+                user.id,
                 &user.email.as_ref().expose_secret(),
                 &hashed_password.expose_secret(),
                 user.requires_2fa
@@ -46,9 +51,10 @@ impl UserStore for PostgresUserStore {
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        sqlx::query!(
+        sqlx::query_as!(
+            UserRow,
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT id, email, password_hash, requires_2fa, locked, password_reset_required
             FROM users
             WHERE email = $1
             "#,
@@ -57,15 +63,25 @@ impl UserStore for PostgresUserStore {
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|row| {
-            Ok(User {
-                email: Email::parse(Secret::new(row.email))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(Secret::new(row.password_hash))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
-        })
+        .map(User::try_from)
+        .ok_or(UserStoreError::UserNotFound)?
+    }
+
+    #[tracing::instrument(name = "Retrieving user by id from PostgreSQL", skip_all)]
+    async fn get_user_by_id(&self, id: Uuid) -> Result<User, UserStoreError> {
+        sqlx::query_as!(
+            UserRow,
+            r#"
+            SELECT id, email, password_hash, requires_2fa, locked, password_reset_required
+            FROM users
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .map(User::try_from)
         .ok_or(UserStoreError::UserNotFound)?
     }
 
@@ -91,6 +107,173 @@ impl UserStore for PostgresUserStore {
             Err(UserStoreError::UserNotFound)
         }
     }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        // `%` and `_` in the search are literal characters, not wildcards
+        let pattern = query.search.as_deref().map(|search| {
+            format!(
+                "%{}%",
+                search
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            )
+        });
+        let limit = i64::try_from(query.limit).unwrap_or(i64::MAX);
+        let offset = i64::try_from(query.offset).unwrap_or(i64::MAX);
+
+        let total = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM users WHERE $1::TEXT IS NULL OR email ILIKE $1"#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let users = sqlx::query_as!(
+            UserRow,
+            r#"
+            SELECT id, email, password_hash, requires_2fa, locked, password_reset_required
+            FROM users
+            WHERE $1::TEXT IS NULL OR email ILIKE $1
+            ORDER BY email
+            LIMIT $2 OFFSET $3
+            "#,
+            pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(User::try_from)
+        .collect::<Result<_, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total.try_into().map_err(|e: std::num::TryFromIntError| {
+                UserStoreError::UnexpectedError(e.into())
+            })?,
+        })
+    }
+
+    #[tracing::instrument(name = "Updating user settings in PostgreSQL", skip_all)]
+    async fn update_settings(
+        &mut self,
+        id: Uuid,
+        settings: &UserSettings,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET
+                requires_2fa = COALESCE($2, requires_2fa),
+                locked = COALESCE($3, locked),
+                password_reset_required = COALESCE($4, password_reset_required)
+            WHERE id = $1
+            "#,
+            id,
+            settings.requires_2fa,
+            settings.locked,
+            settings.password_reset_required
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        found(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let hashed_password = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET password_hash = $2, password_reset_required = FALSE
+            WHERE email = $1
+            "#,
+            email.value(),
+            hashed_password.expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        found(result.rows_affected())
+    }
+
+    // Role assignments follow through the `user_roles` foreign key
+    #[tracing::instrument(name = "Updating user email in PostgreSQL", skip_all)]
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET email = $2 WHERE email = $1",
+            email.value(),
+            new_email.value()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(db_error) if db_error.is_unique_violation() => UserStoreError::UserAlreadyExists,
+            _ => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        found(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!("DELETE FROM users WHERE email = $1", email.value())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        found(result.rows_affected())
+    }
+}
+
+struct UserRow {
+    id: Uuid,
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    locked: bool,
+    password_reset_required: bool,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            id: row.id,
+            email: Email::parse(Secret::new(row.email))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(Secret::new(row.password_hash))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            locked: row.locked,
+            password_reset_required: row.password_reset_required,
+        })
+    }
+}
+
+// Updates of a missing user change no rows
+fn found(rows_affected: u64) -> Result<(), UserStoreError> {
+    match rows_affected {
+        0 => Err(UserStoreError::UserNotFound),
+        _ => Ok(()),
+    }
 }
 
 #[tracing::instrument(name = "Verify password hash", skip_all)]
@@ -101,6 +284,11 @@ async fn verify_password_hash(
     let current_span: tracing::Span = tracing::Span::current();
     let result = tokio::task::spawn_blocking(move || {
         current_span.in_scope(|| {
+            // Observed when dropped, so only the hashing itself is timed
+            let _timer = METRICS
+                .password_hash_duration_seconds
+                .with_label_values(&[hash_operation::VERIFY])
+                .start_timer();
             let expected_password_hash: PasswordHash<'_> =
                 PasswordHash::new(expected_password_hash.expose_secret())?;
 
@@ -122,6 +310,10 @@ async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String
     let current_span: tracing::Span = tracing::Span::current();
     let result = tokio::task::spawn_blocking(move || {
         current_span.in_scope(|| {
+            let _timer = METRICS
+                .password_hash_duration_seconds
+                .with_label_values(&[hash_operation::COMPUTE])
+                .start_timer();
             let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
             let password_hash = Argon2::new(
                 Algorithm::Argon2id,
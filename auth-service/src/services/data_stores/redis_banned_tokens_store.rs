@@ -1,39 +1,40 @@
 use color_eyre::eyre::{Context, Result};
-use redis::{Commands, Connection};
+use redis::{Client, Commands, Connection, RedisResult};
 use secrecy::{ExposeSecret, Secret};
 
 use crate::{
     domain::{BannedTokenStore, BannedTokenStoreError},
+    ping_redis,
     utils::auth::TOKEN_TTL_SECONDS,
 };
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const REVOKED_SESSIONS_KEY_PREFIX: &str = "sessions_revoked:";
 
 pub struct RedisBannedTokenStore {
+    // Health checks use their own connection, as they may be abandoned mid-command
+    client: Client,
     conn: Connection,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Connection) -> Self {
-        Self { conn }
+    pub fn new(client: Client) -> RedisResult<Self> {
+        let conn = client.get_connection()?;
+        Ok(Self { client, conn })
     }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Add Token to REDIS", skip_all)]
-    async fn add_token(&mut self, token: Secret<String>) -> Result<()> {
+    async fn add_token(&mut self, token: Secret<String>, ttl_seconds: u64) -> Result<()> {
         let token_key = get_key(token.expose_secret());
         let value = true;
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
 
         let _: () = self
             .conn
-            .set_ex(&token_key, value, ttl)
+            .set_ex(&token_key, value, ttl_seconds)
             .wrap_err("failed to set banned token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
@@ -52,6 +53,45 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         Ok(is_banned)
     }
+
+    // Kept for as long as the tokens it bans could still be valid
+    #[tracing::instrument(name = "Revoke sessions in REDIS", skip_all)]
+    async fn revoke_sessions(&mut self, subject: &str, revoked_at: u64) -> Result<()> {
+        let ttl: u64 = TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .set_ex(
+                format!("{}{}", REVOKED_SESSIONS_KEY_PREFIX, subject),
+                revoked_at,
+                ttl,
+            )
+            .wrap_err("failed to set revoked sessions in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Check revoked sessions in REDIS", skip_all)]
+    async fn sessions_revoked_at(&mut self, subject: &str) -> Result<Option<u64>> {
+        let revoked_at: Option<u64> = self
+            .conn
+            .get(format!("{}{}", REVOKED_SESSIONS_KEY_PREFIX, subject))
+            .wrap_err("failed to get revoked sessions from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(revoked_at)
+    }
+
+    #[tracing::instrument(name = "Ping REDIS", skip_all)]
+    async fn health_check(&mut self) -> Result<()> {
+        ping_redis(&self.client)
+            .await
+            .wrap_err("failed to ping Redis")
+    }
 }
 
 fn get_key(token: &str) -> String {
@@ -0,0 +1,159 @@
+use color_eyre::eyre::{Context, Result};
+use redis::{Client, Commands, Connection, RedisResult};
+use secrecy::{ExposeSecret, Secret};
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    domain::{AuthorizationCodeGrant, OAuthGrantStore, RefreshTokenGrant},
+    ping_redis,
+    utils::{AUTHORIZATION_CODE_TTL_SECONDS, REFRESH_TOKEN_TTL_SECONDS},
+};
+
+const AUTHORIZATION_CODE_PREFIX: &str = "oauth_code:";
+const REFRESH_TOKEN_PREFIX: &str = "oauth_refresh_token:";
+// A set of each user's refresh token keys, so they can be revoked together
+const USER_REFRESH_TOKENS_PREFIX: &str = "oauth_user_refresh_tokens:";
+
+pub struct RedisOAuthGrantStore {
+    // Health checks use their own connection, as they may be abandoned mid-command
+    client: Client,
+    conn: Connection,
+}
+
+impl RedisOAuthGrantStore {
+    pub fn new(client: Client) -> RedisResult<Self> {
+        let conn = client.get_connection()?;
+        Ok(Self { client, conn })
+    }
+
+    fn set<T: Serialize>(&mut self, key: String, value: &T, ttl: u64) -> Result<()> {
+        let serialized = serde_json::to_string(value).wrap_err("failed to serialize grant")?;
+
+        self.conn
+            .set_ex(key, serialized, ttl)
+            .wrap_err("failed to set grant in Redis")
+    }
+
+    // GETDEL, so concurrent requests cannot both redeem the same grant
+    fn take<T: DeserializeOwned>(&mut self, key: String) -> Result<Option<T>> {
+        let value: Option<String> = self
+            .conn
+            .get_del(key)
+            .wrap_err("failed to take grant from Redis")?;
+
+        value
+            .map(|value| serde_json::from_str(&value).wrap_err("failed to deserialize grant"))
+            .transpose()
+    }
+
+    fn get<T: DeserializeOwned>(&mut self, key: String) -> Result<Option<T>> {
+        let value: Option<String> = self
+            .conn
+            .get(key)
+            .wrap_err("failed to get grant from Redis")?;
+
+        value
+            .map(|value| serde_json::from_str(&value).wrap_err("failed to deserialize grant"))
+            .transpose()
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthGrantStore for RedisOAuthGrantStore {
+    #[tracing::instrument(name = "Adding authorization code to Redis", skip_all)]
+    async fn add_authorization_code(
+        &mut self,
+        code: &Secret<String>,
+        grant: AuthorizationCodeGrant,
+    ) -> Result<()> {
+        self.set(
+            get_key(AUTHORIZATION_CODE_PREFIX, code),
+            &grant,
+            AUTHORIZATION_CODE_TTL_SECONDS,
+        )
+    }
+
+    #[tracing::instrument(name = "Taking authorization code from Redis", skip_all)]
+    async fn take_authorization_code(
+        &mut self,
+        code: &Secret<String>,
+    ) -> Result<Option<AuthorizationCodeGrant>> {
+        self.take(get_key(AUTHORIZATION_CODE_PREFIX, code))
+    }
+
+    #[tracing::instrument(name = "Adding refresh token to Redis", skip_all)]
+    async fn add_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+        grant: RefreshTokenGrant,
+    ) -> Result<()> {
+        let key = get_key(REFRESH_TOKEN_PREFIX, token);
+        let index = user_index_key(grant.user_id);
+        self.set(key.clone(), &grant, REFRESH_TOKEN_TTL_SECONDS)?;
+
+        // The index lives as long as the user's newest token; taken tokens are left in it
+        let ttl: i64 = REFRESH_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("failed to cast REFRESH_TOKEN_TTL_SECONDS to i64")?;
+        redis::pipe()
+            .atomic()
+            .sadd(&index, key)
+            .ignore()
+            .expire(&index, ttl)
+            .ignore()
+            .query::<()>(&mut self.conn)
+            .wrap_err("failed to index refresh token in Redis")
+    }
+
+    #[tracing::instrument(name = "Taking refresh token from Redis", skip_all)]
+    async fn take_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+    ) -> Result<Option<RefreshTokenGrant>> {
+        self.take(get_key(REFRESH_TOKEN_PREFIX, token))
+    }
+
+    #[tracing::instrument(name = "Getting refresh token from Redis", skip_all)]
+    async fn get_refresh_token(
+        &mut self,
+        token: &Secret<String>,
+    ) -> Result<Option<RefreshTokenGrant>> {
+        self.get(get_key(REFRESH_TOKEN_PREFIX, token))
+    }
+
+    #[tracing::instrument(name = "Revoking refresh tokens in Redis", skip_all)]
+    async fn revoke_refresh_tokens(&mut self, user_id: Uuid) -> Result<()> {
+        // Emptied atomically, so tokens added meanwhile go into a new index and survive
+        let index = user_index_key(user_id);
+        let (keys,): (Vec<String>,) = redis::pipe()
+            .atomic()
+            .smembers(&index)
+            .del(&index)
+            .ignore()
+            .query(&mut self.conn)
+            .wrap_err("failed to read refresh token index from Redis")?;
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        self.conn
+            .del(keys)
+            .wrap_err("failed to delete refresh tokens from Redis")
+    }
+
+    #[tracing::instrument(name = "Ping Redis", skip_all)]
+    async fn health_check(&mut self) -> Result<()> {
+        ping_redis(&self.client)
+            .await
+            .wrap_err("failed to ping Redis")
+    }
+}
+
+fn get_key(prefix: &str, value: &Secret<String>) -> String {
+    format!("{}{}", prefix, value.expose_secret())
+}
+
+fn user_index_key(user_id: Uuid) -> String {
+    format!("{}{}", USER_REFRESH_TOKENS_PREFIX, user_id)
+}
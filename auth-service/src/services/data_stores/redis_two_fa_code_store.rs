@@ -1,9 +1,12 @@
-use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use color_eyre::eyre::{Context, Result};
+use redis::{Client, Commands, Connection, RedisResult};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
-use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+use crate::{
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    ping_redis,
+};
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
@@ -12,12 +15,15 @@ const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 struct TwoFATuple(pub String, pub String);
 
 pub struct RedisTwoFACodeStore {
+    // Health checks use their own connection, as they may be abandoned mid-command
+    client: Client,
     conn: Connection,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Connection) -> Self {
-        Self { conn }
+    pub fn new(client: Client) -> RedisResult<Self> {
+        let conn = client.get_connection()?;
+        Ok(Self { client, conn })
     }
 }
 
@@ -90,6 +96,13 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    #[tracing::instrument(name = "Ping Redis", skip_all)]
+    async fn health_check(&mut self) -> Result<()> {
+        ping_redis(&self.client)
+            .await
+            .wrap_err("failed to ping Redis")
+    }
 }
 
 fn get_key(email: &Email) -> String {
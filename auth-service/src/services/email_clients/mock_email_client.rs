@@ -1,11 +1,32 @@
-use color_eyre::eyre::Result;
+use std::sync::{Arc, Mutex};
+
+use color_eyre::eyre::{eyre, Result};
 use secrecy::ExposeSecret;
 use thiserror::Error;
 
 use crate::domain::{Email, EmailClient};
 
+#[derive(Debug, Clone)]
+pub struct SentEmail {
+    pub recipient: Email,
+    pub subject: String,
+    pub content: String,
+}
+
 #[derive(Debug, Default, Clone, Error)]
-pub struct MockEmailClient;
+pub struct MockEmailClient {
+    // Shared between clones so tests can inspect what the app has sent
+    sent_emails: Arc<Mutex<Vec<SentEmail>>>,
+}
+
+impl MockEmailClient {
+    pub fn sent_emails(&self) -> Vec<SentEmail> {
+        self.sent_emails
+            .lock()
+            .map(|emails| emails.clone())
+            .unwrap_or_default()
+    }
+}
 
 // Implement Display for MockEmailClient
 impl std::fmt::Display for MockEmailClient {
@@ -25,6 +46,40 @@ impl EmailClient for MockEmailClient {
             content
         );
 
+        self.sent_emails
+            .lock()
+            .map_err(|_| eyre!("mock email outbox lock poisoned"))?
+            .push(SentEmail {
+                recipient: recipient.clone(),
+                subject: subject.to_owned(),
+                content: content.to_owned(),
+            });
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn send_email_is_recorded_and_shared_between_clones() {
+        let client = MockEmailClient::default();
+        let handle = client.clone();
+        let recipient = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+
+        client
+            .send_email(&recipient, "2FA Code", "123456")
+            .await
+            .unwrap();
+
+        let sent = handle.sent_emails();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].recipient, recipient);
+        assert_eq!(sent[0].subject, "2FA Code");
+        assert_eq!(sent[0].content, "123456");
+    }
+}
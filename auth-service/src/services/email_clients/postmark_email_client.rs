@@ -2,7 +2,12 @@ use color_eyre::eyre::Result; // For improved error handling and reporting
 use reqwest::{Client, Url}; // For making HTTP requests
 use secrecy::{ExposeSecret, Secret}; // For securely handling sensitive data
 
-use crate::domain::{Email, EmailClient}; // Import domain-specific modules
+use reqwest::header::HeaderMap; // For the trace context headers
+
+use crate::{
+    domain::{Email, EmailClient},
+    utils::inject_trace_context,
+}; // Import domain-specific modules
 
 // Define the PostmarkEmailClient struct
 pub struct PostmarkEmailClient {
@@ -47,6 +52,10 @@ impl EmailClient for PostmarkEmailClient {
             message_stream: MESSAGE_STREAM,
         };
 
+        // Continue the current trace in the email provider's logs
+        let mut trace_headers = HeaderMap::new();
+        inject_trace_context(&mut trace_headers);
+
         // Build the HTTP POST request
         let request = self
             .http_client
@@ -55,6 +64,7 @@ impl EmailClient for PostmarkEmailClient {
                 POSTMARK_AUTH_HEADER,
                 self.authorization_token.expose_secret(), // Securely expose the authorization token
             )
+            .headers(trace_headers)
             .json(&request_body);
 
         // Send the request and handle the response
@@ -62,6 +72,24 @@ impl EmailClient for PostmarkEmailClient {
 
         Ok(())
     }
+
+    #[tracing::instrument(name = "Checking email provider", skip_all)]
+    async fn health_check(&self) -> Result<()> {
+        // Fetching the server details checks both connectivity and the token
+        let url = Url::parse(&self.base_url)?.join("/server")?;
+
+        self.http_client
+            .get(url)
+            .header(
+                POSTMARK_AUTH_HEADER,
+                self.authorization_token.expose_secret(),
+            )
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
 }
 
 // Constants for message stream and authorization header
@@ -207,4 +235,82 @@ mod tests {
 
         assert!(outcome.is_err());
     }
+
+    // Test that the caller's trace is continued by the request to Postmark
+    #[tokio::test]
+    async fn send_email_propagates_the_trace_context() {
+        use opentelemetry::trace::{TraceContextExt, TracerProvider};
+        use tracing::Instrument;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::prelude::*;
+
+        let tracer_provider = service_telemetry::tracer_provider("test", None).unwrap();
+        let subscriber = tracing_subscriber::Registry::default()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let span = tracing::info_span!("login");
+        let trace_id = span.context().span().span_context().trace_id();
+
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(TraceparentMatcher(format!("00-{}-", trace_id)))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content())
+            .instrument(span)
+            .await;
+
+        assert!(outcome.is_ok());
+    }
+
+    // Matches requests whose `traceparent` header starts with the expected version and trace id
+    struct TraceparentMatcher(String);
+
+    impl wiremock::Match for TraceparentMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            request
+                .headers
+                .get("traceparent")
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with(&self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn health_check_requests_the_server_details() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists(POSTMARK_AUTH_HEADER))
+            .and(path("/server"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.health_check().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn health_check_fails_if_the_token_is_rejected() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.health_check().await.is_err());
+    }
 }
@@ -1,5 +1,7 @@
+pub mod audit_sinks;
 pub mod data_stores;
 pub mod email_clients;
 
+pub use audit_sinks::*;
 pub use data_stores::*;
 pub use email_clients::*;
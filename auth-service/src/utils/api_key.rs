@@ -0,0 +1,68 @@
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+
+use super::generate_opaque_token;
+
+// Marks API keys, so they are told apart from JWTs and can be found by secret scanners
+pub const API_KEY_PREFIX: &str = "lbk_";
+const KEY_ID_LENGTH: usize = 8;
+
+// A new key, `lbk_<8 hex digits>_<secret>`, and its prefix up to the id, which identifies
+// the key in listings and lookups
+pub fn generate_api_key() -> (String, Secret<String>) {
+    let mut id = [0u8; KEY_ID_LENGTH / 2];
+    rand::thread_rng().fill_bytes(&mut id);
+    let prefix = format!(
+        "{}{}",
+        API_KEY_PREFIX,
+        id.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+    );
+    let key = format!("{}_{}", prefix, generate_opaque_token().expose_secret());
+
+    (prefix, Secret::new(key))
+}
+
+pub fn is_api_key(token: &Secret<String>) -> bool {
+    token.expose_secret().starts_with(API_KEY_PREFIX)
+}
+
+// The prefix of a well-formed key
+pub fn api_key_prefix(key: &Secret<String>) -> Option<&str> {
+    let key = key.expose_secret();
+    let (id, secret) = key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+    (id.len() == KEY_ID_LENGTH && id.bytes().all(|b| b.is_ascii_hexdigit()) && !secret.is_empty())
+        .then(|| &key[..API_KEY_PREFIX.len() + KEY_ID_LENGTH])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys_start_with_their_prefix() {
+        let (prefix, key) = generate_api_key();
+
+        assert!(is_api_key(&key));
+        assert_eq!(prefix.len(), API_KEY_PREFIX.len() + KEY_ID_LENGTH);
+        assert_eq!(api_key_prefix(&key), Some(prefix.as_str()));
+        assert_ne!(generate_api_key().0, prefix);
+    }
+
+    #[test]
+    fn test_malformed_keys_have_no_prefix() {
+        for key in [
+            "lbk_1a2b3c4d",
+            "lbk_1a2b3c4d_",
+            "lbk_1a2b3c_secret",
+            "lbk_1a2b3cxz_secret",
+            "eyJhbGciOiJIUzI1NiJ9.e30.sig",
+        ] {
+            assert_eq!(
+                api_key_prefix(&Secret::new(key.to_owned())),
+                None,
+                "{}",
+                key
+            );
+        }
+    }
+}
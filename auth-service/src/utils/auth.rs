@@ -1,17 +1,39 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::{app_state::BannedTokenStoreType, domain::Email};
+pub use auth_types::{Claims, TokenUse};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use crate::{
+    app_state::{ApiKeyStoreType, AppState, BannedTokenStoreType, UserStoreType},
+    config::AuthSettings,
+    domain::{
+        ApiKey, AuditEvent, AuthAPIError, Email, OAuthClient, OAuthClientStoreError, OAuthError,
+        User, UserStoreError,
+    },
+};
+
+use super::{
+    api_key_prefix,
+    constants::{ADMIN_PERMISSION, JWT_COOKIE_NAME},
+};
 
-// For testing purposes
-// const JWT_SECRET: &str = "your_secret";
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const EMAIL_CHANGE_TTL_SECONDS: i64 = 3600; // 1 hour
+
+const EMAIL_CHANGE_AUDIENCE: &str = "email_change";
 
 #[derive(Debug)]
 pub enum GenerateTokenError {
@@ -19,24 +41,115 @@ pub enum GenerateTokenError {
     UnexpectedError,
 }
 
+// Sent to a new address to confirm that user `sub` asked to move there. The `aud` keeps it
+// from being accepted as a session token, and session tokens from being accepted as one of
+// these.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
+pub struct EmailChangeClaims {
     pub sub: String,
+    pub new_email: String,
+    pub aud: String,
     pub exp: usize,
+    pub iat: usize,
 }
 
 #[tracing::instrument(name = "Generate Auth Cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    user_id: Uuid,
+    roles: &[String],
+    amr: &[&str],
+    settings: &AuthSettings,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user_id, roles, amr, settings)?;
     Ok(create_auth_cookie(token))
 }
 
+// A session token; `amr` lists the RFC 8176 methods the user logged in with
 #[tracing::instrument(name = "Generate Auth Token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
+pub fn generate_auth_token(
+    user_id: Uuid,
+    roles: &[String],
+    amr: &[&str],
+    settings: &AuthSettings,
+) -> Result<String> {
+    create_claims_token(
+        Claims {
+            sub: user_id.to_string(),
+            amr: amr.iter().map(|method| method.to_string()).collect(),
+            roles: roles.to_vec(),
+            ..Claims::default()
+        },
+        settings,
+    )
+}
+
+// Replaces `session`, e.g. after a password change, with the user's current `roles`. The
+// login time and methods are those of the original session.
+#[tracing::instrument(name = "Renew Auth Token", skip_all)]
+pub fn renew_auth_token(
+    user_id: Uuid,
+    roles: &[String],
+    session: &Claims,
+    settings: &AuthSettings,
+) -> Result<String> {
+    create_claims_token(
+        Claims {
+            sub: user_id.to_string(),
+            auth_time: session.authenticated_at(),
+            amr: session.amr.clone(),
+            roles: roles.to_vec(),
+            ..Claims::default()
+        },
+        settings,
+    )
+}
+
+// A token an OAuth client gets for user `sub`. It always carries a scope, empty when none was
+// granted, as a token without one is a session that may do anything its user can.
+#[tracing::instrument(name = "Generate Access Token", skip_all)]
+pub fn generate_access_token(
+    sub: String,
+    client_id: String,
+    scope: Option<String>,
+    settings: &AuthSettings,
+) -> Result<String> {
+    create_claims_token(
+        Claims {
+            sub,
+            client_id: Some(client_id),
+            scope: Some(scope.unwrap_or_default()),
+            ..Claims::default()
+        },
+        settings,
+    )
+}
+
+// A token for an OAuth client itself; `sub` and `client_id` are both the client id
+#[tracing::instrument(name = "Generate Service Token", skip_all)]
+pub fn generate_service_token(
+    client_id: String,
+    scope: Option<String>,
+    settings: &AuthSettings,
+) -> Result<String> {
+    create_claims_token(
+        Claims {
+            sub: client_id.clone(),
+            client_id: Some(client_id),
+            scope,
+            token_use: TokenUse::Service,
+            ..Claims::default()
+        },
+        settings,
+    )
+}
+
+// Signs `claims`, issued now and expiring after `TOKEN_TTL_SECONDS`
+fn create_claims_token(claims: Claims, settings: &AuthSettings) -> Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
-    let exp = Utc::now()
+    let now = Utc::now();
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(eyre!("failed to add 10 minutes to current time"))?
         .timestamp();
@@ -46,11 +159,23 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().to_owned();
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast iat time to usize")?;
+    let iat_us: u64 = now
+        .timestamp_micros()
+        .try_into()
+        .wrap_err("failed to cast iat time to u64")?;
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        exp,
+        iat,
+        iat_us,
+        ..claims
+    };
 
-    create_token(&claims)
+    create_token(&claims, settings)
 }
 
 #[tracing::instrument(name = "Create Auth Cookie", skip_all)]
@@ -64,8 +189,14 @@ pub fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+// Seconds until `exp`, so a banned token stays banned for as long as it would be accepted
+pub fn remaining_lifetime(exp: usize) -> u64 {
+    (exp as i64 - Utc::now().timestamp()).max(1) as u64
+}
+
 #[tracing::instrument(name = "Validate Auth Token", skip_all)]
 pub async fn validate_token(
+    settings: &AuthSettings,
     banned_token_store: BannedTokenStoreType,
     token: Secret<String>,
 ) -> Result<Claims> {
@@ -83,21 +214,378 @@ pub async fn validate_token(
         Err(e) => return Err(e),
     }
 
-    decode::<Claims>(
+    let claims = decode::<Claims>(
         token.expose_secret(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
         &Validation::default(),
     )
     .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    .wrap_err("failed to decode token")?;
+
+    // Tokens issued in the microsecond their sessions were revoked are rejected too
+    let revoked_at = banned_token_store
+        .write()
+        .await
+        .sessions_revoked_at(&claims.sub)
+        .await?;
+    if revoked_at.is_some_and(|revoked_at| claims.issued_at() <= revoked_at) {
+        return Err(eyre!("session was revoked"));
+    }
+
+    Ok(claims)
+}
+
+// Rejects every token of `subject` issued until now, e.g. when an account is locked
+pub async fn revoke_sessions(
+    banned_token_store: BannedTokenStoreType,
+    subject: &str,
+) -> Result<()> {
+    revoke_sessions_now(banned_token_store, subject)
+        .await
+        .map(|_| ())
+}
+
+// Ends every session of a user: their tokens issued until now, and the refresh tokens of the
+// OAuth clients they signed in to
+pub async fn end_user_sessions(state: &AppState, user_id: Uuid) -> Result<()> {
+    revoke_sessions(state.banned_token_store.clone(), &user_id.to_string()).await?;
+    state
+        .oauth_grant_store
+        .write()
+        .await
+        .revoke_refresh_tokens(user_id)
+        .await
+}
+
+// Returns the cutoff, in microseconds since the epoch
+async fn revoke_sessions_now(
+    banned_token_store: BannedTokenStoreType,
+    subject: &str,
+) -> Result<u64> {
+    let now = now_micros()?;
+    banned_token_store
+        .write()
+        .await
+        .revoke_sessions(subject, now)
+        .await?;
+
+    Ok(now)
+}
+
+fn now_micros() -> Result<u64> {
+    Utc::now()
+        .timestamp_micros()
+        .try_into()
+        .wrap_err("failed to cast the current time to u64")
+}
+
+// The account a user's token was issued to. `None` for service tokens, and for users deleted
+// since or tokens from before `sub` became the user id.
+pub async fn token_user(user_store: &UserStoreType, claims: &Claims) -> Result<Option<User>> {
+    if claims.is_service() {
+        return Ok(None);
+    }
+    let Ok(id) = Uuid::parse_str(&claims.sub) else {
+        return Ok(None);
+    };
+
+    match user_store.read().await.get_user_by_id(id).await {
+        Ok(user) => Ok(Some(user)),
+        Err(UserStoreError::UserNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Audit events name users by email: the address of the token's user, or the raw `sub` when
+// there is none to look up
+pub async fn audit_email(user_store: &UserStoreType, claims: &Claims) -> String {
+    match token_user(user_store, claims).await {
+        Ok(Some(user)) => user.email.value().to_owned(),
+        _ => claims.sub.clone(),
+    }
+}
+
+// Like `revoke_sessions`, but only returns once a token issued for `subject` would be accepted
+// again, so the caller can replace its own session. That is at most a microsecond later.
+pub async fn revoke_other_sessions(
+    banned_token_store: BannedTokenStoreType,
+    subject: &str,
+) -> Result<()> {
+    let revoked_at = revoke_sessions_now(banned_token_store, subject).await?;
+    while now_micros()? <= revoked_at {
+        tokio::task::yield_now().await;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Generate Email Change Token", skip_all)]
+pub fn generate_email_change_token(
+    user_id: Uuid,
+    new_email: &Email,
+    settings: &AuthSettings,
+) -> Result<String> {
+    let now = Utc::now().timestamp();
+    let claims = EmailChangeClaims {
+        sub: user_id.to_string(),
+        new_email: new_email.value().to_owned(),
+        aud: EMAIL_CHANGE_AUDIENCE.to_owned(),
+        exp: (now + EMAIL_CHANGE_TTL_SECONDS)
+            .try_into()
+            .wrap_err("failed to cast exp time to usize")?,
+        iat: now
+            .try_into()
+            .wrap_err("failed to cast iat time to usize")?,
+    };
+
+    create_token(&claims, settings)
+}
+
+// Used tokens are banned by the caller, so each confirms one change
+#[tracing::instrument(name = "Validate Email Change Token", skip_all)]
+pub async fn validate_email_change_token(
+    settings: &AuthSettings,
+    banned_token_store: BannedTokenStoreType,
+    token: &Secret<String>,
+) -> Result<EmailChangeClaims> {
+    if banned_token_store
+        .write()
+        .await
+        .contains_token(token.clone())
+        .await?
+    {
+        return Err(eyre!("token is banned"));
+    }
+
+    let mut validation = Validation::default();
+    validation.set_audience(&[EMAIL_CHANGE_AUDIENCE]);
+    decode::<EmailChangeClaims>(
+        token.expose_secret(),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode email change token")
+}
+
+// Checks a personal API key against its stored hash and expiry, and records that it was used
+#[tracing::instrument(name = "Validate API Key", skip_all)]
+pub async fn validate_api_key(
+    api_key_store: ApiKeyStoreType,
+    key: &Secret<String>,
+) -> Result<ApiKey> {
+    let prefix = api_key_prefix(key).ok_or_else(|| eyre!("malformed API key"))?;
+    let mut api_key_store = api_key_store.write().await;
+    let api_key = api_key_store
+        .get_key(prefix)
+        .await
+        .wrap_err("failed to get API key")?;
+
+    let now = Utc::now();
+    if !api_key.verify(key) {
+        return Err(eyre!("wrong API key"));
+    }
+    if api_key.is_expired(now) {
+        return Err(eyre!("API key expired"));
+    }
+    api_key_store
+        .record_use(api_key.id, now)
+        .await
+        .wrap_err("failed to record API key use")?;
+
+    Ok(ApiKey {
+        last_used_at: Some(now),
+        ..api_key
+    })
+}
+
+// The caller's JWT, from `Authorization: Bearer` or, failing that, the `jwt` cookie.
+// Use `Option<AuthToken>` to handle a missing token in the route.
+#[derive(Debug)]
+pub struct AuthToken(pub Secret<String>);
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthToken {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        bearer_token(&parts.headers)
+            .map(str::to_owned)
+            .or_else(|| {
+                CookieJar::from_headers(&parts.headers)
+                    .get(JWT_COOKIE_NAME)
+                    .map(|cookie| cookie.value().to_owned())
+                    .filter(|token| !token.is_empty())
+            })
+            .map(|token| AuthToken(Secret::new(token)))
+            .ok_or(AuthAPIError::MissingToken)
+    }
+}
+
+// Guards the /admin endpoints: requires a login session whose roles grant `ADMIN_PERMISSION`.
+// Only bearer tokens are accepted, as a cookie would let other sites act for an administrator.
+#[derive(Debug)]
+pub struct AdminAccess;
+
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for AdminAccess {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let token = bearer_token(&parts.headers).ok_or(AuthAPIError::MissingToken)?;
+        let claims = validate_token(
+            &state.auth_settings,
+            state.banned_token_store.clone(),
+            Secret::new(token.to_owned()),
+        )
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+        if claims.client_id.is_some() || claims.is_service() {
+            return Err(AuthAPIError::InvalidToken);
+        }
+
+        let granted = state
+            .role_store
+            .read()
+            .await
+            .has_permission(&claims.roles, ADMIN_PERMISSION)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        if !granted {
+            state
+                .audit(AuditEvent::PermissionDenied {
+                    email: Some(audit_email(&state.user_store, &claims).await),
+                    permission: ADMIN_PERMISSION.to_owned(),
+                })
+                .await;
+            return Err(AuthAPIError::Forbidden);
+        }
+
+        Ok(AdminAccess)
+    }
+}
+
+// Authenticates the caller of an /oauth endpoint with HTTP Basic (`client_secret_basic`) or,
+// failing that, the `client_id` and `client_secret` form fields (`client_secret_post`).
+// Returns the client id.
+pub async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&Secret<String>>,
+) -> std::result::Result<String, OAuthError> {
+    let credentials = basic_credentials(headers).or_else(|| {
+        client_id
+            .zip(client_secret)
+            .map(|(client_id, client_secret)| (client_id.to_owned(), client_secret.clone()))
+    });
+    let Some((client_id, client_secret)) = credentials else {
+        state
+            .audit(AuditEvent::OAuthClientRejected {
+                client_id: client_id.map(str::to_owned),
+            })
+            .await;
+        return Err(OAuthError::InvalidClient);
+    };
+
+    match state
+        .oauth_client_store
+        .read()
+        .await
+        .validate_client(&client_id, &client_secret)
+        .await
+    {
+        Ok(()) => Ok(client_id),
+        Err(OAuthClientStoreError::UnexpectedError(e)) => Err(OAuthError::UnexpectedError(e)),
+        Err(_) => {
+            state
+                .audit(AuditEvent::OAuthClientRejected {
+                    client_id: Some(client_id),
+                })
+                .await;
+            Err(OAuthError::InvalidClient)
+        }
+    }
+}
+
+// Like `authenticate_client`, but public clients, which have no secret, identify themselves
+// with the `client_id` form field alone
+pub async fn identify_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&Secret<String>>,
+) -> std::result::Result<OAuthClient, OAuthError> {
+    let authenticated = client_secret.is_some() || basic_credentials(headers).is_some();
+    let client_id = match client_id {
+        Some(client_id) if !authenticated => client_id.to_owned(),
+        _ => authenticate_client(state, headers, client_id, client_secret).await?,
+    };
+
+    let result = state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await;
+    match result {
+        Ok(client) if authenticated || !client.is_confidential() => return Ok(client),
+        Err(OAuthClientStoreError::UnexpectedError(e)) => {
+            return Err(OAuthError::UnexpectedError(e))
+        }
+        // Unknown, or confidential without its secret
+        _ => {}
+    }
+
+    state
+        .audit(AuditEvent::OAuthClientRejected {
+            client_id: Some(client_id),
+        })
+        .await;
+    Err(OAuthError::InvalidClient)
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, Secret<String>)> {
+    let encoded = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+
+    Some((client_id.to_owned(), Secret::new(client_secret.to_owned())))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+// Compares without short-circuiting so response times do not leak the matching prefix
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
 }
 
 #[tracing::instrument(name = "Create Auth Token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
+fn create_token(claims: &impl Serialize, settings: &AuthSettings) -> Result<String> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &EncodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
     )
     .wrap_err("failed to create token")
 }
@@ -107,14 +595,24 @@ mod tests {
     use std::sync::Arc;
     use tokio::sync::RwLock;
 
-    use crate::{domain::Email, services::HashsetBannedTokenStore};
+    use crate::{
+        domain::Email,
+        services::HashsetBannedTokenStore,
+        utils::{constants::test, AMR_PASSWORD},
+    };
 
     use super::*;
 
+    fn settings() -> AuthSettings {
+        AuthSettings {
+            jwt_secret: Secret::new(test::JWT_SECRET.to_owned()),
+        }
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse(secrecy::Secret::new("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let user_id = Uuid::new_v4();
+        let cookie = generate_auth_cookie(user_id, &[], &[AMR_PASSWORD], &settings()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -135,20 +633,47 @@ mod tests {
 
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse(secrecy::Secret::new("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let user_id = Uuid::new_v4();
+        let result = generate_auth_token(user_id, &[], &[AMR_PASSWORD], &settings()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
+    #[tokio::test]
+    async fn test_renewed_sessions_keep_the_login_time() {
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_id = Uuid::new_v4();
+        let mut session = Claims {
+            sub: user_id.to_string(),
+            iat: 1_700_000_100,
+            auth_time: 1_700_000_000,
+            amr: vec![AMR_PASSWORD.to_owned()],
+            ..Claims::default()
+        };
+
+        let token = renew_auth_token(user_id, &[], &session, &settings()).unwrap();
+        let renewed = validate_token(&settings(), banned_token_store, Secret::new(token))
+            .await
+            .unwrap();
+        assert_eq!(renewed.authenticated_at(), 1_700_000_000);
+        assert_eq!(renewed.amr, session.amr);
+        assert!(renewed.iat > session.iat);
+
+        // Sessions issued at login have no `auth_time`
+        session.auth_time = 0;
+        assert_eq!(session.authenticated_at(), 1_700_000_100);
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let email = Email::parse(secrecy::Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let result = validate_token(banned_token_store, Secret::new(token))
+        let user_id = Uuid::new_v4();
+        let roles = ["admin".to_owned()];
+        let token = generate_auth_token(user_id, &roles, &[AMR_PASSWORD], &settings()).unwrap();
+        let result = validate_token(&settings(), banned_token_store, Secret::new(token))
             .await
             .unwrap();
-        assert_eq!(result.sub, "test@example.com");
+        assert_eq!(result.sub, user_id.to_string());
+        assert_eq!(result.roles, roles);
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -158,11 +683,157 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_distinguishes_service_tokens() {
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_id = Uuid::new_v4();
+        let user_token = generate_auth_token(user_id, &[], &[AMR_PASSWORD], &settings()).unwrap();
+        let service_token = generate_service_token(
+            "billing".to_owned(),
+            Some("reports:read".to_owned()),
+            &settings(),
+        )
+        .unwrap();
+
+        let user = validate_token(
+            &settings(),
+            banned_token_store.clone(),
+            Secret::new(user_token),
+        )
+        .await
+        .unwrap();
+        assert!(!user.is_service());
+        let service = validate_token(&settings(), banned_token_store, Secret::new(service_token))
+            .await
+            .unwrap();
+        assert!(service.is_service());
+        assert_eq!(service.sub, "billing");
+        assert_eq!(service.client_id.as_deref(), Some("billing"));
+        assert_eq!(service.scope.as_deref(), Some("reports:read"));
+    }
+
+    #[tokio::test]
+    async fn test_email_change_tokens_are_not_sessions() {
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_id = Uuid::new_v4();
+        let new_email = Email::parse(Secret::new("new@example.com".to_owned())).unwrap();
+        let token = generate_email_change_token(user_id, &new_email, &settings()).unwrap();
+        let session = generate_auth_token(user_id, &[], &[AMR_PASSWORD], &settings()).unwrap();
+
+        let claims = validate_email_change_token(
+            &settings(),
+            banned_token_store.clone(),
+            &Secret::new(token.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.new_email, "new@example.com");
+        assert!(
+            validate_token(&settings(), banned_token_store.clone(), Secret::new(token))
+                .await
+                .is_err()
+        );
+        assert!(validate_email_change_token(
+            &settings(),
+            banned_token_store,
+            &Secret::new(session)
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_prefers_bearer_over_cookie() {
+        async fn extract(headers: &[(&str, &str)]) -> std::result::Result<String, AuthAPIError> {
+            let mut request = axum::http::Request::builder();
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            let (mut parts, _) = request.body(()).unwrap().into_parts();
+
+            AuthToken::from_request_parts(&mut parts, &())
+                .await
+                .map(|AuthToken(token)| token.expose_secret().to_owned())
+        }
+
+        let both = [
+            ("authorization", "Bearer from-header"),
+            ("cookie", "jwt=from-cookie"),
+        ];
+        assert_eq!(extract(&both).await.unwrap(), "from-header");
+        let cookie = [("cookie", "theme=dark; jwt=from-cookie")];
+        assert_eq!(extract(&cookie).await.unwrap(), "from-cookie");
+        assert!(matches!(
+            extract(&[("authorization", "Bearer ")]).await,
+            Err(AuthAPIError::MissingToken)
+        ));
+        assert!(matches!(
+            extract(&[]).await,
+            Err(AuthAPIError::MissingToken)
+        ));
+    }
+
+    #[test]
+    fn test_basic_credentials() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+
+        let (client_id, client_secret) =
+            basic_credentials(&headers("Basic Z2F0ZXdheTpzM2M6cmV0")).unwrap();
+        assert_eq!(client_id, "gateway");
+        assert_eq!(client_secret.expose_secret(), "s3c:ret");
+        assert!(basic_credentials(&headers("Basic not-base64")).is_none());
+        assert!(basic_credentials(&headers("Bearer Z2F0ZXdheTpzM2M6cmV0")).is_none());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
         let token = "invalid_token".to_owned();
-        let result = validate_token(banned_token_store, Secret::new(token)).await;
+        let result = validate_token(&settings(), banned_token_store, Secret::new(token)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_revoked_sessions() {
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_id = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let token = generate_auth_token(user_id, &[], &[AMR_PASSWORD], &settings()).unwrap();
+        let other_token = generate_auth_token(other, &[], &[AMR_PASSWORD], &settings()).unwrap();
+
+        revoke_sessions(banned_token_store.clone(), &user_id.to_string())
+            .await
+            .unwrap();
+
+        let result =
+            validate_token(&settings(), banned_token_store.clone(), Secret::new(token)).await;
+        assert!(result.is_err());
+        let result =
+            validate_token(&settings(), banned_token_store, Secret::new(other_token)).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_sessions_issued_after_a_revocation_are_accepted() {
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_id = Uuid::new_v4();
+        let token = generate_auth_token(user_id, &[], &[AMR_PASSWORD], &settings()).unwrap();
+
+        revoke_other_sessions(banned_token_store.clone(), &user_id.to_string())
+            .await
+            .unwrap();
+        let replacement = generate_auth_token(user_id, &[], &[AMR_PASSWORD], &settings()).unwrap();
+
+        let result =
+            validate_token(&settings(), banned_token_store.clone(), Secret::new(token)).await;
         assert!(result.is_err());
+        let result =
+            validate_token(&settings(), banned_token_store, Secret::new(replacement)).await;
+        assert!(result.is_ok());
     }
 }
@@ -1,112 +1,79 @@
-use dotenvy::dotenv;
-use lazy_static::lazy_static;
-use secrecy::Secret;
-use std::env as std_env;
+pub use auth_types::JWT_COOKIE_NAME;
 
-pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+// A role must grant this for its users to call the /admin endpoints
+pub const ADMIN_PERMISSION: &str = "admin";
 
 pub mod env {
+    pub const CONFIG_FILE_ENV_VAR: &str = "AUTH_SERVICE_CONFIG";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
-    pub const DROPLET_IP_ENV_VAR: &str = "DROPLET_IP";
+    // Comma-separated `client_id:client_secret` pairs
+    pub const OAUTH_CLIENTS_ENV_VAR: &str = "OAUTH_CLIENTS";
+    pub const OIDC_ISSUER_ENV_VAR: &str = "OIDC_ISSUER";
+    pub const OIDC_SIGNING_KEY_ENV_VAR: &str = "OIDC_SIGNING_KEY";
+    pub const APP_ADDRESS_ENV_VAR: &str = "APP_ADDRESS";
+    pub const SHUTDOWN_TIMEOUT_ENV_VAR: &str = "SHUTDOWN_TIMEOUT_MILLISECONDS";
+    pub const CORS_ALLOWED_ORIGINS_ENV_VAR: &str = "CORS_ALLOWED_ORIGINS";
+    pub const CORS_ALLOWED_METHODS_ENV_VAR: &str = "CORS_ALLOWED_METHODS";
+    pub const CORS_ALLOWED_HEADERS_ENV_VAR: &str = "CORS_ALLOWED_HEADERS";
+    pub const HEALTH_CHECK_EMAIL_CLIENT_ENV_VAR: &str = "HEALTH_CHECK_EMAIL_CLIENT";
+    pub const HEALTH_CHECK_TIMEOUT_ENV_VAR: &str = "HEALTH_CHECK_TIMEOUT_MILLISECONDS";
+    pub const LOG_FORMAT_ENV_VAR: &str = "LOG_FORMAT";
+    pub const AUDIT_LOG_FILE_ENV_VAR: &str = "AUDIT_LOG_FILE";
+    // Standard OpenTelemetry variables
+    pub const OTLP_ENDPOINT_ENV_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+    pub const OTEL_SERVICE_NAME_ENV_VAR: &str = "OTEL_SERVICE_NAME";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_URL";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
     pub const POSTMARK_EMAIL_ENV_VAR: &str = "POSTMARK_EMAIL_SENDER";
+    pub const USER_STORE_BACKEND_ENV_VAR: &str = "USER_STORE_BACKEND";
+    pub const OAUTH_CLIENT_STORE_BACKEND_ENV_VAR: &str = "OAUTH_CLIENT_STORE_BACKEND";
+    pub const API_KEY_STORE_BACKEND_ENV_VAR: &str = "API_KEY_STORE_BACKEND";
+    pub const ROLE_STORE_BACKEND_ENV_VAR: &str = "ROLE_STORE_BACKEND";
+    pub const BANNED_TOKEN_STORE_BACKEND_ENV_VAR: &str = "BANNED_TOKEN_STORE_BACKEND";
+    pub const TWO_FA_CODE_STORE_BACKEND_ENV_VAR: &str = "TWO_FA_CODE_STORE_BACKEND";
+    pub const OAUTH_GRANT_STORE_BACKEND_ENV_VAR: &str = "OAUTH_GRANT_STORE_BACKEND";
+    pub const AUDIT_SINK_BACKEND_ENV_VAR: &str = "AUDIT_SINK_BACKEND";
+    pub const EMAIL_CLIENT_BACKEND_ENV_VAR: &str = "EMAIL_CLIENT_BACKEND";
+    // Suffix for Docker-style secret files, e.g. JWT_SECRET_FILE=/run/secrets/jwt_secret
+    pub const FILE_SUFFIX: &str = "_FILE";
 }
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+    pub const OIDC_ISSUER: &str = "http://localhost:3000";
+    // How long in-flight requests may take to finish once shutdown starts
+    pub const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+    pub const CONFIG_FILE: &str = "config/settings.toml";
+    pub const SERVICE_NAME: &str = "auth-service";
+    // Upper bound for each dependency check of /health/ready
+    pub const HEALTH_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+    pub const AUDIT_LOG_FILE: &str = "audit/audit.jsonl";
     pub mod email_client {
-        use lazy_static::lazy_static;
-        use secrecy::Secret;
         use std::time::Duration;
 
-        use crate::utils::constants::set_postmark_email_sender;
-
         pub const BASE_URL: &str = "https://api.postmarkapp.com/email";
-        lazy_static! {
-            pub static ref SENDER: Secret<String> = set_postmark_email_sender();
-        }
         pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
     }
 }
 
 pub mod test {
     pub const APP_ADDRESS: &str = "127.0.0.1:0";
+    pub const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+    pub const JWT_SECRET: &str = "test-jwt-secret";
+    pub const OAUTH_CLIENT_ID: &str = "test-gateway";
+    pub const OAUTH_CLIENT_SECRET: &str = "test-gateway-secret-4kR9vX2mQ7bN5tW8";
+    pub const OAUTH_PUBLIC_CLIENT_ID: &str = "test-spa";
+    pub const OAUTH_REDIRECT_URI: &str = "http://127.0.0.1:3000/callback";
+    pub const OIDC_ISSUER: &str = "https://auth.test";
+    // The role whose sessions the API tests use for the /admin endpoints
+    pub const ADMIN_ROLE: &str = "administrator";
     pub mod email_client {
-        use lazy_static::lazy_static;
-        use secrecy::Secret;
         use std::time::Duration;
 
-        lazy_static! {
-            pub static ref SENDER: Secret<String> = Secret::new("test@email.com".to_string());
-        }
+        pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
 }
-
-lazy_static! {
-    pub static ref JWT_SECRET: Secret<String> = set_token();
-    pub static ref DROPLET_IP: String = set_remote_ip();
-    pub static ref DATABASE_URL: Secret<String> = set_database_url();
-    pub static ref REDIS_HOST_NAME: Secret<String> = set_redis_host();
-    pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
-}
-
-fn set_token() -> Secret<String> {
-    dotenv().ok();
-    let secret =
-        std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT secret must be set in .env file");
-
-    if secret.is_empty() {
-        panic!("JWT secret cannot be empty");
-    }
-
-    Secret::new(secret)
-}
-
-fn set_remote_ip() -> String {
-    dotenv().ok();
-    let remote_ip =
-        std_env::var(env::DROPLET_IP_ENV_VAR).expect("DROPLET_IP must be set in .env file");
-
-    if remote_ip.is_empty() {
-        panic!("DROPLET_IP cannot be empty");
-    }
-
-    remote_ip
-}
-
-fn set_database_url() -> Secret<String> {
-    dotenv().ok();
-    let database_url =
-        std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set in .env file");
-
-    if database_url.is_empty() {
-        panic!("DATABASE_URL cannot be empty");
-    }
-
-    Secret::new(database_url)
-}
-
-fn set_redis_host() -> Secret<String> {
-    dotenv().ok();
-    Secret::new(
-        std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned()),
-    )
-}
-
-fn set_postmark_auth_token() -> Secret<String> {
-    dotenv().ok();
-    let token = std_env::var(env::POSTMARK_AUTH_TOKEN_ENV_VAR)
-        .expect("POSTMARK_AUTH_TOKEN must be set in .env file");
-    Secret::new(token)
-}
-
-fn set_postmark_email_sender() -> Secret<String> {
-    dotenv().ok();
-    let sender = std_env::var(env::POSTMARK_EMAIL_ENV_VAR)
-        .expect("POSTMARK_EMAIL_SENDER must be set in .env file");
-    Secret::new(sender)
-}
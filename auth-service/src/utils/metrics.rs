@@ -0,0 +1,177 @@
+use std::{sync::LazyLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+// Process-wide metrics, exposed in the Prometheus text format at /metrics
+pub static METRICS: LazyLock<Metrics> =
+    LazyLock::new(|| Metrics::new().expect("failed to register metrics"));
+
+// Buckets for Argon2 timings, which sit in the tens to hundreds of milliseconds
+const HASH_DURATION_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests_total: IntCounterVec,
+    pub http_request_duration_seconds: HistogramVec,
+    pub signups_total: IntCounter,
+    pub logins_total: IntCounterVec,
+    pub two_fa_codes_total: IntCounterVec,
+    pub tokens_banned_total: IntCounter,
+    pub email_send_failures_total: IntCounter,
+    pub password_hash_duration_seconds: HistogramVec,
+}
+
+// Label values of `logins_total`
+pub mod login_outcome {
+    pub const SUCCESS: &str = "success";
+    pub const TWO_FA_REQUIRED: &str = "two_fa_required";
+    pub const INVALID_INPUT: &str = "invalid_input";
+    pub const INCORRECT_CREDENTIALS: &str = "incorrect_credentials";
+    // Correct credentials, but the account is locked or needs a new password
+    pub const REFUSED: &str = "refused";
+    pub const ERROR: &str = "error";
+}
+
+// Label values of `two_fa_codes_total`
+pub mod two_fa_event {
+    pub const ISSUED: &str = "issued";
+    pub const VERIFIED: &str = "verified";
+    pub const REJECTED: &str = "rejected";
+    // No code is stored any more for the email, usually because its TTL ran out
+    pub const EXPIRED: &str = "expired";
+}
+
+// Label values of `password_hash_duration_seconds`
+pub mod hash_operation {
+    pub const COMPUTE: &str = "compute";
+    pub const VERIFY: &str = "verify";
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("auth_service".to_owned()), None)?;
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency in seconds",
+            ),
+            &["method", "route", "status"],
+        )?;
+        let signups_total = IntCounter::new("signups_total", "Users signed up")?;
+        let logins_total = IntCounterVec::new(
+            Opts::new("logins_total", "Login attempts by outcome"),
+            &["outcome"],
+        )?;
+        let two_fa_codes_total = IntCounterVec::new(
+            Opts::new(
+                "two_fa_codes_total",
+                "2FA codes issued, verified, rejected or expired",
+            ),
+            &["event"],
+        )?;
+        let tokens_banned_total = IntCounter::new("tokens_banned_total", "JWTs banned on logout")?;
+        let email_send_failures_total =
+            IntCounter::new("email_send_failures_total", "Emails that failed to send")?;
+        let password_hash_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "password_hash_duration_seconds",
+                "Time spent computing or verifying password hashes",
+            )
+            .buckets(HASH_DURATION_BUCKETS.to_vec()),
+            &["operation"],
+        )?;
+
+        registry.register(Box::new(http_requests_total.clone()))?;
+        registry.register(Box::new(http_request_duration_seconds.clone()))?;
+        registry.register(Box::new(signups_total.clone()))?;
+        registry.register(Box::new(logins_total.clone()))?;
+        registry.register(Box::new(two_fa_codes_total.clone()))?;
+        registry.register(Box::new(tokens_banned_total.clone()))?;
+        registry.register(Box::new(email_send_failures_total.clone()))?;
+        registry.register(Box::new(password_hash_duration_seconds.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            signups_total,
+            logins_total,
+            two_fa_codes_total,
+            tokens_banned_total,
+            email_send_failures_total,
+            password_hash_duration_seconds,
+        })
+    }
+
+    pub fn record_login(&self, outcome: &str) {
+        self.logins_total.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_two_fa(&self, event: &str) {
+        self.two_fa_codes_total.with_label_values(&[event]).inc();
+    }
+
+    // Renders every metric in the Prometheus text exposition format
+    pub fn encode(&self) -> prometheus::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+// Records the count and latency of each request, labelled by route template rather than
+// the raw path so that ids in URLs cannot blow up the number of series
+pub async fn track_metrics(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_owned());
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    METRICS.http_requests_total.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration_seconds
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoded_metrics_use_the_service_prefix() {
+        METRICS.record_login(login_outcome::SUCCESS);
+        METRICS
+            .password_hash_duration_seconds
+            .with_label_values(&[hash_operation::VERIFY])
+            .observe(0.02);
+
+        let body = METRICS.encode().unwrap();
+
+        assert!(body.contains("auth_service_logins_total{outcome=\"success\"}"));
+        assert!(body.contains("auth_service_password_hash_duration_seconds_bucket"));
+        assert!(body.contains("# TYPE auth_service_signups_total counter"));
+    }
+}
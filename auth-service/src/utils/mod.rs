@@ -1,7 +1,21 @@
+pub mod api_key;
 pub mod auth;
 pub mod constants;
+pub mod metrics;
+pub mod oauth;
+pub mod oidc;
+pub mod problem;
+pub mod secret;
+pub mod shutdown;
 pub mod tracing;
 
+pub use api_key::*;
 pub use auth::*;
 pub use constants::*;
+pub use metrics::*;
+pub use oauth::*;
+pub use oidc::*;
+pub use problem::*;
+pub use secret::*;
+pub use shutdown::*;
 pub use tracing::*;
@@ -0,0 +1,111 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+use super::constant_time_eq;
+
+pub const AUTHORIZATION_CODE_TTL_SECONDS: u64 = 60;
+pub const REFRESH_TOKEN_TTL_SECONDS: u64 = 30 * 24 * 60 * 60; // 30 days
+
+// Configured client secrets are hashed like generated ones, so they must be as hard to guess
+pub const MIN_CLIENT_SECRET_LENGTH: usize = 32;
+
+// An unguessable value for authorization codes, refresh tokens and client credentials
+pub fn generate_opaque_token() -> Secret<String> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    Secret::new(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+// An unsalted SHA-256 keeps a leaked store from revealing high-entropy tokens: generated
+// client secrets and API keys are random 256-bit values, and configured client secrets are
+// refused at load below `MIN_CLIENT_SECRET_LENGTH`. It would not protect passwords.
+pub fn hash_opaque_token(token: &Secret<String>) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.expose_secret().as_bytes()))
+}
+
+// The RFC 7636 S256 challenge: BASE64URL(SHA256(code_verifier))
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+// Verifiers are 43 to 128 characters of [A-Za-z0-9-._~]
+pub fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    (43..=128).contains(&code_verifier.len())
+        && code_verifier
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b))
+        && constant_time_eq(&pkce_challenge(code_verifier), code_challenge)
+}
+
+// Redirect URIs must be absolute, without a fragment, and use https, http on a loopback
+// host (native apps, RFC 8252 section 7.3) or a reverse-domain private-use scheme such as
+// `com.example.app:/callback` (section 7.1)
+pub fn validate_redirect_uri(uri: &str) -> Result<(), &'static str> {
+    let url = Url::parse(uri).map_err(|_| "must be an absolute URI")?;
+    if url.fragment().is_some() {
+        return Err("must not have a fragment");
+    }
+
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" => match url.host_str() {
+            Some("localhost" | "127.0.0.1" | "[::1]") => Ok(()),
+            _ => Err("may only use http on a loopback host"),
+        },
+        scheme if scheme.contains('.') => Ok(()),
+        _ => Err("must use https, loopback http or a reverse-domain scheme"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pkce_matches_the_rfc_7636_example() {
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+        assert_eq!(pkce_challenge(verifier), challenge);
+        assert!(verify_pkce(verifier, challenge));
+        assert!(!verify_pkce(&verifier[1..], challenge));
+        assert!(!verify_pkce("short", &pkce_challenge("short")));
+    }
+
+    #[test]
+    fn test_redirect_uris() {
+        for valid in [
+            "https://app.example.com/callback",
+            "http://127.0.0.1:8080/callback",
+            "http://localhost/callback",
+            "com.example.app:/callback",
+        ] {
+            assert_eq!(validate_redirect_uri(valid), Ok(()), "{}", valid);
+        }
+        for invalid in [
+            "/callback",
+            "https://app.example.com/callback#token",
+            "http://app.example.com/callback",
+            "javascript:alert(1)",
+            "data:text/html,hi",
+        ] {
+            assert!(validate_redirect_uri(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_opaque_tokens_are_unique_and_url_safe() {
+        let (a, b) = (generate_opaque_token(), generate_opaque_token());
+
+        assert_ne!(a.expose_secret(), b.expose_secret());
+        assert_eq!(a.expose_secret().len(), 43);
+        assert!(a
+            .expose_secret()
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+    }
+}
@@ -0,0 +1,223 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{Context, Result};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey},
+    pkcs8::{DecodePrivateKey, EncodePrivateKey, LineEnding},
+    traits::PublicKeyParts,
+    RsaPrivateKey, RsaPublicKey,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+pub use auth_types::has_scope;
+
+// RFC 8176 authentication method references, recorded in session tokens at login
+pub const AMR_PASSWORD: &str = "pwd";
+pub const AMR_ONE_TIME_PASSWORD: &str = "otp";
+pub const AMR_MULTI_FACTOR: &str = "mfa";
+
+// Requests an ID token and access to /userinfo
+pub const OPENID_SCOPE: &str = "openid";
+// Adds the `email` claim to ID tokens and /userinfo
+pub const EMAIL_SCOPE: &str = "email";
+
+const SIGNING_KEY_BITS: usize = 2048;
+
+// OpenID Connect Core section 2
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    // The client id
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    // When the user logged in, which may be long before the token is issued
+    pub auth_time: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    // Empty for sessions that began before methods were recorded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+// The public half of the signing key, as published at /.well-known/jwks.json
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PublicJwk {
+    // Always `RSA`
+    pub kty: String,
+    // Always `sig`
+    #[serde(rename = "use")]
+    pub key_use: String,
+    // Always `RS256`
+    pub alg: String,
+    pub kid: String,
+    pub n: String,
+    pub e: String,
+}
+
+// Signs ID tokens with an RSA key. Relying parties verify them with the published JWK.
+#[derive(Clone)]
+pub struct OidcProvider {
+    issuer: String,
+    encoding_key: EncodingKey,
+    public_key: RsaPublicKey,
+    key_id: String,
+}
+
+impl OidcProvider {
+    // `signing_key` is a PKCS#8 or PKCS#1 PEM private key
+    pub fn from_pem(issuer: &str, signing_key: &Secret<String>) -> Result<Self> {
+        let pem = signing_key.expose_secret();
+        let private_key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .wrap_err("failed to parse the OIDC signing key")?;
+
+        Self::from_private_key(issuer, private_key)
+    }
+
+    // A key that only lives as long as the process, so ID tokens cannot be verified after
+    // a restart or by another instance
+    pub fn generate(issuer: &str) -> Result<Self> {
+        Self::from_private_key(issuer, new_private_key()?)
+    }
+
+    // A new PKCS#8 PEM private key for `from_pem`, e.g. to rotate the configured one
+    pub fn generate_signing_key() -> Result<Secret<String>> {
+        let pem = new_private_key()?
+            .to_pkcs8_pem(LineEnding::LF)
+            .wrap_err("failed to encode the OIDC signing key")?;
+
+        Ok(Secret::new(pem.to_string()))
+    }
+
+    fn from_private_key(issuer: &str, private_key: RsaPrivateKey) -> Result<Self> {
+        let der = private_key
+            .to_pkcs1_der()
+            .wrap_err("failed to encode the OIDC signing key")?;
+        let public_key = private_key.to_public_key();
+        let key_id = thumbprint(&public_key);
+
+        Ok(Self {
+            issuer: issuer.trim_end_matches('/').to_owned(),
+            encoding_key: EncodingKey::from_rsa_der(der.as_bytes()),
+            public_key,
+            key_id,
+        })
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    // The absolute URL of an endpoint of this service
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.issuer, path)
+    }
+
+    pub fn jwk(&self) -> PublicJwk {
+        PublicJwk {
+            kty: "RSA".to_owned(),
+            key_use: "sig".to_owned(),
+            alg: "RS256".to_owned(),
+            kid: self.key_id.clone(),
+            n: URL_SAFE_NO_PAD.encode(self.public_key.n().to_bytes_be()),
+            e: URL_SAFE_NO_PAD.encode(self.public_key.e().to_bytes_be()),
+        }
+    }
+
+    #[tracing::instrument(name = "Sign ID token", skip_all)]
+    pub fn sign_id_token(&self, claims: &IdTokenClaims) -> Result<String> {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.key_id.clone());
+
+        encode(&header, claims, &self.encoding_key).wrap_err("failed to sign ID token")
+    }
+}
+
+fn new_private_key() -> Result<RsaPrivateKey> {
+    RsaPrivateKey::new(&mut rand::thread_rng(), SIGNING_KEY_BITS)
+        .wrap_err("failed to generate an OIDC signing key")
+}
+
+// The RFC 7638 JWK thumbprint, so the key id changes whenever the key does
+fn thumbprint(public_key: &RsaPublicKey) -> String {
+    let canonical = format!(
+        r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#,
+        URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+        URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be())
+    );
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+
+    use super::*;
+
+    const SIGNING_KEY: &str = include_str!("../../tests/fixtures/oidc_signing_key.pem");
+
+    fn provider() -> OidcProvider {
+        OidcProvider::from_pem(
+            "https://auth.example.com/",
+            &Secret::new(SIGNING_KEY.to_owned()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_id_tokens_verify_with_the_published_jwk() {
+        let provider = provider();
+        let claims = IdTokenClaims {
+            iss: provider.issuer().to_owned(),
+            sub: "user@example.com".to_owned(),
+            aud: "client".to_owned(),
+            exp: 4_000_000_000,
+            iat: 1_700_000_000,
+            auth_time: 1_700_000_000,
+            nonce: Some("n-0S6_WzA2Mj".to_owned()),
+            amr: vec![AMR_PASSWORD.to_owned()],
+            email: None,
+        };
+
+        let token = provider.sign_id_token(&claims).unwrap();
+
+        let jwk = provider.jwk();
+        assert_eq!(decode_header(&token).unwrap().kid, Some(jwk.kid));
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&["client"]);
+        validation.set_issuer(&["https://auth.example.com"]);
+        let decoded = decode::<IdTokenClaims>(
+            &token,
+            &DecodingKey::from_rsa_components(&jwk.n, &jwk.e).unwrap(),
+            &validation,
+        )
+        .unwrap();
+        assert_eq!(decoded.claims, claims);
+    }
+
+    #[test]
+    fn test_key_id_is_a_stable_thumbprint() {
+        assert_eq!(provider().jwk().kid, provider().jwk().kid);
+        assert_eq!(provider().jwk().kid.len(), 43);
+        assert!(
+            OidcProvider::from_pem("https://auth.example.com", &Secret::new("nope".into()))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_generated_signing_keys_are_loadable_and_new() {
+        let signing_key = OidcProvider::generate_signing_key().unwrap();
+
+        let generated = OidcProvider::from_pem("https://auth.example.com", &signing_key).unwrap();
+        assert_ne!(generated.jwk().kid, provider().jwk().kid);
+    }
+}
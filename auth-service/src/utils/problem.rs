@@ -0,0 +1,127 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderMap, HeaderValue,
+    },
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::domain::FieldError;
+
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+// Prefix of the `type` member; the error code completes it
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:auth-service:error:";
+
+// An RFC 7807 problem document, carrying the same code and field errors as the legacy body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    // Path of the request that failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ProblemDetails {
+    pub fn new(code: &str, title: &str, status: u16, errors: Vec<FieldError>) -> Self {
+        Self {
+            problem_type: format!("{}{}", PROBLEM_TYPE_PREFIX, code),
+            title: title.to_owned(),
+            status,
+            instance: None,
+            code: code.to_owned(),
+            errors,
+        }
+    }
+}
+
+// Error responses are rendered in the legacy `{"error": ...}` shape and carry their problem
+// document as an extension; clients that accept `application/problem+json` get that instead
+pub async fn negotiate_error_format(request: Request, next: Next) -> Response {
+    let wants_problem = accepts_problem_json(request.headers());
+    let path = request.uri().path().to_owned();
+
+    let response = next.run(request).await;
+    if !wants_problem {
+        return response;
+    }
+
+    let Some(mut problem) = response.extensions().get::<ProblemDetails>().cloned() else {
+        return response;
+    };
+    problem.instance = Some(path);
+
+    let body = match serde_json::to_vec(&problem) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!(error = ?e, "failed to serialize problem details");
+            return response;
+        }
+    };
+
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(PROBLEM_JSON_CONTENT_TYPE),
+    );
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn accepts_problem_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| range.split(';').next())
+        .any(|media_type| {
+            media_type
+                .trim()
+                .eq_ignore_ascii_case(PROBLEM_JSON_CONTENT_TYPE)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(accept: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_str(accept).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_problem_json_must_be_named_in_accept() {
+        assert!(accepts_problem_json(&headers("application/problem+json")));
+        assert!(accepts_problem_json(&headers(
+            "application/json;q=0.5, Application/Problem+JSON;q=0.9"
+        )));
+        assert!(!accepts_problem_json(&headers("application/json")));
+        assert!(!accepts_problem_json(&headers("*/*")));
+        assert!(!accepts_problem_json(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_problem_type_is_derived_from_the_code() {
+        let problem = ProblemDetails::new("invalid_token", "Invalid token", 401, Vec::new());
+
+        let json = serde_json::to_value(&problem).unwrap();
+        assert_eq!(json["type"], "urn:auth-service:error:invalid_token");
+        assert!(json.get("errors").is_none());
+        assert!(json.get("instance").is_none());
+    }
+}
@@ -0,0 +1,14 @@
+use secrecy::Secret;
+use serde::Serializer;
+
+pub use auth_types::serialize_secret;
+
+pub fn serialize_optional_secret<S: Serializer>(
+    secret: &Option<Secret<String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(secret) => serialize_secret(secret, serializer),
+        None => serializer.serialize_none(),
+    }
+}
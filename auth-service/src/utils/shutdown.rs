@@ -0,0 +1,71 @@
+use tokio_util::sync::CancellationToken;
+
+// Stops a running `Application`. Cloning shares the handle, so any clone can trigger shutdown.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle {
+    token: CancellationToken,
+}
+
+impl ShutdownHandle {
+    // Stops accepting connections and starts draining in-flight requests
+    pub fn shutdown(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    // Resolves once shutdown has been triggered
+    pub async fn wait(&self) {
+        self.token.cancelled().await;
+    }
+}
+
+// Resolves on SIGINT (Ctrl+C) or, on Unix, SIGTERM as sent by `docker stop`
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT"),
+        _ = terminate => tracing::info!("received SIGTERM"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_resolves_for_every_clone_after_shutdown() {
+        let handle = ShutdownHandle::default();
+        let clone = handle.clone();
+        assert!(!clone.is_shutdown());
+
+        let waiter = tokio::spawn(async move { clone.wait().await });
+        handle.shutdown();
+
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("wait did not resolve")
+            .unwrap();
+        assert!(handle.is_shutdown());
+    }
+}
@@ -3,43 +3,71 @@ use std::time::Duration;
 use color_eyre::eyre::Result;
 
 use axum::{body::Body, extract::Request, response::Response};
+use opentelemetry::trace::TraceContextExt;
 use tracing::{Level, Span};
-use tracing_error::ErrorLayer;
-use tracing_subscriber::prelude::*;
-use tracing_subscriber::{fmt, EnvFilter};
-
-pub fn init_tracing() -> Result<()> {
-    // Create a formatting layer for tracing output with a compact format
-    let fmt_layer = fmt::layer().compact();
-
-    // Create a filter layer to control the verbosity of logs
-    // Try to get the filter configuration from the environment variables
-    // If it fails, default to the "info" log level
-    let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
-
-    // Build the tracing subscriber registry with the formatting layer,
-    // the filter layer, and the error layer for enhanced error reporting
-    tracing_subscriber::registry()
-        .with(filter_layer) // Add the filter layer to control log verbosity
-        .with(fmt_layer) // Add the formatting layer for compact log output
-        .with(ErrorLayer::default()) // Add the error layer to capture error contexts
-        .init(); // Initialize the tracing subscriber
-
-    Ok(())
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{fmt, Layer};
+
+pub use service_telemetry::{
+    extract_trace_context, inject_trace_context, tracer_provider, TelemetryGuard,
+};
+
+use crate::{
+    config::{LogFormat, Settings},
+    utils::constants::REQUEST_ID_HEADER,
+};
+
+pub fn init_tracing(settings: &Settings) -> Result<TelemetryGuard> {
+    // Create a formatting layer for tracing output in the configured format
+    let fmt_layer = match settings.logging.format {
+        LogFormat::Compact => fmt::layer().compact().boxed(),
+        LogFormat::Pretty => fmt::layer().pretty().boxed(),
+        // Span fields such as request_id and trace_id are included with every event
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+
+    let telemetry = service_telemetry::init_tracing(
+        fmt_layer,
+        &settings.telemetry.service_name,
+        settings.telemetry.otlp_endpoint.as_deref(),
+    )?;
+
+    Ok(telemetry)
 }
 
-// Creates a new tracing span with a unique request ID for each incoming request.
-// This helps in tracking and correlating logs for individual requests.
+// Creates a new tracing span tagged with the request's `X-Request-Id`, which is either the
+// caller's or one generated by the request id layer.
+// The span joins the caller's trace when the request carries a `traceparent` header.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
-    tracing::span!(
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span = tracing::span!(
         Level::INFO,
         "[REQUEST]",
         method = tracing::field::display(request.method()),
         uri = tracing::field::display(request.uri()),
         version = tracing::field::debug(request.version()),
         request_id = tracing::field::display(request_id),
-    )
+        trace_id = tracing::field::Empty,
+    );
+
+    // Fails only when no OpenTelemetry layer is installed, e.g. in tests
+    let _ = span.set_parent(extract_trace_context(request.headers()));
+
+    let span_context = span.context().span().span_context().clone();
+    if span_context.is_valid() {
+        span.record("trace_id", tracing::field::display(span_context.trace_id()));
+    }
+
+    span
 }
 
 // Logs an event indicating the start of a request.
@@ -73,3 +101,68 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderMap;
+    use opentelemetry::trace::{TraceId, TracerProvider};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::{prelude::*, Registry};
+
+    use super::*;
+    use crate::config::TelemetrySettings;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn test_tracer_provider() -> SdkTracerProvider {
+        tracer_provider(&TelemetrySettings::default().service_name, None).unwrap()
+    }
+
+    fn subscriber(tracer_provider: &SdkTracerProvider) -> impl tracing::Subscriber {
+        Registry::default()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("test")))
+    }
+
+    #[test]
+    fn test_incoming_traceparent_is_continued_by_outgoing_requests() {
+        let tracer_provider = test_tracer_provider();
+
+        tracing::subscriber::with_default(subscriber(&tracer_provider), || {
+            let request = Request::builder()
+                .uri("/verify-token")
+                .header("traceparent", TRACEPARENT)
+                .body(Body::empty())
+                .unwrap();
+
+            let span = make_span_with_request_id(&request);
+            assert_eq!(
+                span.context().span().span_context().trace_id(),
+                TraceId::from_hex(TRACE_ID).unwrap()
+            );
+
+            let mut headers = HeaderMap::new();
+            span.in_scope(|| inject_trace_context(&mut headers));
+
+            let traceparent = headers["traceparent"].to_str().unwrap();
+            assert!(traceparent.starts_with(&format!("00-{}-", TRACE_ID)));
+            // The outgoing call is a child of the request span, not of the caller's span
+            assert!(!traceparent.contains("00f067aa0ba902b7"));
+        });
+    }
+
+    #[test]
+    fn test_requests_without_traceparent_start_a_new_trace() {
+        let tracer_provider = test_tracer_provider();
+
+        tracing::subscriber::with_default(subscriber(&tracer_provider), || {
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+
+            let span = make_span_with_request_id(&request);
+            let trace_id = span.context().span().span_context().trace_id();
+
+            assert_ne!(trace_id, TraceId::INVALID);
+            assert_ne!(trace_id, TraceId::from_hex(TRACE_ID).unwrap());
+        });
+    }
+}
@@ -0,0 +1,284 @@
+use auth_service::{
+    routes::{CreateApiKeyResponse, ListApiKeysResponse, TokenResponse},
+    utils::TOKEN_TTL_SECONDS,
+    ErrorResponse,
+};
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth::logged_in_token,
+    oauth_authorize::{log_in, oauth_error, public_client_refresh_token, refresh},
+};
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+        "tokenDelivery": "body"
+    }))
+    .await
+}
+
+async fn verify(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+async fn error_code(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .code
+}
+
+async fn request_email_change(
+    app: &TestApp,
+    token: &str,
+    new_email: &str,
+    password: &str,
+) -> reqwest::Response {
+    app.post_account(
+        "/email",
+        &serde_json::json!({ "new_email": new_email, "password": password }),
+        Some(token),
+    )
+    .await
+}
+
+// The confirmation token is the last line of the email sent to the new address
+async fn confirmation_token(app: &TestApp, new_email: &str) -> String {
+    let emails = app.emails_sent_to(new_email).await;
+    assert_eq!(emails.len(), 1);
+
+    emails[0]
+        .lines()
+        .last()
+        .expect("Confirmation email is empty")
+        .to_owned()
+}
+
+fn change(current_password: &str, new_password: &str) -> serde_json::Value {
+    serde_json::json!({ "current_password": current_password, "new_password": new_password })
+}
+
+async fn confirm(app: &TestApp, token: &str) -> reqwest::Response {
+    app.post_account(
+        "/email/confirm",
+        &serde_json::json!({ "token": token }),
+        None,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn changing_the_password_ends_other_sessions() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    let other = login(&app, &email, "password123")
+        .await
+        .json::<TokenResponse>()
+        .await
+        .unwrap()
+        .token;
+
+    let response = app
+        .post_account(
+            "/password",
+            &serde_json::json!({
+                "current_password": "password123",
+                "new_password": "new-password456",
+                "tokenDelivery": "body"
+            }),
+            Some(&token),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let replacement = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    assert_eq!(verify(&app, &replacement).await, 200);
+    assert_eq!(verify(&app, &token).await, 401);
+    assert_eq!(verify(&app, &other).await, 401);
+    assert_eq!(login(&app, &email, "password123").await.status(), 401);
+    assert_eq!(login(&app, &email, "new-password456").await.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn changing_the_password_revokes_oauth_refresh_tokens() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    log_in(&app, &email).await;
+    let refresh_token = public_client_refresh_token(&app).await;
+    let token = login(&app, &email, "password123")
+        .await
+        .json::<TokenResponse>()
+        .await
+        .unwrap()
+        .token;
+
+    let response = app
+        .post_account(
+            "/password",
+            &change("password123", "new-password456"),
+            Some(&token),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let response = refresh(&app, &refresh_token).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn password_changes_need_the_current_password_and_a_strong_new_one() {
+    let app = TestApp::new().await;
+    let token = logged_in_token(&app, &get_random_email()).await;
+
+    let response = app
+        .post_account(
+            "/password",
+            &change("wrong-password", "new-password456"),
+            Some(&token),
+        )
+        .await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(error_code(response).await, "incorrect_credentials");
+
+    let response = app
+        .post_account("/password", &change("password123", "short"), Some(&token))
+        .await;
+    assert_eq!(response.status(), 400);
+    let body = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(body.code, "invalid_request");
+    assert_eq!(body.errors[0].field, "new_password");
+
+    let response = app
+        .post_account("/password", &change("password123", "new-password456"), None)
+        .await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "missing_token");
+
+    // Nothing changed, so the session is still valid
+    assert_eq!(verify(&app, &token).await, 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn email_changes_take_effect_once_confirmed() {
+    let app = TestApp::new().await;
+    app.expect_emails(2).await;
+    let email = get_random_email();
+    let new_email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    let key = app
+        .post_api_key(
+            &serde_json::json!({ "name": "CI", "scopes": ["deploy"] }),
+            &token,
+        )
+        .await
+        .json::<CreateApiKeyResponse>()
+        .await
+        .unwrap();
+
+    let response = request_email_change(&app, &token, &new_email, "password123").await;
+    assert_eq!(response.status(), 202);
+    // Nothing changes until the new address confirms
+    assert_eq!(verify(&app, &token).await, 200);
+    assert_eq!(login(&app, &email, "password123").await.status(), 200);
+
+    let confirmation = confirmation_token(&app, &new_email).await;
+    assert_eq!(confirm(&app, &confirmation).await.status(), 204);
+
+    // Tokens name the user by id, so sessions outlive the old address
+    assert_eq!(verify(&app, &token).await, 200);
+    assert_eq!(login(&app, &email, "password123").await.status(), 401);
+    let new_token = login(&app, &new_email, "password123")
+        .await
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+    let keys = app
+        .get_api_keys(&new_token)
+        .await
+        .json::<ListApiKeysResponse>()
+        .await
+        .unwrap();
+    assert_eq!(keys.keys.len(), 1);
+    assert_eq!(keys.keys[0].id, key.info.id);
+    let notifications = app.emails_sent_to(&email).await;
+    assert_eq!(notifications.len(), 1);
+    assert!(notifications[0].contains(&new_email));
+
+    // Each confirmation is used once
+    let response = confirm(&app, &confirmation).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(error_code(response).await, "invalid_token");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn used_email_change_tokens_stay_banned_for_their_lifetime() {
+    let app = TestApp::new().await;
+    app.expect_emails(4).await;
+    let email = get_random_email();
+    let first_email = get_random_email();
+    let second_email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+
+    request_email_change(&app, &token, &first_email, "password123").await;
+    let first_confirmation = confirmation_token(&app, &first_email).await;
+    assert_eq!(confirm(&app, &first_confirmation).await.status(), 204);
+    request_email_change(&app, &token, &second_email, "password123").await;
+    let second_confirmation = confirmation_token(&app, &second_email).await;
+    assert_eq!(confirm(&app, &second_confirmation).await.status(), 204);
+
+    // Outlasting a session token's ban must not make the first change replayable
+    app.age_banned_tokens(TOKEN_TTL_SECONDS + 1).await;
+    let response = confirm(&app, &first_confirmation).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(error_code(response).await, "invalid_token");
+    assert_eq!(
+        login(&app, &second_email, "password123").await.status(),
+        200
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn email_changes_are_refused_for_taken_or_invalid_addresses() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let taken = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    logged_in_token(&app, &taken).await;
+
+    let response = request_email_change(&app, &token, &taken, "password123").await;
+    assert_eq!(response.status(), 409);
+    assert_eq!(error_code(response).await, "user_already_exists");
+
+    let new_email = get_random_email();
+    let response = request_email_change(&app, &token, &new_email, "wrong-password").await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(error_code(response).await, "incorrect_credentials");
+
+    for new_email in ["not-an-email", email.as_str()] {
+        let response = request_email_change(&app, &token, new_email, "password123").await;
+        assert_eq!(response.status(), 400);
+        assert_eq!(error_code(response).await, "invalid_request");
+    }
+
+    // A session token is not a confirmation
+    let response = confirm(&app, &token).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
@@ -0,0 +1,369 @@
+use auth_service::{
+    domain::{Email, LoginAttemptId, TwoFACode},
+    routes::{AuditEventsResponse, CreateApiKeyResponse, ListUsersResponse, UserDetails},
+    utils::test,
+    ErrorResponse,
+};
+use reqwest::Method;
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth::logged_in_token,
+    oauth_authorize::{
+        consent, exchange_code, log_in, oauth_error, public_client_refresh_token, refresh,
+        CODE_VERIFIER,
+    },
+};
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "tokenDelivery": "body"
+    }))
+    .await
+}
+
+async fn update(app: &TestApp, email: &str, body: serde_json::Value) -> UserDetails {
+    let response = app.admin_user(Method::PATCH, email, "", Some(&body)).await;
+    assert_eq!(response.status(), 200);
+
+    response
+        .json::<UserDetails>()
+        .await
+        .expect("Could not deserialize response body to UserDetails")
+}
+
+async fn verify(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+async fn error_code(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .code
+}
+
+#[tokio::test]
+async fn users_are_searched_and_paged_by_email() {
+    let app = TestApp::new().await;
+    let tag = Uuid::new_v4().simple().to_string();
+    for name in ["carol", "alice", "bob"] {
+        logged_in_token(&app, &format!("{}-{}@example.com", name, tag)).await;
+    }
+    logged_in_token(&app, &get_random_email()).await;
+
+    let response = app
+        .get_admin_users(
+            &[
+                ("search", &tag.to_uppercase()),
+                ("offset", "1"),
+                ("limit", "1"),
+            ],
+            Some(&app.admin_token),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let page = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(page.total, 3);
+    assert_eq!((page.offset, page.limit), (1, 1));
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].email, format!("bob-{}@example.com", tag));
+    assert!(!page.users[0].locked);
+
+    let page = app
+        .get_admin_users(&[("search", &tag), ("offset", "3")], Some(&app.admin_token))
+        .await
+        .json::<ListUsersResponse>()
+        .await
+        .unwrap();
+    assert_eq!(page.total, 3);
+    assert!(page.users.is_empty());
+
+    let response = app.get_admin_users(&[], None).await;
+    assert_eq!(response.status(), 400);
+    let response = app.get_admin_users(&[], Some("wrong-token")).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn user_details_include_roles_and_api_keys() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    app.post_role(
+        &serde_json::json!({ "name": "support" }),
+        Some(&app.admin_token),
+    )
+    .await;
+    app.user_role(Method::PUT, &email, "support", Some(&app.admin_token))
+        .await;
+    let key = app
+        .post_api_key(
+            &serde_json::json!({ "name": "CI", "scopes": ["deploy"] }),
+            &token,
+        )
+        .await
+        .json::<CreateApiKeyResponse>()
+        .await
+        .unwrap();
+
+    let response = app.admin_user(Method::GET, &email, "", None).await;
+    assert_eq!(response.status(), 200);
+    let details = response
+        .json::<UserDetails>()
+        .await
+        .expect("Could not deserialize response body to UserDetails");
+    assert_eq!(details.user.email, email);
+    assert!(!details.user.requires_2fa);
+    assert_eq!(details.roles, vec!["support"]);
+    assert_eq!(details.api_keys.len(), 1);
+    assert_eq!(details.api_keys[0].prefix, key.info.prefix);
+
+    // Users are also named by id
+    let id = details.user.id.to_string();
+    let response = app.admin_user(Method::GET, &id, "", None).await;
+    assert_eq!(response.status(), 200);
+    let by_id = response.json::<UserDetails>().await.unwrap();
+    assert_eq!(by_id.user.email, email);
+    assert_eq!(by_id.roles, vec!["support"]);
+
+    let response = app
+        .admin_user(Method::GET, &get_random_email(), "", None)
+        .await;
+    assert_eq!(response.status(), 404);
+    let response = app
+        .admin_user(Method::GET, &uuid::Uuid::new_v4().to_string(), "", None)
+        .await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(error_code(response).await, "user_not_found");
+    let response = app.admin_user(Method::GET, "not-an-email", "", None).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "invalid_request");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn locked_accounts_cannot_log_in_or_use_their_tokens() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    let key = app
+        .post_api_key(
+            &serde_json::json!({ "name": "CI", "scopes": ["deploy"] }),
+            &token,
+        )
+        .await
+        .json::<CreateApiKeyResponse>()
+        .await
+        .unwrap()
+        .key;
+
+    let details = update(&app, &email, serde_json::json!({ "locked": true })).await;
+    assert!(details.user.locked);
+    assert_eq!(verify(&app, &token).await, 401);
+    assert_eq!(verify(&app, &key).await, 401);
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(error_code(response).await, "account_locked");
+
+    update(&app, &email, serde_json::json!({ "locked": false })).await;
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(verify(&app, &key).await, 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn accounts_locked_during_2fa_cannot_finish_logging_in() {
+    let app = TestApp::new().await;
+    app.expect_emails(1).await;
+    let email = get_random_email();
+    logged_in_token(&app, &email).await;
+
+    let details = update(&app, &email, serde_json::json!({ "requires_2fa": true })).await;
+    assert!(details.user.requires_2fa);
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 206);
+
+    update(&app, &email, serde_json::json!({ "locked": true })).await;
+    let parsed = Email::parse(Secret::new(email.clone())).unwrap();
+    let (login_attempt_id, code): (LoginAttemptId, TwoFACode) = app
+        .two_fa_code_store
+        .write()
+        .await
+        .get_code(&parsed)
+        .await
+        .unwrap();
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(error_code(response).await, "account_locked");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn forced_password_resets_end_sessions_and_refuse_logins() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+
+    let details = update(
+        &app,
+        &email,
+        serde_json::json!({ "password_reset_required": true }),
+    )
+    .await;
+    assert!(details.user.password_reset_required);
+    assert_eq!(verify(&app, &token).await, 401);
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(error_code(response).await, "password_reset_required");
+
+    // A wrong password is still just wrong
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "wrong-password1" }))
+        .await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoking_sessions_rejects_earlier_tokens_only() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let other_email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    let other_token = logged_in_token(&app, &other_email).await;
+
+    let response = app
+        .admin_user(Method::DELETE, &email, "/sessions", None)
+        .await;
+    assert_eq!(response.status(), 204);
+    assert_eq!(verify(&app, &token).await, 401);
+    assert_eq!(verify(&app, &other_token).await, 200);
+
+    let token = logged_in_token(&app, &email).await;
+    assert_eq!(verify(&app, &token).await, 200);
+
+    let response = app
+        .admin_user(Method::DELETE, &get_random_email(), "/sessions", None)
+        .await;
+    assert_eq!(response.status(), 404);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn locked_accounts_lose_their_oauth_grants() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    log_in(&app, &email).await;
+    let refresh_token = public_client_refresh_token(&app).await;
+    let query = consent(&app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+
+    update(&app, &email, serde_json::json!({ "locked": true })).await;
+    // Codes outlive the lock, but are not exchanged for a locked account
+    let response = exchange_code(&app, &query["code"], CODE_VERIFIER, None).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    let response = refresh(&app, &refresh_token).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+
+    // Refresh tokens were revoked rather than suspended
+    update(&app, &email, serde_json::json!({ "locked": false })).await;
+    let response = refresh(&app, &refresh_token).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn deleted_accounts_lose_their_tokens_roles_and_keys() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    app.post_role(
+        &serde_json::json!({ "name": "support" }),
+        Some(&app.admin_token),
+    )
+    .await;
+    app.user_role(Method::PUT, &email, "support", Some(&app.admin_token))
+        .await;
+    let key = app
+        .post_api_key(
+            &serde_json::json!({ "name": "CI", "scopes": ["deploy"] }),
+            &token,
+        )
+        .await
+        .json::<CreateApiKeyResponse>()
+        .await
+        .unwrap()
+        .key;
+
+    let response = app.admin_user(Method::DELETE, &email, "", None).await;
+    assert_eq!(response.status(), 204);
+    assert_eq!(verify(&app, &token).await, 401);
+    assert_eq!(verify(&app, &key).await, 401);
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 401);
+    let response = app.admin_user(Method::DELETE, &email, "", None).await;
+    assert_eq!(response.status(), 404);
+
+    // Signing up again starts afresh
+    logged_in_token(&app, &email).await;
+    let details = app
+        .admin_user(Method::GET, &email, "", None)
+        .await
+        .json::<UserDetails>()
+        .await
+        .unwrap();
+    assert!(details.roles.is_empty());
+    assert!(details.api_keys.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn admin_changes_are_audited() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    logged_in_token(&app, &email).await;
+    update(&app, &email, serde_json::json!({ "locked": true })).await;
+    app.admin_user(Method::DELETE, &email, "/sessions", None)
+        .await;
+    app.admin_user(Method::DELETE, &email, "", None).await;
+
+    let events = app
+        .get_audit_events(&[("user", &email)], Some(&app.admin_token))
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events;
+    let types: Vec<_> = events
+        .iter()
+        .map(|record| record.event.event_type())
+        .take(3)
+        .collect();
+    assert_eq!(
+        types,
+        vec!["user_deleted", "sessions_revoked", "user_updated"]
+    );
+    let updated = serde_json::to_value(&events[2].event).unwrap();
+    assert_eq!(updated["locked"], true);
+    assert_eq!(updated["requires_2fa"], serde_json::Value::Null);
+    app.clean_up().await;
+}
@@ -0,0 +1,233 @@
+use auth_service::{
+    routes::{
+        AuditEventsResponse, CreateApiKeyResponse, ListApiKeysResponse, OAuthTokenResponse,
+        RegisterClientResponse, VerifyTokenResponse,
+    },
+    utils::TokenUse,
+    ErrorResponse,
+};
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth::logged_in_token,
+};
+
+async fn create_key(app: &TestApp, token: &str, body: serde_json::Value) -> CreateApiKeyResponse {
+    let response = app.post_api_key(&body, token).await;
+    assert_eq!(response.status(), 201);
+
+    response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse")
+}
+
+async fn verify(app: &TestApp, key: &str) -> reqwest::Response {
+    app.post_verify_token(&serde_json::json!({ "token": key }))
+        .await
+}
+
+#[tokio::test]
+async fn api_keys_are_shown_once_and_verified_with_their_scopes() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+
+    let created = create_key(
+        &app,
+        &token,
+        serde_json::json!({ "name": " CI deploys ", "scopes": ["deploy", "reports:read"] }),
+    )
+    .await;
+    assert!(created
+        .key
+        .starts_with(&format!("{}_", created.info.prefix)));
+    assert!(created.info.prefix.starts_with("lbk_"));
+    assert_eq!(created.info.name, "CI deploys");
+    assert_eq!(created.info.expires_at, None);
+    assert_eq!(created.info.last_used_at, None);
+
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status(), 200);
+    let verified = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(verified.sub, app.user_id(&email).await);
+    assert_eq!(verified.email, Some(email.clone()));
+    assert_eq!(verified.token_use, TokenUse::ApiKey);
+    assert_eq!(verified.scope.as_deref(), Some("deploy reports:read"));
+    assert_eq!(verified.exp, None);
+
+    // The key works as a bearer token too, but only the prefix is ever listed
+    let response = app.post_with_bearer("/verify-token", &created.key).await;
+    assert_eq!(response.status(), 200);
+    let listed = app
+        .get_api_keys(&token)
+        .await
+        .json::<ListApiKeysResponse>()
+        .await
+        .expect("Could not deserialize response body to ListApiKeysResponse");
+    assert_eq!(listed.keys.len(), 1);
+    assert_eq!(listed.keys[0].id, created.info.id);
+    assert!(listed.keys[0].last_used_at.is_some());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoked_expired_and_tampered_keys_are_rejected() {
+    let app = TestApp::new().await;
+    let token = logged_in_token(&app, &get_random_email()).await;
+    let created = create_key(
+        &app,
+        &token,
+        serde_json::json!({
+            "name": "Nightly",
+            "scopes": ["deploy"],
+            "expires_at": Utc::now() + Duration::days(30)
+        }),
+    )
+    .await;
+    let response = verify(&app, &created.key).await;
+    let verified = response.json::<VerifyTokenResponse>().await.unwrap();
+    assert_eq!(
+        verified.exp,
+        created.info.expires_at.map(|at| at.timestamp() as usize)
+    );
+
+    let tampered = format!("{}x", created.key);
+    assert_eq!(verify(&app, &tampered).await.status(), 401);
+    assert_eq!(verify(&app, "lbk_00000000_unknown").await.status(), 401);
+
+    let id = created.info.id.to_string();
+    assert_eq!(app.delete_api_key(&id, &token).await.status(), 204);
+    assert_eq!(verify(&app, &created.key).await.status(), 401);
+    let response = app.delete_api_key(&id, &token).await;
+    assert_eq!(response.status(), 404);
+    let body = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(body.code, "api_key_not_found");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn users_only_see_and_revoke_their_own_keys() {
+    let app = TestApp::new().await;
+    let owner = logged_in_token(&app, &get_random_email()).await;
+    let other = logged_in_token(&app, &get_random_email()).await;
+    let created = create_key(
+        &app,
+        &owner,
+        serde_json::json!({ "name": "CI", "scopes": ["deploy"] }),
+    )
+    .await;
+
+    let listed = app
+        .get_api_keys(&other)
+        .await
+        .json::<ListApiKeysResponse>()
+        .await
+        .unwrap();
+    assert!(listed.keys.is_empty());
+    let response = app
+        .delete_api_key(&created.info.id.to_string(), &other)
+        .await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(verify(&app, &created.key).await.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn api_keys_need_a_name_scopes_and_a_future_expiry() {
+    let app = TestApp::new().await;
+    let token = logged_in_token(&app, &get_random_email()).await;
+
+    let cases = [
+        serde_json::json!({ "name": " ", "scopes": ["deploy"] }),
+        serde_json::json!({ "name": "CI", "scopes": [] }),
+        serde_json::json!({ "name": "CI", "scopes": ["deploy all"] }),
+        serde_json::json!({
+            "name": "CI",
+            "scopes": ["deploy"],
+            "expires_at": Utc::now() - Duration::minutes(1)
+        }),
+    ];
+    for body in cases {
+        let response = app.post_api_key(&body, &token).await;
+
+        assert_eq!(response.status(), 400, "Failed for {body}");
+        let error = response.json::<ErrorResponse>().await.unwrap();
+        assert_eq!(error.code, "invalid_request", "Failed for {body}");
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn only_login_sessions_manage_api_keys() {
+    let app = TestApp::new().await;
+    let token = logged_in_token(&app, &get_random_email()).await;
+    let created = create_key(
+        &app,
+        &token,
+        serde_json::json!({ "name": "CI", "scopes": ["deploy"] }),
+    )
+    .await;
+    let body = serde_json::json!({ "name": "Escalated", "scopes": ["deploy"] });
+
+    let response = app.post_api_key(&body, &created.key).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(app.get_api_keys(&created.key).await.status(), 401);
+
+    // Nor can a machine client's own token
+    let client = app
+        .post_oauth_client(
+            &serde_json::json!({ "client_name": "Job", "redirect_uris": [], "scopes": ["deploy"] }),
+            Some(&app.admin_token),
+        )
+        .await
+        .json::<RegisterClientResponse>()
+        .await
+        .unwrap();
+    let tokens = app
+        .post_oauth(
+            "/oauth/token",
+            &[("grant_type", "client_credentials")],
+            Some((&client.client_id, client.client_secret.as_deref().unwrap())),
+        )
+        .await
+        .json::<OAuthTokenResponse>()
+        .await
+        .unwrap();
+    let response = app
+        .post_api_key(&body, tokens.access_token.expose_secret())
+        .await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn api_key_changes_are_audited() {
+    let app = TestApp::new().await;
+    let token = logged_in_token(&app, &get_random_email()).await;
+    let created = create_key(
+        &app,
+        &token,
+        serde_json::json!({ "name": "CI", "scopes": ["deploy"] }),
+    )
+    .await;
+    app.delete_api_key(&created.info.id.to_string(), &token)
+        .await;
+
+    let response = app.get_audit_events(&[], Some(&app.admin_token)).await;
+    let types: Vec<_> = response
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events
+        .into_iter()
+        .map(|record| record.event.event_type().to_owned())
+        .collect();
+    assert_eq!(types[..2], ["api_key_revoked", "api_key_created"]);
+    app.clean_up().await;
+}
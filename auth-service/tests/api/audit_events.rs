@@ -0,0 +1,129 @@
+use auth_service::routes::AuditEventsResponse;
+use chrono::{Duration, Utc};
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth::logged_in_token,
+};
+
+async fn audit_event_types(app: &TestApp, query: &[(&str, &str)]) -> Vec<String> {
+    let response = app.get_audit_events(query, Some(&app.admin_token)).await;
+    assert_eq!(response.status(), 200);
+
+    response
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events
+        .into_iter()
+        .map(|record| record.event.event_type().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn auth_flow_is_recorded_newest_first() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "Asdf1234@",
+        "requires2FA": false
+    }))
+    .await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "wrong-password1",
+        }))
+        .await;
+    assert_eq!(response.status(), 401);
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "Asdf1234@",
+        }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = app.post_logout().await;
+    assert_eq!(response.status(), 200);
+
+    let types = audit_event_types(&app, &[("user", &email)]).await;
+
+    assert_eq!(
+        types,
+        vec![
+            "logged_out",
+            "token_banned",
+            "login_succeeded",
+            "login_failed",
+            "user_signed_up"
+        ]
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn audit_events_are_filtered_by_type_and_time_range() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let before = Utc::now();
+
+    for _ in 0..2 {
+        app.post_login(&serde_json::json!({
+            "email": email,
+            "password": "Asdf1234@",
+        }))
+        .await;
+    }
+    app.post_verify_token(&serde_json::json!({ "token": "invalid" }))
+        .await;
+
+    let from = before.to_rfc3339();
+    let types = audit_event_types(&app, &[("type", "login_failed"), ("from", &from)]).await;
+    assert_eq!(types, vec!["login_failed", "login_failed"]);
+
+    let types = audit_event_types(
+        &app,
+        &[("type", "login_failed"), ("user", &email), ("limit", "1")],
+    )
+    .await;
+    assert_eq!(types, vec!["login_failed"]);
+
+    let to = (before - Duration::seconds(1)).to_rfc3339();
+    let types = audit_event_types(&app, &[("type", "token_rejected"), ("to", &to)]).await;
+    assert!(types.is_empty());
+    let types = audit_event_types(&app, &[("type", "token_rejected"), ("from", &from)]).await;
+    assert_eq!(types, vec!["token_rejected"]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn audit_events_require_an_admin_session() {
+    let app = TestApp::new().await;
+
+    let response = app.get_audit_events(&[], None).await;
+    assert_eq!(response.status(), 400);
+
+    let response = app.get_audit_events(&[], Some("not-a-token")).await;
+    assert_eq!(response.status(), 401);
+
+    let token = logged_in_token(&app, &get_random_email()).await;
+    let response = app.get_audit_events(&[], Some(&token)).await;
+    assert_eq!(response.status(), 403);
+    let types = audit_event_types(&app, &[("type", "permission_denied")]).await;
+    assert_eq!(types, vec!["permission_denied"]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn audit_events_reject_malformed_timestamps() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .get_audit_events(&[("from", "yesterday")], Some(&app.admin_token))
+        .await;
+
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
@@ -0,0 +1,166 @@
+use auth_service::{
+    routes::{IntrospectResponse, OAuthTokenResponse, RegisterClientResponse},
+    utils::{test, TokenUse},
+};
+use secrecy::ExposeSecret;
+
+use crate::{helpers::TestApp, oauth_authorize::oauth_error};
+
+// Registers a confidential client for a backend job, returning its id and secret
+async fn register_service(app: &TestApp, scopes: &[&str]) -> (String, String) {
+    let response = app
+        .post_oauth_client(
+            &serde_json::json!({
+                "client_name": "Nightly Reports",
+                "redirect_uris": [],
+                "scopes": scopes
+            }),
+            Some(&app.admin_token),
+        )
+        .await;
+    assert_eq!(response.status(), 201);
+    let client = response
+        .json::<RegisterClientResponse>()
+        .await
+        .expect("Could not deserialize response body to RegisterClientResponse");
+    assert_eq!(client.scopes, scopes);
+
+    (client.client_id, client.client_secret.unwrap())
+}
+
+async fn request_token(
+    app: &TestApp,
+    scope: Option<&str>,
+    credentials: Option<(&str, &str)>,
+) -> reqwest::Response {
+    let mut form = vec![("grant_type", "client_credentials")];
+    form.extend(scope.map(|scope| ("scope", scope)));
+
+    app.post_oauth("/oauth/token", &form, credentials).await
+}
+
+#[tokio::test]
+async fn client_credentials_issue_a_service_token() {
+    let app = TestApp::new().await;
+    let (client_id, client_secret) =
+        register_service(&app, &["reports:read", "reports:write"]).await;
+
+    let response = request_token(&app, None, Some((&client_id, &client_secret))).await;
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let tokens = response
+        .json::<OAuthTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthTokenResponse");
+    assert_eq!(tokens.scope.as_deref(), Some("reports:read reports:write"));
+    assert!(tokens.refresh_token.is_none());
+    assert!(tokens.id_token.is_none());
+
+    let access_token = tokens.access_token.expose_secret();
+    let response = app.post_with_bearer("/verify-token", access_token).await;
+    assert_eq!(response.status(), 200);
+
+    let response = app
+        .post_oauth(
+            "/oauth/introspect",
+            &[("token", access_token)],
+            Some((test::OAUTH_CLIENT_ID, test::OAUTH_CLIENT_SECRET)),
+        )
+        .await;
+    let body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+    assert!(body.active);
+    assert_eq!(body.sub.as_deref(), Some(client_id.as_str()));
+    assert_eq!(body.client_id.as_deref(), Some(client_id.as_str()));
+    assert_eq!(body.token_use, Some(TokenUse::Service));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn client_credentials_are_limited_to_the_client_scopes() {
+    let app = TestApp::new().await;
+    let (client_id, client_secret) =
+        register_service(&app, &["reports:read", "reports:write"]).await;
+    let credentials = Some((client_id.as_str(), client_secret.as_str()));
+
+    let response = request_token(&app, Some("reports:read"), credentials).await;
+    assert_eq!(response.status(), 200);
+    let tokens = response.json::<OAuthTokenResponse>().await.unwrap();
+    assert_eq!(tokens.scope.as_deref(), Some("reports:read"));
+
+    let response = request_token(&app, Some("reports:read users:admin"), credentials).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_scope");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn client_credentials_require_a_confidential_client_with_scopes() {
+    let app = TestApp::new().await;
+    let (client_id, _) = register_service(&app, &["reports:read"]).await;
+
+    let response = request_token(&app, None, Some((&client_id, "wrong-secret"))).await;
+    assert_eq!(oauth_error(response, 401).await, "invalid_client");
+
+    // The seeded gateway only signs users in
+    let response = request_token(
+        &app,
+        None,
+        Some((test::OAUTH_CLIENT_ID, test::OAUTH_CLIENT_SECRET)),
+    )
+    .await;
+    assert_eq!(oauth_error(response, 400).await, "unauthorized_client");
+
+    let response = app
+        .post_oauth(
+            "/oauth/token",
+            &[
+                ("grant_type", "client_credentials"),
+                ("client_id", test::OAUTH_PUBLIC_CLIENT_ID),
+            ],
+            None,
+        )
+        .await;
+    assert_eq!(oauth_error(response, 400).await, "unauthorized_client");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn service_tokens_are_not_user_sessions() {
+    let app = TestApp::new().await;
+    let (client_id, client_secret) = register_service(&app, &["openid", "email"]).await;
+
+    let response = request_token(&app, None, Some((&client_id, &client_secret))).await;
+    let tokens = response.json::<OAuthTokenResponse>().await.unwrap();
+
+    // Even with the openid scope there is no user to describe
+    let response = app
+        .post_with_bearer("/userinfo", tokens.access_token.expose_secret())
+        .await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn client_registration_validates_scopes() {
+    let app = TestApp::new().await;
+
+    let cases = [
+        serde_json::json!({
+            "client_name": "App",
+            "redirect_uris": [test::OAUTH_REDIRECT_URI],
+            "public": true,
+            "scopes": ["reports:read"]
+        }),
+        serde_json::json!({ "client_name": "Job", "redirect_uris": [], "scopes": ["a b"] }),
+        serde_json::json!({ "client_name": "Job", "redirect_uris": [], "scopes": [""] }),
+    ];
+    for body in cases {
+        let response = app.post_oauth_client(&body, Some(&app.admin_token)).await;
+
+        assert_eq!(response.status(), 400, "Failed for {body}");
+    }
+    app.clean_up().await;
+}
@@ -0,0 +1,84 @@
+use crate::helpers::{test_settings, TestApp};
+
+const ALLOW_ORIGIN: &str = "access-control-allow-origin";
+const ALLOW_CREDENTIALS: &str = "access-control-allow-credentials";
+const ALLOW_METHODS: &str = "access-control-allow-methods";
+
+async fn app_with_origins(origins: &[&str]) -> TestApp {
+    let mut settings = test_settings();
+    settings.application.cors.allowed_origins =
+        origins.iter().map(|origin| origin.to_string()).collect();
+
+    TestApp::with_settings(settings).await
+}
+
+#[tokio::test]
+async fn preflight_from_configured_origin_succeeds() {
+    let app = app_with_origins(&["http://localhost:8000"]).await;
+
+    let response = app
+        .preflight("/login", "http://localhost:8000", "POST")
+        .await;
+
+    assert_eq!(response.status(), 200);
+    let headers = response.headers();
+    assert_eq!(headers[ALLOW_ORIGIN], "http://localhost:8000");
+    assert_eq!(headers[ALLOW_CREDENTIALS], "true");
+    assert!(headers[ALLOW_METHODS].to_str().unwrap().contains("POST"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn preflight_from_wildcard_subdomain_succeeds() {
+    let app = app_with_origins(&["https://*.example.com"]).await;
+
+    let response = app
+        .preflight("/signup", "https://app.example.com", "POST")
+        .await;
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()[ALLOW_ORIGIN], "https://app.example.com");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn preflight_from_other_origins_is_rejected() {
+    let app = app_with_origins(&["http://localhost:8000", "https://*.example.com"]).await;
+
+    for origin in [
+        "http://localhost:9000",
+        "https://example.com",
+        "https://app.example.com.evil.io",
+        "http://evil.io",
+    ] {
+        let response = app.preflight("/login", origin, "POST").await;
+
+        assert!(
+            response.headers().get(ALLOW_ORIGIN).is_none(),
+            "Origin {} should not be allowed",
+            origin
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn simple_request_from_configured_origin_gets_cors_headers() {
+    let app = app_with_origins(&["http://localhost:8000"]).await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/", &app.address))
+        .header("Origin", "http://localhost:8000")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()[ALLOW_ORIGIN], "http://localhost:8000");
+    assert_eq!(
+        response.headers()["access-control-expose-headers"],
+        "x-request-id"
+    );
+    app.clean_up().await;
+}
@@ -0,0 +1,124 @@
+use auth_service::{
+    domain::{field_error, FieldError},
+    utils::{ProblemDetails, PROBLEM_JSON_CONTENT_TYPE},
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn errors_use_the_legacy_shape_with_a_code_by_default() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": "invalid" }))
+        .await;
+
+    assert_eq!(response.status(), 401);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("application/json"));
+    let body = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(body.error, "Invalid token");
+    assert_eq!(body.code, "invalid_token");
+    assert!(body.errors.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn signup_names_each_invalid_field() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": "not-an-email",
+            "password": "short",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status(), 400);
+    let body = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(body.error, "Invalid credentials");
+    assert_eq!(body.code, "invalid_credentials");
+    let fields: Vec<(&str, &str)> = body
+        .errors
+        .iter()
+        .map(|error| (error.field.as_str(), error.code.as_str()))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("email", field_error::INVALID_FORMAT),
+            ("password", field_error::TOO_WEAK)
+        ]
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn errors_are_problem_documents_when_accepted() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_with_accept(
+            "/verify-2fa",
+            &serde_json::json!({
+                "email": get_random_email(),
+                "loginAttemptId": "not-a-uuid",
+                "2FACode": "123456"
+            }),
+            PROBLEM_JSON_CONTENT_TYPE,
+        )
+        .await;
+
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.headers()["content-type"],
+        PROBLEM_JSON_CONTENT_TYPE
+    );
+    assert!(response.headers().contains_key("x-request-id"));
+    let problem = response.json::<ProblemDetails>().await.unwrap();
+    assert_eq!(
+        problem.problem_type,
+        "urn:auth-service:error:invalid_credentials"
+    );
+    assert_eq!(problem.title, "Invalid credentials");
+    assert_eq!(problem.status, 400);
+    assert_eq!(problem.instance.as_deref(), Some("/verify-2fa"));
+    assert_eq!(problem.code, "invalid_credentials");
+    assert_eq!(
+        problem.errors,
+        vec![FieldError::new(
+            "loginAttemptId",
+            field_error::INVALID_FORMAT,
+            "Login Attempt ID doesn't match with UUID format"
+        )]
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn successful_responses_ignore_the_problem_preference() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_with_accept(
+            "/signup",
+            &serde_json::json!({
+                "email": get_random_email(),
+                "password": "Asdf1234@",
+                "requires2FA": false
+            }),
+            PROBLEM_JSON_CONTENT_TYPE,
+        )
+        .await;
+
+    assert_eq!(response.status(), 201);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("application/json"));
+    app.clean_up().await;
+}
@@ -0,0 +1,70 @@
+use auth_service::routes::{HealthResponse, HealthStatus};
+
+use crate::helpers::{test_settings, TestApp, TestBackend};
+
+#[tokio::test]
+async fn health_live_returns_200() {
+    let app = TestApp::new().await;
+
+    let response = app.get_health_live().await;
+
+    assert_eq!(response.status(), 200);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.status, HealthStatus::Up);
+    assert!(body.checks.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn health_ready_reports_each_dependency() {
+    let app = TestApp::new().await;
+
+    let response = app.get_health_ready().await;
+
+    assert_eq!(response.status(), 200);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.status, HealthStatus::Up);
+
+    let mut expected = vec![
+        "banned_token_store",
+        "two_fa_code_store",
+        "oauth_grant_store",
+    ];
+    if TestBackend::from_env() == TestBackend::Persistent {
+        expected.push("postgres");
+    }
+    for name in expected {
+        let check = body
+            .checks
+            .get(name)
+            .unwrap_or_else(|| panic!("Missing {} check", name));
+        assert_eq!(check.status, HealthStatus::Up, "{} is down", name);
+        assert!(check.latency_ms >= 0.0);
+    }
+    // The email provider is only checked when enabled
+    assert!(!body.checks.contains_key("email_client"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn health_ready_checks_the_email_client_when_enabled() {
+    let mut settings = test_settings();
+    settings.health.check_email_client = true;
+    let app = TestApp::with_settings(settings).await;
+
+    let response = app.get_health_ready().await;
+
+    assert_eq!(response.status(), 200);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.checks["email_client"].status, HealthStatus::Up);
+    app.clean_up().await;
+}
@@ -1,92 +1,127 @@
 use auth_service::{
     app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType},
-    domain::Email,
-    get_postgres_pool, get_redis_client,
+    config::{
+        ApplicationSettings, AuthSettings, OAuthClientSettings, OAuthSettings, OidcSettings,
+        Settings,
+    },
+    domain::{Email, Role},
+    get_postgres_pool,
+    routes::UserDetails,
     services::{
-        PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore, RedisTwoFACodeStore,
+        HashmapOAuthClientStore, HashmapOAuthGrantStore, HashmapTwoFACodeStore, HashmapUserStore,
+        HashsetBannedTokenStore, MockEmailClient, PostgresApiKeyStore, PostgresAuditSink,
+        PostgresOAuthClientStore, PostgresRoleStore, PostgresUserStore, PostmarkEmailClient,
+        RedisBannedTokenStore, RedisOAuthGrantStore, RedisTwoFACodeStore,
+    },
+    utils::{
+        generate_auth_token, test, OidcProvider, ShutdownHandle, ADMIN_PERMISSION, AMR_PASSWORD,
     },
-    utils::{test, DATABASE_URL, REDIS_HOST_NAME},
     Application,
 };
 use std::{
     str::FromStr,
     sync::{
-        atomic::{AtomicU8, Ordering},
+        atomic::{AtomicU64, AtomicU8, Ordering},
         Arc,
     },
 };
 
+use redis::Commands;
 use reqwest::{cookie::Jar, Client};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     Connection, Executor, PgConnection, PgPool,
 };
-use tokio::sync::RwLock;
+use tokio::{
+    sync::{Mutex, RwLock},
+    task::JoinHandle,
+};
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+// Environment variable selecting the stores the API tests run against.
+// `persistent` (default) needs a live Postgres and Redis, `memory` runs fully in-process.
+pub const TEST_BACKEND_ENV_VAR: &str = "TEST_BACKEND";
 
 // Global counter for Redis database selection (0-15 are available)
 static REDIS_DB_COUNTER: AtomicU8 = AtomicU8::new(0);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestBackend {
+    Persistent,
+    InMemory,
+}
+
+impl TestBackend {
+    pub fn from_env() -> Self {
+        match std::env::var(TEST_BACKEND_ENV_VAR).as_deref() {
+            Ok("memory") | Ok("in-memory") => TestBackend::InMemory,
+            Ok("persistent") | Ok("") | Err(_) => TestBackend::Persistent,
+            Ok(other) => panic!(
+                "Unknown {} value '{}', expected 'persistent' or 'memory'",
+                TEST_BACKEND_ENV_VAR, other
+            ),
+        }
+    }
+}
+
+// Resources owned by the selected backend that need to be inspected or cleaned up
+enum BackendResources {
+    Persistent {
+        // Connection settings for the live Postgres and Redis, read from the environment
+        settings: Box<Settings>,
+        database_name: String,
+        redis_db: u8,
+        email_server: MockServer,
+    },
+    InMemory {
+        banned_token_store: Arc<RwLock<HashsetBannedTokenStore>>,
+        email_client: MockEmailClient,
+        expected_emails: AtomicU64,
+    },
+}
+
 pub struct TestApp {
-    pub database_name: String,
     pub address: String,
     pub http_client: reqwest::Client,
-    pub email_server: MockServer,
     pub cookie_jar: Arc<Jar>,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub redis_db: u8,
+    // A session granted the `admin` permission, for the /admin endpoints
+    pub admin_token: String,
+    pub shutdown_handle: ShutdownHandle,
+    server: Mutex<Option<JoinHandle<Result<(), std::io::Error>>>>,
+    backend: BackendResources,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
-        // We are creating a new database for each test case, and we need to ensure each database has a unique name!
-        let database_name = Uuid::new_v4().to_string();
-
-        // Get a unique Redis database number (0-15) for this test
-        let redis_db = REDIS_DB_COUNTER.fetch_add(1, Ordering::SeqCst) % 16;
-
-        // In memory storage
-        // let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
-        // In DB storage
-        let pg_pool = configure_postgresql(database_name.clone().to_string()).await;
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        // In memory storage
-        // let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        // In REDIS storage
-        let redis_conn = configure_redis(redis_db);
-        let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn)));
-        // In memory storage
-        // let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
-        // In REDIS storage
-        let redis_conn = configure_redis(redis_db);
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
-        // In memory email client
-        // let email_client_type = Arc::new(RwLock::new(MockEmailClient::default()));
-        // Mock email server
-        let email_server = MockServer::start().await;
-        let base_url = email_server.uri();
-        let email_client_type = Arc::new(RwLock::new(configure_postmark_email_client(
-            base_url.to_string(),
-        )));
-
-        let app_state = AppState::new(
-            user_store,
-            banned_token_store.clone(),
-            two_fa_code_store.clone(),
-            email_client_type.clone(),
-        );
+        Self::with_settings(test_settings()).await
+    }
 
-        let app = Application::build(app_state, test::APP_ADDRESS)
+    // Starts the app with customised settings, e.g. a different CORS policy
+    pub async fn with_settings(settings: Settings) -> Self {
+        let (app_state, backend) = match TestBackend::from_env() {
+            TestBackend::Persistent => persistent_app_state(&settings).await,
+            TestBackend::InMemory => in_memory_app_state(&settings),
+        };
+
+        let banned_token_store = app_state.banned_token_store.clone();
+        let two_fa_code_store = app_state.two_fa_code_store.clone();
+        let admin_token = admin_session(&app_state, &settings).await;
+
+        let app = Application::build(app_state, &settings)
             .await
             .expect("Failed to build app");
 
         let address = format!("http://{}", app.address.clone());
+        let shutdown_handle = app.shutdown_handle();
 
-        #[allow(clippy::let_underscore_future)]
-        let _ = tokio::spawn(app.run());
+        let server = tokio::spawn(app.run());
 
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
@@ -95,27 +130,217 @@ impl TestApp {
             .expect("Failed to build HTTP client.");
 
         Self {
-            database_name: database_name,
-            address: address,
-            http_client: http_client,
-            email_server: email_server,
-            cookie_jar: cookie_jar,
-            banned_token_store: banned_token_store,
-            two_fa_code_store: two_fa_code_store,
-            redis_db: redis_db,
+            address,
+            http_client,
+            cookie_jar,
+            banned_token_store,
+            two_fa_code_store,
+            admin_token,
+            shutdown_handle,
+            server: Mutex::new(Some(server)),
+            backend,
+        }
+    }
+
+    // Stops the app and waits until in-flight requests are drained and the stores are closed
+    pub async fn shutdown(&self) {
+        self.shutdown_handle.shutdown();
+
+        if let Some(server) = self.server.lock().await.take() {
+            server
+                .await
+                .expect("Server task panicked")
+                .expect("Server failed while shutting down");
+        }
+    }
+
+    // Declares how many emails the test expects the app to send.
+    // Checked by the mock Postmark server on drop, or by `clean_up` when running in memory.
+    pub async fn expect_emails(&self, count: u64) {
+        match &self.backend {
+            BackendResources::Persistent { email_server, .. } => {
+                Mock::given(path("/email"))
+                    .and(method("POST"))
+                    .respond_with(ResponseTemplate::new(200))
+                    .expect(count)
+                    .mount(email_server)
+                    .await;
+            }
+            BackendResources::InMemory {
+                expected_emails, ..
+            } => expected_emails.store(count, Ordering::SeqCst),
+        }
+    }
+
+    // The content of the emails sent to `recipient`, oldest first
+    pub async fn emails_sent_to(&self, recipient: &str) -> Vec<String> {
+        match &self.backend {
+            BackendResources::Persistent { email_server, .. } => email_server
+                .received_requests()
+                .await
+                .unwrap_or_default()
+                .iter()
+                .filter(|request| request.url.path() == "/email")
+                .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+                .filter(|body| body["To"] == recipient)
+                .filter_map(|body| body["TextBody"].as_str().map(str::to_owned))
+                .collect(),
+            BackendResources::InMemory { email_client, .. } => email_client
+                .sent_emails()
+                .into_iter()
+                .filter(|email| email.recipient.value() == recipient)
+                .map(|email| email.content)
+                .collect(),
+        }
+    }
+
+    // Ages every token ban by `seconds`, as if that much time had passed, lifting those that
+    // would have lapsed. Tokens themselves still expire on the real clock.
+    pub async fn age_banned_tokens(&self, seconds: i64) {
+        match &self.backend {
+            BackendResources::Persistent {
+                settings, redis_db, ..
+            } => {
+                let mut conn = redis_connection(settings, *redis_db);
+                let keys: Vec<String> = conn
+                    .keys("banned_token:*")
+                    .expect("Failed to list banned tokens");
+                for key in keys {
+                    let ttl: i64 = conn.ttl(&key).expect("Failed to get a ban's TTL");
+                    let _: () = if ttl > seconds {
+                        conn.expire(&key, ttl - seconds)
+                    } else {
+                        conn.del(&key)
+                    }
+                    .expect("Failed to age a ban");
+                }
+            }
+            BackendResources::InMemory {
+                banned_token_store, ..
+            } => {
+                for banned_until in banned_token_store.write().await.tokens.values_mut() {
+                    *banned_until -= seconds;
+                }
+            }
         }
     }
 
     pub async fn clean_up(&self) {
-        delete_database(&self.database_name).await;
-        // Flush the specific Redis database to avoid test interference
-        let mut redis_conn = configure_redis(self.redis_db);
-        let _: Result<(), redis::RedisError> = redis::cmd("FLUSHDB").query(&mut redis_conn);
+        self.shutdown().await;
+
+        match &self.backend {
+            BackendResources::Persistent {
+                settings,
+                database_name,
+                redis_db,
+                ..
+            } => {
+                delete_database(settings, database_name).await;
+                // Flush the specific Redis database to avoid test interference
+                let mut redis_conn = redis_connection(settings, *redis_db);
+                let _: Result<(), redis::RedisError> = redis::cmd("FLUSHDB").query(&mut redis_conn);
+            }
+            BackendResources::InMemory {
+                email_client,
+                expected_emails,
+                ..
+            } => {
+                // Without an expectation none may be sent, as the mock server would refuse them
+                assert_eq!(
+                    email_client.sent_emails().len() as u64,
+                    expected_emails.load(Ordering::SeqCst),
+                    "Unexpected number of emails sent"
+                );
+            }
+        }
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/.well-known/openid-configuration",
+                &self.address
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_live(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/live", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_ready(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/ready", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_root_with_request_id(&self, request_id: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/", &self.address))
+            .header("X-Request-Id", request_id)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/metrics", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Lists audit events with the given filters, authenticating with `admin_token` if any
+    pub async fn get_audit_events(
+        &self,
+        query: &[(&str, &str)],
+        admin_token: Option<&str>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/admin/audit-events", &self.address))
+            .query(query);
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn preflight(&self, path: &str, origin: &str, method: &str) -> reqwest::Response {
+        self.http_client
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("{}{}", &self.address, path),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", method)
+            .header("Access-Control-Request-Headers", "content-type")
             .send()
             .await
             .expect("Failed to execute request.")
@@ -126,7 +351,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .header("User-agent", "unit-tests")
             .header("Content-type", "application/json")
             .json(body)
@@ -140,7 +365,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .header("User-agent", "unit-tests")
             .header("Content-type", "application/json")
             .json(body)
@@ -151,7 +376,7 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
             .header("User-agent", "unit-tests")
             .header("Content-type", "application/json")
             .send()
@@ -159,12 +384,22 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    // Sends `token` as `Authorization: Bearer` from a client without cookies
+    pub async fn post_with_bearer(&self, path: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}{}", &self.address, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
             .header("User-agent", "unit-tests")
             .header("Content-type", "application/json")
             .json(body)
@@ -173,12 +408,245 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    // Posts a JSON body to `path`, negotiating the response format with `accept`
+    pub async fn post_with_accept<Body>(
+        &self,
+        path: &str,
+        body: &Body,
+        accept: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}{}", &self.address, path))
+            .header("Accept", accept)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Posts a form to an /oauth endpoint, authenticating with HTTP Basic if `credentials` are given
+    pub async fn post_oauth(
+        &self,
+        path: &str,
+        form: &[(&str, &str)],
+        credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .post(format!("{}{}", &self.address, path))
+            .form(form);
+        if let Some((client_id, client_secret)) = credentials {
+            request = request.basic_auth(client_id, Some(client_secret));
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // Opens the authorization page. Redirects are not followed, so tests can read the
+    // `Location` sent to the client.
+    pub async fn get_oauth_authorize(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.no_redirect_client()
+            .get(format!("{}/oauth/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Submits the consent form with the session cookie, without following the redirect
+    pub async fn post_oauth_authorize(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.no_redirect_client()
+            .post(format!("{}/oauth/authorize", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_client<Body>(
+        &self,
+        body: &Body,
+        admin_token: Option<&str>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin/oauth/clients", &self.address))
+            .json(body);
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // `token` is optional as confirming an email change needs no session
+    pub async fn post_account<Body>(
+        &self,
+        path: &str,
+        body: &Body,
+        token: Option<&str>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/account{}", &self.address, path))
+            .json(body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_key<Body>(&self, body: &Body, token: &str) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/api-keys", &self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_keys(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api-keys", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_api_key(&self, id: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/api-keys/{}", &self.address, id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_role<Body>(&self, body: &Body, admin_token: Option<&str>) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin/roles", &self.address))
+            .json(body);
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_roles(&self, admin_token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/admin/roles", &self.address));
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // Assigns `role` to `email` with PUT, or unassigns it with DELETE
+    pub async fn user_role(
+        &self,
+        method: reqwest::Method,
+        email: &str,
+        role: &str,
+        admin_token: Option<&str>,
+    ) -> reqwest::Response {
+        let mut request = self.http_client.request(
+            method,
+            format!("{}/admin/users/{}/roles/{}", &self.address, email, role),
+        );
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users(
+        &self,
+        query: &[(&str, &str)],
+        admin_token: Option<&str>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/admin/users", &self.address))
+            .query(query);
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // Calls `/admin/users/{user}` followed by `suffix` with the admin token, where `user` is
+    // an id or email
+    pub async fn admin_user(
+        &self,
+        method: reqwest::Method,
+        user: &str,
+        suffix: &str,
+        body: Option<&serde_json::Value>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .request(
+                method,
+                format!("{}/admin/users/{}{}", &self.address, user, suffix),
+            )
+            .bearer_auth(&self.admin_token);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // The `sub` of the user's tokens, looked up through the admin API
+    pub async fn user_id(&self, email: &str) -> String {
+        self.admin_user(reqwest::Method::GET, email, "", None)
+            .await
+            .json::<UserDetails>()
+            .await
+            .expect("Could not deserialize response body to UserDetails")
+            .user
+            .id
+            .to_string()
+    }
+
+    fn no_redirect_client(&self) -> Client {
+        Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to build HTTP client.")
+    }
+
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-token", &self.address))
+            .post(format!("{}/verify-token", &self.address))
             .header("User-agent", "unit-tests")
             .header("Content-type", "application/json")
             .json(body)
@@ -194,8 +662,177 @@ pub fn get_random_email() -> String {
     format!("{}@example.com", uuid)
 }
 
-async fn configure_postgresql(db_name: String) -> PgPool {
-    let postgresql_conn_url = DATABASE_URL.expose_secret().to_owned();
+// Generating an RSA key is slow in debug builds, so the tests share one
+const OIDC_SIGNING_KEY: &str = include_str!("../fixtures/oidc_signing_key.pem");
+
+// Settings shared by both backends; the JWT secret and addresses never come from the environment
+pub fn test_settings() -> Settings {
+    Settings {
+        application: ApplicationSettings {
+            address: test::APP_ADDRESS.to_owned(),
+            shutdown_timeout_milliseconds: test::SHUTDOWN_TIMEOUT.as_millis() as u64,
+            ..ApplicationSettings::default()
+        },
+        auth: AuthSettings {
+            jwt_secret: Secret::new(test::JWT_SECRET.to_owned()),
+        },
+        oauth: OAuthSettings {
+            clients: vec![
+                OAuthClientSettings {
+                    client_id: test::OAUTH_CLIENT_ID.to_owned(),
+                    client_name: Some("Test Gateway".to_owned()),
+                    client_secret: Some(Secret::new(test::OAUTH_CLIENT_SECRET.to_owned())),
+                    redirect_uris: vec![test::OAUTH_REDIRECT_URI.to_owned()],
+                    scopes: Vec::new(),
+                },
+                OAuthClientSettings {
+                    client_id: test::OAUTH_PUBLIC_CLIENT_ID.to_owned(),
+                    client_name: Some("Test SPA".to_owned()),
+                    client_secret: None,
+                    redirect_uris: vec![test::OAUTH_REDIRECT_URI.to_owned()],
+                    scopes: Vec::new(),
+                },
+            ],
+            ..OAuthSettings::default()
+        },
+        oidc: OidcSettings {
+            issuer: test::OIDC_ISSUER.to_owned(),
+            signing_key: Some(Secret::new(OIDC_SIGNING_KEY.to_owned())),
+        },
+        ..Settings::default()
+    }
+}
+
+// A token for an administrator who needs no account, so the tests' users and audit events
+// are their own
+async fn admin_session(app_state: &AppState, settings: &Settings) -> String {
+    app_state
+        .role_store
+        .write()
+        .await
+        .add_role(Role {
+            name: test::ADMIN_ROLE.to_owned(),
+            permissions: vec![ADMIN_PERMISSION.to_owned()],
+        })
+        .await
+        .expect("Failed to add the admin role");
+
+    generate_auth_token(
+        Uuid::new_v4(),
+        &[test::ADMIN_ROLE.to_owned()],
+        &[AMR_PASSWORD],
+        &settings.auth,
+    )
+    .expect("Failed to generate the admin token")
+}
+
+async fn persistent_app_state(test_settings: &Settings) -> (AppState, BackendResources) {
+    let settings = Settings::load_unvalidated().expect("Failed to load settings");
+
+    // We are creating a new database for each test case, and we need to ensure each database has a unique name!
+    let database_name = Uuid::new_v4().to_string();
+
+    // Get a unique Redis database number (0-15) for this test
+    let redis_db = REDIS_DB_COUNTER.fetch_add(1, Ordering::SeqCst) % 16;
+
+    let pg_pool = configure_postgresql(&settings, database_name.clone()).await;
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+    let oauth_client_store = PostgresOAuthClientStore::new(pg_pool.clone());
+    oauth_client_store
+        .save_clients(test_settings.oauth.clients())
+        .await
+        .expect("Failed to save the test OAuth clients");
+    let oauth_client_store = Arc::new(RwLock::new(oauth_client_store));
+    let redis_client = configure_redis(&settings, redis_db);
+    let banned_token_store = Arc::new(RwLock::new(
+        RedisBannedTokenStore::new(redis_client.clone()).expect("Failed to get Redis connection"),
+    ));
+    let two_fa_code_store = Arc::new(RwLock::new(
+        RedisTwoFACodeStore::new(redis_client.clone()).expect("Failed to get Redis connection"),
+    ));
+    let oauth_grant_store = Arc::new(RwLock::new(
+        RedisOAuthGrantStore::new(redis_client).expect("Failed to get Redis connection"),
+    ));
+    // Mock email server
+    let email_server = MockServer::start().await;
+    // Postmark server details, fetched by the readiness check
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&email_server)
+        .await;
+    let email_client = Arc::new(RwLock::new(configure_postmark_email_client(
+        email_server.uri(),
+    )));
+
+    let app_state = AppState::new(
+        user_store,
+        banned_token_store,
+        two_fa_code_store,
+        email_client,
+        test_settings.auth.clone(),
+    )
+    .with_health_settings(test_settings.health.clone())
+    .with_audit_sink(Arc::new(RwLock::new(PostgresAuditSink::new(
+        pg_pool.clone(),
+    ))))
+    .with_oauth_client_store(oauth_client_store)
+    .with_oauth_grant_store(oauth_grant_store)
+    .with_api_key_store(Arc::new(RwLock::new(PostgresApiKeyStore::new(
+        pg_pool.clone(),
+    ))))
+    .with_role_store(Arc::new(RwLock::new(PostgresRoleStore::new(
+        pg_pool.clone(),
+    ))))
+    .with_oidc(oidc_provider(test_settings))
+    .with_pg_pool(pg_pool);
+
+    let backend = BackendResources::Persistent {
+        settings: Box::new(settings),
+        database_name,
+        redis_db,
+        email_server,
+    };
+
+    (app_state, backend)
+}
+
+fn in_memory_app_state(test_settings: &Settings) -> (AppState, BackendResources) {
+    let email_client = MockEmailClient::default();
+    let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+    let app_state = AppState::new(
+        Arc::new(RwLock::new(HashmapUserStore::default())),
+        banned_token_store.clone(),
+        Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+        Arc::new(RwLock::new(email_client.clone())),
+        test_settings.auth.clone(),
+    )
+    .with_health_settings(test_settings.health.clone())
+    .with_oauth_client_store(Arc::new(RwLock::new(
+        HashmapOAuthClientStore::with_clients(test_settings.oauth.clients()),
+    )))
+    .with_oauth_grant_store(Arc::new(RwLock::new(HashmapOAuthGrantStore::default())))
+    .with_oidc(oidc_provider(test_settings));
+
+    let backend = BackendResources::InMemory {
+        banned_token_store,
+        email_client,
+        expected_emails: AtomicU64::new(0),
+    };
+
+    (app_state, backend)
+}
+
+fn oidc_provider(test_settings: &Settings) -> OidcProvider {
+    let signing_key = test_settings.oidc.signing_key.as_ref().unwrap();
+
+    OidcProvider::from_pem(&test_settings.oidc.issuer, signing_key)
+        .expect("Failed to load the OIDC signing key")
+}
+
+async fn configure_postgresql(settings: &Settings, db_name: String) -> PgPool {
+    let postgresql_conn_url = database_url(settings).expose_secret().to_owned();
 
     configure_database(&postgresql_conn_url, &db_name).await;
 
@@ -235,26 +872,27 @@ async fn configure_database(db_conn_string: &str, db_name: &str) {
         .expect("Failed to migrate the database");
 }
 
-fn configure_redis(db: u8) -> redis::Connection {
-    let client = get_redis_client(REDIS_HOST_NAME.to_owned()).expect("Failed to get Redis client");
+// Its connections use the specific database for this test
+fn configure_redis(settings: &Settings, db: u8) -> redis::Client {
+    let redis_url = format!(
+        "redis://{}/{}",
+        settings.redis.host_name.expose_secret(),
+        db
+    );
 
-    let mut conn = client
-        .get_connection()
-        .expect("Failed to get Redis connection");
-
-    // Select the specific database for this test
-    redis::cmd("SELECT")
-        .arg(db)
-        .query::<()>(&mut conn)
-        .expect("Failed to select Redis database");
+    redis::Client::open(redis_url).expect("Failed to get Redis client")
+}
 
-    conn
+fn redis_connection(settings: &Settings, db: u8) -> redis::Connection {
+    configure_redis(settings, db)
+        .get_connection()
+        .expect("Failed to get Redis connection")
 }
 
 fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
     let postmark_auth_token = Secret::new("auth_token".to_owned());
 
-    let sender = Email::parse(test::email_client::SENDER.to_owned()).unwrap();
+    let sender = Email::parse(Secret::new(test::email_client::SENDER.to_owned())).unwrap();
 
     let http_client = Client::builder()
         .timeout(test::email_client::TIMEOUT)
@@ -264,10 +902,10 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
 
-async fn delete_database(db_name: &str) {
-    let postgresql_conn_url: Secret<String> = DATABASE_URL.to_owned();
+async fn delete_database(settings: &Settings, db_name: &str) {
+    let postgresql_conn_url = database_url(settings);
 
-    let connection_options = PgConnectOptions::from_str(&postgresql_conn_url.expose_secret())
+    let connection_options = PgConnectOptions::from_str(postgresql_conn_url.expose_secret())
         .expect("Failed to parse PostgreSQL connection string");
 
     let mut connection = PgConnection::connect_with(&connection_options)
@@ -297,3 +935,11 @@ async fn delete_database(db_name: &str) {
         .await
         .expect("Failed to drop the database.");
 }
+
+fn database_url(settings: &Settings) -> Secret<String> {
+    settings
+        .database
+        .url
+        .clone()
+        .expect("DATABASE_URL must be set to run the API tests against Postgres")
+}
@@ -1,11 +1,11 @@
 use crate::helpers::{get_random_email, TestApp};
 
-use auth_service::{domain::Email, routes::LoginResponse2FA, utils::constants::JWT_COOKIE_NAME};
-use secrecy::{ExposeSecret, Secret};
-use wiremock::{
-    matchers::{method, path},
-    Mock, ResponseTemplate,
+use auth_service::{
+    domain::Email,
+    routes::{LoginResponse2FA, TokenResponse},
+    utils::constants::JWT_COOKIE_NAME,
 };
+use secrecy::{ExposeSecret, Secret};
 
 #[tokio::test]
 async fn login_returns_200_if_valid_credentials_and_2fa_disabled() {
@@ -41,6 +41,37 @@ async fn login_returns_200_if_valid_credentials_and_2fa_disabled() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn login_returns_token_in_body_if_requested() {
+    let app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": random_email,
+        "password": "asdf1234",
+        "requires2FA": false
+    }))
+    .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "asdf1234",
+            "tokenDelivery": "body"
+        }))
+        .await;
+
+    assert_eq!(response.status(), 200);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+    let body = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(body.token.split('.').count(), 3);
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn login_returns_206_if_valid_credentials_and_2fa_enabled() {
     let random_email = get_random_email();
@@ -56,13 +87,8 @@ async fn login_returns_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(response.status().as_u16(), 201);
 
-    // Define an expectation for the mock server
-    Mock::given(path("/email")) // Expect an HTTP request to the "/email" path
-        .and(method("POST")) // Expect the HTTP method to be POST
-        .respond_with(ResponseTemplate::new(200)) // Respond with an HTTP 200 OK status
-        .expect(1) // Expect this request to be made exactly once
-        .mount(&app.email_server) // Mount this expectation on the mock email server
-        .await; // Await the asynchronous operation to ensure the mock server is set up before proceeding
+    // Expect the 2FA code to be emailed exactly once
+    app.expect_emails(1).await;
 
     let test_case = serde_json::json!(
         {
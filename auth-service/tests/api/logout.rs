@@ -1,6 +1,6 @@
 use reqwest::Url;
 
-use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::{routes::TokenResponse, utils::JWT_COOKIE_NAME};
 use secrecy::Secret;
 
 use crate::helpers::{get_random_email, TestApp};
@@ -57,7 +57,7 @@ async fn logout_returns_400_if_jwt_cookie_missing() {
     let app = TestApp::new().await;
 
     app.cookie_jar.add_cookie_str(
-        &"HttpOnly; SameSite=Lax; Secure; Path=/",
+        "HttpOnly; SameSite=Lax; Secure; Path=/",
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
     );
 
@@ -83,6 +83,36 @@ async fn logout_returns_401_if_invalid_token() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn logout_accepts_bearer_token() {
+    let app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": random_email,
+        "password": "asdf1234",
+        "requires2FA": false
+    }))
+    .await;
+    let token = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "asdf1234",
+            "tokenDelivery": "body"
+        }))
+        .await
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app.post_with_bearer("/logout", &token).await;
+    assert_eq!(response.status(), 200);
+
+    let response = app.post_with_bearer("/logout", &token).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
 async fn setup_user_for_logout(app: &TestApp) -> Result<String, String> {
     // Create a user
     let random_email = get_random_email();
@@ -1,7 +1,23 @@
+mod account;
+mod admin_users;
+mod api_keys;
+mod audit_events;
+mod client_credentials;
+mod cors;
+mod errors;
+mod health;
 mod helpers;
 mod login;
 mod logout;
+mod metrics;
+mod oauth;
+mod oauth_authorize;
+mod oidc;
+mod openapi;
+mod request_id;
+mod roles;
 mod root;
+mod shutdown;
 mod signup;
 mod verify_2fa;
 mod verify_token;
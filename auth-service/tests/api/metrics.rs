@@ -0,0 +1,107 @@
+use crate::helpers::{get_random_email, TestApp};
+
+// Metrics are process-wide and tests run concurrently, so assertions compare
+// values before and after an action instead of expecting exact totals
+async fn metric_value(app: &TestApp, series: &str) -> f64 {
+    let body = app.get_metrics().await.text().await.unwrap();
+
+    body.lines()
+        .find_map(|line| {
+            line.strip_prefix(series)
+                .and_then(|value| value.strip_prefix(' '))
+        })
+        .map(|value| value.parse().unwrap())
+        .unwrap_or(0.0)
+}
+
+#[tokio::test]
+async fn metrics_are_exposed_in_prometheus_format() {
+    let app = TestApp::new().await;
+    app.get_root().await;
+
+    let response = app.get_metrics().await;
+
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    let body = response.text().await.unwrap();
+    assert!(body.contains("# TYPE auth_service_http_requests_total counter"));
+    assert!(body.contains("# TYPE auth_service_signups_total counter"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn requests_are_counted_per_route_and_status() {
+    let app = TestApp::new().await;
+    let series = r#"auth_service_http_requests_total{method="POST",route="/signup",status="201"}"#;
+    let before = metric_value(&app, series).await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "Asdf1234@",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status(), 201);
+
+    assert!(metric_value(&app, series).await >= before + 1.0);
+    let body = app.get_metrics().await.text().await.unwrap();
+    assert!(body.contains(
+        r#"auth_service_http_request_duration_seconds_count{method="POST",route="/signup",status="201"}"#
+    ));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_outcomes_and_2fa_codes_are_counted() {
+    let app = TestApp::new().await;
+    let incorrect = r#"auth_service_logins_total{outcome="incorrect_credentials"}"#;
+    let two_fa_required = r#"auth_service_logins_total{outcome="two_fa_required"}"#;
+    let issued = r#"auth_service_two_fa_codes_total{event="issued"}"#;
+    let expired = r#"auth_service_two_fa_codes_total{event="expired"}"#;
+
+    let email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "Asdf1234@",
+        "requires2FA": true
+    }))
+    .await;
+    app.expect_emails(1).await;
+
+    let before = (
+        metric_value(&app, incorrect).await,
+        metric_value(&app, two_fa_required).await,
+        metric_value(&app, issued).await,
+        metric_value(&app, expired).await,
+    );
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "Asdf1234@" }))
+        .await;
+    assert_eq!(response.status(), 206);
+
+    // No code was ever issued for this address
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": get_random_email(),
+            "loginAttemptId": uuid::Uuid::new_v4().to_string(),
+            "2FACode": "123456"
+        }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    assert!(metric_value(&app, incorrect).await >= before.0 + 1.0);
+    assert!(metric_value(&app, two_fa_required).await >= before.1 + 1.0);
+    assert!(metric_value(&app, issued).await >= before.2 + 1.0);
+    assert!(metric_value(&app, expired).await >= before.3 + 1.0);
+    app.clean_up().await;
+}
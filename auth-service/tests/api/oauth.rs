@@ -0,0 +1,274 @@
+use auth_service::{
+    routes::{AuditEventsResponse, IntrospectResponse, OAuthTokenResponse, TokenResponse},
+    utils::test,
+    OAuthErrorResponse,
+};
+use secrecy::ExposeSecret;
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth_authorize::{
+        consent, exchange_code, log_in, oauth_error, public_client_refresh_token, refresh, CLIENT,
+        CODE_VERIFIER,
+    },
+};
+
+pub async fn logged_in_token(app: &TestApp, email: &str) -> String {
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    }))
+    .await;
+
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "tokenDelivery": "body"
+    }))
+    .await
+    .json::<TokenResponse>()
+    .await
+    .expect("Could not deserialize response body to TokenResponse")
+    .token
+}
+
+async fn introspect(app: &TestApp, token: &str) -> serde_json::Value {
+    let response = app
+        .post_oauth("/oauth/introspect", &[("token", token)], CLIENT)
+        .await;
+    assert_eq!(response.status(), 200);
+
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn introspect_returns_the_claims_of_active_tokens() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+
+    let response = app
+        .post_oauth("/oauth/introspect", &[("token", &token)], CLIENT)
+        .await;
+
+    assert_eq!(response.status(), 200);
+    let body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+    assert!(body.active);
+    assert_eq!(body.sub, Some(app.user_id(&email).await));
+    assert_eq!(body.token_type.as_deref(), Some("Bearer"));
+    let (iat, exp) = (body.iat.unwrap(), body.exp.unwrap());
+    assert_eq!(exp - iat, 600);
+    assert_eq!(body.scope, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn introspect_reports_invalid_and_logged_out_tokens_as_inactive() {
+    let app = TestApp::new().await;
+    let token = logged_in_token(&app, &get_random_email()).await;
+
+    assert_eq!(
+        introspect(&app, "invalid").await,
+        serde_json::json!({ "active": false })
+    );
+
+    assert_eq!(app.post_with_bearer("/logout", &token).await.status(), 200);
+    assert_eq!(
+        introspect(&app, &token).await,
+        serde_json::json!({ "active": false })
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn introspect_accepts_client_credentials_in_the_form() {
+    let app = TestApp::new().await;
+    let token = logged_in_token(&app, &get_random_email()).await;
+
+    let response = app
+        .post_oauth(
+            "/oauth/introspect",
+            &[
+                ("token", &token),
+                ("token_type_hint", "access_token"),
+                ("client_id", test::OAUTH_CLIENT_ID),
+                ("client_secret", test::OAUTH_CLIENT_SECRET),
+            ],
+            None,
+        )
+        .await;
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap()["active"],
+        true
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn oauth_endpoints_reject_unknown_clients() {
+    let app = TestApp::new().await;
+
+    for path in ["/oauth/introspect", "/oauth/revoke"] {
+        for credentials in [
+            None,
+            Some((test::OAUTH_CLIENT_ID, "wrong-secret")),
+            Some(("unknown", test::OAUTH_CLIENT_SECRET)),
+        ] {
+            let response = app
+                .post_oauth(path, &[("token", "anything")], credentials)
+                .await;
+
+            assert_eq!(response.status(), 401, "{} {:?}", path, credentials);
+            assert_eq!(
+                response.headers()["www-authenticate"],
+                r#"Basic realm="oauth""#
+            );
+            let body = response
+                .json::<OAuthErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to OAuthErrorResponse");
+            assert_eq!(body.error, "invalid_client");
+        }
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn oauth_endpoints_return_400_without_a_token() {
+    let app = TestApp::new().await;
+
+    for path in ["/oauth/introspect", "/oauth/revoke"] {
+        let response = app.post_oauth(path, &[], CLIENT).await;
+
+        assert_eq!(response.status(), 400, "{}", path);
+        let body = response
+            .json::<OAuthErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to OAuthErrorResponse");
+        assert_eq!(body.error, "invalid_request");
+    }
+    app.clean_up().await;
+}
+
+// The access and refresh tokens of the confidential client, for a new user
+async fn client_tokens(app: &TestApp, email: &str) -> (String, String) {
+    log_in(app, email).await;
+    let query = consent(app, test::OAUTH_CLIENT_ID, "allow").await;
+    let tokens = exchange_code(app, &query["code"], CODE_VERIFIER, CLIENT)
+        .await
+        .json::<OAuthTokenResponse>()
+        .await
+        .unwrap();
+
+    (
+        tokens.access_token.expose_secret().to_owned(),
+        tokens.refresh_token.unwrap().expose_secret().to_owned(),
+    )
+}
+
+async fn revoke(app: &TestApp, token: &str, hint: Option<&str>) -> reqwest::Response {
+    let mut form = vec![("token", token)];
+    form.extend(hint.map(|hint| ("token_type_hint", hint)));
+
+    app.post_oauth("/oauth/revoke", &form, CLIENT).await
+}
+
+async fn refresh_with_client(app: &TestApp, refresh_token: &str) -> u16 {
+    app.post_oauth(
+        "/oauth/token",
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ],
+        CLIENT,
+    )
+    .await
+    .status()
+    .as_u16()
+}
+
+#[tokio::test]
+async fn revoke_bans_access_tokens() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let (token, _) = client_tokens(&app, &email).await;
+
+    let response = revoke(&app, &token, None).await;
+    assert_eq!(response.status(), 200);
+
+    assert_eq!(
+        app.post_with_bearer("/verify-token", &token).await.status(),
+        401
+    );
+    assert_eq!(introspect(&app, &token).await["active"], false);
+
+    let events = app
+        .get_audit_events(
+            &[("user", &email), ("type", "token_revoked")],
+            Some(&app.admin_token),
+        )
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events;
+    assert_eq!(events.len(), 1);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoke_deletes_refresh_tokens_whatever_the_hint() {
+    let app = TestApp::new().await;
+
+    for hint in [None, Some("refresh_token"), Some("access_token")] {
+        let (_, refresh_token) = client_tokens(&app, &get_random_email()).await;
+
+        let response = revoke(&app, &refresh_token, hint).await;
+        assert_eq!(response.status(), 200, "Failed for hint {:?}", hint);
+
+        assert_eq!(refresh_with_client(&app, &refresh_token).await, 400);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoke_refuses_tokens_issued_to_others() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let session_token = logged_in_token(&app, &get_random_email()).await;
+    log_in(&app, &email).await;
+    let refresh_token = public_client_refresh_token(&app).await;
+
+    for token in [session_token.as_str(), refresh_token.as_str()] {
+        let response = revoke(&app, token, None).await;
+
+        assert_eq!(oauth_error(response, 400).await, "unauthorized_client");
+    }
+    assert_eq!(
+        app.post_with_bearer("/verify-token", &session_token)
+            .await
+            .status(),
+        200
+    );
+    assert_eq!(refresh(&app, &refresh_token).await.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoke_succeeds_for_invalid_and_revoked_tokens() {
+    let app = TestApp::new().await;
+    let (token, refresh_token) = client_tokens(&app, &get_random_email()).await;
+
+    for token in ["invalid", &token, &token, &refresh_token, &refresh_token] {
+        let response = revoke(&app, token, None).await;
+
+        assert_eq!(response.status(), 200);
+    }
+    app.clean_up().await;
+}
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+
+use auth_service::{
+    routes::{
+        AuditEventsResponse, OAuthTokenResponse, RegisterClientResponse, VerifyTokenResponse,
+    },
+    utils::{pkce_challenge, test},
+    ErrorResponse, OAuthErrorResponse,
+};
+use reqwest::{header::LOCATION, Url};
+use secrecy::ExposeSecret;
+
+use crate::helpers::{get_random_email, TestApp};
+
+pub const CLIENT: Option<(&str, &str)> = Some((test::OAUTH_CLIENT_ID, test::OAUTH_CLIENT_SECRET));
+pub const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mJ92K9Wc4bXr2Xd4bQ9b1hXvVnR7kQWaE5Hq3pYtL8sMzUoNx";
+
+pub async fn log_in(app: &TestApp, email: &str) {
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    }))
+    .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123"
+        }))
+        .await;
+    assert_eq!(response.status(), 200);
+}
+
+pub fn authorize_params(client_id: &str) -> Vec<(&str, String)> {
+    vec![
+        ("response_type", "code".to_owned()),
+        ("client_id", client_id.to_owned()),
+        ("redirect_uri", test::OAUTH_REDIRECT_URI.to_owned()),
+        ("scope", "email".to_owned()),
+        ("state", "xyz".to_owned()),
+        ("code_challenge", pkce_challenge(CODE_VERIFIER)),
+        ("code_challenge_method", "S256".to_owned()),
+    ]
+}
+
+pub fn borrowed<'a>(params: &'a [(&'a str, String)]) -> Vec<(&'a str, &'a str)> {
+    params
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .collect()
+}
+
+// The query sent back to the client, after checking the redirect goes to its redirect URI
+pub fn redirect_query(response: &reqwest::Response) -> HashMap<String, String> {
+    assert_eq!(response.status(), 303);
+    let location = response.headers()[LOCATION].to_str().unwrap();
+    assert!(location.starts_with(test::OAUTH_REDIRECT_URI));
+
+    Url::parse(location)
+        .unwrap()
+        .query_pairs()
+        .into_owned()
+        .collect()
+}
+
+pub async fn consent(app: &TestApp, client_id: &str, decision: &str) -> HashMap<String, String> {
+    consent_to(app, authorize_params(client_id), decision).await
+}
+
+// Answers the consent form for the given authorization request
+pub async fn consent_to(
+    app: &TestApp,
+    mut form: Vec<(&str, String)>,
+    decision: &str,
+) -> HashMap<String, String> {
+    form.push(("decision", decision.to_owned()));
+    let response = app.post_oauth_authorize(&borrowed(&form)).await;
+
+    redirect_query(&response)
+}
+
+pub async fn exchange_code(
+    app: &TestApp,
+    code: &str,
+    code_verifier: &str,
+    credentials: Option<(&str, &str)>,
+) -> reqwest::Response {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", test::OAUTH_REDIRECT_URI),
+        ("code_verifier", code_verifier),
+    ];
+    if credentials.is_none() {
+        form.push(("client_id", test::OAUTH_PUBLIC_CLIENT_ID));
+    }
+
+    app.post_oauth("/oauth/token", &form, credentials).await
+}
+
+pub async fn refresh(app: &TestApp, refresh_token: &str) -> reqwest::Response {
+    app.post_oauth(
+        "/oauth/token",
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", test::OAUTH_PUBLIC_CLIENT_ID),
+        ],
+        None,
+    )
+    .await
+}
+
+// A refresh token the public client got for the user logged in with the cookie
+pub async fn public_client_refresh_token(app: &TestApp) -> String {
+    let query = consent(app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+    let tokens = exchange_code(app, &query["code"], CODE_VERIFIER, None)
+        .await
+        .json::<OAuthTokenResponse>()
+        .await
+        .unwrap();
+
+    tokens.refresh_token.unwrap().expose_secret().to_owned()
+}
+
+pub async fn oauth_error(response: reqwest::Response, status: u16) -> String {
+    assert_eq!(response.status(), status);
+
+    response
+        .json::<OAuthErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn authorize_serves_the_login_page_for_registered_clients() {
+    let app = TestApp::new().await;
+    let params = authorize_params(test::OAUTH_CLIENT_ID);
+
+    let response = app.get_oauth_authorize(&borrowed(&params)).await;
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-frame-options"], "DENY");
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let page = response.text().await.unwrap();
+    assert!(page.contains(r#"<body data-oauth-client="Test Gateway">"#));
+    assert!(page.contains(r#"id="consent-form""#));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn authorize_does_not_redirect_to_unregistered_uris() {
+    let app = TestApp::new().await;
+
+    let cases = [
+        ("client_id", "unknown-client"),
+        ("redirect_uri", "https://attacker.example/callback"),
+    ];
+    for (name, value) in cases {
+        let mut params = authorize_params(test::OAUTH_CLIENT_ID);
+        params.retain(|(param, _)| *param != name);
+        params.push((name, value.to_owned()));
+
+        let response = app.get_oauth_authorize(&borrowed(&params)).await;
+
+        assert_eq!(
+            oauth_error(response, 400).await,
+            "invalid_request",
+            "Failed for {name}={value}"
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn authorize_reports_invalid_requests_to_the_client() {
+    let app = TestApp::new().await;
+
+    let cases = [
+        ("response_type", "token", "unsupported_response_type"),
+        ("code_challenge_method", "plain", "invalid_request"),
+        ("code_challenge", "", "invalid_request"),
+        ("scope", "openid reports:read", "invalid_scope"),
+    ];
+    for (name, value, error) in cases {
+        let mut params = authorize_params(test::OAUTH_CLIENT_ID);
+        params.retain(|(param, _)| *param != name);
+        if !value.is_empty() {
+            params.push((name, value.to_owned()));
+        }
+
+        let response = app.get_oauth_authorize(&borrowed(&params)).await;
+
+        let query = redirect_query(&response);
+        assert_eq!(query["error"], error, "Failed for {name}={value}");
+        assert_eq!(query["state"], "xyz");
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn consent_requires_a_session() {
+    let app = TestApp::new().await;
+    let mut form = authorize_params(test::OAUTH_CLIENT_ID);
+    form.push(("decision", "allow".to_owned()));
+
+    let response = app.post_oauth_authorize(&borrowed(&form)).await;
+
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn denied_consent_redirects_with_access_denied() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    log_in(&app, &email).await;
+
+    let query = consent(&app, test::OAUTH_CLIENT_ID, "deny").await;
+
+    assert_eq!(query["error"], "access_denied");
+    assert_eq!(query["state"], "xyz");
+    assert!(!query.contains_key("code"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn authorization_code_flow_issues_tokens_for_the_client() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    log_in(&app, &email).await;
+
+    let query = consent(&app, test::OAUTH_CLIENT_ID, "allow").await;
+    assert_eq!(query["state"], "xyz");
+    let response = exchange_code(&app, &query["code"], CODE_VERIFIER, CLIENT).await;
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let tokens = response
+        .json::<OAuthTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthTokenResponse");
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.expires_in, 600);
+    assert_eq!(tokens.scope.as_deref(), Some("email"));
+
+    let access_token = tokens.access_token.expose_secret();
+    assert_eq!(
+        app.post_with_bearer("/verify-token", access_token)
+            .await
+            .status(),
+        200
+    );
+    let introspection: serde_json::Value = app
+        .post_oauth("/oauth/introspect", &[("token", access_token)], CLIENT)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(introspection["sub"], app.user_id(&email).await);
+    assert_eq!(introspection["client_id"], test::OAUTH_CLIENT_ID);
+    assert_eq!(introspection["scope"], "email");
+
+    let events = app
+        .get_audit_events(
+            &[("user", &email), ("type", "tokens_issued")],
+            Some(&app.admin_token),
+        )
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events;
+    assert_eq!(events.len(), 1);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn tokens_granted_without_a_scope_carry_an_empty_one() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    log_in(&app, &email).await;
+
+    let mut params = authorize_params(test::OAUTH_CLIENT_ID);
+    params.retain(|(name, _)| *name != "scope");
+    let query = consent_to(&app, params, "allow").await;
+    let tokens = exchange_code(&app, &query["code"], CODE_VERIFIER, CLIENT)
+        .await
+        .json::<OAuthTokenResponse>()
+        .await
+        .unwrap();
+
+    // Services read a missing scope as a login session's, which may do anything
+    let verified = app
+        .post_with_bearer("/verify-token", tokens.access_token.expose_secret())
+        .await
+        .json::<VerifyTokenResponse>()
+        .await
+        .unwrap();
+    assert_eq!(verified.client_id.as_deref(), Some(test::OAUTH_CLIENT_ID));
+    assert_eq!(verified.scope.as_deref(), Some(""));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn authorization_codes_are_single_use() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+    let query = consent(&app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+
+    let response = exchange_code(&app, &query["code"], CODE_VERIFIER, None).await;
+    assert_eq!(response.status(), 200);
+    let response = exchange_code(&app, &query["code"], CODE_VERIFIER, None).await;
+
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn token_rejects_a_wrong_code_verifier() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+    let query = consent(&app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+
+    let wrong_verifier = CODE_VERIFIER.replace('d', "e");
+    let response = exchange_code(&app, &query["code"], &wrong_verifier, None).await;
+
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    // The failed attempt used up the code
+    let response = exchange_code(&app, &query["code"], CODE_VERIFIER, None).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn token_requires_the_secret_of_confidential_clients() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+    let query = consent(&app, test::OAUTH_CLIENT_ID, "allow").await;
+
+    let response = app
+        .post_oauth(
+            "/oauth/token",
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", &query["code"]),
+                ("code_verifier", CODE_VERIFIER),
+                ("client_id", test::OAUTH_CLIENT_ID),
+            ],
+            None,
+        )
+        .await;
+
+    assert_eq!(oauth_error(response, 401).await, "invalid_client");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn token_rejects_codes_issued_to_other_clients() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+    let query = consent(&app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+
+    let response = exchange_code(&app, &query["code"], CODE_VERIFIER, CLIENT).await;
+
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    app.clean_up().await;
+}
+
+async fn exchange_without_redirect_uri(app: &TestApp, code: &str) -> reqwest::Response {
+    app.post_oauth(
+        "/oauth/token",
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("code_verifier", CODE_VERIFIER),
+            ("client_id", test::OAUTH_PUBLIC_CLIENT_ID),
+        ],
+        None,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn token_requires_the_redirect_uri_of_the_authorization_request() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+
+    let query = consent(&app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+    let response = exchange_without_redirect_uri(&app, &query["code"]).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+
+    // Not needed when the authorization request relied on the only registered one
+    let mut params = authorize_params(test::OAUTH_PUBLIC_CLIENT_ID);
+    params.retain(|(name, _)| *name != "redirect_uri");
+    let query = consent_to(&app, params, "allow").await;
+    let response = exchange_without_redirect_uri(&app, &query["code"]).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn token_rejects_unknown_grant_types() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_oauth("/oauth/token", &[("grant_type", "password")], CLIENT)
+        .await;
+    assert_eq!(oauth_error(response, 400).await, "unsupported_grant_type");
+
+    let response = app.post_oauth("/oauth/token", &[], CLIENT).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_request");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn refresh_tokens_rotate() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+    let query = consent(&app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+    let tokens = exchange_code(&app, &query["code"], CODE_VERIFIER, None)
+        .await
+        .json::<OAuthTokenResponse>()
+        .await
+        .unwrap();
+
+    let first = tokens.refresh_token.unwrap().expose_secret().to_owned();
+    let response = refresh(&app, &first).await;
+    assert_eq!(response.status(), 200);
+    let rotated = response.json::<OAuthTokenResponse>().await.unwrap();
+    assert_ne!(
+        rotated.refresh_token.as_ref().unwrap().expose_secret(),
+        &first
+    );
+    assert_eq!(rotated.scope.as_deref(), Some("email"));
+
+    let response = refresh(&app, &first).await;
+    assert_eq!(oauth_error(response, 400).await, "invalid_grant");
+    let response = refresh(
+        &app,
+        rotated.refresh_token.as_ref().unwrap().expose_secret(),
+    )
+    .await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn oauth_tokens_cannot_grant_consent() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+    let query = consent(&app, test::OAUTH_PUBLIC_CLIENT_ID, "allow").await;
+    let tokens = exchange_code(&app, &query["code"], CODE_VERIFIER, None)
+        .await
+        .json::<OAuthTokenResponse>()
+        .await
+        .unwrap();
+
+    let mut form = authorize_params(test::OAUTH_PUBLIC_CLIENT_ID);
+    form.push(("decision", "allow".to_owned()));
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .post(format!("{}/oauth/authorize", &app.address))
+        .bearer_auth(tokens.access_token.expose_secret())
+        .form(&borrowed(&form))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn registered_clients_can_authorize() {
+    let app = TestApp::new().await;
+    let body = serde_json::json!({
+        "client_name": "Partner App",
+        "redirect_uris": [test::OAUTH_REDIRECT_URI]
+    });
+
+    let response = app.post_oauth_client(&body, None).await;
+    assert_eq!(response.status(), 400);
+    let response = app.post_oauth_client(&body, Some(&app.admin_token)).await;
+    assert_eq!(response.status(), 201);
+    let client = response
+        .json::<RegisterClientResponse>()
+        .await
+        .expect("Could not deserialize response body to RegisterClientResponse");
+    assert!(client.client_secret.is_some());
+
+    let params = authorize_params(&client.client_id);
+    let response = app.get_oauth_authorize(&borrowed(&params)).await;
+    assert_eq!(response.status(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains(r#"data-oauth-client="Partner App""#));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn client_registration_validates_redirect_uris() {
+    let app = TestApp::new().await;
+
+    let cases = [
+        serde_json::json!({ "client_name": "App", "redirect_uris": ["http://example.com/cb"] }),
+        serde_json::json!({ "client_name": "App", "redirect_uris": ["https://example.com/cb#x"] }),
+        serde_json::json!({ "client_name": "App", "redirect_uris": [], "public": true }),
+        serde_json::json!({ "client_name": " ", "redirect_uris": ["https://example.com/cb"] }),
+    ];
+    for body in cases {
+        let response = app.post_oauth_client(&body, Some(&app.admin_token)).await;
+
+        assert_eq!(response.status(), 400, "Failed for {body}");
+        let error = response.json::<ErrorResponse>().await.unwrap();
+        assert_eq!(error.code, "invalid_request", "Failed for {body}");
+    }
+    app.clean_up().await;
+}
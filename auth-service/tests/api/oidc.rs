@@ -0,0 +1,258 @@
+use auth_service::{
+    domain::Email,
+    routes::{JwksResponse, OAuthTokenResponse, OpenIdConfiguration, UserInfoResponse},
+    utils::{test, IdTokenClaims},
+};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth_authorize::{authorize_params, consent_to, exchange_code, log_in, CLIENT, CODE_VERIFIER},
+};
+
+// An authorization request for an ID token with `scope`
+fn openid_params(scope: &str) -> Vec<(&'static str, String)> {
+    let mut params = authorize_params(test::OAUTH_CLIENT_ID);
+    params.retain(|(name, _)| *name != "scope");
+    params.push(("scope", scope.to_owned()));
+    params.push(("nonce", "n-0S6_WzA2Mj".to_owned()));
+    params
+}
+
+async fn openid_tokens(app: &TestApp, scope: &str) -> OAuthTokenResponse {
+    let query = consent_to(app, openid_params(scope), "allow").await;
+    let response = exchange_code(app, &query["code"], CODE_VERIFIER, CLIENT).await;
+    assert_eq!(response.status(), 200);
+
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to OAuthTokenResponse")
+}
+
+// Verifies the ID token as a relying party would, with the published key
+async fn verify_id_token(app: &TestApp, id_token: &str) -> IdTokenClaims {
+    let jwks = app
+        .get_jwks()
+        .await
+        .json::<JwksResponse>()
+        .await
+        .expect("Could not deserialize response body to JwksResponse");
+    let jwk = &jwks.keys[0];
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&[test::OAUTH_CLIENT_ID]);
+    validation.set_issuer(&[test::OIDC_ISSUER]);
+    decode::<IdTokenClaims>(
+        id_token,
+        &DecodingKey::from_rsa_components(&jwk.n, &jwk.e).unwrap(),
+        &validation,
+    )
+    .expect("The ID token does not verify")
+    .claims
+}
+
+async fn get_userinfo(app: &TestApp, access_token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/userinfo", &app.address))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn discovery_advertises_the_endpoints() {
+    let app = TestApp::new().await;
+
+    let response = app.get_openid_configuration().await;
+
+    assert_eq!(response.status(), 200);
+    let config = response
+        .json::<OpenIdConfiguration>()
+        .await
+        .expect("Could not deserialize response body to OpenIdConfiguration");
+    assert_eq!(config.issuer, test::OIDC_ISSUER);
+    assert_eq!(
+        config.authorization_endpoint,
+        format!("{}/oauth/authorize", test::OIDC_ISSUER)
+    );
+    assert_eq!(
+        config.token_endpoint,
+        format!("{}/oauth/token", test::OIDC_ISSUER)
+    );
+    assert_eq!(
+        config.jwks_uri,
+        format!("{}/.well-known/jwks.json", test::OIDC_ISSUER)
+    );
+    assert_eq!(
+        config.grant_types_supported,
+        ["authorization_code", "refresh_token", "client_credentials"]
+    );
+    assert_eq!(config.id_token_signing_alg_values_supported, ["RS256"]);
+    assert_eq!(config.code_challenge_methods_supported, ["S256"]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn id_tokens_carry_the_nonce_auth_time_and_password_amr() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    log_in(&app, &email).await;
+
+    let tokens = openid_tokens(&app, "openid email").await;
+
+    let claims = verify_id_token(&app, tokens.id_token.as_deref().unwrap()).await;
+    assert_eq!(claims.sub, app.user_id(&email).await);
+    assert_eq!(claims.email.as_deref(), Some(email.as_str()));
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+    assert_eq!(claims.amr, ["pwd"]);
+    assert!(claims.auth_time <= claims.iat);
+    assert_eq!(claims.exp - claims.iat, 600);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn id_tokens_report_email_two_factor_authentication() {
+    let app = TestApp::new().await;
+    app.expect_emails(1).await;
+    let email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    }))
+    .await;
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status(), 206);
+    let (login_attempt_id, code) = app
+        .two_fa_code_store
+        .write()
+        .await
+        .get_code(&Email::parse(Secret::new(email.clone())).unwrap())
+        .await
+        .unwrap();
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": code.as_ref().expose_secret()
+        }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let tokens = openid_tokens(&app, "openid").await;
+
+    let claims = verify_id_token(&app, tokens.id_token.as_deref().unwrap()).await;
+    assert_eq!(claims.amr, ["pwd", "otp", "mfa"]);
+    assert_eq!(claims.email, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn refreshed_id_tokens_keep_the_login_but_not_the_nonce() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+    let tokens = openid_tokens(&app, "openid").await;
+    let first = verify_id_token(&app, tokens.id_token.as_deref().unwrap()).await;
+
+    let response = app
+        .post_oauth(
+            "/oauth/token",
+            &[
+                ("grant_type", "refresh_token"),
+                (
+                    "refresh_token",
+                    tokens.refresh_token.as_ref().unwrap().expose_secret(),
+                ),
+            ],
+            CLIENT,
+        )
+        .await;
+
+    assert_eq!(response.status(), 200);
+    let tokens = response.json::<OAuthTokenResponse>().await.unwrap();
+    let refreshed = verify_id_token(&app, tokens.id_token.as_deref().unwrap()).await;
+    assert_eq!(refreshed.auth_time, first.auth_time);
+    assert_eq!(refreshed.amr, first.amr);
+    assert_eq!(refreshed.nonce, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn id_tokens_require_the_openid_scope() {
+    let app = TestApp::new().await;
+    log_in(&app, &get_random_email()).await;
+
+    let tokens = openid_tokens(&app, "email").await;
+
+    assert_eq!(tokens.id_token, None);
+    let response = get_userinfo(&app, tokens.access_token.expose_secret()).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn userinfo_returns_the_user_for_openid_access_tokens() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    log_in(&app, &email).await;
+    let tokens = openid_tokens(&app, "openid email").await;
+
+    let response = get_userinfo(&app, tokens.access_token.expose_secret()).await;
+
+    assert_eq!(response.status(), 200);
+    let userinfo = response
+        .json::<UserInfoResponse>()
+        .await
+        .expect("Could not deserialize response body to UserInfoResponse");
+    assert_eq!(userinfo.sub, app.user_id(&email).await);
+    assert_eq!(userinfo.email.as_deref(), Some(email.as_str()));
+
+    app.post_oauth(
+        "/oauth/revoke",
+        &[("token", tokens.access_token.expose_secret())],
+        CLIENT,
+    )
+    .await;
+    let response = get_userinfo(&app, tokens.access_token.expose_secret()).await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn userinfo_rejects_session_tokens() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    }))
+    .await;
+    let token = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "tokenDelivery": "body"
+        }))
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    assert_eq!(get_userinfo(&app, &token).await.status(), 401);
+    let response = reqwest::Client::new()
+        .get(format!("{}/userinfo", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
@@ -0,0 +1,661 @@
+use auth_service::{
+    domain::Email,
+    routes::ApiDoc,
+    utils::{pkce_challenge, test, JWT_COOKIE_NAME},
+};
+use reqwest::{Method, Response};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::Value;
+use utoipa::OpenApi;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn served_spec(app: &TestApp) -> Value {
+    let response = app
+        .http_client
+        .get(format!("{}/openapi.json", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), 200);
+
+    response.json().await.unwrap()
+}
+
+// Checks `value` against the subset of JSON Schema that utoipa emits for our types
+fn conforms(spec: &Value, schema: &Value, value: &Value) -> Result<(), String> {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/components/schemas/");
+        return conforms(spec, &spec["components"]["schemas"][name], value);
+    }
+    if let Some(schemas) = schema["allOf"].as_array() {
+        return schemas
+            .iter()
+            .try_for_each(|schema| conforms(spec, schema, value));
+    }
+    if let Some(schemas) = schema["oneOf"].as_array().or(schema["anyOf"].as_array()) {
+        return match schemas
+            .iter()
+            .any(|schema| conforms(spec, schema, value).is_ok())
+        {
+            true => Ok(()),
+            false => Err(format!("{} matches none of {}", value, schema)),
+        };
+    }
+
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let type_matches = |name: &str| match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    };
+    if !types.is_empty() && !types.iter().any(|name| type_matches(name)) {
+        return Err(format!("{} is not of type {:?}", value, types));
+    }
+    if let Some(allowed) = schema["enum"].as_array() {
+        if !allowed.contains(value) {
+            return Err(format!("{} is not one of {:?}", value, allowed));
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        for key in schema["required"].as_array().into_iter().flatten() {
+            let key = key.as_str().unwrap();
+            if !object.contains_key(key) {
+                return Err(format!("{} is missing required property {}", value, key));
+            }
+        }
+        for (key, property) in object {
+            match schema["properties"].get(key) {
+                Some(property_schema) => conforms(spec, property_schema, property)?,
+                None if schema["additionalProperties"].is_object() => {
+                    conforms(spec, &schema["additionalProperties"], property)?
+                }
+                None => {}
+            }
+        }
+    }
+    if let (Some(items), Some(schema)) = (value.as_array(), schema.get("items")) {
+        items
+            .iter()
+            .try_for_each(|item| conforms(spec, schema, item))?;
+    }
+
+    Ok(())
+}
+
+// Fails unless the spec documents the response's status for the operation and its body
+// matches the documented schema
+async fn assert_documented(spec: &Value, method: &str, path: &str, response: Response) {
+    let status = response.status().as_u16().to_string();
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let body = response.text().await.unwrap();
+
+    let documented = &spec["paths"][path][method]["responses"][&status];
+    assert!(
+        documented.is_object(),
+        "{} {} answered {}, which is not documented",
+        method,
+        path,
+        status
+    );
+
+    // Bodies documented for the token delivery modes are absent when the token is in a cookie
+    let Some(content) = documented["content"]
+        .as_object()
+        .filter(|_| !body.is_empty())
+    else {
+        return;
+    };
+    let media_type = content
+        .keys()
+        .find(|media_type| content_type.starts_with(media_type.as_str()))
+        .unwrap_or_else(|| {
+            panic!(
+                "{} {} {} answered {}, documented {:?}",
+                method,
+                path,
+                status,
+                content_type,
+                content.keys()
+            )
+        });
+
+    if media_type == "application/json" {
+        let value: Value = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("{} {} {} returned invalid JSON", method, path, status));
+        if let Err(e) = conforms(spec, &content[media_type]["schema"], &value) {
+            panic!("{} {} {}: {}", method, path, status, e);
+        }
+    }
+}
+
+#[tokio::test]
+async fn served_spec_is_the_generated_one() {
+    let app = TestApp::new().await;
+
+    let spec = served_spec(&app).await;
+
+    assert_eq!(spec, serde_json::to_value(ApiDoc::openapi()).unwrap());
+    assert_eq!(spec["openapi"], "3.1.0");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn docs_ui_embeds_the_spec() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/docs", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let body = response.text().await.unwrap();
+    assert!(body.contains("id=\"api-reference\""));
+    assert!(body.contains("\"/verify-2fa\""));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn every_documented_operation_is_routed() {
+    let app = TestApp::new().await;
+    let spec = served_spec(&app).await;
+
+    for (path, operations) in spec["paths"].as_object().unwrap() {
+        for method in operations.as_object().unwrap().keys() {
+            let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            let mut request = app
+                .http_client
+                .request(method.clone(), format!("{}{}", &app.address, path))
+                .bearer_auth(&app.admin_token);
+            if method == Method::POST {
+                request = request.json(&serde_json::json!({}));
+            }
+
+            let status = request.send().await.unwrap().status().as_u16();
+
+            assert!(
+                status != 404 && status != 405,
+                "{} {} is documented but answered {}",
+                method,
+                path,
+                status
+            );
+        }
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn handler_responses_match_the_spec() {
+    let app = TestApp::new().await;
+    let spec = served_spec(&app).await;
+    let email = get_random_email();
+    let two_fa_email = get_random_email();
+    let signup = |email: &str, requires_2fa: bool| serde_json::json!({ "email": email, "password": "Asdf1234@", "requires2FA": requires_2fa });
+    let login =
+        |email: &str, password: &str| serde_json::json!({ "email": email, "password": password });
+
+    let response = app.post_signup(&signup(&email, false)).await;
+    assert_documented(&spec, "post", "/signup", response).await;
+    let response = app.post_signup(&signup(&email, false)).await;
+    assert_documented(&spec, "post", "/signup", response).await;
+    let response = app.post_signup(&signup("invalid", false)).await;
+    assert_documented(&spec, "post", "/signup", response).await;
+    let response = app.post_signup(&serde_json::json!({})).await;
+    assert_documented(&spec, "post", "/signup", response).await;
+
+    let response = app.post_login(&login(&email, "wrong-password")).await;
+    assert_documented(&spec, "post", "/login", response).await;
+    let response = app.post_login(&login("invalid", "short")).await;
+    assert_documented(&spec, "post", "/login", response).await;
+    let response = app.post_login(&login(&email, "Asdf1234@")).await;
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    assert_documented(&spec, "post", "/login", response).await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_documented(&spec, "post", "/verify-token", response).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": "invalid" }))
+        .await;
+    assert_documented(&spec, "post", "/verify-token", response).await;
+
+    let response = app.post_logout().await;
+    assert_documented(&spec, "post", "/logout", response).await;
+    let response = app.post_logout().await;
+    assert_documented(&spec, "post", "/logout", response).await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "Asdf1234@", "tokenDelivery": "body" }))
+        .await;
+    let token = response.json::<Value>().await.unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let response = app.post_with_bearer("/verify-token", &token).await;
+    assert_documented(&spec, "post", "/verify-token", response).await;
+    let response = app.post_with_bearer("/logout", &token).await;
+    assert_documented(&spec, "post", "/logout", response).await;
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "Asdf1234@", "tokenDelivery": "body" }))
+        .await;
+    let token = response.json::<Value>().await.unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let client = Some((test::OAUTH_CLIENT_ID, test::OAUTH_CLIENT_SECRET));
+    let response = app
+        .post_oauth("/oauth/introspect", &[("token", &token)], client)
+        .await;
+    assert_documented(&spec, "post", "/oauth/introspect", response).await;
+    let response = app
+        .post_oauth("/oauth/revoke", &[("token", &token)], client)
+        .await;
+    assert_documented(&spec, "post", "/oauth/revoke", response).await;
+    let response = app
+        .post_oauth("/oauth/introspect", &[("token", &token)], client)
+        .await;
+    assert_documented(&spec, "post", "/oauth/introspect", response).await;
+    let response = app.post_oauth("/oauth/revoke", &[], client).await;
+    assert_documented(&spec, "post", "/oauth/revoke", response).await;
+    let response = app
+        .post_oauth("/oauth/introspect", &[("token", &token)], None)
+        .await;
+    assert_documented(&spec, "post", "/oauth/introspect", response).await;
+
+    let code_verifier = "a".repeat(43);
+    let code_challenge = pkce_challenge(&code_verifier);
+    let authorize = |client_id: &'static str, response_type: &'static str| {
+        vec![
+            ("response_type", response_type),
+            ("client_id", client_id),
+            ("redirect_uri", test::OAUTH_REDIRECT_URI),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+            ("scope", "openid email"),
+        ]
+    };
+    let response = app
+        .get_oauth_authorize(&authorize(test::OAUTH_CLIENT_ID, "code"))
+        .await;
+    assert_documented(&spec, "get", "/oauth/authorize", response).await;
+    let response = app
+        .get_oauth_authorize(&authorize(test::OAUTH_CLIENT_ID, "token"))
+        .await;
+    assert_documented(&spec, "get", "/oauth/authorize", response).await;
+    let response = app.get_oauth_authorize(&authorize("unknown", "code")).await;
+    assert_documented(&spec, "get", "/oauth/authorize", response).await;
+    let mut consent = authorize(test::OAUTH_CLIENT_ID, "code");
+    consent.push(("decision", "allow"));
+    let response = app.post_oauth_authorize(&consent).await;
+    assert_documented(&spec, "post", "/oauth/authorize", response).await;
+    let oauth_email = get_random_email();
+    app.post_signup(&signup(&oauth_email, false)).await;
+    app.post_login(&login(&oauth_email, "Asdf1234@")).await;
+    let response = app.post_oauth_authorize(&consent).await;
+    let location = response.headers()["location"].to_str().unwrap().to_owned();
+    assert_documented(&spec, "post", "/oauth/authorize", response).await;
+    let code = location.split("code=").nth(1).unwrap().to_owned();
+    let exchange = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", test::OAUTH_REDIRECT_URI),
+        ("code_verifier", code_verifier.as_str()),
+    ];
+    let response = app.post_oauth("/oauth/token", &exchange, client).await;
+    assert_documented(&spec, "post", "/oauth/token", response).await;
+    let response = app.post_oauth("/oauth/token", &exchange, client).await;
+    assert_documented(&spec, "post", "/oauth/token", response).await;
+    let response = app.post_oauth("/oauth/token", &exchange, None).await;
+    assert_documented(&spec, "post", "/oauth/token", response).await;
+
+    let response = app.get_openid_configuration().await;
+    assert_documented(&spec, "get", "/.well-known/openid-configuration", response).await;
+    let response = app.get_jwks().await;
+    assert_documented(&spec, "get", "/.well-known/jwks.json", response).await;
+    let location = app.post_oauth_authorize(&consent).await.headers()["location"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let code = location.split("code=").nth(1).unwrap().to_owned();
+    let exchange = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", test::OAUTH_REDIRECT_URI),
+        ("code_verifier", code_verifier.as_str()),
+    ];
+    let tokens = app
+        .post_oauth("/oauth/token", &exchange, client)
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let userinfo = |token: Option<&str>| {
+        let mut request = reqwest::Client::new().get(format!("{}/userinfo", &app.address));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send()
+    };
+    let response = userinfo(Some(access_token)).await.unwrap();
+    assert_documented(&spec, "get", "/userinfo", response).await;
+    let response = userinfo(Some("invalid")).await.unwrap();
+    assert_documented(&spec, "get", "/userinfo", response).await;
+    let response = userinfo(None).await.unwrap();
+    assert_documented(&spec, "get", "/userinfo", response).await;
+    app.post_logout().await;
+
+    app.post_signup(&signup(&two_fa_email, true)).await;
+    // Two 2FA codes, then the email change confirmation and notification
+    app.expect_emails(4).await;
+    let response = app.post_login(&login(&two_fa_email, "Asdf1234@")).await;
+    assert_documented(&spec, "post", "/login", response).await;
+    let (login_attempt_id, two_fa_code) = app
+        .two_fa_code_store
+        .write()
+        .await
+        .get_code(&Email::parse(Secret::new(two_fa_email.clone())).unwrap())
+        .await
+        .unwrap();
+    let verify = |code: &str| {
+        serde_json::json!({
+            "email": two_fa_email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": code
+        })
+    };
+    let response = app.post_verify_2fa(&verify("000000")).await;
+    assert_documented(&spec, "post", "/verify-2fa", response).await;
+    let response = app.post_verify_2fa(&verify("abc")).await;
+    assert_documented(&spec, "post", "/verify-2fa", response).await;
+    let response = app
+        .post_verify_2fa(&verify(two_fa_code.as_ref().expose_secret()))
+        .await;
+    assert_documented(&spec, "post", "/verify-2fa", response).await;
+    app.post_login(&login(&two_fa_email, "Asdf1234@")).await;
+    let (login_attempt_id, two_fa_code) = app
+        .two_fa_code_store
+        .write()
+        .await
+        .get_code(&Email::parse(Secret::new(two_fa_email.clone())).unwrap())
+        .await
+        .unwrap();
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": two_fa_email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+            "tokenDelivery": "body"
+        }))
+        .await;
+    assert_documented(&spec, "post", "/verify-2fa", response).await;
+
+    let response = app.get_health_live().await;
+    assert_documented(&spec, "get", "/health/live", response).await;
+    let response = app.get_health_ready().await;
+    assert_documented(&spec, "get", "/health/ready", response).await;
+    let response = app.get_metrics().await;
+    assert_documented(&spec, "get", "/metrics", response).await;
+
+    let response = app
+        .get_audit_events(&[("user", &email)], Some(&app.admin_token))
+        .await;
+    assert_documented(&spec, "get", "/admin/audit-events", response).await;
+    let response = app.get_audit_events(&[], Some("wrong")).await;
+    assert_documented(&spec, "get", "/admin/audit-events", response).await;
+    let response = app.get_audit_events(&[], None).await;
+    assert_documented(&spec, "get", "/admin/audit-events", response).await;
+
+    let client =
+        serde_json::json!({ "client_name": "App", "redirect_uris": ["https://example.com/cb"] });
+    let response = app.post_oauth_client(&client, Some(&app.admin_token)).await;
+    assert_documented(&spec, "post", "/admin/oauth/clients", response).await;
+    let response = app
+        .post_oauth_client(
+            &serde_json::json!({ "client_name": "", "redirect_uris": [] }),
+            Some(&app.admin_token),
+        )
+        .await;
+    assert_documented(&spec, "post", "/admin/oauth/clients", response).await;
+    let response = app.post_oauth_client(&client, Some("wrong")).await;
+    assert_documented(&spec, "post", "/admin/oauth/clients", response).await;
+
+    let service = app
+        .post_oauth_client(
+            &serde_json::json!({
+                "client_name": "Job",
+                "redirect_uris": [],
+                "scopes": ["reports:read"]
+            }),
+            Some(&app.admin_token),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    let service = Some((
+        service["client_id"].as_str().unwrap(),
+        service["client_secret"].as_str().unwrap(),
+    ));
+    let grant = |scope: &'static str| [("grant_type", "client_credentials"), ("scope", scope)];
+    let response = app
+        .post_oauth("/oauth/token", &grant("reports:read"), service)
+        .await;
+    let service_token = response.json::<Value>().await.unwrap();
+    let response = app
+        .post_oauth("/oauth/token", &grant("reports:read"), service)
+        .await;
+    assert_documented(&spec, "post", "/oauth/token", response).await;
+    let response = app
+        .post_oauth("/oauth/token", &grant("reports:write"), service)
+        .await;
+    assert_documented(&spec, "post", "/oauth/token", response).await;
+    let response = app
+        .post_oauth(
+            "/oauth/introspect",
+            &[("token", service_token["access_token"].as_str().unwrap())],
+            service,
+        )
+        .await;
+    assert_documented(&spec, "post", "/oauth/introspect", response).await;
+
+    // A new user, as the first one's sessions were revoked
+    let key_email = get_random_email();
+    app.post_signup(&signup(&key_email, false)).await;
+    let response = app
+        .post_login(&serde_json::json!({ "email": key_email, "password": "Asdf1234@", "tokenDelivery": "body" }))
+        .await;
+    let token = response.json::<Value>().await.unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let key = serde_json::json!({ "name": "CI", "scopes": ["deploy"] });
+    let api_key = app
+        .post_api_key(&key, &token)
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    let response = app.post_api_key(&key, &token).await;
+    assert_documented(&spec, "post", "/api-keys", response).await;
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "CI", "scopes": [] }), &token)
+        .await;
+    assert_documented(&spec, "post", "/api-keys", response).await;
+    let response = app
+        .post_api_key(&key, api_key["key"].as_str().unwrap())
+        .await;
+    assert_documented(&spec, "post", "/api-keys", response).await;
+    let response = app.get_api_keys(&token).await;
+    assert_documented(&spec, "get", "/api-keys", response).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": api_key["key"] }))
+        .await;
+    assert_documented(&spec, "post", "/verify-token", response).await;
+    let id = api_key["id"].as_str().unwrap();
+    let response = app.delete_api_key(id, &token).await;
+    assert_documented(&spec, "delete", "/api-keys/{id}", response).await;
+    let response = app.delete_api_key(id, &token).await;
+    assert_documented(&spec, "delete", "/api-keys/{id}", response).await;
+
+    let admin = Some(app.admin_token.as_str());
+    let role = serde_json::json!({ "name": "admin", "permissions": ["reports:read"] });
+    let response = app.post_role(&role, admin).await;
+    assert_documented(&spec, "post", "/admin/roles", response).await;
+    let response = app.post_role(&role, admin).await;
+    assert_documented(&spec, "post", "/admin/roles", response).await;
+    let response = app
+        .post_role(&serde_json::json!({ "name": "" }), admin)
+        .await;
+    assert_documented(&spec, "post", "/admin/roles", response).await;
+    let response = app.get_roles(admin).await;
+    assert_documented(&spec, "get", "/admin/roles", response).await;
+    let response = app.get_roles(Some(&token)).await;
+    assert_documented(&spec, "get", "/admin/roles", response).await;
+    let user_roles = "/admin/users/{user}/roles/{role}";
+    let response = app.user_role(Method::PUT, &key_email, "admin", admin).await;
+    assert_documented(&spec, "put", user_roles, response).await;
+    let response = app.user_role(Method::PUT, &key_email, "owner", admin).await;
+    assert_documented(&spec, "put", user_roles, response).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token, "permission": "reports:read" }))
+        .await;
+    assert_documented(&spec, "post", "/verify-token", response).await;
+    let response = app
+        .user_role(Method::DELETE, &key_email, "admin", admin)
+        .await;
+    assert_documented(&spec, "delete", user_roles, response).await;
+    let response = app
+        .user_role(Method::DELETE, &key_email, "admin", admin)
+        .await;
+    assert_documented(&spec, "delete", user_roles, response).await;
+
+    let response = app.get_admin_users(&[("search", &key_email)], admin).await;
+    assert_documented(&spec, "get", "/admin/users", response).await;
+    let response = app.get_admin_users(&[], None).await;
+    assert_documented(&spec, "get", "/admin/users", response).await;
+    let user = "/admin/users/{user}";
+    let response = app.admin_user(Method::GET, &key_email, "", None).await;
+    assert_documented(&spec, "get", user, response).await;
+    let response = app.admin_user(Method::GET, "not-an-email", "", None).await;
+    assert_documented(&spec, "get", user, response).await;
+    let locked = serde_json::json!({ "locked": true });
+    let response = app
+        .admin_user(Method::PATCH, &key_email, "", Some(&locked))
+        .await;
+    assert_documented(&spec, "patch", user, response).await;
+    let response = app
+        .admin_user(Method::PATCH, &get_random_email(), "", Some(&locked))
+        .await;
+    assert_documented(&spec, "patch", user, response).await;
+    let response = app
+        .post_login(&serde_json::json!({ "email": key_email, "password": "password123" }))
+        .await;
+    assert_documented(&spec, "post", "/login", response).await;
+    let sessions = "/admin/users/{user}/sessions";
+    let response = app
+        .admin_user(Method::DELETE, &key_email, "/sessions", None)
+        .await;
+    assert_documented(&spec, "delete", sessions, response).await;
+    let response = app.admin_user(Method::DELETE, &key_email, "", None).await;
+    assert_documented(&spec, "delete", user, response).await;
+    let response = app.admin_user(Method::DELETE, &key_email, "", None).await;
+    assert_documented(&spec, "delete", user, response).await;
+    let response = app
+        .admin_user(Method::DELETE, &key_email, "/sessions", None)
+        .await;
+    assert_documented(&spec, "delete", sessions, response).await;
+
+    let account_email = get_random_email();
+    app.post_signup(&signup(&account_email, false)).await;
+    let response = app
+        .post_login(&serde_json::json!({ "email": account_email, "password": "Asdf1234@", "tokenDelivery": "body" }))
+        .await;
+    let token = response.json::<Value>().await.unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let password = |current: &str, new: &str| serde_json::json!({ "current_password": current, "new_password": new, "tokenDelivery": "body" });
+    let response = app
+        .post_account(
+            "/password",
+            &password("wrong-password", "Qwer5678@"),
+            Some(&token),
+        )
+        .await;
+    assert_documented(&spec, "post", "/account/password", response).await;
+    let response = app
+        .post_account("/password", &password("Asdf1234@", "short"), Some(&token))
+        .await;
+    assert_documented(&spec, "post", "/account/password", response).await;
+    let response = app
+        .post_account(
+            "/password",
+            &password("Asdf1234@", "Qwer5678@"),
+            Some(&token),
+        )
+        .await;
+    let token = response.json::<Value>().await.unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let response = app
+        .post_account("/password", &password("Qwer5678@", "Zxcv9012@"), None)
+        .await;
+    assert_documented(&spec, "post", "/account/password", response).await;
+    let change =
+        |new_email: &str| serde_json::json!({ "new_email": new_email, "password": "Qwer5678@" });
+    let response = app
+        .post_account("/email", &change(&email), Some(&token))
+        .await;
+    assert_documented(&spec, "post", "/account/email", response).await;
+    let response = app
+        .post_account("/email", &change("invalid"), Some(&token))
+        .await;
+    assert_documented(&spec, "post", "/account/email", response).await;
+    let new_email = get_random_email();
+    let response = app
+        .post_account("/email", &change(&new_email), Some(&token))
+        .await;
+    assert_documented(&spec, "post", "/account/email", response).await;
+    let confirmation = app.emails_sent_to(&new_email).await[0]
+        .lines()
+        .last()
+        .unwrap()
+        .to_owned();
+    let confirm = serde_json::json!({ "token": confirmation });
+    let response = app.post_account("/email/confirm", &confirm, None).await;
+    assert_documented(&spec, "post", "/account/email/confirm", response).await;
+    let response = app.post_account("/email/confirm", &confirm, None).await;
+    assert_documented(&spec, "post", "/account/email/confirm", response).await;
+    app.clean_up().await;
+}
@@ -0,0 +1,67 @@
+use auth_service::ErrorResponse;
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[tokio::test]
+async fn responses_carry_a_generated_request_id() {
+    let app = TestApp::new().await;
+
+    let first = app.get_root().await;
+    let second = app.get_root().await;
+
+    let first_id = first.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+    let second_id = second.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+    assert!(Uuid::parse_str(first_id).is_ok());
+    assert_ne!(first_id, second_id);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn inbound_request_id_is_echoed() {
+    let app = TestApp::new().await;
+
+    let response = app.get_root_with_request_id("support-ticket-1234").await;
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()[REQUEST_ID_HEADER], "support-ticket-1234");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn inbound_request_id_is_echoed_on_errors() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("X-Request-Id", "failed-login-42")
+        .json(&serde_json::json!({
+            "email": "not-an-email",
+            "password": "password123",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.headers()[REQUEST_ID_HEADER], "failed-login-42");
+    assert!(response.json::<ErrorResponse>().await.is_ok());
+
+    // Rejected before reaching a handler
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("X-Request-Id", "malformed-body-43")
+        .header("Content-Type", "application/json")
+        .body("{")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.headers()[REQUEST_ID_HEADER], "malformed-body-43");
+    app.clean_up().await;
+}
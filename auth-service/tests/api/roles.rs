@@ -0,0 +1,351 @@
+use auth_service::{
+    routes::{AuditEventsResponse, CreateApiKeyResponse, ListRolesResponse, VerifyTokenResponse},
+    ErrorResponse,
+};
+use reqwest::Method;
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth::logged_in_token,
+};
+
+async fn create_role(app: &TestApp, name: &str, permissions: &[&str]) {
+    let response = app
+        .post_role(
+            &serde_json::json!({ "name": name, "permissions": permissions }),
+            Some(&app.admin_token),
+        )
+        .await;
+    assert_eq!(response.status(), 201);
+}
+
+async fn error_code(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .code
+}
+
+#[tokio::test]
+async fn roles_are_created_and_listed_by_name() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_role(
+            &serde_json::json!({
+                "name": "auditor",
+                "permissions": ["reports:read", "audit:read", "reports:read"]
+            }),
+            Some(&app.admin_token),
+        )
+        .await;
+    assert_eq!(response.status(), 201);
+    create_role(&app, "admin", &["reports:read", "users:write"]).await;
+
+    let response = app.get_roles(Some(&app.admin_token)).await;
+    assert_eq!(response.status(), 200);
+    let listed = response
+        .json::<ListRolesResponse>()
+        .await
+        .expect("Could not deserialize response body to ListRolesResponse");
+    let roles: Vec<_> = listed
+        .roles
+        .iter()
+        .map(|role| (role.name.as_str(), role.permissions.clone()))
+        .collect();
+    assert_eq!(
+        roles,
+        vec![
+            (
+                "admin",
+                vec!["reports:read".to_owned(), "users:write".to_owned()]
+            ),
+            ("administrator", vec!["admin".to_owned()]),
+            (
+                "auditor",
+                vec!["audit:read".to_owned(), "reports:read".to_owned()]
+            ),
+        ]
+    );
+
+    let response = app
+        .post_role(
+            &serde_json::json!({ "name": "admin" }),
+            Some(&app.admin_token),
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+    assert_eq!(error_code(response).await, "role_already_exists");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn invalid_roles_are_rejected() {
+    let app = TestApp::new().await;
+
+    let test_cases = [
+        (serde_json::json!({ "name": "" }), "name"),
+        (serde_json::json!({ "name": "super admin" }), "name"),
+        (
+            serde_json::json!({ "name": "admin", "permissions": ["read all"] }),
+            "permissions",
+        ),
+    ];
+    for (body, field) in test_cases {
+        let response = app.post_role(&body, Some(&app.admin_token)).await;
+        assert_eq!(response.status(), 400, "Failed for input: {:?}", body);
+        let error = response.json::<ErrorResponse>().await.unwrap();
+        assert_eq!(error.code, "invalid_request");
+        assert_eq!(error.errors[0].field, field);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn role_endpoints_require_an_admin_session() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+
+    let response = app
+        .post_role(&serde_json::json!({ "name": "admin" }), None)
+        .await;
+    assert_eq!(response.status(), 400);
+    let response = app.get_roles(Some("wrong-token")).await;
+    assert_eq!(response.status(), 401);
+    // Every user has a session, but only roles with the admin permission may manage others
+    let response = app
+        .user_role(Method::PUT, &email, "admin", Some(&token))
+        .await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(error_code(response).await, "forbidden");
+
+    create_role(&app, "admin", &["admin"]).await;
+    app.user_role(Method::PUT, &email, "admin", Some(&app.admin_token))
+        .await;
+    // Roles are read at login
+    let response = app.get_roles(Some(&token)).await;
+    assert_eq!(response.status(), 403);
+    let token = logged_in_token(&app, &email).await;
+    let response = app.get_roles(Some(&token)).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn assigning_needs_an_existing_user_and_role() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    logged_in_token(&app, &email).await;
+    create_role(&app, "admin", &[]).await;
+
+    let response = app
+        .user_role(
+            Method::PUT,
+            &get_random_email(),
+            "admin",
+            Some(&app.admin_token),
+        )
+        .await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(error_code(response).await, "user_not_found");
+
+    let response = app
+        .user_role(Method::PUT, &email, "owner", Some(&app.admin_token))
+        .await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(error_code(response).await, "role_not_found");
+
+    let response = app
+        .user_role(Method::PUT, "not-an-email", "admin", Some(&app.admin_token))
+        .await;
+    assert_eq!(response.status(), 400);
+
+    // Assigning twice is harmless, but only assigned roles can be unassigned
+    for _ in 0..2 {
+        let response = app
+            .user_role(Method::PUT, &email, "admin", Some(&app.admin_token))
+            .await;
+        assert_eq!(response.status(), 204);
+    }
+    let response = app
+        .user_role(Method::DELETE, &email, "admin", Some(&app.admin_token))
+        .await;
+    assert_eq!(response.status(), 204);
+    let response = app
+        .user_role(Method::DELETE, &email, "admin", Some(&app.admin_token))
+        .await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(error_code(response).await, "role_not_found");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_tokens_carry_the_roles_assigned_before_the_login() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let before = logged_in_token(&app, &email).await;
+    create_role(&app, "admin", &["reports:read"]).await;
+    create_role(&app, "support", &[]).await;
+    app.user_role(Method::PUT, &email, "support", Some(&app.admin_token))
+        .await;
+    app.user_role(Method::PUT, &email, "admin", Some(&app.admin_token))
+        .await;
+
+    let verified = app
+        .post_verify_token(&serde_json::json!({ "token": before }))
+        .await
+        .json::<VerifyTokenResponse>()
+        .await
+        .unwrap();
+    assert!(verified.roles.is_empty());
+
+    // Tokens issued within the same second are identical, so wait for a new one
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    let after = logged_in_token(&app, &email).await;
+    let verified = app
+        .post_verify_token(&serde_json::json!({ "token": after }))
+        .await
+        .json::<VerifyTokenResponse>()
+        .await
+        .unwrap();
+    assert_eq!(verified.roles, vec!["admin", "support"]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_token_checks_a_required_permission() {
+    let app = TestApp::new().await;
+    let admin_email = get_random_email();
+    let user_email = get_random_email();
+    create_role(&app, "admin", &["reports:read"]).await;
+    app.post_signup(&serde_json::json!({
+        "email": admin_email,
+        "password": "password123",
+        "requires2FA": false
+    }))
+    .await;
+    app.user_role(Method::PUT, &admin_email, "admin", Some(&app.admin_token))
+        .await;
+    let admin_token = logged_in_token(&app, &admin_email).await;
+    let user_token = logged_in_token(&app, &user_email).await;
+
+    let response = app
+        .post_verify_token(
+            &serde_json::json!({ "token": admin_token, "permission": "reports:read" }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = app
+        .post_verify_token(
+            &serde_json::json!({ "token": admin_token, "permission": "users:write" }),
+        )
+        .await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(error_code(response).await, "forbidden");
+    let response = app
+        .post_verify_token(
+            &serde_json::json!({ "token": user_token, "permission": "reports:read" }),
+        )
+        .await;
+    assert_eq!(response.status(), 403);
+
+    // With a bearer token, the permission is a query parameter
+    let response = app
+        .post_with_bearer("/verify-token?permission=reports:read", &admin_token)
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = app
+        .post_with_bearer("/verify-token?permission=reports:read", &user_token)
+        .await;
+    assert_eq!(response.status(), 403);
+
+    // Invalid tokens are still a 401, whatever the permission
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": "invalid", "permission": "reports:read" }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let denied: Vec<_> = app
+        .get_audit_events(&[("type", "permission_denied")], Some(&app.admin_token))
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events
+        .into_iter()
+        .map(|record| record.event.email().map(str::to_owned))
+        .collect();
+    assert_eq!(
+        denied,
+        vec![
+            Some(user_email.clone()),
+            Some(user_email),
+            Some(admin_email)
+        ]
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn api_keys_act_with_their_owners_current_roles() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = logged_in_token(&app, &email).await;
+    create_role(&app, "admin", &["reports:read"]).await;
+    let key = app
+        .post_api_key(
+            &serde_json::json!({ "name": "CI", "scopes": ["reports"] }),
+            &token,
+        )
+        .await
+        .json::<CreateApiKeyResponse>()
+        .await
+        .unwrap()
+        .key;
+    let check = serde_json::json!({ "token": key, "permission": "reports:read" });
+
+    let response = app.post_verify_token(&check).await;
+    assert_eq!(response.status(), 403);
+
+    // Unlike a session token, the key needs no new login to pick up the role
+    app.user_role(Method::PUT, &email, "admin", Some(&app.admin_token))
+        .await;
+    let response = app.post_verify_token(&check).await;
+    assert_eq!(response.status(), 200);
+    let verified = response.json::<VerifyTokenResponse>().await.unwrap();
+    assert_eq!(verified.roles, vec!["admin"]);
+
+    app.user_role(Method::DELETE, &email, "admin", Some(&app.admin_token))
+        .await;
+    let response = app.post_verify_token(&check).await;
+    assert_eq!(response.status(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn role_changes_are_audited() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    logged_in_token(&app, &email).await;
+    create_role(&app, "admin", &[]).await;
+    app.user_role(Method::PUT, &email, "admin", Some(&app.admin_token))
+        .await;
+    app.user_role(Method::DELETE, &email, "admin", Some(&app.admin_token))
+        .await;
+
+    let types: Vec<_> = app
+        .get_audit_events(&[("user", &email)], Some(&app.admin_token))
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events
+        .into_iter()
+        .map(|record| record.event.event_type().to_owned())
+        .take(2)
+        .collect();
+    assert_eq!(types, vec!["role_unassigned", "role_assigned"]);
+    app.clean_up().await;
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Opens a raw connection and sends a signup request whose body is still missing its last byte
+async fn start_signup_request(app: &TestApp) -> (TcpStream, Vec<u8>) {
+    let body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "Asdf1234@",
+        "requires2FA": false
+    })
+    .to_string();
+    let request = format!(
+        "POST /signup HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let (head, rest) = request.as_bytes().split_at(request.len() - 1);
+
+    let address = app.address.trim_start_matches("http://");
+    let mut stream = TcpStream::connect(address)
+        .await
+        .expect("Failed to connect");
+    stream.write_all(head).await.expect("Failed to write");
+    // Give the server time to read the headers so the request counts as in flight
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    (stream, rest.to_vec())
+}
+
+#[tokio::test]
+async fn shutdown_stops_accepting_connections() {
+    let app = TestApp::new().await;
+    assert_eq!(app.get_root().await.status(), 200);
+
+    app.shutdown().await;
+
+    let result = reqwest::Client::new()
+        .get(format!("{}/", &app.address))
+        .send()
+        .await;
+    assert!(result.is_err(), "Request succeeded after shutdown");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn shutdown_drains_in_flight_requests() {
+    let app = TestApp::new().await;
+    let (mut stream, rest) = start_signup_request(&app).await;
+
+    let finish_request = async {
+        // Shutdown has started by now, but the request must still be answered
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(app.shutdown_handle.is_shutdown());
+        stream.write_all(&rest).await.expect("Failed to write");
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .await
+            .expect("Failed to read response");
+        response
+    };
+
+    let ((), response) = tokio::join!(app.shutdown(), finish_request);
+
+    assert!(
+        response.starts_with("HTTP/1.1 201"),
+        "Unexpected response: {}",
+        response
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn shutdown_gives_up_on_requests_after_the_timeout() {
+    let app = TestApp::new().await;
+    // Never completed, so draining can only end through the shutdown timeout
+    let (_stream, _rest) = start_signup_request(&app).await;
+
+    tokio::time::timeout(Duration::from_secs(5), app.shutdown())
+        .await
+        .expect("Shutdown did not respect its timeout");
+    app.clean_up().await;
+}
@@ -1,13 +1,10 @@
 use auth_service::{
     domain::{Email, LoginAttemptId, TwoFACode},
+    routes::TokenResponse,
     utils::JWT_COOKIE_NAME,
 };
 use secrecy::{ExposeSecret, Secret};
 use uuid::Uuid;
-use wiremock::{
-    matchers::{method, path},
-    Mock, ResponseTemplate,
-};
 
 use crate::helpers::{get_random_email, TestApp};
 
@@ -15,13 +12,8 @@ use crate::helpers::{get_random_email, TestApp};
 async fn verify_2fa_returns_200_if_correct_code() {
     let app: TestApp = TestApp::new().await;
 
-    // Set up mock BEFORE calling setup_user_for_verify_2fa (which sends email during login)
-    Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .expect(1)
-        .mount(&app.email_server)
-        .await;
+    // Set up the email expectation BEFORE calling setup_user_for_verify_2fa (which sends email during login)
+    app.expect_emails(1).await;
 
     let random_email = get_random_email();
     let (login_attempt_id, two_fa_code) =
@@ -46,6 +38,35 @@ async fn verify_2fa_returns_200_if_correct_code() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn verify_2fa_returns_token_in_body_if_requested() {
+    let app: TestApp = TestApp::new().await;
+    app.expect_emails(1).await;
+    let random_email = get_random_email();
+    let (login_attempt_id, two_fa_code) =
+        setup_user_for_verify_2fa(&app, random_email.clone()).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+            "tokenDelivery": "body"
+        }))
+        .await;
+
+    assert_eq!(response.status(), 200);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+    let body = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(body.token.split('.').count(), 3);
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn verify_2fa_returns_400_if_invalid_input() {
     let app = TestApp::new().await;
@@ -97,13 +118,8 @@ async fn verify_2fa_returns_400_if_invalid_input() {
 async fn verify_2fa_returns_401_if_incorect_credentials() {
     let app: TestApp = TestApp::new().await;
 
-    // Set up mock BEFORE calling setup_user_for_verify_2fa (which sends email during login)
-    Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .expect(1)
-        .mount(&app.email_server)
-        .await;
+    // Set up the email expectation BEFORE calling setup_user_for_verify_2fa (which sends email during login)
+    app.expect_emails(1).await;
 
     let random_email = get_random_email();
     let (login_attempt_id, two_fa_code) =
@@ -149,13 +165,8 @@ async fn verify_2fa_returns_401_if_incorect_credentials() {
 async fn verify_2fa_returns_401_if_old_code() {
     let app: TestApp = TestApp::new().await;
 
-    // Set up mock BEFORE any login calls (first login in setup + second login below = 2 emails)
-    Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .expect(2)
-        .mount(&app.email_server)
-        .await;
+    // Set up the email expectation BEFORE any login calls (first login in setup + second login below = 2 emails)
+    app.expect_emails(2).await;
 
     let random_email = get_random_email();
     let (first_login_attempt_id, first_two_fa_code) =
@@ -186,6 +197,7 @@ async fn verify_2fa_returns_401_if_old_code() {
 #[tokio::test]
 async fn should_return_401_if_same_code_twice() {
     let app: TestApp = TestApp::new().await;
+    app.expect_emails(1).await;
 
     let random_email = get_random_email();
     let (login_attempt_id, two_fa_code) =
@@ -273,7 +285,7 @@ async fn setup_user_for_verify_2fa(app: &TestApp, email: String) -> (LoginAttemp
     let _ = app.post_login(&login_user).await;
 
     let example_email = Email::parse(Secret::new(email.clone()));
-    get_two_fa_code_and_login_attemp(&app, example_email.as_ref().unwrap()).await
+    get_two_fa_code_and_login_attemp(app, example_email.as_ref().unwrap()).await
 }
 
 // To avoid locking the resource I am recreating this function to have a smaller scope
@@ -101,6 +101,66 @@ async fn verify_token_returns_401_for_invalid_token() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn verify_token_checks_own_token_without_body() {
+    let app = TestApp::new().await;
+    let random_email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    }))
+    .await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123"
+        }))
+        .await;
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app.post_with_bearer("/verify-token", &token).await;
+    assert_eq!(response.status(), 200);
+
+    // The cookie set by the login is sent by the app's client
+    let response = app
+        .http_client
+        .post(format!("{}/verify-token", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), 200);
+
+    let response = app.post_with_bearer("/verify-token", "invalid").await;
+    assert_eq!(response.status(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_token_returns_400_without_body_or_token() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/verify-token", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), 400);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.code, "missing_token");
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn verify_token_returns_422_for_malformed_input() {
     let app = TestApp::new().await;
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// The legacy error body. `code` and `errors` were added later, so older clients that only
+// read `error` keep working.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    #[serde(default)]
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+// A request field that failed validation, named as in the JSON body, e.g. `2FACode`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+// Values of `FieldError::code`
+pub mod field_error {
+    pub const INVALID_FORMAT: &str = "invalid_format";
+    pub const TOO_WEAK: &str = "too_weak";
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl ToString) -> Self {
+        Self {
+            field: field.to_owned(),
+            code: code.to_owned(),
+            message: message.to_string(),
+        }
+    }
+}
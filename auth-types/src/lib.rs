@@ -0,0 +1,16 @@
+// The token claims and API bodies shared by the auth service and the crates that talk to it
+mod error;
+mod login;
+mod secret;
+mod signup;
+mod token;
+mod verify_2fa;
+mod verify_token;
+
+pub use error::*;
+pub use login::*;
+pub use secret::*;
+pub use signup::*;
+pub use token::*;
+pub use verify_2fa::*;
+pub use verify_token::*;
@@ -0,0 +1,49 @@
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::serialize_secret;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LoginRequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = "email")]
+    pub email: Secret<String>,
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = Password)]
+    pub password: Secret<String>,
+    #[serde(default, rename = "tokenDelivery")]
+    pub token_delivery: TokenDelivery,
+}
+
+// Where a successful login puts the JWT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenDelivery {
+    // The `jwt` cookie, for browsers
+    #[default]
+    Cookie,
+    // A `TokenResponse` body, for clients that cannot hold cookies
+    Body,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct TokenResponse {
+    // Send as `Authorization: Bearer <token>`
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct LoginResponse2FA {
+    pub message: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    RegularAuth,
+    TwoFactorAuth(LoginResponse2FA),
+    Token(TokenResponse),
+}
@@ -0,0 +1,10 @@
+use secrecy::{ExposeSecret, Secret};
+use serde::Serializer;
+
+// `Secret` deliberately has no `Serialize`; request bodies that clients send opt in per field
+pub fn serialize_secret<S: Serializer>(
+    secret: &Secret<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(secret.expose_secret())
+}
@@ -0,0 +1,23 @@
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::serialize_secret;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SignupRequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = "email")]
+    pub email: Secret<String>,
+    // At least 8 characters
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = Password, min_length = 8)]
+    pub password: Secret<String>,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SignupResponse {
+    pub message: String,
+}
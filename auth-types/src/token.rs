@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// The cookie holding a browser's session token
+pub const JWT_COOKIE_NAME: &str = "jwt";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Claims {
+    // The user's id, or the client id of a service token
+    pub sub: String,
+    pub exp: usize,
+    // Zero for tokens issued before `iat` was added
+    #[serde(default)]
+    pub iat: usize,
+    // `iat` in microseconds, which tells a session issued right after its user's sessions
+    // were revoked from the ones revoked. Zero for tokens issued before it was added.
+    #[serde(default)]
+    pub iat_us: u64,
+    // When the user of a renewed session, e.g. one replaced by a password change, logged in.
+    // Zero for other tokens, including sessions issued at login as that is their `iat`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub auth_time: usize,
+    // The OAuth client the token was issued to; user sessions have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // Space-separated OAuth scopes granted to the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // How a session's user logged in, e.g. `pwd` or `pwd otp mfa`; passed on to ID tokens
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    // The user's roles when they logged in; only session tokens carry them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    // Only written for service tokens, so every token issued before it was added is a user's
+    #[serde(default, skip_serializing_if = "TokenUse::is_user")]
+    pub token_use: TokenUse,
+}
+
+impl Claims {
+    // A machine client's own token: `sub` is a client id, not an email
+    pub fn is_service(&self) -> bool {
+        self.token_use == TokenUse::Service
+    }
+
+    // In seconds since the epoch; sessions without an `auth_time` were issued at login
+    pub fn authenticated_at(&self) -> usize {
+        match self.auth_time {
+            0 => self.iat,
+            auth_time => auth_time,
+        }
+    }
+
+    // In microseconds since the epoch
+    pub fn issued_at(&self) -> u64 {
+        match self.iat_us {
+            0 => self.iat as u64 * 1_000_000,
+            iat_us => iat_us,
+        }
+    }
+}
+
+// Who a token was issued to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenUse {
+    // A person, directly or through an OAuth client; `sub` is their user id
+    #[default]
+    User,
+    // An OAuth client acting for itself, through the `client_credentials` grant
+    Service,
+    // A user's personal API key. Keys are not JWTs, so no `Claims` carry this.
+    ApiKey,
+}
+
+impl TokenUse {
+    fn is_user(&self) -> bool {
+        *self == TokenUse::User
+    }
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+// Whether the space-separated `scope` includes `wanted`
+pub fn has_scope(scope: Option<&str>, wanted: &str) -> bool {
+    scope.is_some_and(|scope| scope.split_whitespace().any(|scope| scope == wanted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_scope() {
+        assert!(has_scope(Some("openid email"), "openid"));
+        assert!(has_scope(Some("email  openid"), "openid"));
+        assert!(!has_scope(Some("openidx"), "openid"));
+        assert!(!has_scope(None, "openid"));
+    }
+}
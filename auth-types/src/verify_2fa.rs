@@ -0,0 +1,21 @@
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{serialize_secret, TokenDelivery};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Verify2FARequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = "email")]
+    pub email: Secret<String>,
+    #[serde(rename = "loginAttemptId", serialize_with = "serialize_secret")]
+    #[schema(value_type = String, format = Uuid)]
+    pub login_attempt_id: Secret<String>,
+    // The 6-digit code sent by email
+    #[serde(rename = "2FACode", serialize_with = "serialize_secret")]
+    #[schema(value_type = String, pattern = "^[0-9]{6}$")]
+    pub two_fa_code: Secret<String>,
+    #[serde(default, rename = "tokenDelivery")]
+    pub token_delivery: TokenDelivery,
+}
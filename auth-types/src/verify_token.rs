@@ -0,0 +1,38 @@
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{serialize_secret, TokenUse};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VerifyTokenRequest {
+    #[serde(serialize_with = "serialize_secret")]
+    #[schema(value_type = String)]
+    pub token: Secret<String>,
+    // Also require one of the user's roles to grant this permission
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission: Option<String>,
+}
+
+// Who the token identifies, for services that accept API keys as well as JWTs and so cannot
+// read the claims themselves
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VerifyTokenResponse {
+    // The user's id or, for service tokens, the client id
+    pub sub: String,
+    // The user's current address; service tokens have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    // Seconds since the epoch; API keys without an expiry have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // Space-separated; sessions have none and may do anything their user can
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub token_use: TokenUse,
+    // The user's roles: those in a session token, or the current ones of an API key's owner
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("Invalid log filter")]
+    Filter(#[from] tracing_subscriber::filter::ParseError),
+    #[error("Failed to build the OTLP span exporter")]
+    Exporter(#[from] opentelemetry_otlp::ExporterBuildError),
+    #[error("A global tracing subscriber is already installed")]
+    AlreadyInstalled(#[from] tracing_subscriber::util::TryInitError),
+}
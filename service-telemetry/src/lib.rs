@@ -0,0 +1,7 @@
+mod error;
+mod propagation;
+mod subscriber;
+
+pub use error::*;
+pub use propagation::*;
+pub use subscriber::*;
@@ -0,0 +1,54 @@
+use http::HeaderMap;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+// Reads the W3C `traceparent`/`tracestate` headers of an incoming request
+pub fn extract_trace_context(headers: &HeaderMap) -> opentelemetry::Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+// Writes the current span's trace context into the headers of an outgoing request
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let context = Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(headers));
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{TraceContextExt, TraceId, TracerProvider};
+    use tracing_subscriber::{prelude::*, Registry};
+
+    use super::*;
+    use crate::tracer_provider;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_extracted_context_is_injected_into_child_spans() {
+        let tracer_provider = tracer_provider("test", None).unwrap();
+        let subscriber = Registry::default()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut incoming = HeaderMap::new();
+            incoming.insert("traceparent", TRACEPARENT.parse().unwrap());
+            let span = tracing::info_span!("request");
+            let _ = span.set_parent(extract_trace_context(&incoming));
+            assert_eq!(
+                span.context().span().span_context().trace_id(),
+                TraceId::from_hex(TRACE_ID).unwrap()
+            );
+
+            let mut outgoing = HeaderMap::new();
+            span.in_scope(|| inject_trace_context(&mut outgoing));
+
+            let traceparent = outgoing["traceparent"].to_str().unwrap();
+            assert!(traceparent.starts_with(&format!("00-{}-", TRACE_ID)));
+            assert!(!traceparent.contains("00f067aa0ba902b7"));
+        });
+    }
+}
@@ -0,0 +1,108 @@
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use tracing_error::ErrorLayer;
+use tracing_subscriber::{prelude::*, EnvFilter, Layer, Registry};
+
+use crate::TelemetryError;
+
+// Flushes and stops the span exporter when dropped, so spans are not lost on shutdown
+pub struct TelemetryGuard {
+    tracer_provider: SdkTracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("failed to shut down the tracer provider: {:?}", e);
+        }
+    }
+}
+
+// Installs the global subscriber: `fmt_layer` writes the logs, filtered by `RUST_LOG` (`info`
+// by default), and spans are linked to OpenTelemetry traces. Keep the guard until exit.
+pub fn init_tracing<L>(
+    fmt_layer: L,
+    service_name: &str,
+    otlp_endpoint: Option<&str>,
+) -> Result<TelemetryGuard, TelemetryError>
+where
+    L: Layer<Registry> + Send + Sync + 'static,
+{
+    let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
+    let tracer_provider = tracer_provider(service_name, otlp_endpoint)?;
+    let otel_layer =
+        tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(service_name.to_owned()));
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(filter_layer)
+        .with(otel_layer)
+        // Captures span traces for error reports
+        .with(ErrorLayer::default())
+        .try_init()?;
+
+    Ok(TelemetryGuard { tracer_provider })
+}
+
+// Spans are batched and exported over OTLP/HTTP when an endpoint, e.g.
+// `http://localhost:4318`, is given. Without one, spans still carry trace ids so that
+// context is propagated.
+pub fn tracer_provider(
+    service_name: &str,
+    otlp_endpoint: Option<&str>,
+) -> Result<SdkTracerProvider, TelemetryError> {
+    let resource = Resource::builder()
+        .with_service_name(service_name.to_owned())
+        .build();
+    let builder = SdkTracerProvider::builder().with_resource(resource);
+
+    let Some(endpoint) = otlp_endpoint else {
+        return Ok(builder.build());
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+
+    Ok(builder.with_batch_exporter(exporter).build())
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::Instrument;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spans_are_exported_to_the_collector() {
+        // Stands in for an OpenTelemetry collector's OTLP/HTTP receiver
+        let collector = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/traces"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1..)
+            .mount(&collector)
+            .await;
+
+        let tracer_provider = tracer_provider("test", Some(&collector.uri())).unwrap();
+
+        let subscriber = Registry::default()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        async {}
+            .instrument(tracing::info_span!("exported span"))
+            .await;
+
+        // Flushing blocks until the batch has been sent
+        tokio::task::spawn_blocking(move || tracer_provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}